@@ -0,0 +1,128 @@
+//! Crash-loop alerting for the babysitter
+
+use crate::babysitter::BabysitterState;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Number of recent stderr lines included in alerts
+const ALERT_STDERR_LINES: usize = 20;
+
+/// Counts crashes in a sliding time window
+pub struct CrashLoopDetector {
+    threshold: u32,
+    window: Duration,
+    crashes: VecDeque<Instant>,
+}
+
+impl CrashLoopDetector {
+    pub fn new(threshold: u32, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            crashes: VecDeque::new(),
+        }
+    }
+
+    /// Record a crash; returns the crash count once it reaches the threshold within the window.
+    /// The window is cleared after triggering so a single crash loop alerts once per window.
+    pub fn record_crash(&mut self, at: Instant) -> Option<usize> {
+        if self.threshold == 0 {
+            return None;
+        }
+
+        self.crashes.push_back(at);
+        while let Some(&oldest) = self.crashes.front() {
+            if at.duration_since(oldest) > self.window {
+                self.crashes.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if self.crashes.len() >= self.threshold as usize {
+            let count = self.crashes.len();
+            self.crashes.clear();
+            Some(count)
+        } else {
+            None
+        }
+    }
+}
+
+/// Posts alerts to the configured webhook (generic JSON, Slack-compatible "text" field)
+pub struct AlertNotifier {
+    webhook_url: Option<String>,
+    client: Client,
+}
+
+impl AlertNotifier {
+    pub fn new(webhook_url: Option<String>) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        Self {
+            webhook_url,
+            client,
+        }
+    }
+
+    pub async fn send(&self, state: &BabysitterState, event: &str, message: &str, details: Value) {
+        let Some(url) = &self.webhook_url else {
+            return;
+        };
+
+        let service_name = state.config.service_name();
+        let payload = json!({
+            "text": format!("[{}@{}] {}", service_name, state.config.host, message),
+            "event": event,
+            "service": service_name,
+            "host": state.config.host,
+            "restart_count": *state.restart_count.read().await,
+            "details": details,
+            "last_stderr": state.recent_stderr.tail(ALERT_STDERR_LINES),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+
+        match self.client.post(url).json(&payload).send().await {
+            Ok(response) if response.status().is_success() => {
+                info!("Sent {} alert for {}", event, service_name);
+            }
+            Ok(response) => {
+                warn!("Alert webhook returned status {}", response.status());
+            }
+            Err(e) => {
+                warn!("Failed to send alert webhook: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_loop_detection() {
+        let mut detector = CrashLoopDetector::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(detector.record_crash(start), None);
+        assert_eq!(detector.record_crash(start + Duration::from_secs(10)), None);
+        // Both earlier crashes fall out of the window
+        assert_eq!(detector.record_crash(start + Duration::from_secs(80)), None);
+        assert_eq!(detector.record_crash(start + Duration::from_secs(90)), None);
+        assert_eq!(
+            detector.record_crash(start + Duration::from_secs(100)),
+            Some(3)
+        );
+        // Cleared after triggering
+        assert_eq!(
+            detector.record_crash(start + Duration::from_secs(105)),
+            None
+        );
+    }
+}
@@ -1,7 +1,12 @@
 //! Configuration for the babysitter
 
 use clap::Parser;
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use tracing::warn;
+
+use crate::registry::tls::RegistryTls;
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "infini-babysitter")]
@@ -15,11 +20,20 @@ pub struct BabysitterConfig {
     #[arg(long, default_value = "localhost")]
     pub host: String,
 
-    /// Service port (babysitter will use port+1)
+    /// Service port (babysitter will use port+1 unless --babysitter-port is set)
     /// Required if config_file is not provided
     #[arg(long)]
     pub port: Option<u16>,
 
+    /// Port of the babysitter HTTP server (default: service port + 1)
+    #[arg(long)]
+    pub babysitter_port: Option<u16>,
+
+    /// Address the babysitter HTTP server listens on, e.g. 0.0.0.0, :: or [::]
+    /// (repeatable; default 0.0.0.0)
+    #[arg(long, value_parser = crate::utils::listen::parse_bind_addr)]
+    pub bind: Vec<IpAddr>,
+
     /// Service type: "InfiniLM", "InfiniLM-Rust", "vLLM", "mock", or "command"
     #[arg(long, default_value = "command")]
     pub service_type: String,
@@ -41,27 +55,205 @@ pub struct BabysitterConfig {
     #[arg(long)]
     pub work_dir: Option<PathBuf>,
 
+    /// GPU devices assigned to the service (comma-separated, e.g. "0,1")
+    #[arg(long)]
+    pub gpus: Option<String>,
+
+    /// Environment variable used to expose the assigned GPUs to the service
+    #[arg(long, default_value = "CUDA_VISIBLE_DEVICES")]
+    pub gpu_env_var: String,
+
     /// Registry URL (optional)
     #[arg(long)]
     pub registry_url: Option<String>,
 
-    /// Router URL (optional, for future use)
+    /// PEM bundle of extra CA certificates trusted for an https registry URL
+    #[arg(long)]
+    pub registry_ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate presented to the registry (mutual TLS)
+    #[arg(long, requires = "registry_client_key")]
+    pub registry_client_cert: Option<PathBuf>,
+
+    /// PEM private key for --registry-client-cert
+    #[arg(long, requires = "registry_client_cert")]
+    pub registry_client_key: Option<PathBuf>,
+
+    /// Shared secret sent to the registry with registrations and heartbeats
+    #[arg(long, env = "INFINI_REGISTRY_TOKEN", hide_env_values = true)]
+    pub registry_token: Option<String>,
+
+    /// Shared secret required (Authorization: Bearer) on /restart, /stop, /start, /logs
+    /// and /update; while none is set, only local clients may call them and /update is refused
+    #[arg(long, env = "INFINI_BABYSITTER_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    /// Let /update replace the backend args, not only the model path
+    #[arg(long)]
+    pub allow_update_args: bool,
+
+    /// Router URL(s), comma-separated, asked to drain the service before planned restarts
     #[arg(long)]
     pub router_url: Option<String>,
 
+    /// Admin token of the routers in router_url (their --admin-token)
+    #[arg(long, env = "INFINI_ADMIN_TOKEN", hide_env_values = true)]
+    pub router_token: Option<String>,
+
     /// Maximum number of restarts
     #[arg(long, default_value = "10000")]
     pub max_restarts: u32,
 
-    /// Delay between restarts (seconds)
+    /// Initial delay between restarts (seconds), doubled after each consecutive crash
     #[arg(long, default_value = "5")]
     pub restart_delay: u64,
 
+    /// Upper bound for the exponential restart backoff (seconds)
+    #[arg(long, default_value = "300")]
+    pub max_restart_delay: u64,
+
+    /// Uptime after which the service is considered stable and the restart counter resets (seconds)
+    #[arg(long, default_value = "600")]
+    pub stability_window: u64,
+
     /// Heartbeat interval (seconds)
     #[arg(long, default_value = "30")]
     pub heartbeat_interval: u64,
 
-    /// Configuration file (TOML format) - if provided, loads config from file
+    /// Grace period after SIGTERM before the child is killed (seconds)
+    #[arg(long, alias = "shutdown-timeout", default_value = "10")]
+    pub shutdown_grace_period: u64,
+
+    /// Seconds to wait for the service port to be released by the previous process
+    /// before a restart is declared failed
+    #[arg(long, default_value = "10")]
+    pub port_release_timeout: u64,
+
+    /// Webhook URL (generic JSON or Slack) called on crash loops and exhausted restarts
+    #[arg(long)]
+    pub alert_webhook_url: Option<String>,
+
+    /// Crashes within the crash-loop window that trigger an alert (0 disables)
+    #[arg(long, default_value = "5")]
+    pub crash_loop_threshold: u32,
+
+    /// Sliding window for crash-loop detection (seconds)
+    #[arg(long, default_value = "600")]
+    pub crash_loop_window: u64,
+
+    /// Regex matched against the child's output to detect the port it listens on;
+    /// the first capture group must be the port (e.g. "listening on .*:(\d+)")
+    #[arg(long)]
+    pub port_log_pattern: Option<String>,
+
+    /// HTTP path probed on the managed service to detect hangs
+    #[arg(long, default_value = "/health")]
+    pub health_probe_path: String,
+
+    /// Interval between health probes of the managed service (seconds, 0 disables probing)
+    #[arg(long, default_value = "0")]
+    pub health_probe_interval: u64,
+
+    /// Timeout for a single health probe (seconds)
+    #[arg(long, default_value = "5")]
+    pub health_probe_timeout: u64,
+
+    /// Consecutive failed probes before the service is restarted, counted after the first pass
+    #[arg(long, default_value = "3")]
+    pub health_probe_failures: u32,
+
+    /// Restart the service when its resident memory stays above this many MiB
+    #[arg(long)]
+    pub memory_limit_mb: Option<u64>,
+
+    /// Restart the service when its GPU memory (all devices) stays above this many MiB
+    /// (requires the `nvml` feature)
+    #[arg(long)]
+    pub gpu_memory_limit_mb: Option<u64>,
+
+    /// Seconds memory must stay above a limit before the service is restarted
+    #[arg(long, default_value = "60")]
+    pub memory_limit_sustain: u64,
+
+    /// Interval between GPU health checks (seconds, 0 disables; requires the `nvml` feature)
+    #[arg(long, default_value = "0")]
+    pub gpu_check_interval: u64,
+
+    /// GPU temperature (Celsius) above which a GPU is faulty
+    #[arg(long)]
+    pub gpu_max_temperature: Option<u32>,
+
+    /// Uncorrected ECC errors a GPU may report before it is faulty
+    #[arg(long, default_value = "0")]
+    pub gpu_max_ecc_errors: u64,
+
+    /// Free GPU memory (MiB) below which a GPU is faulty
+    #[arg(long)]
+    pub gpu_min_free_memory_mb: Option<u64>,
+
+    /// Restart the service when a GPU fault is detected (otherwise only report it)
+    #[arg(long)]
+    pub gpu_fault_restart: bool,
+
+    /// HTTP path polled to decide the service is ready
+    /// (default: /v1/models, falling back to /models)
+    #[arg(long)]
+    pub readiness_path: Option<String>,
+
+    /// Status code the readiness path must return (default: any 2xx)
+    #[arg(long)]
+    pub readiness_status: Option<u16>,
+
+    /// JSON pointer (e.g. "/status") into the readiness response that must be truthy,
+    /// or equal --readiness-json-value when given
+    #[arg(long)]
+    pub readiness_json_pointer: Option<String>,
+
+    /// Expected value at the readiness JSON pointer (parsed as JSON, else compared as a string)
+    #[arg(long)]
+    pub readiness_json_value: Option<String>,
+
+    /// Inference request that must succeed before the service is registered: `completion`
+    /// or `chat` send a one-token request for the first model, any other value is a path
+    /// POSTed with --deep-readiness-body
+    #[arg(long)]
+    pub deep_readiness_check: Option<String>,
+
+    /// JSON body POSTed to a custom --deep-readiness-check path
+    #[arg(long)]
+    pub deep_readiness_body: Option<String>,
+
+    /// Timeout for a single deep readiness request (seconds); the first inference after
+    /// loading a large model can be slow
+    #[arg(long, default_value = "120")]
+    pub deep_readiness_timeout: u64,
+
+    /// Cron expression (local time) on which the service is gracefully restarted,
+    /// e.g. "0 3 * * *" for nightly at 03:00
+    #[arg(long)]
+    pub restart_schedule: Option<String>,
+
+    /// Seconds between deregistering the service and a planned restart, so routers
+    /// stop sending new requests and in-flight ones finish; with --router-url, the
+    /// longest wait for the routers' in-flight requests to finish
+    #[arg(long, default_value = "30")]
+    pub restart_drain_period: u64,
+
+    /// HTTP path on the managed service scraped (Prometheus text format) for queue depth,
+    /// in-flight requests and KV-cache utilization sent with heartbeats; empty disables
+    #[arg(long, default_value = "/metrics")]
+    pub load_metrics_path: String,
+
+    /// Kill a process already listening on the service port (e.g. an orphaned child of
+    /// a crashed babysitter) instead of refusing to start
+    #[arg(long)]
+    pub kill_orphans: bool,
+
+    /// Lines of child stdout and of child stderr kept in memory for GET /logs
+    #[arg(long, default_value = "1000")]
+    pub log_buffer_lines: usize,
+
+    /// Configuration file (TOML, or YAML/JSON by extension) - if provided, loads config from file
     /// CLI arguments override file values
     #[arg(long)]
     pub config_file: Option<PathBuf>,
@@ -86,6 +278,15 @@ pub struct BabysitterConfig {
 }
 
 impl BabysitterConfig {
+    /// TLS settings for connections to the registry
+    pub fn registry_tls(&self) -> RegistryTls {
+        RegistryTls {
+            ca_cert: self.registry_ca_cert.clone(),
+            client_cert: self.registry_client_cert.clone(),
+            client_key: self.registry_client_key.clone(),
+        }
+    }
+
     pub fn service_name(&self) -> String {
         self.name.clone().unwrap_or_else(|| {
             let port_str = self
@@ -103,4 +304,68 @@ impl BabysitterConfig {
     pub fn is_command_based(&self) -> bool {
         self.service_type == "command" || self.command.is_some()
     }
+
+    /// Assigned GPU device ids, in the order given
+    pub fn gpu_devices(&self) -> Vec<String> {
+        self.gpus
+            .as_deref()
+            .map(|gpus| {
+                gpus.split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parse the `--env KEY=VALUE` pairs, skipping malformed entries
+    pub fn env_vars(&self) -> HashMap<String, String> {
+        self.env
+            .iter()
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| match entry.split_once('=') {
+                Some((key, value)) if !key.is_empty() => Some((key.to_string(), value.to_string())),
+                _ => {
+                    warn!(
+                        "Ignoring invalid --env entry (expected KEY=VALUE): {}",
+                        entry
+                    );
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_vars_parsing() {
+        let config = BabysitterConfig::parse_from([
+            "infini-babysitter",
+            "--port",
+            "8100",
+            "--env",
+            "CUDA_VISIBLE_DEVICES=0,1 OPTS=a=b INVALID =novalue",
+        ]);
+        let env = config.env_vars();
+        assert_eq!(env.len(), 2);
+        assert_eq!(env["CUDA_VISIBLE_DEVICES"], "0,1");
+        assert_eq!(env["OPTS"], "a=b");
+    }
+
+    #[test]
+    fn test_gpu_devices_parsing() {
+        let config = BabysitterConfig::parse_from([
+            "infini-babysitter",
+            "--port",
+            "8100",
+            "--gpus",
+            "2, 3,",
+        ]);
+        assert_eq!(config.gpu_devices(), vec!["2", "3"]);
+    }
 }
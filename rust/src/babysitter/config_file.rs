@@ -3,6 +3,7 @@
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use toml::Value as TomlValue;
 
@@ -16,15 +17,51 @@ pub struct BabysitterConfigFile {
     #[serde(default = "default_host")]
     pub host: String,
 
-    /// Service port (babysitter will use port+1)
+    /// Service port (babysitter will use port+1 unless babysitter_port is set)
     pub port: u16,
 
+    /// Port of the babysitter HTTP server (default: port + 1)
+    #[serde(default)]
+    pub babysitter_port: Option<u16>,
+
+    /// Addresses the babysitter HTTP server listens on (default: 0.0.0.0)
+    #[serde(default)]
+    pub bind: Vec<IpAddr>,
+
     /// Registry URL (optional)
     pub registry_url: Option<String>,
 
-    /// Router URL (optional)
+    /// PEM bundle of extra CA certificates trusted for an https registry URL
+    #[serde(default)]
+    pub registry_ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate presented to the registry (mutual TLS)
+    #[serde(default)]
+    pub registry_client_cert: Option<PathBuf>,
+
+    /// PEM private key for registry_client_cert
+    #[serde(default)]
+    pub registry_client_key: Option<PathBuf>,
+
+    /// Shared secret sent to the registry with registrations and heartbeats
+    #[serde(default)]
+    pub registry_token: Option<String>,
+
+    /// Shared secret required on /restart, /stop, /start, /logs and /update
+    #[serde(default)]
+    pub admin_token: Option<String>,
+
+    /// Let /update replace the backend args, not only the model path
+    #[serde(default)]
+    pub allow_update_args: bool,
+
+    /// Router URL(s), comma-separated, asked to drain the service before planned restarts
     pub router_url: Option<String>,
 
+    /// Admin token of the routers in router_url
+    #[serde(default)]
+    pub router_token: Option<String>,
+
     /// Babysitter settings
     #[serde(default)]
     pub babysitter: BabysitterSettings,
@@ -48,13 +85,313 @@ pub struct BabysitterSettings {
     #[serde(default = "default_max_restarts")]
     pub max_restarts: u32,
 
-    /// Delay between restarts (seconds)
+    /// Initial delay between restarts (seconds), doubled after each consecutive crash
     #[serde(default = "default_restart_delay")]
     pub restart_delay: u64,
 
+    /// Upper bound for the exponential restart backoff (seconds)
+    #[serde(default = "default_max_restart_delay")]
+    pub max_restart_delay: u64,
+
+    /// Uptime after which the restart counter resets (seconds)
+    #[serde(default = "default_stability_window")]
+    pub stability_window: u64,
+
     /// Heartbeat interval (seconds)
     #[serde(default = "default_heartbeat_interval")]
     pub heartbeat_interval: u64,
+
+    /// Grace period after SIGTERM before the child is killed (seconds)
+    #[serde(default = "default_shutdown_grace_period", alias = "shutdown_timeout")]
+    pub shutdown_grace_period: u64,
+
+    /// Seconds to wait for the previous process to release the service port before a
+    /// restart is declared failed
+    #[serde(default = "default_port_release_timeout")]
+    pub port_release_timeout: u64,
+
+    /// Health probing of the managed service
+    #[serde(default)]
+    pub health_probe: HealthProbeSettings,
+
+    /// Memory limits that trigger a restart of the managed service
+    #[serde(default)]
+    pub memory_watchdog: MemoryWatchdogSettings,
+
+    /// GPU fault detection
+    #[serde(default)]
+    pub gpu_check: GpuCheckSettings,
+
+    /// Regex matched against the child's output to detect its port (first capture group)
+    #[serde(default)]
+    pub port_log_pattern: Option<String>,
+
+    /// Crash-loop alerting
+    #[serde(default)]
+    pub alerts: AlertSettings,
+
+    /// How to tell that the managed service is ready to serve
+    #[serde(default)]
+    pub readiness: ReadinessSettings,
+
+    /// Commands run at lifecycle points of the managed service
+    #[serde(default)]
+    pub hooks: HookSettings,
+
+    /// Environment variable used to expose the assigned GPUs to the service
+    #[serde(default = "default_gpu_env_var")]
+    pub gpu_env_var: String,
+
+    /// Path on the managed service scraped for load metrics sent with heartbeats
+    /// (empty disables)
+    #[serde(default = "default_load_metrics_path")]
+    pub load_metrics_path: String,
+
+    /// Lines of child stdout and of child stderr kept in memory for GET /logs
+    #[serde(default = "default_log_buffer_lines")]
+    pub log_buffer_lines: usize,
+
+    /// Kill a process already listening on the service port instead of refusing to start
+    #[serde(default)]
+    pub kill_orphans: bool,
+
+    /// Cron expression (local time) for graceful periodic restarts
+    #[serde(default)]
+    pub restart_schedule: Option<String>,
+
+    /// Seconds between deregistration and a planned restart (longest drain wait with router_url)
+    #[serde(default = "default_restart_drain_period")]
+    pub restart_drain_period: u64,
+}
+
+fn default_gpu_env_var() -> String {
+    "CUDA_VISIBLE_DEVICES".to_string()
+}
+
+/// Crash-loop alert webhook settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertSettings {
+    /// Webhook URL (generic JSON or Slack)
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+
+    /// Crashes within the window that trigger an alert (0 disables)
+    #[serde(default = "default_crash_loop_threshold")]
+    pub crash_loop_threshold: u32,
+
+    /// Sliding window for crash-loop detection (seconds)
+    #[serde(default = "default_crash_loop_window")]
+    pub crash_loop_window: u64,
+}
+
+fn default_crash_loop_threshold() -> u32 {
+    5
+}
+
+fn default_crash_loop_window() -> u64 {
+    600
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            crash_loop_threshold: default_crash_loop_threshold(),
+            crash_loop_window: default_crash_loop_window(),
+        }
+    }
+}
+
+/// Shell commands run at lifecycle points (cache warmers, GPU resets, notifications)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookSettings {
+    /// Before the service process is spawned
+    #[serde(default)]
+    pub pre_start: Option<String>,
+
+    /// Once the started service is ready
+    #[serde(default)]
+    pub post_start: Option<String>,
+
+    /// After the service exited unexpectedly (exit code in INFINI_EXIT_CODE)
+    #[serde(default)]
+    pub on_crash: Option<String>,
+
+    /// Before the running service is stopped
+    #[serde(default)]
+    pub pre_stop: Option<String>,
+
+    /// Seconds a hook may run before it is killed
+    #[serde(default = "default_hook_timeout")]
+    pub timeout: u64,
+}
+
+fn default_hook_timeout() -> u64 {
+    60
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        Self {
+            pre_start: None,
+            post_start: None,
+            on_crash: None,
+            pre_stop: None,
+            timeout: default_hook_timeout(),
+        }
+    }
+}
+
+/// Restart the service when its memory stays above a limit (slow leaks)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryWatchdogSettings {
+    /// Resident memory limit (MiB)
+    #[serde(default)]
+    pub rss_limit_mb: Option<u64>,
+
+    /// GPU memory limit summed over all devices (MiB, requires the `nvml` feature)
+    #[serde(default)]
+    pub gpu_limit_mb: Option<u64>,
+
+    /// Seconds usage must stay above a limit before restarting
+    #[serde(default = "default_memory_limit_sustain")]
+    pub sustain: u64,
+}
+
+fn default_memory_limit_sustain() -> u64 {
+    60
+}
+
+impl Default for MemoryWatchdogSettings {
+    fn default() -> Self {
+        Self {
+            rss_limit_mb: None,
+            gpu_limit_mb: None,
+            sustain: default_memory_limit_sustain(),
+        }
+    }
+}
+
+/// GPU health checks of the assigned devices (requires the `nvml` feature)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GpuCheckSettings {
+    /// Interval between checks (seconds, 0 disables checking)
+    #[serde(default)]
+    pub interval: u64,
+
+    /// Temperature (Celsius) above which a GPU is faulty
+    #[serde(default)]
+    pub max_temperature: Option<u32>,
+
+    /// Uncorrected ECC errors tolerated per GPU
+    #[serde(default)]
+    pub max_ecc_errors: u64,
+
+    /// Free memory (MiB) below which a GPU is faulty
+    #[serde(default)]
+    pub min_free_memory_mb: Option<u64>,
+
+    /// Restart the service on a fault instead of only reporting it unhealthy
+    #[serde(default)]
+    pub restart_on_fault: bool,
+}
+
+/// Readiness check of the managed service after (re)start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadinessSettings {
+    /// HTTP path to poll (default: /v1/models, falling back to /models)
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Expected status code (default: any 2xx)
+    #[serde(default)]
+    pub expected_status: Option<u16>,
+
+    /// JSON pointer into the response body that must be truthy (or equal `json_value`)
+    #[serde(default)]
+    pub json_pointer: Option<String>,
+
+    /// Expected value at `json_pointer` (parsed as JSON, else compared as a string)
+    #[serde(default)]
+    pub json_value: Option<String>,
+
+    /// Inference request that must succeed before registration: `completion`, `chat`,
+    /// or a path POSTed with `deep_check_body`
+    #[serde(default)]
+    pub deep_check: Option<String>,
+
+    /// JSON body for a custom `deep_check` path
+    #[serde(default)]
+    pub deep_check_body: Option<String>,
+
+    /// Timeout for a single deep check request (seconds)
+    #[serde(default = "default_deep_check_timeout")]
+    pub deep_check_timeout: u64,
+}
+
+fn default_deep_check_timeout() -> u64 {
+    120
+}
+
+impl Default for ReadinessSettings {
+    fn default() -> Self {
+        Self {
+            path: None,
+            expected_status: None,
+            json_pointer: None,
+            json_value: None,
+            deep_check: None,
+            deep_check_body: None,
+            deep_check_timeout: default_deep_check_timeout(),
+        }
+    }
+}
+
+/// Periodic HTTP probing of the managed service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthProbeSettings {
+    /// HTTP path to probe
+    #[serde(default = "default_health_probe_path")]
+    pub path: String,
+
+    /// Interval between probes (seconds, 0 disables probing)
+    #[serde(default = "default_health_probe_interval")]
+    pub interval: u64,
+
+    /// Timeout for a single probe (seconds)
+    #[serde(default = "default_health_probe_timeout")]
+    pub timeout: u64,
+
+    /// Consecutive failed probes before the service is restarted
+    #[serde(default = "default_health_probe_failures")]
+    pub failure_threshold: u32,
+}
+
+fn default_health_probe_path() -> String {
+    "/health".to_string()
+}
+
+fn default_health_probe_interval() -> u64 {
+    0
+}
+
+fn default_health_probe_timeout() -> u64 {
+    5
+}
+
+fn default_health_probe_failures() -> u32 {
+    3
+}
+
+impl Default for HealthProbeSettings {
+    fn default() -> Self {
+        Self {
+            path: default_health_probe_path(),
+            interval: default_health_probe_interval(),
+            timeout: default_health_probe_timeout(),
+            failure_threshold: default_health_probe_failures(),
+        }
+    }
 }
 
 fn default_max_restarts() -> u32 {
@@ -65,16 +402,61 @@ fn default_restart_delay() -> u64 {
     5
 }
 
+fn default_max_restart_delay() -> u64 {
+    300
+}
+
+fn default_stability_window() -> u64 {
+    600
+}
+
 fn default_heartbeat_interval() -> u64 {
     30
 }
 
+fn default_shutdown_grace_period() -> u64 {
+    10
+}
+
+fn default_port_release_timeout() -> u64 {
+    10
+}
+
+fn default_load_metrics_path() -> String {
+    "/metrics".to_string()
+}
+
+fn default_log_buffer_lines() -> usize {
+    1000
+}
+
+fn default_restart_drain_period() -> u64 {
+    30
+}
+
 impl Default for BabysitterSettings {
     fn default() -> Self {
         Self {
             max_restarts: default_max_restarts(),
             restart_delay: default_restart_delay(),
+            max_restart_delay: default_max_restart_delay(),
+            stability_window: default_stability_window(),
             heartbeat_interval: default_heartbeat_interval(),
+            shutdown_grace_period: default_shutdown_grace_period(),
+            port_release_timeout: default_port_release_timeout(),
+            health_probe: HealthProbeSettings::default(),
+            memory_watchdog: MemoryWatchdogSettings::default(),
+            gpu_check: GpuCheckSettings::default(),
+            port_log_pattern: None,
+            alerts: AlertSettings::default(),
+            readiness: ReadinessSettings::default(),
+            hooks: HookSettings::default(),
+            gpu_env_var: default_gpu_env_var(),
+            load_metrics_path: default_load_metrics_path(),
+            log_buffer_lines: default_log_buffer_lines(),
+            kill_orphans: false,
+            restart_schedule: None,
+            restart_drain_period: default_restart_drain_period(),
         }
     }
 }
@@ -96,6 +478,9 @@ pub enum BackendConfig {
         /// Environment variables
         #[serde(default)]
         env: HashMap<String, String>,
+        /// GPU devices assigned to this service
+        #[serde(default, alias = "devices")]
+        gpus: Vec<u32>,
     },
 
     /// vLLM backend
@@ -112,6 +497,9 @@ pub enum BackendConfig {
         /// Environment variables
         #[serde(default)]
         env: HashMap<String, String>,
+        /// GPU devices assigned to this service
+        #[serde(default, alias = "devices")]
+        gpus: Vec<u32>,
     },
 
     /// Mock backend
@@ -128,6 +516,9 @@ pub enum BackendConfig {
         config_file: PathBuf,
         /// Working directory
         work_dir: Option<PathBuf>,
+        /// GPU devices assigned to this service
+        #[serde(default, alias = "devices")]
+        gpus: Vec<u32>,
     },
 
     /// InfiniLM Python backend
@@ -143,19 +534,148 @@ pub enum BackendConfig {
         /// Environment variables
         #[serde(default)]
         env: HashMap<String, String>,
+        /// GPU devices assigned to this service
+        #[serde(default, alias = "devices")]
+        gpus: Vec<u32>,
     },
 }
 
+/// Config file format, picked by extension: `.yaml`/`.yml` and `.json` files are
+/// accepted next to TOML (the default), so templated orchestration configs need no
+/// conversion step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &std::path::Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    /// Parse a config document into the TOML value tree the loader works on
+    fn parse(self, content: &str) -> anyhow::Result<TomlValue> {
+        let value: serde_json::Value = match self {
+            ConfigFormat::Toml => return Ok(toml::from_str(content)?),
+            ConfigFormat::Yaml => serde_yaml::from_str(content)?,
+            ConfigFormat::Json => serde_json::from_str(content)?,
+        };
+        Ok(serde_json::from_value(without_nulls(value))?)
+    }
+}
+
+/// Drop null mapping entries, which TOML cannot represent; for optional settings they
+/// mean the same as leaving the key out
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, without_nulls(v)))
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(without_nulls).collect())
+        }
+        other => other,
+    }
+}
+
 impl BabysitterConfigFile {
-    /// Load configuration from a TOML file
-    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
+    /// Load one configuration per managed service from a TOML, YAML or JSON file
+    ///
+    /// A file may declare several backends in a `[[services]]` array. Each entry
+    /// inherits the top-level settings (host, registry_url, babysitter, ...) and
+    /// overrides them with its own keys; `metadata` and `babysitter` tables are
+    /// merged key by key. Files without `[[services]]` yield a single config.
+    pub fn from_file_all<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Vec<Self>> {
         let content = std::fs::read_to_string(path.as_ref())
             .with_context(|| format!("Failed to read config file: {:?}", path.as_ref()))?;
 
-        let config: BabysitterConfigFile = toml::from_str(&content)
-            .with_context(|| format!("Failed to parse TOML config file: {:?}", path.as_ref()))?;
+        let format = ConfigFormat::from_path(path.as_ref());
+        let root = format.parse(&content).with_context(|| {
+            format!(
+                "Failed to parse {:?} config file: {:?}",
+                format,
+                path.as_ref()
+            )
+        })?;
+        let TomlValue::Table(mut base) = root else {
+            anyhow::bail!("Config file must be a table/mapping: {:?}", path.as_ref());
+        };
+
+        let Some(services) = base.remove("services") else {
+            let config: BabysitterConfigFile = TomlValue::Table(base)
+                .try_into()
+                .with_context(|| format!("Invalid config file: {:?}", path.as_ref()))?;
+            return Ok(vec![config]);
+        };
+
+        let TomlValue::Array(entries) = services else {
+            anyhow::bail!("`services` must be an array of tables ([[services]])");
+        };
+
+        let mut configs: Vec<BabysitterConfigFile> = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.into_iter().enumerate() {
+            let TomlValue::Table(entry) = entry else {
+                anyhow::bail!("services[{}] must be a table", index);
+            };
+
+            let mut merged = base.clone();
+            for (key, value) in entry {
+                match (merged.get_mut(&key), value) {
+                    (Some(TomlValue::Table(base_table)), TomlValue::Table(overrides))
+                        if key == "metadata" || key == "babysitter" =>
+                    {
+                        merge_tables(base_table, overrides)
+                    }
+                    (_, value) => {
+                        merged.insert(key, value);
+                    }
+                }
+            }
+
+            let config: BabysitterConfigFile = TomlValue::Table(merged)
+                .try_into()
+                .with_context(|| format!("Invalid configuration for services[{}]", index))?;
+
+            for port in config.ports() {
+                if let Some(other) = configs.iter().find(|c| c.ports().contains(&port)) {
+                    anyhow::bail!(
+                        "services[{}] uses port {} which is already used by {:?}",
+                        index,
+                        port,
+                        other.name
+                    );
+                }
+            }
+            configs.push(config);
+        }
+
+        if configs.is_empty() {
+            anyhow::bail!("`services` must declare at least one service");
+        }
+
+        Ok(configs)
+    }
 
-        Ok(config)
+    /// Ports bound for this service: the service itself and its babysitter
+    fn ports(&self) -> [u16; 2] {
+        [
+            self.port,
+            self.babysitter_port
+                .unwrap_or_else(|| self.port.saturating_add(1)),
+        ]
     }
 
     /// Convert to CLI-compatible config
@@ -166,16 +686,59 @@ impl BabysitterConfigFile {
             name: self.name.clone(),
             host: self.host.clone(),
             port: Some(self.port),
+            babysitter_port: self.babysitter_port,
+            bind: self.bind.clone(),
             service_type: self.backend.service_type_name().to_string(),
             path: self.backend.path(),
             command: self.backend.command(),
             args: self.backend.args_string(),
             work_dir: self.backend.work_dir(),
+            gpus: self.backend.gpus(),
+            gpu_env_var: self.babysitter.gpu_env_var.clone(),
             registry_url: self.registry_url.clone(),
+            registry_ca_cert: self.registry_ca_cert.clone(),
+            registry_client_cert: self.registry_client_cert.clone(),
+            registry_client_key: self.registry_client_key.clone(),
+            registry_token: self.registry_token.clone(),
+            admin_token: self.admin_token.clone(),
+            allow_update_args: self.allow_update_args,
             router_url: self.router_url.clone(),
+            router_token: self.router_token.clone(),
             max_restarts: self.babysitter.max_restarts,
             restart_delay: self.babysitter.restart_delay,
+            max_restart_delay: self.babysitter.max_restart_delay,
+            stability_window: self.babysitter.stability_window,
             heartbeat_interval: self.babysitter.heartbeat_interval,
+            shutdown_grace_period: self.babysitter.shutdown_grace_period,
+            port_release_timeout: self.babysitter.port_release_timeout,
+            port_log_pattern: self.babysitter.port_log_pattern.clone(),
+            alert_webhook_url: self.babysitter.alerts.webhook_url.clone(),
+            crash_loop_threshold: self.babysitter.alerts.crash_loop_threshold,
+            crash_loop_window: self.babysitter.alerts.crash_loop_window,
+            health_probe_path: self.babysitter.health_probe.path.clone(),
+            health_probe_interval: self.babysitter.health_probe.interval,
+            health_probe_timeout: self.babysitter.health_probe.timeout,
+            health_probe_failures: self.babysitter.health_probe.failure_threshold,
+            memory_limit_mb: self.babysitter.memory_watchdog.rss_limit_mb,
+            gpu_memory_limit_mb: self.babysitter.memory_watchdog.gpu_limit_mb,
+            memory_limit_sustain: self.babysitter.memory_watchdog.sustain,
+            gpu_check_interval: self.babysitter.gpu_check.interval,
+            gpu_max_temperature: self.babysitter.gpu_check.max_temperature,
+            gpu_max_ecc_errors: self.babysitter.gpu_check.max_ecc_errors,
+            gpu_min_free_memory_mb: self.babysitter.gpu_check.min_free_memory_mb,
+            gpu_fault_restart: self.babysitter.gpu_check.restart_on_fault,
+            readiness_path: self.babysitter.readiness.path.clone(),
+            readiness_status: self.babysitter.readiness.expected_status,
+            readiness_json_pointer: self.babysitter.readiness.json_pointer.clone(),
+            readiness_json_value: self.babysitter.readiness.json_value.clone(),
+            deep_readiness_check: self.babysitter.readiness.deep_check.clone(),
+            deep_readiness_body: self.babysitter.readiness.deep_check_body.clone(),
+            deep_readiness_timeout: self.babysitter.readiness.deep_check_timeout,
+            load_metrics_path: self.babysitter.load_metrics_path.clone(),
+            log_buffer_lines: self.babysitter.log_buffer_lines,
+            kill_orphans: self.babysitter.kill_orphans,
+            restart_schedule: self.babysitter.restart_schedule.clone(),
+            restart_drain_period: self.babysitter.restart_drain_period,
             config_file: None,
             dev: None,
             ndev: None,
@@ -204,23 +767,36 @@ impl BabysitterConfigFile {
     }
 }
 
+/// Merge a service's overrides into the shared table, recursing into nested tables so
+/// overriding one key of e.g. [babysitter.readiness] keeps the shared siblings
+fn merge_tables(base: &mut toml::value::Table, overrides: toml::value::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(TomlValue::Table(base_table)), TomlValue::Table(nested)) => {
+                merge_tables(base_table, nested)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
 /// Convert TOML value to JSON value
 fn toml_to_json_value(toml_val: &TomlValue) -> Result<serde_json::Value, serde_json::Error> {
     // Serialize TOML value to string, then deserialize as JSON
     // This is a simple conversion approach
     match toml_val {
         TomlValue::String(s) => Ok(serde_json::Value::String(s.clone())),
-        TomlValue::Integer(i) => Ok(serde_json::Value::Number(
-            serde_json::Number::from(*i)
-        )),
-        TomlValue::Float(f) => {
-            serde_json::Number::from_f64(*f)
-                .map(serde_json::Value::Number)
-                .ok_or_else(|| serde_json::Error::io(std::io::Error::new(
+        TomlValue::Integer(i) => Ok(serde_json::Value::Number(serde_json::Number::from(*i))),
+        TomlValue::Float(f) => serde_json::Number::from_f64(*f)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| {
+                serde_json::Error::io(std::io::Error::new(
                     std::io::ErrorKind::InvalidData,
-                    "Invalid float"
-                )))
-        }
+                    "Invalid float",
+                ))
+            }),
         TomlValue::Boolean(b) => Ok(serde_json::Value::Bool(*b)),
         TomlValue::Datetime(dt) => Ok(serde_json::Value::String(dt.to_string())),
         TomlValue::Array(arr) => {
@@ -232,9 +808,7 @@ fn toml_to_json_value(toml_val: &TomlValue) -> Result<serde_json::Value, serde_j
                 .iter()
                 .map(|(k, v)| toml_to_json_value(v).map(|val| (k.clone(), val)))
                 .collect();
-            json_obj.map(|map| {
-                serde_json::Value::Object(map.into_iter().collect())
-            })
+            json_obj.map(|map| serde_json::Value::Object(map.into_iter().collect()))
         }
     }
 }
@@ -292,6 +866,25 @@ impl BackendConfig {
         }
     }
 
+    fn gpus(&self) -> Option<String> {
+        match self {
+            BackendConfig::Command { gpus, .. }
+            | BackendConfig::VLLM { gpus, .. }
+            | BackendConfig::InfiniLMRust { gpus, .. }
+            | BackendConfig::InfiniLM { gpus, .. }
+                if !gpus.is_empty() =>
+            {
+                Some(
+                    gpus.iter()
+                        .map(|id| id.to_string())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                )
+            }
+            _ => None,
+        }
+    }
+
     pub fn env(&self) -> HashMap<String, String> {
         match self {
             BackendConfig::Command { env, .. }
@@ -301,3 +894,206 @@ impl BackendConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_file_all_multiple_services() {
+        let toml = r#"
+host = "10.0.0.1"
+registry_url = "http://localhost:18000"
+
+[babysitter]
+max_restarts = 5
+
+[metadata]
+zone = "a"
+
+[[services]]
+name = "svc-a"
+port = 8100
+[services.backend]
+type = "mock"
+models = ["model-a"]
+
+[[services]]
+name = "svc-b"
+port = 8200
+[services.babysitter]
+restart_delay = 1
+[services.metadata]
+cache_type = "static"
+[services.backend]
+type = "mock"
+models = ["model-b"]
+"#;
+
+        let temp_file = std::env::temp_dir().join("test_babysitter_multi.toml");
+        std::fs::write(&temp_file, toml).unwrap();
+
+        let configs = BabysitterConfigFile::from_file_all(&temp_file).unwrap();
+        std::fs::remove_file(&temp_file).unwrap();
+
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].name.as_deref(), Some("svc-a"));
+        assert_eq!(configs[0].host, "10.0.0.1");
+        assert_eq!(configs[1].port, 8200);
+        assert_eq!(configs[1].babysitter.max_restarts, 5);
+        assert_eq!(configs[1].babysitter.restart_delay, 1);
+        assert!(configs[1].metadata.contains_key("zone"));
+        assert!(configs[1].metadata.contains_key("cache_type"));
+        assert!(!configs[0].metadata.contains_key("cache_type"));
+    }
+
+    #[test]
+    fn test_from_file_all_merges_nested_babysitter_tables() {
+        let toml = r#"
+[babysitter.readiness]
+path = "/ready"
+expected_status = 204
+
+[babysitter.hooks]
+pre_start = "warm-cache"
+
+[[services]]
+name = "svc-a"
+port = 8100
+[services.backend]
+type = "mock"
+models = ["model-a"]
+
+[[services]]
+name = "svc-b"
+port = 8200
+[services.babysitter.readiness]
+expected_status = 200
+[services.backend]
+type = "mock"
+models = ["model-b"]
+"#;
+
+        let temp_file = std::env::temp_dir().join("test_babysitter_nested_merge.toml");
+        std::fs::write(&temp_file, toml).unwrap();
+
+        let configs = BabysitterConfigFile::from_file_all(&temp_file).unwrap();
+        std::fs::remove_file(&temp_file).unwrap();
+
+        let readiness = &configs[1].babysitter.readiness;
+        assert_eq!(readiness.expected_status, Some(200));
+        assert_eq!(readiness.path.as_deref(), Some("/ready"));
+        assert_eq!(
+            configs[1].babysitter.hooks.pre_start.as_deref(),
+            Some("warm-cache")
+        );
+        assert_eq!(configs[0].babysitter.readiness.expected_status, Some(204));
+    }
+
+    #[test]
+    fn test_from_file_all_yaml_and_json() {
+        let yaml = r#"
+host: 10.0.0.1
+registry_url: null
+babysitter:
+  max_restarts: 5
+services:
+  - name: svc-a
+    port: 8100
+    backend: {type: mock, models: [model-a]}
+  - name: svc-b
+    port: 8200
+    babysitter: {restart_delay: 1}
+    backend: {type: mock, models: [model-b]}
+"#;
+        let yaml_file = std::env::temp_dir().join("test_babysitter_multi.yaml");
+        std::fs::write(&yaml_file, yaml).unwrap();
+        let configs = BabysitterConfigFile::from_file_all(&yaml_file).unwrap();
+        std::fs::remove_file(&yaml_file).unwrap();
+
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].host, "10.0.0.1");
+        assert!(configs[0].registry_url.is_none());
+        assert_eq!(configs[1].babysitter.max_restarts, 5);
+        assert_eq!(configs[1].babysitter.restart_delay, 1);
+
+        let json = r#"{"port": 8100, "backend": {"type": "mock", "models": ["model-a"]}}"#;
+        let json_file = std::env::temp_dir().join("test_babysitter_single.json");
+        std::fs::write(&json_file, json).unwrap();
+        let configs = BabysitterConfigFile::from_file_all(&json_file).unwrap();
+        std::fs::remove_file(&json_file).unwrap();
+
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].port, 8100);
+    }
+
+    #[test]
+    fn test_hooks_settings() {
+        let toml = r#"
+port = 8100
+[babysitter.hooks]
+pre_start = "nvidia-smi -r"
+on_crash = "notify.sh"
+[backend]
+type = "mock"
+models = ["model-a"]
+"#;
+
+        let config: BabysitterConfigFile = toml::from_str(toml).unwrap();
+        let hooks = &config.babysitter.hooks;
+        assert_eq!(hooks.pre_start.as_deref(), Some("nvidia-smi -r"));
+        assert_eq!(hooks.on_crash.as_deref(), Some("notify.sh"));
+        assert!(hooks.post_start.is_none());
+        assert_eq!(hooks.timeout, 60);
+    }
+
+    #[test]
+    fn test_shutdown_timeout_alias() {
+        let toml = r#"
+port = 8100
+[babysitter]
+shutdown_timeout = 45
+[backend]
+type = "mock"
+models = ["model-a"]
+"#;
+
+        let config: BabysitterConfigFile = toml::from_str(toml).unwrap();
+        let cli = config.to_cli_config();
+        assert_eq!(cli.shutdown_grace_period, 45);
+        assert_eq!(cli.port_release_timeout, 10);
+    }
+
+    #[test]
+    fn test_from_file_all_rejects_babysitter_port_collision() {
+        let services = |second: &str| {
+            format!(
+                r#"
+[[services]]
+port = 8100
+[services.backend]
+type = "mock"
+models = ["model-a"]
+
+[[services]]
+{}
+[services.backend]
+type = "mock"
+models = ["model-b"]
+"#,
+                second
+            )
+        };
+
+        let temp_file = std::env::temp_dir().join("test_babysitter_port_collision.toml");
+
+        // 8101 is the first service's default babysitter port
+        std::fs::write(&temp_file, services("port = 8101")).unwrap();
+        assert!(BabysitterConfigFile::from_file_all(&temp_file).is_err());
+
+        std::fs::write(&temp_file, services("port = 8102\nbabysitter_port = 9102")).unwrap();
+        let configs = BabysitterConfigFile::from_file_all(&temp_file).unwrap();
+        std::fs::remove_file(&temp_file).unwrap();
+        assert_eq!(configs[1].to_cli_config().babysitter_port, Some(9102));
+    }
+}
@@ -0,0 +1,102 @@
+//! GPU fault detection for the devices assigned to the managed service
+
+/// Health-relevant readings of one GPU (requires the `nvml` feature)
+#[derive(Debug, Clone, Default)]
+pub struct GpuReading {
+    pub index: u32,
+    pub temperature_c: Option<u32>,
+    /// Uncorrected ECC errors since the driver loaded
+    pub ecc_uncorrected: Option<u64>,
+    pub memory_free_bytes: Option<u64>,
+}
+
+/// Limits beyond which a GPU is considered faulty
+#[derive(Debug, Clone, Default)]
+pub struct GpuLimits {
+    pub max_temperature_c: Option<u32>,
+    pub max_ecc_errors: u64,
+    pub min_free_memory_bytes: Option<u64>,
+}
+
+/// Describe every fault among `readings`; each of `expected` devices must be visible
+pub fn gpu_faults(readings: &[GpuReading], expected: &[u32], limits: &GpuLimits) -> Vec<String> {
+    let mut faults = Vec::new();
+    for index in expected {
+        if !readings.iter().any(|r| r.index == *index) {
+            faults.push(format!("GPU {} is not visible", index));
+        }
+    }
+    for reading in readings {
+        if let (Some(temperature), Some(limit)) = (reading.temperature_c, limits.max_temperature_c)
+        {
+            if temperature > limit {
+                faults.push(format!(
+                    "GPU {} temperature {}C above {}C",
+                    reading.index, temperature, limit
+                ));
+            }
+        }
+        if let Some(errors) = reading.ecc_uncorrected {
+            if errors > limits.max_ecc_errors {
+                faults.push(format!(
+                    "GPU {} has {} uncorrected ECC errors",
+                    reading.index, errors
+                ));
+            }
+        }
+        if let (Some(free), Some(min_free)) =
+            (reading.memory_free_bytes, limits.min_free_memory_bytes)
+        {
+            if free < min_free {
+                faults.push(format!(
+                    "GPU {} has {} MiB free memory, below {} MiB",
+                    reading.index,
+                    free >> 20,
+                    min_free >> 20
+                ));
+            }
+        }
+    }
+    faults
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpu_faults() {
+        let limits = GpuLimits {
+            max_temperature_c: Some(85),
+            max_ecc_errors: 0,
+            min_free_memory_bytes: Some(1 << 30),
+        };
+        let healthy = GpuReading {
+            index: 0,
+            temperature_c: Some(60),
+            ecc_uncorrected: Some(0),
+            memory_free_bytes: Some(4 << 30),
+        };
+        assert!(gpu_faults(std::slice::from_ref(&healthy), &[0], &limits).is_empty());
+
+        let faulty = GpuReading {
+            index: 1,
+            temperature_c: Some(90),
+            ecc_uncorrected: Some(2),
+            memory_free_bytes: Some(512 << 20),
+        };
+        let faults = gpu_faults(&[healthy, faulty], &[0, 1, 2], &limits);
+        assert_eq!(faults.len(), 4);
+        assert_eq!(faults[0], "GPU 2 is not visible");
+        assert!(faults[1].contains("temperature 90C"));
+        assert!(faults[2].contains("2 uncorrected ECC errors"));
+        assert!(faults[3].contains("512 MiB free"));
+
+        // Readings the driver could not provide are not faults
+        let unknown = GpuReading {
+            index: 0,
+            ..Default::default()
+        };
+        assert!(gpu_faults(&[unknown], &[0], &limits).is_empty());
+    }
+}
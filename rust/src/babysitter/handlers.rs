@@ -1,12 +1,44 @@
 //! HTTP handlers for the babysitter
 
-use axum::{extract::State, http::StatusCode, response::Json, routing::get, Router};
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
 use serde_json::json;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use tokio::net::TcpListener;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+use crate::babysitter::process_manager::ProcessManager;
+use crate::babysitter::registry_client::BabysitterRegistryClient;
 use crate::babysitter::BabysitterState;
+use crate::proxy::forwarded::peer_addr;
+use crate::utils::listen;
+
+/// Lines returned by /logs when the query does not say
+const DEFAULT_LOG_LINES: usize = 200;
+
+#[derive(Debug, Deserialize)]
+struct LogsQuery {
+    /// Number of most recent lines to return
+    lines: Option<usize>,
+    /// `stdout`, `stderr` or `all` (default)
+    stream: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateRequest {
+    /// New model path (vLLM model, InfiniLM-Rust config file)
+    path: Option<PathBuf>,
+    /// New extra backend args, replacing the configured ones
+    args: Option<String>,
+}
 
 pub struct BabysitterHandlers {
     state: Arc<BabysitterState>,
@@ -18,19 +50,38 @@ impl BabysitterHandlers {
     }
 
     pub async fn start_server(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let admin = middleware::from_fn_with_state(self.state.clone(), require_admin_token);
         let app = Router::new()
             .route("/health", get(Self::health_handler))
             .route("/models", get(Self::models_handler))
             .route("/info", get(Self::info_handler))
+            .route(
+                "/restart",
+                post(Self::restart_handler).route_layer(admin.clone()),
+            )
+            .route("/stop", post(Self::stop_handler).route_layer(admin.clone()))
+            .route(
+                "/start",
+                post(Self::start_handler).route_layer(admin.clone()),
+            )
+            .route("/logs", get(Self::logs_handler).route_layer(admin.clone()))
+            .route("/update", post(Self::update_handler).route_layer(admin))
             .with_state(self.state.clone());
+        if self.state.config.admin_token.is_none() {
+            warn!(
+                "No --admin-token set; /restart, /stop, /start and /logs only accept local clients and /update is disabled"
+            );
+        }
 
-        let port = self.state.babysitter_port();
-        let addr = format!("0.0.0.0:{}", port);
-        let listener = TcpListener::bind(&addr).await?;
+        let addrs = listen::bind_addrs(&self.state.config.bind, self.state.babysitter_port());
+        let listeners = listen::bind_all(&addrs)?;
 
-        info!("Babysitter HTTP server started on port {}", port);
+        info!(
+            "Babysitter HTTP server started on {}",
+            listen::describe(&addrs)
+        );
 
-        axum::serve(listener, app).await?;
+        listen::serve_all(listeners, app, std::future::pending()).await?;
         Ok(())
     }
 
@@ -118,6 +169,9 @@ impl BabysitterHandlers {
         };
 
         let uptime = state.start_time.elapsed().as_secs();
+        let resources = state.resource_usage().await;
+        let watchdog = state.watchdog.lock().unwrap().clone();
+        let restart_events = state.restart_events.lock().unwrap().clone();
 
         Ok(Json(json!({
             "name": state.config.service_name(),
@@ -125,9 +179,255 @@ impl BabysitterHandlers {
             "port": state.babysitter_port(),
             "url": format!("http://{}:{}", state.config.host, state.babysitter_port()),
             "service_type": state.config.service_type,
+            "model_path": state.model_path(),
+            "model_args": state.model_args(),
             "infinilm_server_port": service_port,
             "uptime": uptime,
-            "restart_count": restart_count
+            "restart_count": restart_count,
+            "last_exit_code": restart_events.last_exit_code,
+            "last_restart_time": restart_events.last_restart_time,
+            "resources": resources,
+            "gpu_faults": *state.gpu_faults.lock().unwrap(),
+            "memory_watchdog": {
+                "rss_limit_mb": state.config.memory_limit_mb,
+                "gpu_limit_mb": state.config.gpu_memory_limit_mb,
+                "sustain_seconds": state.config.memory_limit_sustain,
+                "restarts": watchdog.restarts,
+                "last_restart": watchdog.last_restart,
+                "last_reason": watchdog.last_reason
+            }
+        })))
+    }
+
+    /// Restart the managed service without counting it as a crash
+    async fn restart_handler(
+        State(state): State<Arc<BabysitterState>>,
+    ) -> Result<Json<serde_json::Value>, StatusCode> {
+        if state.control.stopped.load(Ordering::SeqCst) {
+            // A stopped service is simply started again
+            state.control.stopped.store(false, Ordering::SeqCst);
+            state.control.start_notify.notify_one();
+        } else {
+            state
+                .control
+                .restart_requested
+                .store(true, Ordering::SeqCst);
+            ProcessManager::new(state.clone()).stop_service().await;
+        }
+
+        info!(
+            "Restart of {} requested via API",
+            state.config.service_name()
+        );
+        Ok(Json(json!({
+            "status": "restarting",
+            "service": state.config.service_name()
+        })))
+    }
+
+    /// Stop the managed service and keep it stopped until /start is called
+    async fn stop_handler(
+        State(state): State<Arc<BabysitterState>>,
+    ) -> Result<Json<serde_json::Value>, StatusCode> {
+        state.control.stopped.store(true, Ordering::SeqCst);
+        ProcessManager::new(state.clone()).stop_service().await;
+
+        info!("Stop of {} requested via API", state.config.service_name());
+        Ok(Json(json!({
+            "status": "stopped",
+            "service": state.config.service_name()
+        })))
+    }
+
+    /// Recent output of the managed service, oldest first, so crash output can be
+    /// inspected without access to the node
+    async fn logs_handler(
+        State(state): State<Arc<BabysitterState>>,
+        Query(query): Query<LogsQuery>,
+    ) -> Result<Json<serde_json::Value>, StatusCode> {
+        let lines = query.lines.unwrap_or(DEFAULT_LOG_LINES);
+        let stream = query.stream.as_deref().unwrap_or("all");
+        if stream != "all" && stream != "stdout" && stream != "stderr" {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let mut entries: Vec<serde_json::Value> = Vec::new();
+        for (name, buffer) in [
+            ("stdout", &state.recent_stdout),
+            ("stderr", &state.recent_stderr),
+        ] {
+            if stream != "all" && stream != name {
+                continue;
+            }
+            entries.extend(buffer.tail_entries(lines).into_iter().map(|entry| {
+                json!({
+                    "timestamp": entry.timestamp,
+                    "stream": name,
+                    "line": entry.line,
+                })
+            }));
+        }
+
+        // Interleave both streams by arrival time, keeping only the most recent lines
+        entries.sort_by(|a, b| {
+            let a = a["timestamp"].as_f64().unwrap_or_default();
+            let b = b["timestamp"].as_f64().unwrap_or_default();
+            a.total_cmp(&b)
+        });
+        let skip = entries.len().saturating_sub(lines);
+        entries.drain(..skip);
+
+        Ok(Json(json!({
+            "service": state.config.service_name(),
+            "stream": stream,
+            "count": entries.len(),
+            "lines": entries
+        })))
+    }
+
+    /// Swap in a new model path and/or args, then drain and restart the service with
+    /// them; the service re-registers with whatever models the new process lists
+    async fn update_handler(
+        State(state): State<Arc<BabysitterState>>,
+        Json(request): Json<UpdateRequest>,
+    ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+        if request.path.is_none() && request.args.is_none() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        if request.args.is_some() && !state.config.allow_update_args {
+            warn!("Rejected /update replacing backend args (--allow-update-args not set)");
+            return Err(StatusCode::FORBIDDEN);
+        }
+        if state.control.updating.swap(true, Ordering::SeqCst) {
+            return Err(StatusCode::CONFLICT);
+        }
+
+        {
+            let mut model_override = state.model_override.write().unwrap();
+            if request.path.is_some() {
+                model_override.path = request.path.clone();
+            }
+            if request.args.is_some() {
+                model_override.args = request.args.clone();
+            }
+        }
+        info!(
+            "Update of {} requested via API (path: {:?}, args: {:?})",
+            state.config.service_name(),
+            state.model_path(),
+            state.model_args()
+        );
+
+        if state.control.stopped.load(Ordering::SeqCst) {
+            // The new settings are picked up by the next /start
+            state.control.updating.store(false, Ordering::SeqCst);
+            return Ok((
+                StatusCode::OK,
+                Json(json!({
+                    "status": "stopped",
+                    "service": state.config.service_name(),
+                    "message": "Service is stopped; the update applies on the next start"
+                })),
+            ));
+        }
+
+        // Draining takes a while, so the restart runs after the response is sent
+        tokio::spawn({
+            let state = state.clone();
+            async move {
+                let registry_client = state.config.registry_url.clone().and_then(|url| {
+                    BabysitterRegistryClient::new(url, state.clone())
+                        .map_err(|e| error!("Failed to create registry client: {}", e))
+                        .ok()
+                });
+                ProcessManager::new(state.clone())
+                    .planned_restart(registry_client.as_ref())
+                    .await;
+                state.control.updating.store(false, Ordering::SeqCst);
+            }
+        });
+
+        Ok((
+            StatusCode::ACCEPTED,
+            Json(json!({
+                "status": "updating",
+                "service": state.config.service_name(),
+                "path": state.model_path(),
+                "args": state.model_args()
+            })),
+        ))
+    }
+
+    /// Start a service previously stopped via /stop
+    async fn start_handler(
+        State(state): State<Arc<BabysitterState>>,
+    ) -> Result<Json<serde_json::Value>, StatusCode> {
+        if !state.control.stopped.swap(false, Ordering::SeqCst) {
+            return Ok(Json(json!({
+                "status": "running",
+                "service": state.config.service_name(),
+                "message": "Service is already running"
+            })));
+        }
+        state.control.start_notify.notify_one();
+
+        info!("Start of {} requested via API", state.config.service_name());
+        Ok(Json(json!({
+            "status": "starting",
+            "service": state.config.service_name()
         })))
     }
 }
+
+/// Reject control and log requests that do not carry the admin token; without a token configured,
+/// only clients on this host are accepted and /update (which decides what the backend
+/// runs) is refused outright
+async fn require_admin_token(
+    State(state): State<Arc<BabysitterState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match state.config.admin_token.as_deref() {
+        Some(token) => {
+            let presented = request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "));
+            if presented != Some(token) {
+                warn!(
+                    "Rejected unauthenticated {} {}",
+                    request.method(),
+                    request.uri().path()
+                );
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    Json(json!({"error": "Missing or invalid admin token"})),
+                )
+                    .into_response();
+            }
+        }
+        None if request.uri().path() == "/update" => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": "/update is disabled until an admin token is configured"})),
+            )
+                .into_response();
+        }
+        None => {
+            let local = peer_addr(request.extensions()).is_some_and(|addr| addr.is_loopback());
+            if !local {
+                warn!(
+                    "Rejected remote {} {} (no admin token configured)",
+                    request.method(),
+                    request.uri().path()
+                );
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(json!({"error": "Only local clients are accepted until an admin token is configured"})),
+                )
+                    .into_response();
+            }
+        }
+    }
+    next.run(request).await
+}
@@ -0,0 +1,85 @@
+//! Operator-configured lifecycle hook commands for the babysitter
+
+use crate::babysitter::BabysitterState;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+/// Lifecycle points at which a hook command can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    PreStart,
+    PostStart,
+    OnCrash,
+    PreStop,
+}
+
+impl Hook {
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::PreStart => "pre_start",
+            Hook::PostStart => "post_start",
+            Hook::OnCrash => "on_crash",
+            Hook::PreStop => "pre_stop",
+        }
+    }
+}
+
+/// Run the command configured for `hook` (if any) through `sh -c` and wait for it.
+/// The service name, ports, restart count and, for `on_crash`, the exit code are passed
+/// as `INFINI_*` environment variables. Failures are logged and never stop the lifecycle.
+pub async fn run_hook(state: &BabysitterState, hook: Hook, exit_code: Option<i32>) {
+    let Some(settings) = state.config_file.as_ref().map(|c| &c.babysitter.hooks) else {
+        return;
+    };
+    let command = match hook {
+        Hook::PreStart => &settings.pre_start,
+        Hook::PostStart => &settings.post_start,
+        Hook::OnCrash => &settings.on_crash,
+        Hook::PreStop => &settings.pre_stop,
+    };
+    let Some(command) = command else {
+        return;
+    };
+
+    let service_port = state
+        .service_port
+        .read()
+        .await
+        .unwrap_or_else(|| state.service_target_port());
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .env("INFINI_HOOK", hook.name())
+        .env("INFINI_SERVICE_NAME", state.config.service_name())
+        .env("INFINI_SERVICE_PORT", service_port.to_string())
+        .env(
+            "INFINI_BABYSITTER_PORT",
+            state.babysitter_port().to_string(),
+        )
+        .env(
+            "INFINI_RESTART_COUNT",
+            state.restart_count.read().await.to_string(),
+        )
+        .kill_on_drop(true);
+    if let Some(exit_code) = exit_code {
+        cmd.env("INFINI_EXIT_CODE", exit_code.to_string());
+    }
+    if let Some(work_dir) = &state.config.work_dir {
+        cmd.current_dir(work_dir);
+    }
+
+    info!("Running {} hook: {}", hook.name(), command);
+    let limit = Duration::from_secs(settings.timeout);
+    match timeout(limit, cmd.status()).await {
+        Ok(Ok(status)) if status.success() => {}
+        Ok(Ok(status)) => warn!("{} hook exited with {}", hook.name(), status),
+        Ok(Err(e)) => warn!("Failed to run {} hook: {}", hook.name(), e),
+        Err(_) => warn!(
+            "{} hook did not finish within {}s, killed",
+            hook.name(),
+            settings.timeout
+        ),
+    }
+}
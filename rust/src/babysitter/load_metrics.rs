@@ -0,0 +1,103 @@
+//! Runtime load of the managed service, scraped from its Prometheus metrics endpoint
+//!
+//! The managed service's heartbeat carries these as `metadata.load` so routers can see
+//! backend saturation. vLLM and SGLang metric names are recognized; gauges exported per
+//! engine (label set) are summed, and cache utilization takes the highest engine.
+
+use serde::Serialize;
+
+/// Metrics counted as requests waiting in the backend's queue
+const QUEUE_DEPTH_METRICS: &[&str] = &["vllm:num_requests_waiting", "sglang:num_queue_reqs"];
+
+/// Metrics counted as requests currently being processed
+const IN_FLIGHT_METRICS: &[&str] = &["vllm:num_requests_running", "sglang:num_running_reqs"];
+
+/// Metrics giving KV-cache utilization as a fraction (0.0-1.0)
+const KV_CACHE_METRICS: &[&str] = &[
+    "vllm:gpu_cache_usage_perc",
+    "vllm:kv_cache_usage_perc",
+    "sglang:token_usage",
+];
+
+/// Load reported by the backend; fields it does not export are left out
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LoadMetrics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_depth: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_flight: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kv_cache_utilization: Option<f64>,
+}
+
+impl LoadMetrics {
+    /// Extract the load gauges from a Prometheus text exposition
+    pub fn parse(text: &str) -> Self {
+        let mut load = LoadMetrics::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = parse_sample(line) else {
+                continue;
+            };
+            if QUEUE_DEPTH_METRICS.contains(&name) {
+                *load.queue_depth.get_or_insert(0) += value.max(0.0) as u64;
+            } else if IN_FLIGHT_METRICS.contains(&name) {
+                *load.in_flight.get_or_insert(0) += value.max(0.0) as u64;
+            } else if KV_CACHE_METRICS.contains(&name) {
+                let value = value.clamp(0.0, 1.0);
+                load.kv_cache_utilization =
+                    Some(load.kv_cache_utilization.map_or(value, |v| v.max(value)));
+            }
+        }
+        load
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == LoadMetrics::default()
+    }
+}
+
+/// Metric name and value of one sample line (`name{labels} value [timestamp]`)
+fn parse_sample(line: &str) -> Option<(&str, f64)> {
+    let (name, rest) = match line.find(['{', ' ']) {
+        Some(i) if line.as_bytes()[i] == b'{' => {
+            let close = line[i..].find('}')? + i;
+            (&line[..i], &line[close + 1..])
+        }
+        Some(i) => (&line[..i], &line[i..]),
+        None => return None,
+    };
+    let value = rest.split_whitespace().next()?.parse::<f64>().ok()?;
+    value.is_finite().then_some((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vllm_metrics() {
+        let text = r#"
+# HELP vllm:num_requests_running Number of requests currently running on GPU.
+# TYPE vllm:num_requests_running gauge
+vllm:num_requests_running{engine="0",model_name="qwen"} 3.0
+vllm:num_requests_running{engine="1",model_name="qwen"} 2.0
+vllm:num_requests_waiting{engine="0",model_name="qwen"} 7.0
+vllm:gpu_cache_usage_perc{engine="0",model_name="qwen"} 0.42
+vllm:gpu_cache_usage_perc{engine="1",model_name="qwen"} 0.61
+vllm:prompt_tokens_total{model_name="qwen"} 12345.0
+"#;
+        assert_eq!(
+            LoadMetrics::parse(text),
+            LoadMetrics {
+                queue_depth: Some(7),
+                in_flight: Some(5),
+                kv_cache_utilization: Some(0.61),
+            }
+        );
+        assert!(LoadMetrics::parse("process_cpu_seconds_total 1.5\n").is_empty());
+    }
+}
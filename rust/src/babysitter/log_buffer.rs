@@ -0,0 +1,78 @@
+//! Bounded in-memory buffer of recent output lines from the managed service
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One captured output line
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    /// Seconds since the Unix epoch when the line was read
+    pub timestamp: f64,
+    pub line: String,
+}
+
+/// Keeps the most recent `capacity` lines, dropping the oldest first
+pub struct LogBuffer {
+    capacity: usize,
+    lines: Mutex<VecDeque<LogLine>>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(LogLine { timestamp, line });
+    }
+
+    /// The last `n` lines, oldest first
+    pub fn tail(&self, n: usize) -> Vec<String> {
+        self.tail_entries(n).into_iter().map(|l| l.line).collect()
+    }
+
+    /// The last `n` lines with their timestamps, oldest first
+    pub fn tail_entries(&self, n: usize) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_most_recent_lines() {
+        let buffer = LogBuffer::new(3);
+        for i in 0..5 {
+            buffer.push(format!("line {}", i));
+        }
+        assert_eq!(buffer.tail(10), vec!["line 2", "line 3", "line 4"]);
+        assert_eq!(buffer.tail(2), vec!["line 3", "line 4"]);
+
+        let disabled = LogBuffer::new(0);
+        disabled.push("ignored".to_string());
+        assert!(disabled.tail(1).is_empty());
+    }
+}
@@ -1,16 +1,32 @@
 //! Babysitter module for service lifecycle management
 
+pub mod alerts;
 pub mod config;
 pub mod config_file;
+pub mod gpu_health;
 pub mod handlers;
+pub mod hooks;
+pub mod load_metrics;
+pub mod log_buffer;
+pub mod port_guard;
 pub mod process_manager;
 pub mod registry_client;
+pub mod router_drain;
+pub mod schedule;
+pub mod service;
+pub mod telemetry;
+pub mod watchdog;
 
-use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::RwLock;
 use config::BabysitterConfig;
 use config_file::BabysitterConfigFile;
+use log_buffer::LogBuffer;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+use telemetry::{ResourceMonitor, ResourceUsage};
+use tokio::sync::{Notify, RwLock};
+use watchdog::WatchdogReport;
 
 /// Shared state for the babysitter
 #[derive(Clone)]
@@ -18,17 +34,124 @@ pub struct BabysitterState {
     pub config: BabysitterConfig,
     pub config_file: Option<BabysitterConfigFile>,
     pub process: Arc<RwLock<Option<tokio::process::Child>>>,
+    /// Process group of the managed service, kept after the leader is reaped so its
+    /// grandchildren can still be signalled
+    pub process_group: Arc<std::sync::Mutex<Option<u32>>>,
     pub service_port: Arc<RwLock<Option<u16>>>,
     pub start_time: Instant,
     pub restart_count: Arc<RwLock<u32>>,
+    pub resource_monitor: Arc<ResourceMonitor>,
+    pub control: Arc<ProcessControl>,
+    /// Recent stdout lines of the managed service (served by /logs)
+    pub recent_stdout: Arc<LogBuffer>,
+    /// Recent stderr lines of the managed service (served by /logs, included in alerts)
+    pub recent_stderr: Arc<LogBuffer>,
+    /// Restarts triggered by the memory watchdog
+    pub watchdog: Arc<std::sync::Mutex<WatchdogReport>>,
+    /// GPU faults found by the latest GPU check; the service is reported unhealthy
+    /// while any are present
+    pub gpu_faults: Arc<std::sync::Mutex<Vec<String>>>,
+    /// Model path and args swapped in via /update; they take effect on the next start
+    pub model_override: Arc<std::sync::RwLock<ModelOverride>>,
+    /// Last crash of the managed service, reported to the registry with restart_count
+    pub restart_events: Arc<std::sync::Mutex<RestartEvents>>,
+}
+
+/// Last crash restart of the managed service
+#[derive(Debug, Clone, Default)]
+pub struct RestartEvents {
+    /// Exit code of the crashed process (None if it was killed by a signal)
+    pub last_exit_code: Option<i32>,
+    /// Unix time of the crash restart
+    pub last_restart_time: Option<f64>,
+}
+
+/// Replacement launch settings for the managed service, set by /update
+#[derive(Debug, Clone, Default)]
+pub struct ModelOverride {
+    pub path: Option<PathBuf>,
+    pub args: Option<String>,
+}
+
+/// Operator-requested lifecycle changes for the managed service
+#[derive(Default)]
+pub struct ProcessControl {
+    /// Service was stopped on request and must not be restarted automatically
+    pub stopped: AtomicBool,
+    /// The next process exit was requested and must not count as a crash
+    pub restart_requested: AtomicBool,
+    /// Wakes the process manager when a stopped service should start again
+    pub start_notify: Notify,
+    /// A model update is draining and restarting the service
+    pub updating: AtomicBool,
 }
 
 impl BabysitterState {
+    /// Fresh state for a service that has not been started yet
+    pub fn new(config: BabysitterConfig, config_file: Option<BabysitterConfigFile>) -> Self {
+        let log_buffer_lines = config.log_buffer_lines;
+        Self {
+            config,
+            config_file,
+            process: Arc::new(RwLock::new(None)),
+            process_group: Arc::new(std::sync::Mutex::new(None)),
+            service_port: Arc::new(RwLock::new(None)),
+            start_time: Instant::now(),
+            restart_count: Arc::new(RwLock::new(0)),
+            resource_monitor: Arc::new(ResourceMonitor::new()),
+            control: Arc::new(ProcessControl::default()),
+            recent_stdout: Arc::new(LogBuffer::new(log_buffer_lines)),
+            recent_stderr: Arc::new(LogBuffer::new(log_buffer_lines)),
+            watchdog: Arc::new(std::sync::Mutex::new(WatchdogReport::default())),
+            gpu_faults: Arc::new(std::sync::Mutex::new(Vec::new())),
+            model_override: Arc::new(std::sync::RwLock::new(ModelOverride::default())),
+            restart_events: Arc::new(std::sync::Mutex::new(RestartEvents::default())),
+        }
+    }
+
     pub fn babysitter_port(&self) -> u16 {
-        self.config.port.expect("Port must be set") + 1
+        self.config
+            .babysitter_port
+            .unwrap_or_else(|| self.config.port.expect("Port must be set") + 1)
     }
 
     pub fn service_target_port(&self) -> u16 {
         self.config.port.expect("Port must be set")
     }
+
+    /// Model path to launch, preferring one set via /update
+    pub fn model_path(&self) -> Option<PathBuf> {
+        let model_override = self.model_override.read().unwrap();
+        model_override
+            .path
+            .clone()
+            .or_else(|| self.config.path.clone())
+    }
+
+    /// Extra backend args to launch with, preferring ones set via /update
+    pub fn model_args(&self) -> Option<String> {
+        let model_override = self.model_override.read().unwrap();
+        model_override
+            .args
+            .clone()
+            .or_else(|| self.config.args.clone())
+    }
+
+    /// Restart churn reported in the managed service's registry metadata, so routers can
+    /// show it and deprioritize flapping services
+    pub async fn restart_metadata(&self) -> serde_json::Value {
+        let restart_count = *self.restart_count.read().await;
+        let events = self.restart_events.lock().unwrap().clone();
+        serde_json::json!({
+            "restart_count": restart_count,
+            "last_exit_code": events.last_exit_code,
+            "last_restart_time": events.last_restart_time,
+        })
+    }
+
+    /// Sample resource usage of the managed process, if it is running
+    pub async fn resource_usage(&self) -> Option<ResourceUsage> {
+        let pid = self.process.read().await.as_ref().and_then(|p| p.id())?;
+        Some(self.resource_monitor.sample(pid))
+    }
 }
@@ -0,0 +1,190 @@
+//! Detection of stale listeners (e.g. orphaned children of a crashed babysitter) on the
+//! service port before the service is started
+
+use std::net::TcpListener;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+/// A process found listening on the service port
+#[derive(Debug, Clone)]
+pub struct PortHolder {
+    pub pid: u32,
+    pub command: String,
+}
+
+/// Whether something already listens on `port`
+pub fn port_in_use(port: u16) -> bool {
+    TcpListener::bind(("0.0.0.0", port)).is_err()
+}
+
+/// Wait up to `timeout` for `port` to be released (e.g. by a previous process that is
+/// still shutting down); returns whether it is free
+pub async fn wait_port_released(port: u16, timeout: Duration) -> bool {
+    let started = std::time::Instant::now();
+    while port_in_use(port) {
+        if started.elapsed() >= timeout {
+            return false;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    true
+}
+
+/// Make sure `port` is free before the service is started. With `kill_orphans`, a
+/// process holding it is terminated (SIGTERM, then SIGKILL); otherwise, or when it
+/// cannot be freed, a descriptive error is returned.
+pub async fn ensure_port_free(port: u16, kill_orphans: bool) -> Result<(), String> {
+    if !port_in_use(port) {
+        return Ok(());
+    }
+
+    let holders = find_listeners(port);
+    let described = if holders.is_empty() {
+        "an unidentified process".to_string()
+    } else {
+        holders
+            .iter()
+            .map(|h| format!("PID {} ({})", h.pid, h.command))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if !kill_orphans || holders.is_empty() {
+        return Err(format!(
+            "Port {} is already in use by {}; stop it or enable kill_orphans",
+            port, described
+        ));
+    }
+
+    warn!("Port {} is held by {}, terminating it", port, described);
+    for signal in [SIGTERM, SIGKILL] {
+        for holder in &holders {
+            send_signal(holder.pid, signal);
+        }
+        for _ in 0..50 {
+            sleep(Duration::from_millis(100)).await;
+            if !port_in_use(port) {
+                info!("Port {} released", port);
+                return Ok(());
+            }
+        }
+    }
+    Err(format!(
+        "Port {} is still in use after killing {}",
+        port, described
+    ))
+}
+
+#[cfg(unix)]
+const SIGTERM: i32 = libc::SIGTERM;
+#[cfg(unix)]
+const SIGKILL: i32 = libc::SIGKILL;
+#[cfg(not(unix))]
+const SIGTERM: i32 = 15;
+#[cfg(not(unix))]
+const SIGKILL: i32 = 9;
+
+fn send_signal(pid: u32, signal: i32) {
+    #[cfg(unix)]
+    {
+        // SAFETY: kill has no memory-safety preconditions
+        if unsafe { libc::kill(pid as libc::pid_t, signal) } != 0 {
+            warn!(
+                "Failed to signal PID {}: {}",
+                pid,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (pid, signal);
+}
+
+/// Processes listening on `port`, found through /proc (Linux only; empty elsewhere)
+pub fn find_listeners(port: u16) -> Vec<PortHolder> {
+    let mut inodes = Vec::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(contents) = std::fs::read_to_string(table) {
+            inodes.extend(listening_inodes(&contents, port));
+        }
+    }
+    if inodes.is_empty() {
+        return Vec::new();
+    }
+
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut holders = Vec::new();
+    for entry in processes.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let holds_socket = fds.flatten().any(|fd| {
+            std::fs::read_link(fd.path())
+                .ok()
+                .and_then(|target| {
+                    let target = target.to_string_lossy().into_owned();
+                    target
+                        .strip_prefix("socket:[")
+                        .and_then(|rest| rest.strip_suffix(']'))
+                        .and_then(|inode| inode.parse::<u64>().ok())
+                })
+                .is_some_and(|inode| inodes.contains(&inode))
+        });
+        if holds_socket {
+            let command = std::fs::read(entry.path().join("cmdline"))
+                .map(|raw| {
+                    String::from_utf8_lossy(&raw)
+                        .split('\0')
+                        .filter(|s| !s.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .unwrap_or_default();
+            holders.push(PortHolder { pid, command });
+        }
+    }
+    holders
+}
+
+/// Socket inodes of listening sockets on `port` in a /proc/net/tcp{,6} table
+fn listening_inodes(table: &str, port: u16) -> Vec<u64> {
+    const TCP_LISTEN: &str = "0A";
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let local_port = fields.get(1)?.rsplit(':').next()?;
+            if u16::from_str_radix(local_port, 16).ok()? != port || *fields.get(3)? != TCP_LISTEN {
+                return None;
+            }
+            fields.get(9)?.parse().ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listening_inodes() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:1FA4 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 662 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1FA4 0100007F:D431 01 00000000:00000000 00:00000000 00000000     0        0 900 1 0000000000000000 20 4 30 10 -1
+   2: 0100007F:1FA5 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 926 1 0000000000000000 100 0 0 10 0";
+        // 0x1FA4 = 8100; the established connection on the same port is not a listener
+        assert_eq!(listening_inodes(table, 8100), vec![662]);
+        assert_eq!(listening_inodes(table, 8101), vec![926]);
+        assert!(listening_inodes(table, 8102).is_empty());
+    }
+}
@@ -1,13 +1,25 @@
 //! Process management for the babysitter
 
+use crate::babysitter::alerts::{AlertNotifier, CrashLoopDetector};
+use crate::babysitter::gpu_health::{gpu_faults, GpuLimits};
+use crate::babysitter::hooks::{run_hook, Hook};
+use crate::babysitter::port_guard::{ensure_port_free, wait_port_released};
+use crate::babysitter::registry_client::BabysitterRegistryClient;
+use crate::babysitter::router_drain::RouterDrain;
+use crate::babysitter::watchdog::MemoryWatchdog;
 use crate::babysitter::BabysitterState;
+use rand::Rng;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::process::{Command, Stdio};
-use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command as TokioCommand;
+use tokio::process::{Child, Command as TokioCommand};
 use tokio::time::{sleep, timeout};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
 pub struct ProcessManager {
     state: Arc<BabysitterState>,
@@ -19,14 +31,90 @@ impl ProcessManager {
     }
 
     pub async fn run(&self) {
+        let config = &self.state.config;
+        let stability_window = Duration::from_secs(config.stability_window);
+        let mut consecutive_crashes: u32 = 0;
+        let alerts = AlertNotifier::new(config.alert_webhook_url.clone());
+        let mut crash_loop = CrashLoopDetector::new(
+            config.crash_loop_threshold,
+            Duration::from_secs(config.crash_loop_window),
+        );
+
         loop {
+            // Stay idle while the service is stopped on request
+            while self.state.control.stopped.load(Ordering::SeqCst) {
+                self.state.control.start_notify.notified().await;
+            }
+
+            let started_at = std::time::Instant::now();
+
             // Start the service
             if let Err(e) = self.start_service().await {
                 error!("Failed to start service: {}", e);
             }
 
             // Monitor the service
-            self.monitor_service().await;
+            let exit_code = self.monitor_service().await;
+
+            // Exits requested through the control endpoints are not crashes
+            if self.state.control.stopped.load(Ordering::SeqCst) {
+                info!("Service stopped on request");
+                continue;
+            }
+            if self
+                .state
+                .control
+                .restart_requested
+                .swap(false, Ordering::SeqCst)
+            {
+                info!("Restarting service on request");
+                consecutive_crashes = 0;
+                continue;
+            }
+
+            {
+                let mut events = self.state.restart_events.lock().unwrap();
+                events.last_exit_code = exit_code;
+                events.last_restart_time = Some(
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs_f64(),
+                );
+            }
+            run_hook(&self.state, Hook::OnCrash, exit_code).await;
+
+            if let Some(crashes) = crash_loop.record_crash(std::time::Instant::now()) {
+                error!(
+                    "Service is crash-looping ({} crashes within {}s)",
+                    crashes, config.crash_loop_window
+                );
+                alerts
+                    .send(
+                        &self.state,
+                        "crash_loop",
+                        &format!(
+                            "Service crashed {} times within {}s",
+                            crashes, config.crash_loop_window
+                        ),
+                        json!({
+                            "crashes": crashes,
+                            "window_seconds": config.crash_loop_window,
+                        }),
+                    )
+                    .await;
+            }
+
+            // A service that stayed up long enough gets its restart budget back
+            let uptime = started_at.elapsed();
+            if uptime >= stability_window {
+                info!(
+                    "Service was stable for {:?}, resetting restart counter",
+                    uptime
+                );
+                consecutive_crashes = 0;
+                *self.state.restart_count.write().await = 0;
+            }
 
             // Check restart limit
             let restart_count = {
@@ -39,6 +127,17 @@ impl ProcessManager {
                     "Maximum restart limit ({}) reached",
                     self.state.config.max_restarts
                 );
+                alerts
+                    .send(
+                        &self.state,
+                        "max_restarts_exhausted",
+                        &format!(
+                            "Maximum restart limit ({}) reached, giving up",
+                            self.state.config.max_restarts
+                        ),
+                        json!({ "max_restarts": self.state.config.max_restarts }),
+                    )
+                    .await;
                 break;
             }
 
@@ -48,30 +147,123 @@ impl ProcessManager {
                 *count += 1;
             }
 
+            let delay = with_jitter(backoff_delay(
+                Duration::from_secs(self.state.config.restart_delay),
+                consecutive_crashes,
+                Duration::from_secs(self.state.config.max_restart_delay),
+            ));
+            consecutive_crashes = consecutive_crashes.saturating_add(1);
+
             info!(
-                "Service crashed, restarting in {} seconds... (restart {}/{})",
-                self.state.config.restart_delay,
+                "Service crashed, restarting in {:.1} seconds... (restart {}/{})",
+                delay.as_secs_f64(),
                 restart_count + 1,
                 self.state.config.max_restarts
             );
 
-            sleep(Duration::from_secs(self.state.config.restart_delay)).await;
+            sleep(delay).await;
+        }
+    }
+
+    /// Stop the managed child (if any), giving it a chance to exit cleanly
+    pub async fn stop_service(&self) {
+        let running = self
+            .state
+            .process
+            .write()
+            .await
+            .as_mut()
+            .is_some_and(|child| matches!(child.try_wait(), Ok(None)));
+        if running {
+            run_hook(&self.state, Hook::PreStop, None).await;
+        }
+        *self.state.service_port.write().await = None;
+        let child = self.state.process.write().await.take();
+        let process_group = self.state.process_group.lock().unwrap().take();
+        if let Some(mut child) = child {
+            let grace_period = Duration::from_secs(self.state.config.shutdown_grace_period);
+            terminate_child(&mut child, process_group, grace_period).await;
+        }
+    }
+
+    /// Stop the service for a deliberate restart, which the run loop starts again right
+    /// away instead of counting it as a crash
+    async fn restart_in_place(&self) {
+        self.state
+            .control
+            .restart_requested
+            .store(true, Ordering::SeqCst);
+        self.stop_service().await;
+    }
+
+    /// Restart the service without dropping client traffic: mark it draining on the
+    /// routers and deregister it, wait for in-flight requests to finish (or the drain
+    /// period to pass), restart, then register it again and end the drain
+    pub async fn planned_restart(&self, registry_client: Option<&BabysitterRegistryClient>) {
+        let drain = RouterDrain::new(
+            self.state.config.router_url.as_deref(),
+            self.state.config.router_token.as_deref(),
+            format!("{}-server", self.state.config.service_name()),
+        );
+        let drain_period = Duration::from_secs(self.state.config.restart_drain_period);
+
+        drain.begin().await;
+        if let Some(registry_client) = registry_client {
+            registry_client.deregister_managed_service().await;
         }
+        if !drain.is_empty() {
+            drain.wait_idle(drain_period).await;
+        } else if registry_client.is_some() {
+            // Without routers to ask, give them the whole period to notice
+            sleep(drain_period).await;
+        }
+
+        self.state
+            .control
+            .restart_requested
+            .store(true, Ordering::SeqCst);
+        self.stop_service().await;
+
+        if registry_client.is_none() && drain.is_empty() {
+            return;
+        }
+        let registry_client = registry_client.cloned();
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            match registry_client {
+                Some(registry_client) => registry_client.register_managed_service().await,
+                None => {
+                    while state.service_port.read().await.is_none() {
+                        sleep(Duration::from_millis(100)).await;
+                    }
+                }
+            }
+            drain.end().await;
+        });
     }
 
     async fn start_service(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Clean up any existing process before starting a new one
-        {
-            let mut process = self.state.process.write().await;
-            if let Some(mut child) = process.take() {
-                let _ = child.kill().await;
-                let _ = child.wait().await;
-                info!("Cleaned up previous process");
-            }
+        if self.state.process.read().await.is_some() {
+            self.stop_service().await;
+            info!("Cleaned up previous process");
         }
 
         info!("Starting {} service...", self.state.config.service_type);
 
+        // The previous process (or its children) may take a moment to release the port
+        let port = self.state.service_target_port();
+        let release_timeout = Duration::from_secs(self.state.config.port_release_timeout);
+        if !wait_port_released(port, release_timeout).await {
+            warn!(
+                "Port {} was not released within {:?} of the previous process stopping",
+                port, release_timeout
+            );
+        }
+
+        // A stale listener would otherwise be "detected" as the freshly started service
+        ensure_port_free(port, self.state.config.kill_orphans).await?;
+
         let mut cmd = if self.state.config.is_command_based() {
             self.build_command_based()?
         } else if self.state.config.service_type == "InfiniLM-Rust" {
@@ -91,22 +283,29 @@ impl ProcessManager {
             cmd.current_dir(work_dir);
         }
 
-        // Set environment variables from config file if available
+        // Expose the assigned GPUs, then apply the config file env and --env on top
+        // (explicit variables win on conflicts)
+        let mut env_vars = HashMap::new();
+        let gpus = self.state.config.gpu_devices();
+        if !gpus.is_empty() {
+            env_vars.insert(self.state.config.gpu_env_var.clone(), gpus.join(","));
+        }
         if let Some(config_file) = &self.state.config_file {
-            let env_vars = config_file.backend_env();
-            if !env_vars.is_empty() {
-                info!("Setting {} environment variables from config file", env_vars.len());
-                for (key, value) in &env_vars {
-                    info!("  {}={}", key, value);
-                }
-                // Inherit parent environment and merge with config env vars
-                cmd.envs(std::env::vars());
-                cmd.envs(env_vars);
-            } else {
-                warn!("Config file has no environment variables");
-            }
-        } else {
-            warn!("No config file available for environment variables");
+            env_vars.extend(config_file.backend_env());
+        }
+        env_vars.extend(self.state.config.env_vars());
+        if !env_vars.is_empty() {
+            // Values often carry API keys and tokens, so only the names are logged
+            let mut names: Vec<&str> = env_vars.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            info!(
+                "Setting {} environment variables: {}",
+                env_vars.len(),
+                names.join(", ")
+            );
+            // Inherit parent environment and merge with configured env vars
+            cmd.envs(std::env::vars());
+            cmd.envs(env_vars);
         }
 
         // Convert std::process::Command to tokio::process::Command for async I/O
@@ -125,25 +324,42 @@ impl ProcessManager {
         tokio_cmd.stdout(Stdio::piped());
         tokio_cmd.stderr(Stdio::piped());
 
+        // Run the service in its own process group so wrappers (python -m, shell scripts)
+        // and their grandchildren can be terminated together
+        #[cfg(unix)]
+        tokio_cmd.process_group(0);
+
+        run_hook(&self.state, Hook::PreStart, None).await;
+
         // Start the process
         let mut child = tokio_cmd.spawn()?;
 
         let pid = child.id().expect("Failed to get process ID");
         info!("Service started with PID: {}", pid);
+        *self.state.process_group.lock().unwrap() = Some(pid);
 
         // Capture stdout and stderr for logging
         let stdout = child.stdout.take();
         let stderr = child.stderr.take();
         let service_name = self.state.config.service_name().clone();
 
+        // Optionally scrape the port the child reports in its logs
+        let port_pattern = self.port_log_pattern();
+        let log_port: Arc<Mutex<Option<u16>>> = Arc::new(Mutex::new(None));
+
         // Spawn task to read stdout
         if let Some(stdout) = stdout {
             let service_name_clone = service_name.clone();
+            let port_pattern = port_pattern.clone();
+            let log_port = log_port.clone();
+            let recent_stdout = self.state.recent_stdout.clone();
             tokio::spawn(async move {
                 let reader = BufReader::new(stdout);
                 let mut lines = reader.lines();
                 while let Ok(Some(line)) = lines.next_line().await {
                     info!("[{} stdout] {}", service_name_clone, line);
+                    scrape_port(port_pattern.as_deref(), &line, &log_port);
+                    recent_stdout.push(line);
                 }
             });
         }
@@ -151,11 +367,16 @@ impl ProcessManager {
         // Spawn task to read stderr
         if let Some(stderr) = stderr {
             let service_name_clone = service_name.clone();
+            let port_pattern = port_pattern.clone();
+            let log_port = log_port.clone();
+            let recent_stderr = self.state.recent_stderr.clone();
             tokio::spawn(async move {
                 let reader = BufReader::new(stderr);
                 let mut lines = reader.lines();
                 while let Ok(Some(line)) = lines.next_line().await {
                     warn!("[{} stderr] {}", service_name_clone, line);
+                    scrape_port(port_pattern.as_deref(), &line, &log_port);
+                    recent_stderr.push(line);
                 }
             });
         }
@@ -167,7 +388,8 @@ impl ProcessManager {
         }
 
         // Detect service port
-        self.detect_service_port().await;
+        self.detect_service_port(&log_port).await;
+        run_hook(&self.state, Hook::PostStart, None).await;
 
         Ok(())
     }
@@ -192,7 +414,7 @@ impl ProcessManager {
         }
 
         // Add additional args if provided
-        if let Some(args_str) = &self.state.config.args {
+        if let Some(args_str) = self.state.model_args() {
             for arg in args_str.split_whitespace() {
                 cmd.arg(arg);
             }
@@ -207,9 +429,7 @@ impl ProcessManager {
     fn build_rust_command(&self) -> Result<Command, Box<dyn std::error::Error + Send + Sync>> {
         let path = self
             .state
-            .config
-            .path
-            .as_ref()
+            .model_path()
             .ok_or_else(|| "Path not specified for InfiniLM-Rust service".to_string())?;
 
         let mut cmd = Command::new("xtask");
@@ -236,9 +456,7 @@ impl ProcessManager {
         // vLLM backend support
         let path = self
             .state
-            .config
-            .path
-            .as_ref()
+            .model_path()
             .ok_or_else(|| "Model path not specified for vLLM service".to_string())?;
 
         let mut cmd = Command::new("python3");
@@ -252,7 +470,7 @@ impl ProcessManager {
             .arg(&self.state.config.host);
 
         // Add optional vLLM arguments if provided
-        if let Some(args_str) = &self.state.config.args {
+        if let Some(args_str) = self.state.model_args() {
             for arg in args_str.split_whitespace() {
                 cmd.arg(arg);
             }
@@ -262,10 +480,13 @@ impl ProcessManager {
     }
 
     fn build_mock_command(&self) -> Result<Command, Box<dyn std::error::Error + Send + Sync>> {
-        // Mock backend support - can use the mock_service.py from integration tests
-        let mut cmd = Command::new("python3");
+        // Prefer the infini-mock binary installed next to the babysitter
+        let mock_binary = std::env::current_exe()
+            .ok()
+            .map(|exe| exe.with_file_name("infini-mock"))
+            .filter(|path| path.exists());
 
-        // Try to find mock_service.py
+        // Otherwise fall back to mock_service.py from the integration tests
         let mock_script = std::env::current_dir().ok().and_then(|d| {
             let paths = vec![
                 d.join("rust/tests/integration/mock_service.py"),
@@ -275,12 +496,17 @@ impl ProcessManager {
             paths.into_iter().find(|p| p.exists())
         });
 
-        if let Some(script) = mock_script {
+        let is_binary = mock_binary.is_some();
+        let mut cmd = if let Some(binary) = mock_binary {
+            Command::new(binary)
+        } else if let Some(script) = mock_script {
+            let mut cmd = Command::new("python3");
             cmd.arg(script.to_str().unwrap());
+            cmd
         } else {
             // Fallback: use command-based approach
             return self.build_command_based();
-        }
+        };
 
         // Mock service arguments
         if let Some(name) = &self.state.config.name {
@@ -292,7 +518,7 @@ impl ProcessManager {
         cmd.arg("--port")
             .arg(self.state.service_target_port().to_string());
 
-        if let Some(models) = &self.state.config.args {
+        if let Some(models) = self.state.model_args() {
             cmd.arg("--models").arg(models);
         } else {
             cmd.arg("--models").arg("test-model");
@@ -302,12 +528,48 @@ impl ProcessManager {
             cmd.arg("--registry-url").arg(registry_url);
         }
 
+        // Only infini-mock understands the registry TLS and token options
+        if is_binary {
+            let tls = self.state.config.registry_tls();
+            for (flag, path) in [
+                ("--registry-ca-cert", &tls.ca_cert),
+                ("--registry-client-cert", &tls.client_cert),
+                ("--registry-client-key", &tls.client_key),
+            ] {
+                if let Some(path) = path {
+                    cmd.arg(flag).arg(path);
+                }
+            }
+            if let Some(token) = &self.state.config.registry_token {
+                cmd.env("INFINI_REGISTRY_TOKEN", token);
+            }
+        }
+
         Ok(cmd)
     }
 
-    async fn detect_service_port(&self) {
-        // Simplified port detection - in production, parse logs or check HTTP endpoint
-        let target_port = self.state.service_target_port();
+    /// Compile the configured port log pattern; an invalid pattern disables log scraping
+    fn port_log_pattern(&self) -> Option<Arc<Regex>> {
+        let pattern = self.state.config.port_log_pattern.as_ref()?;
+        match Regex::new(pattern) {
+            Ok(regex) if regex.captures_len() > 1 => Some(Arc::new(regex)),
+            Ok(_) => {
+                warn!(
+                    "Port log pattern '{}' has no capture group, ignoring it",
+                    pattern
+                );
+                None
+            }
+            Err(e) => {
+                warn!("Invalid port log pattern '{}': {}", pattern, e);
+                None
+            }
+        }
+    }
+
+    async fn detect_service_port(&self, log_port: &Mutex<Option<u16>>) {
+        // Prefer the port scraped from the child's logs, otherwise assume the configured port
+        let configured_port = self.state.service_target_port();
 
         // For fast services (like mock services), check more aggressively
         // Start with very short intervals and use shorter timeouts
@@ -319,6 +581,8 @@ impl ProcessManager {
         sleep(Duration::from_millis(100)).await;
 
         loop {
+            let target_port = log_port.lock().unwrap().unwrap_or(configured_port);
+
             if start.elapsed() > max_wait {
                 warn!(
                     "Could not detect service port within {}s, using target port {}",
@@ -357,17 +621,24 @@ impl ProcessManager {
         .await
         {
             Ok(Ok(_)) => {
+                let config = &self.state.config;
+                let http_timeout = Duration::from_millis(500); // Give it a bit more time
+                let client = reqwest::Client::builder()
+                    .timeout(http_timeout)
+                    .build()
+                    .unwrap_or_else(|_| reqwest::Client::new());
+
+                if let Some(path) = &config.readiness_path {
+                    let url = local_url(port, path);
+                    return self.check_readiness_endpoint(&client, &url).await;
+                }
+
                 // Port is listening, now verify HTTP endpoint is actually ready
                 // Try /v1/models first (OpenAI API format), then fallback to /models
                 let urls = vec![
                     format!("http://127.0.0.1:{}/v1/models", port),
                     format!("http://127.0.0.1:{}/models", port),
                 ];
-                let http_timeout = Duration::from_millis(500); // Give it a bit more time
-                let client = reqwest::Client::builder()
-                    .timeout(http_timeout)
-                    .build()
-                    .unwrap_or_else(|_| reqwest::Client::new());
 
                 for url in urls {
                     match timeout(http_timeout, client.get(&url).send()).await {
@@ -390,11 +661,79 @@ impl ProcessManager {
         }
     }
 
-    async fn monitor_service(&self) {
+    /// Check the configured readiness endpoint: status code, then the optional JSON predicate
+    async fn check_readiness_endpoint(&self, client: &reqwest::Client, url: &str) -> bool {
+        let config = &self.state.config;
+        let response = match client.get(url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                debug!("Readiness check {} failed: {}", url, e);
+                return false;
+            }
+        };
+
+        let status = response.status();
+        let status_ok = match config.readiness_status {
+            Some(expected) => status.as_u16() == expected,
+            None => status.is_success(),
+        };
+        if !status_ok {
+            debug!("Readiness check {} returned {}", url, status);
+            return false;
+        }
+
+        let Some(pointer) = &config.readiness_json_pointer else {
+            return true;
+        };
+        match response.json::<Value>().await {
+            Ok(body) => {
+                readiness_predicate_matches(&body, pointer, config.readiness_json_value.as_deref())
+            }
+            Err(e) => {
+                debug!("Readiness check {} returned invalid JSON: {}", url, e);
+                false
+            }
+        }
+    }
+
+    /// Wait for the service process to exit; returns its exit code when it has one
+    async fn monitor_service(&self) -> Option<i32> {
+        let config = &self.state.config;
+        let probe_interval = Duration::from_secs(config.health_probe_interval);
+        let probe_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.health_probe_timeout))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        let mut last_probe = std::time::Instant::now();
+        let mut consecutive_failures: u32 = 0;
+        // Slow backends may still be loading when the port is assumed after the detection
+        // timeout, so failures only count once a probe has passed
+        let mut probe_passed = false;
+        let mut watchdog = MemoryWatchdog::new(
+            config.memory_limit_mb.map(|mb| mb << 20),
+            config.gpu_memory_limit_mb.map(|mb| mb << 20),
+            Duration::from_secs(config.memory_limit_sustain),
+        );
+        let gpu_check_interval = Duration::from_secs(config.gpu_check_interval);
+        let gpu_limits = GpuLimits {
+            max_temperature_c: config.gpu_max_temperature,
+            max_ecc_errors: config.gpu_max_ecc_errors,
+            min_free_memory_bytes: config.gpu_min_free_memory_mb.map(|mb| mb << 20),
+        };
+        let assigned_gpus: Vec<u32> = config
+            .gpu_devices()
+            .iter()
+            .filter_map(|device| device.parse().ok())
+            .collect();
+        let mut last_gpu_check = std::time::Instant::now();
+        if !gpu_check_interval.is_zero() && self.state.resource_monitor.gpu_readings().is_none() {
+            warn!("GPU checks are enabled but NVML is unavailable (build with the nvml feature)");
+        }
+
         loop {
             sleep(Duration::from_secs(5)).await;
 
-            let process_died = {
+            let process_exit = {
                 let mut process = self.state.process.write().await;
                 match process.as_mut() {
                     Some(p) => {
@@ -402,23 +741,398 @@ impl ProcessManager {
                         match p.try_wait() {
                             Ok(Some(status)) => {
                                 error!("Service process exited with status: {:?}", status);
-                                true
+                                Some(status.code())
                             }
-                            Ok(None) => false, // Still running
+                            Ok(None) => None, // Still running
                             Err(e) => {
                                 error!("Error checking process status: {}", e);
-                                true
+                                Some(None)
                             }
                         }
                     }
-                    None => true,
+                    None => Some(None),
                 }
             };
 
-            if process_died {
+            if let Some(exit_code) = process_exit {
                 info!("Service process died");
-                break;
+                return exit_code;
+            }
+
+            // Restart slowly leaking services before they exhaust the node
+            if watchdog.enabled() {
+                let pid = self
+                    .state
+                    .process
+                    .read()
+                    .await
+                    .as_ref()
+                    .and_then(|p| p.id());
+                let reason = pid.and_then(|pid| {
+                    let usage = self.state.resource_monitor.memory_usage(pid);
+                    watchdog.observe(usage, std::time::Instant::now())
+                });
+                if let Some(reason) = reason {
+                    error!(
+                        "Memory watchdog restarting {}: {}",
+                        config.service_name(),
+                        reason
+                    );
+                    {
+                        let mut report = self.state.watchdog.lock().unwrap();
+                        report.restarts += 1;
+                        report.last_restart = Some(chrono::Utc::now().to_rfc3339());
+                        report.last_reason = Some(reason);
+                    }
+                    // A leak is not a crash: restart right away, without backoff
+                    self.restart_in_place().await;
+                    return None;
+                }
+            }
+
+            if !gpu_check_interval.is_zero() && last_gpu_check.elapsed() >= gpu_check_interval {
+                last_gpu_check = std::time::Instant::now();
+                if let Some(readings) = self.state.resource_monitor.gpu_readings() {
+                    let faults = gpu_faults(&readings, &assigned_gpus, &gpu_limits);
+                    let previous = std::mem::replace(
+                        &mut *self.state.gpu_faults.lock().unwrap(),
+                        faults.clone(),
+                    );
+                    let had_faults = !previous.is_empty();
+                    if faults.is_empty() {
+                        if had_faults {
+                            info!("GPU faults of {} cleared", config.service_name());
+                        }
+                    } else {
+                        if !had_faults {
+                            error!(
+                                "GPU fault detected for {}: {}",
+                                config.service_name(),
+                                faults.join("; ")
+                            );
+                        }
+                        if config.gpu_fault_restart {
+                            error!("Restarting {} after GPU fault", config.service_name());
+                            self.restart_in_place().await;
+                            return None;
+                        }
+                    }
+                }
+            }
+
+            // Actively probe the service to catch hung processes that never exit
+            if probe_interval.is_zero() || last_probe.elapsed() < probe_interval {
+                continue;
+            }
+            last_probe = std::time::Instant::now();
+
+            let service_port = *self.state.service_port.read().await;
+            let Some(port) = service_port else {
+                continue;
+            };
+
+            if self.probe_health(&probe_client, port).await {
+                if !probe_passed {
+                    info!("Health probes of {} are now active", config.service_name());
+                }
+                probe_passed = true;
+                consecutive_failures = 0;
+                continue;
+            }
+
+            if !probe_passed {
+                debug!(
+                    "Health probe failed for {} before it became healthy, not counted",
+                    config.service_name()
+                );
+                continue;
+            }
+
+            consecutive_failures += 1;
+            warn!(
+                "Health probe failed for {} ({}/{})",
+                config.service_name(),
+                consecutive_failures,
+                config.health_probe_failures
+            );
+
+            if consecutive_failures >= config.health_probe_failures {
+                error!(
+                    "Service failed {} consecutive health probes, restarting",
+                    consecutive_failures
+                );
+                self.stop_service().await;
+                return None;
+            }
+        }
+    }
+
+    async fn probe_health(&self, client: &reqwest::Client, port: u16) -> bool {
+        let url = local_url(port, &self.state.config.health_probe_path);
+
+        match client.get(&url).send().await {
+            Ok(response) => response.status().is_success(),
+            Err(e) => {
+                debug!("Health probe to {} failed: {}", url, e);
+                false
+            }
+        }
+    }
+}
+
+/// URL of `path` on the service's local port, with or without a leading slash
+pub(crate) fn local_url(port: u16, path: &str) -> String {
+    let separator = if path.starts_with('/') { "" } else { "/" };
+    format!("http://127.0.0.1:{}{}{}", port, separator, path)
+}
+
+/// Record the port from a log line matching the port pattern (first match wins)
+fn scrape_port(pattern: Option<&Regex>, line: &str, log_port: &Mutex<Option<u16>>) {
+    let Some(pattern) = pattern else {
+        return;
+    };
+    let Some(port) = pattern
+        .captures(line)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse::<u16>().ok())
+    else {
+        return;
+    };
+
+    let mut detected = log_port.lock().unwrap();
+    if detected.is_none() {
+        info!("Detected service port {} from logs", port);
+        *detected = Some(port);
+    }
+}
+
+/// Evaluate the readiness JSON predicate: the value at `pointer` must equal `expected`
+/// (parsed as JSON, else taken as a string), or be truthy when nothing is expected
+fn readiness_predicate_matches(body: &Value, pointer: &str, expected: Option<&str>) -> bool {
+    let Some(actual) = body.pointer(pointer) else {
+        return false;
+    };
+    match expected {
+        Some(expected) => {
+            let expected = serde_json::from_str::<Value>(expected)
+                .unwrap_or_else(|_| Value::String(expected.to_string()));
+            *actual == expected
+        }
+        None => !matches!(actual, Value::Null | Value::Bool(false)),
+    }
+}
+
+/// Exponential backoff: `base * 2^attempt`, capped at `max`
+fn backoff_delay(base: Duration, attempt: u32, max: Duration) -> Duration {
+    base.checked_mul(2u32.saturating_pow(attempt))
+        .unwrap_or(max)
+        .min(max)
+}
+
+/// Spread a delay by ±20% so crash-looping services don't restart in lockstep
+fn with_jitter(delay: Duration) -> Duration {
+    delay.mul_f64(rand::thread_rng().gen_range(0.8..1.2))
+}
+
+/// Send SIGTERM to the child's process group, wait up to `grace_period` for the group
+/// to exit, then SIGKILL whatever is left of it. `process_group` is the group recorded at
+/// spawn; it is still signalled when the leader has already been reaped after a crash,
+/// since wrappers (python -m, shell scripts) leave grandchildren holding the port and GPUs
+pub async fn terminate_child(
+    child: &mut Child,
+    process_group: Option<u32>,
+    grace_period: Duration,
+) {
+    #[cfg(unix)]
+    if let Some(pgid) = process_group.or_else(|| child.id()) {
+        match signal_process_group(pgid, libc::SIGTERM) {
+            Ok(()) => {
+                info!(
+                    "Sent SIGTERM to service process group {} (grace period: {:?})",
+                    pgid, grace_period
+                );
+                if timeout(grace_period, wait_group_exit(child, pgid))
+                    .await
+                    .is_err()
+                {
+                    warn!(
+                        "Service process group {} did not exit within {:?}, sending SIGKILL",
+                        pgid, grace_period
+                    );
+                }
+            }
+            // Nothing left in the group
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+            Err(e) => {
+                warn!(
+                    "Failed to send SIGTERM to service process group {}: {}",
+                    pgid, e
+                );
             }
         }
+        let _ = signal_process_group(pgid, libc::SIGKILL);
+    }
+
+    #[cfg(not(unix))]
+    let _ = (process_group, grace_period);
+
+    let _ = child.kill().await;
+    let _ = child.wait().await;
+}
+
+/// Wait for the group leader to exit, then for the rest of its group
+#[cfg(unix)]
+async fn wait_group_exit(child: &mut Child, pgid: u32) {
+    match child.wait().await {
+        Ok(status) => info!("Service process {} exited with status: {}", pgid, status),
+        Err(e) => warn!("Error waiting for service process {}: {}", pgid, e),
+    }
+    while signal_process_group(pgid, 0).is_ok() {
+        sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Send `signal` to every process in the group led by `pgid`
+#[cfg(unix)]
+fn signal_process_group(pgid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    // SAFETY: kill(2) has no memory-safety preconditions; a negative pid targets the group
+    let ret = unsafe { libc::kill(-(pgid as libc::pid_t), signal) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrape_port() {
+        let pattern = Regex::new(r"listening on .*:(\d+)").unwrap();
+        let log_port = Mutex::new(None);
+
+        scrape_port(Some(&pattern), "loading weights...", &log_port);
+        assert_eq!(*log_port.lock().unwrap(), None);
+
+        scrape_port(Some(&pattern), "INFO listening on 0.0.0.0:8123", &log_port);
+        assert_eq!(*log_port.lock().unwrap(), Some(8123));
+
+        // The first detected port is kept
+        scrape_port(Some(&pattern), "listening on 127.0.0.1:9000", &log_port);
+        assert_eq!(*log_port.lock().unwrap(), Some(8123));
+    }
+
+    #[test]
+    fn test_local_url() {
+        assert_eq!(local_url(8000, "/health"), "http://127.0.0.1:8000/health");
+        assert_eq!(local_url(8000, "health"), "http://127.0.0.1:8000/health");
+    }
+
+    #[test]
+    fn test_readiness_predicate() {
+        let body = json!({"status": "ready", "loaded": true, "workers": 2, "error": null});
+        assert!(readiness_predicate_matches(&body, "/status", Some("ready")));
+        assert!(readiness_predicate_matches(&body, "/workers", Some("2")));
+        assert!(readiness_predicate_matches(&body, "/loaded", None));
+        assert!(!readiness_predicate_matches(
+            &body,
+            "/status",
+            Some("loading")
+        ));
+        assert!(!readiness_predicate_matches(&body, "/error", None));
+        assert!(!readiness_predicate_matches(&body, "/missing", None));
+    }
+
+    /// Whether `pid` is gone (or only left as a zombie nobody has reaped yet)
+    #[cfg(target_os = "linux")]
+    fn process_gone(pid: u32) -> bool {
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => stat
+                .rsplit_once(')')
+                .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z')),
+            Err(_) => true,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_stop_service_kills_grandchildren_of_reaped_leader() {
+        let toml = r#"
+port = 18950
+[babysitter]
+shutdown_grace_period = 2
+[backend]
+type = "mock"
+models = ["model-a"]
+"#;
+        let config_file: crate::babysitter::config_file::BabysitterConfigFile =
+            toml::from_str(toml).unwrap();
+        let state = Arc::new(BabysitterState::new(config_file.to_cli_config(), None));
+
+        // A shell wrapper that leaves a grandchild behind and exits, like a crash
+        let mut child = TokioCommand::new("sh")
+            .args(["-c", "sleep 300 >/dev/null 2>&1 & echo $!"])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let leader = child.id().unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .await
+            .unwrap();
+        let grandchild: u32 = line.trim().parse().unwrap();
+        child.wait().await.unwrap();
+        assert!(child.id().is_none());
+        assert!(!process_gone(grandchild));
+
+        *state.process.write().await = Some(child);
+        *state.process_group.lock().unwrap() = Some(leader);
+        ProcessManager::new(state.clone()).stop_service().await;
+
+        assert!(process_gone(grandchild));
+        assert!(state.process_group.lock().unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_restart_in_place_is_not_a_crash() {
+        let toml = r#"
+port = 18951
+[backend]
+type = "mock"
+models = ["model-a"]
+"#;
+        let config_file: crate::babysitter::config_file::BabysitterConfigFile =
+            toml::from_str(toml).unwrap();
+        let state = Arc::new(BabysitterState::new(config_file.to_cli_config(), None));
+        let child = TokioCommand::new("sleep")
+            .arg("300")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        *state.process_group.lock().unwrap() = child.id();
+        *state.process.write().await = Some(child);
+
+        // Used by the memory watchdog and GPU fault restarts
+        ProcessManager::new(state.clone()).restart_in_place().await;
+
+        assert!(state.process.read().await.is_none());
+        assert!(state.control.restart_requested.load(Ordering::SeqCst));
+        assert_eq!(*state.restart_count.read().await, 0);
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_and_caps() {
+        let base = Duration::from_secs(5);
+        let max = Duration::from_secs(60);
+        assert_eq!(backoff_delay(base, 0, max), Duration::from_secs(5));
+        assert_eq!(backoff_delay(base, 1, max), Duration::from_secs(10));
+        assert_eq!(backoff_delay(base, 3, max), Duration::from_secs(40));
+        assert_eq!(backoff_delay(base, 4, max), max);
+        assert_eq!(backoff_delay(base, 100, max), max);
     }
 }
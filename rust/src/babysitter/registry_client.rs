@@ -1,7 +1,9 @@
 //! Registry client for the babysitter
 
+use crate::babysitter::load_metrics::LoadMetrics;
+use crate::babysitter::process_manager::local_url;
 use crate::babysitter::BabysitterState;
-use reqwest::Client;
+use reqwest::{Client, Method, RequestBuilder};
 use serde_json::json;
 use std::sync::Arc;
 use tokio::time::{sleep, Duration};
@@ -14,11 +16,27 @@ pub struct BabysitterRegistryClient {
 }
 
 impl BabysitterRegistryClient {
-    pub fn new(registry_url: String, state: Arc<BabysitterState>) -> Self {
-        Self {
+    pub fn new(registry_url: String, state: Arc<BabysitterState>) -> anyhow::Result<Self> {
+        let client = state
+            .config
+            .registry_tls()
+            .configure(Client::builder())?
+            .build()?;
+        Ok(Self {
             registry_url: registry_url.trim_end_matches('/').to_string(),
-            client: Client::new(),
+            client,
             state,
+        })
+    }
+
+    /// Request to the registry, carrying the shared token when one is configured
+    fn registry_request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, format!("{}{}", self.registry_url, path));
+        match &self.state.config.registry_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
         }
     }
 
@@ -40,7 +58,7 @@ impl BabysitterRegistryClient {
 
             // Send heartbeat for babysitter
             let service_name = self.state.config.service_name();
-            self.send_heartbeat(&service_name).await;
+            self.send_heartbeat(&service_name, false).await;
 
             // Send heartbeat for managed service if registered
             let service_port = {
@@ -50,11 +68,16 @@ impl BabysitterRegistryClient {
 
             if service_port.is_some() {
                 let server_name = format!("{}-server", self.state.config.service_name());
-                self.send_heartbeat(&server_name).await;
+                self.send_heartbeat(&server_name, true).await;
             }
         }
     }
 
+    /// Registry heartbeat timeout: four missed heartbeats (120s at the default interval)
+    fn heartbeat_timeout(&self) -> u64 {
+        self.state.config.heartbeat_interval.max(1) * 4
+    }
+
     async fn register_babysitter(&self) {
         let service_name = self.state.config.service_name();
         let service_data = json!({
@@ -64,15 +87,16 @@ impl BabysitterRegistryClient {
             "port": self.state.babysitter_port(),
             "url": format!("http://{}:{}", self.state.config.host, self.state.babysitter_port()),
             "status": "running",
+            "heartbeat_timeout": self.heartbeat_timeout(),
             "metadata": {
                 "type": self.state.config.service_type,
-                "babysitter": "enhanced"
+                "babysitter": "enhanced",
+                "gpus": self.state.config.gpu_devices()
             }
         });
 
         match self
-            .client
-            .post(format!("{}/services", self.registry_url))
+            .registry_request(Method::POST, "/services")
             .json(&service_data)
             .send()
             .await
@@ -90,7 +114,8 @@ impl BabysitterRegistryClient {
         }
     }
 
-    async fn register_managed_service(&self) {
+    /// Register the managed service once it is ready (retrying until it succeeds)
+    pub async fn register_managed_service(&self) {
         // Wait for service to be ready
         loop {
             let service_port = {
@@ -112,6 +137,12 @@ impl BabysitterRegistryClient {
                 continue;
             }
 
+            // Large models list themselves long before they can serve inference
+            if !self.deep_readiness_ok(service_port.unwrap(), &models).await {
+                sleep(Duration::from_secs(2)).await;
+                continue;
+            }
+
             // Register service
             let service_name = self.state.config.service_name();
 
@@ -121,9 +152,19 @@ impl BabysitterRegistryClient {
                 "parent_service": service_name,
                 "babysitter": "enhanced",
                 "models": models.iter().map(|m| m.get("id").and_then(|v| v.as_str()).unwrap_or("")).collect::<Vec<_>>(),
-                "models_list": models
+                "models_list": models,
+                "gpus": self.state.config.gpu_devices(),
+                "babysitter_port": self.state.babysitter_port(),
+                "babysitter_url": format!("http://{}:{}", self.state.config.host, self.state.babysitter_port())
             });
 
+            if let (Some(metadata_obj), serde_json::Value::Object(restarts)) = (
+                metadata.as_object_mut(),
+                self.state.restart_metadata().await,
+            ) {
+                metadata_obj.extend(restarts);
+            }
+
             // Merge metadata from config file if available
             if let Some(ref config_file) = self.state.config_file {
                 if let Some(metadata_obj) = metadata.as_object_mut() {
@@ -141,12 +182,12 @@ impl BabysitterRegistryClient {
                 "port": service_port.unwrap(),
                 "url": format!("http://{}:{}", self.state.config.host, service_port.unwrap()),
                 "status": "running",
+                "heartbeat_timeout": self.heartbeat_timeout(),
                 "metadata": metadata
             });
 
             match self
-                .client
-                .post(format!("{}/services", self.registry_url))
+                .registry_request(Method::POST, "/services")
                 .json(&service_data)
                 .send()
                 .await
@@ -176,6 +217,55 @@ impl BabysitterRegistryClient {
         }
     }
 
+    /// Send the configured deep readiness request; true when none is configured
+    async fn deep_readiness_ok(&self, port: u16, models: &[serde_json::Value]) -> bool {
+        let config = &self.state.config;
+        let Some(check) = &config.deep_readiness_check else {
+            return true;
+        };
+        let model = models
+            .first()
+            .and_then(|m| m.get("id"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let Some((path, body)) =
+            deep_readiness_request(check, config.deep_readiness_body.as_deref(), model)
+        else {
+            warn!("Invalid deep readiness body, skipping deep readiness check");
+            return true;
+        };
+
+        let url = local_url(port, &path);
+        match self
+            .client
+            .post(&url)
+            .timeout(Duration::from_secs(config.deep_readiness_timeout))
+            .json(&body)
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => {
+                info!("Deep readiness check {} passed", url);
+                true
+            }
+            Ok(response) => {
+                info!(
+                    "Deep readiness check {} returned {}, not registering yet",
+                    url,
+                    response.status()
+                );
+                false
+            }
+            Err(e) => {
+                info!(
+                    "Deep readiness check {} failed: {}, not registering yet",
+                    url, e
+                );
+                false
+            }
+        }
+    }
+
     async fn fetch_models(&self, port: u16) -> Vec<serde_json::Value> {
         // Try /v1/models first (OpenAI API format), fallback to /models
         // Always use localhost for fetching models since the service runs locally
@@ -195,7 +285,9 @@ impl BabysitterRegistryClient {
                         if response.status().is_success() {
                             if let Ok(data) = response.json::<serde_json::Value>().await {
                                 // Handle both OpenAI API format {"data": [...]} and direct array format
-                                let models = if let Some(models) = data.get("data").and_then(|v| v.as_array()) {
+                                let models = if let Some(models) =
+                                    data.get("data").and_then(|v| v.as_array())
+                                {
                                     models.clone()
                                 } else if data.is_array() {
                                     // Direct array format
@@ -205,10 +297,17 @@ impl BabysitterRegistryClient {
                                 };
 
                                 if !models.is_empty() {
-                                    info!("Fetched {} models from service via {}", models.len(), url);
+                                    info!(
+                                        "Fetched {} models from service via {}",
+                                        models.len(),
+                                        url
+                                    );
                                     return models;
                                 } else {
-                                    debug!("Service returned empty models list from {}, retrying...", url);
+                                    debug!(
+                                        "Service returned empty models list from {}, retrying...",
+                                        url
+                                    );
                                 }
                             } else {
                                 debug!("Failed to parse JSON response from {}, retrying...", url);
@@ -252,13 +351,107 @@ impl BabysitterRegistryClient {
         vec![]
     }
 
-    async fn send_heartbeat(&self, service_name: &str) {
+    /// Remove the babysitter and managed service entries from the registry
+    pub async fn deregister(&self) {
+        let service_name = self.state.config.service_name();
+        let server_name = format!("{}-server", service_name);
+
+        for name in [&server_name, &service_name] {
+            self.deregister_entry(name).await;
+        }
+    }
+
+    /// Remove only the managed service entry, so routers stop sending it requests
+    pub async fn deregister_managed_service(&self) {
+        let server_name = format!("{}-server", self.state.config.service_name());
+        self.deregister_entry(&server_name).await;
+    }
+
+    async fn deregister_entry(&self, name: &str) {
         match self
+            .registry_request(Method::DELETE, &format!("/services/{}", name))
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+        {
+            Ok(response) => {
+                if response.status().is_success() {
+                    info!("Deregistered {} from registry", name);
+                } else if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    debug!("{} was not registered, nothing to deregister", name);
+                } else {
+                    warn!("Failed to deregister {}: {}", name, response.status());
+                }
+            }
+            Err(e) => {
+                warn!("Error deregistering {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Scrape the managed service's load metrics; None when disabled or unavailable
+    async fn scrape_load_metrics(&self) -> Option<LoadMetrics> {
+        let path = &self.state.config.load_metrics_path;
+        if path.is_empty() {
+            return None;
+        }
+        let port = (*self.state.service_port.read().await)?;
+        let url = local_url(port, path);
+        let response = match self
             .client
-            .post(format!(
-                "{}/services/{}/heartbeat",
-                self.registry_url, service_name
-            ))
+            .get(&url)
+            .timeout(Duration::from_secs(2))
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                debug!("Load metrics {} returned {}", url, response.status());
+                return None;
+            }
+            Err(e) => {
+                debug!("Error scraping load metrics from {}: {}", url, e);
+                return None;
+            }
+        };
+        let load = LoadMetrics::parse(&response.text().await.ok()?);
+        (!load.is_empty()).then_some(load)
+    }
+
+    /// Heartbeat one registry entry; the managed service's entry also carries restart
+    /// churn, GPU faults and backend load, and is reported unhealthy while there are
+    /// any GPU faults
+    async fn send_heartbeat(&self, service_name: &str, managed_service: bool) {
+        // Piggyback resource telemetry so the registry (and routers) can see node load
+        let resources = self.state.resource_usage().await;
+        let mut payload = json!({
+            "metadata": {
+                "resources": resources
+            }
+        });
+        if managed_service {
+            let gpu_faults = self.state.gpu_faults.lock().unwrap().clone();
+            payload["status"] = json!(if gpu_faults.is_empty() {
+                "running"
+            } else {
+                "unhealthy"
+            });
+            payload["metadata"]["gpu_faults"] = json!(gpu_faults);
+            // Always sent (null when unavailable) so stale load does not linger in the registry
+            payload["metadata"]["load"] = json!(self.scrape_load_metrics().await);
+            if let serde_json::Value::Object(restarts) = self.state.restart_metadata().await {
+                for (key, value) in restarts {
+                    payload["metadata"][key] = value;
+                }
+            }
+        }
+
+        match self
+            .registry_request(
+                Method::POST,
+                &format!("/services/{}/heartbeat", service_name),
+            )
+            .json(&payload)
             .send()
             .await
         {
@@ -278,6 +471,36 @@ impl BabysitterRegistryClient {
     }
 }
 
+/// Path and JSON body of a deep readiness request: a one-token `completion` or `chat`
+/// request for `model`, or `body` POSTed to the path given as `check`
+fn deep_readiness_request(
+    check: &str,
+    body: Option<&str>,
+    model: &str,
+) -> Option<(String, serde_json::Value)> {
+    match check {
+        "completion" => Some((
+            "/v1/completions".to_string(),
+            json!({"model": model, "prompt": "Hello", "max_tokens": 1}),
+        )),
+        "chat" => Some((
+            "/v1/chat/completions".to_string(),
+            json!({
+                "model": model,
+                "messages": [{"role": "user", "content": "Hello"}],
+                "max_tokens": 1
+            }),
+        )),
+        path => {
+            let body = match body {
+                Some(body) => serde_json::from_str(body).ok()?,
+                None => json!({}),
+            };
+            Some((path.to_string(), body))
+        }
+    }
+}
+
 impl Clone for BabysitterRegistryClient {
     fn clone(&self) -> Self {
         Self {
@@ -287,3 +510,27 @@ impl Clone for BabysitterRegistryClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deep_readiness_request() {
+        let (path, body) = deep_readiness_request("completion", None, "llama").unwrap();
+        assert_eq!(path, "/v1/completions");
+        assert_eq!(body["model"], "llama");
+        assert_eq!(body["max_tokens"], 1);
+
+        let (path, body) = deep_readiness_request("chat", None, "llama").unwrap();
+        assert_eq!(path, "/v1/chat/completions");
+        assert_eq!(body["messages"][0]["role"], "user");
+
+        let (path, body) =
+            deep_readiness_request("/generate", Some(r#"{"inputs": "hi"}"#), "llama").unwrap();
+        assert_eq!(path, "/generate");
+        assert_eq!(body["inputs"], "hi");
+
+        assert!(deep_readiness_request("/generate", Some("not json"), "llama").is_none());
+    }
+}
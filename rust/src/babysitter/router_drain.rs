@@ -0,0 +1,165 @@
+//! Router drain coordination around planned restarts
+
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::Client;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+/// How often routers are polled for the service's in-flight requests
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Routers (from `router_url`, comma-separated) that are told to stop routing to the
+/// managed service before a planned restart
+#[derive(Clone)]
+pub struct RouterDrain {
+    client: Client,
+    router_urls: Vec<String>,
+    service_name: String,
+}
+
+impl RouterDrain {
+    pub fn new(router_url: Option<&str>, token: Option<&str>, service_name: String) -> Self {
+        // The drain endpoints are admin endpoints: send the routers' admin token
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            match HeaderValue::from_str(&format!("Bearer {}", token)) {
+                Ok(value) => {
+                    headers.insert(AUTHORIZATION, value);
+                }
+                Err(_) => warn!("Ignoring router_token: not a valid header value"),
+            }
+        }
+        Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(5))
+                .default_headers(headers)
+                .build()
+                .unwrap_or_default(),
+            router_urls: parse_router_urls(router_url),
+            service_name,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.router_urls.is_empty()
+    }
+
+    fn drain_url(&self, router_url: &str) -> String {
+        format!("{}/admin/services/{}/drain", router_url, self.service_name)
+    }
+
+    /// Ask every router to stop sending new requests to the service
+    pub async fn begin(&self) {
+        for router_url in &self.router_urls {
+            match self.client.post(self.drain_url(router_url)).send().await {
+                Ok(response) if response.status().is_success() => {
+                    info!("Draining {} on router {}", self.service_name, router_url)
+                }
+                Ok(response) => warn!(
+                    "Router {} refused to drain {}: HTTP {}",
+                    router_url,
+                    self.service_name,
+                    response.status()
+                ),
+                Err(e) => warn!(
+                    "Failed to drain {} on router {}: {}",
+                    self.service_name, router_url, e
+                ),
+            }
+        }
+    }
+
+    /// Wait until no router has requests in flight to the service, or `timeout` passes.
+    /// Routers that cannot be reached or no longer know the service are not waited for.
+    pub async fn wait_idle(&self, timeout: Duration) {
+        let started = Instant::now();
+        loop {
+            let mut in_flight = 0;
+            for router_url in &self.router_urls {
+                in_flight += self.in_flight(router_url).await;
+            }
+            if in_flight == 0 {
+                info!(
+                    "{} drained after {:.1}s",
+                    self.service_name,
+                    started.elapsed().as_secs_f64()
+                );
+                return;
+            }
+            if started.elapsed() >= timeout {
+                warn!(
+                    "{} still has {} request(s) in flight after {}s, restarting anyway",
+                    self.service_name,
+                    in_flight,
+                    timeout.as_secs()
+                );
+                return;
+            }
+            sleep(DRAIN_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn in_flight(&self, router_url: &str) -> u64 {
+        let response = match self.client.get(self.drain_url(router_url)).send().await {
+            Ok(response) if response.status().is_success() => response,
+            _ => return 0,
+        };
+        response
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|body| body.get("in_flight").and_then(|v| v.as_u64()))
+            .unwrap_or(0)
+    }
+
+    /// Put the service back into rotation on every router
+    pub async fn end(&self) {
+        for router_url in &self.router_urls {
+            match self.client.delete(self.drain_url(router_url)).send().await {
+                Ok(response) if response.status().is_success() => info!(
+                    "{} is back in rotation on router {}",
+                    self.service_name, router_url
+                ),
+                // The router dropped the service after it was deregistered; it comes
+                // back undrained once the registration is synced
+                Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {}
+                Ok(response) => warn!(
+                    "Router {} refused to undrain {}: HTTP {}",
+                    router_url,
+                    self.service_name,
+                    response.status()
+                ),
+                Err(e) => warn!(
+                    "Failed to undrain {} on router {}: {}",
+                    self.service_name, router_url, e
+                ),
+            }
+        }
+    }
+}
+
+/// Split a comma-separated `router_url` setting into base URLs
+fn parse_router_urls(router_url: Option<&str>) -> Vec<String> {
+    router_url
+        .unwrap_or_default()
+        .split(',')
+        .map(|url| url.trim().trim_end_matches('/'))
+        .filter(|url| !url.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_router_urls() {
+        assert!(parse_router_urls(None).is_empty());
+        assert_eq!(
+            parse_router_urls(Some("http://r1:8000/, http://r2:8000,")),
+            vec!["http://r1:8000", "http://r2:8000"]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Cron-style schedules for periodic restarts of the managed service
+
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDateTime, Timelike};
+
+/// A five-field cron expression (minute hour day-of-month month day-of-week) in local
+/// time. Fields accept `*`, numbers, ranges (`1-5`), lists (`1,3`) and steps (`*/15`);
+/// `@hourly`, `@daily`/`@midnight` and `@weekly` are also understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Day-of-month and day-of-week were both restricted: either may match (as in cron)
+    day_either: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+
+        // Sunday may be written as 0 or 7
+        let mut days_of_week = parse_field(dow, 0, 7)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days_of_month: parse_field(dom, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            days_of_week,
+            day_either: dom != "*" && dow != "*",
+        })
+    }
+
+    fn matches(&self, at: NaiveDateTime) -> bool {
+        let bit = |mask: u64, value: u32| mask & (1 << value) != 0;
+        let dom = bit(self.days_of_month, at.day());
+        let dow = bit(self.days_of_week, at.weekday().num_days_from_sunday());
+        let day = if self.day_either {
+            dom || dow
+        } else {
+            dom && dow
+        };
+        bit(self.minutes, at.minute())
+            && bit(self.hours, at.hour())
+            && bit(self.months, at.month())
+            && day
+    }
+
+    /// The first matching minute strictly after `after`, searching up to a year ahead
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut candidate = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        for _ in 0..(366 * 24 * 60) {
+            if self.matches(candidate) {
+                return Some(candidate);
+            }
+            candidate += ChronoDuration::minutes(1);
+        }
+        None
+    }
+}
+
+/// Bitmask of the values selected by one cron field
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("invalid step in '{}'", item))?,
+            ),
+            None => (item, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, item)?, parse_value(end, item)?)
+        } else {
+            let value = parse_value(range, item)?;
+            // "5/15" means from 5 to the end of the range in steps of 15
+            (value, if step > 1 { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("'{}' is outside {}-{}", item, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, item: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value in '{}'", item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        let nightly = CronSchedule::parse("0 3 * * *").unwrap();
+        assert_eq!(
+            nightly.next_after(at(2024, 5, 1, 2, 59)),
+            Some(at(2024, 5, 1, 3, 0))
+        );
+        // Strictly after: a restart at 03:00 schedules the next one for tomorrow
+        assert_eq!(
+            nightly.next_after(at(2024, 5, 1, 3, 0)),
+            Some(at(2024, 5, 2, 3, 0))
+        );
+
+        let quarter_hours = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(
+            quarter_hours.next_after(at(2024, 5, 1, 10, 16)),
+            Some(at(2024, 5, 1, 10, 30))
+        );
+
+        // 2024-05-04 is a Saturday; Sunday may be written as 7
+        let weekends = CronSchedule::parse("30 4 * * 6,7").unwrap();
+        assert_eq!(
+            weekends.next_after(at(2024, 5, 1, 0, 0)),
+            Some(at(2024, 5, 4, 4, 30))
+        );
+        assert_eq!(
+            weekends.next_after(at(2024, 5, 4, 4, 30)),
+            Some(at(2024, 5, 5, 4, 30))
+        );
+
+        assert_eq!(
+            CronSchedule::parse("@daily").unwrap(),
+            CronSchedule::parse("0 0 * * *").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        assert!(CronSchedule::parse("0 3 * *").is_err());
+        assert!(CronSchedule::parse("60 3 * * *").is_err());
+        assert!(CronSchedule::parse("0 3 * * mon").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 5-3 * * *").is_err());
+    }
+}
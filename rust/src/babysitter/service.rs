@@ -0,0 +1,192 @@
+//! Lifecycle of managed services: the entrypoints used by the infini-babysitter binary
+//! and by applications embedding the babysitter
+
+use std::future::Future;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::babysitter::config::BabysitterConfig;
+use crate::babysitter::config_file::BabysitterConfigFile;
+use crate::babysitter::handlers::BabysitterHandlers;
+use crate::babysitter::port_guard::ensure_port_free;
+use crate::babysitter::process_manager::ProcessManager;
+use crate::babysitter::registry_client::BabysitterRegistryClient;
+use crate::babysitter::schedule::CronSchedule;
+use crate::babysitter::BabysitterState;
+
+/// Tasks and handles for one managed service
+pub struct ManagedService {
+    pub state: Arc<BabysitterState>,
+    server_handle: JoinHandle<()>,
+    process_manager: Arc<ProcessManager>,
+    process_handle: JoinHandle<()>,
+    registry_client: Option<BabysitterRegistryClient>,
+    registry_handle: Option<JoinHandle<()>>,
+    schedule_handle: Option<JoinHandle<()>>,
+}
+
+impl ManagedService {
+    /// Spawn the HTTP server, process manager and registry client for one service
+    pub fn start(
+        config: BabysitterConfig,
+        config_file: Option<BabysitterConfigFile>,
+    ) -> anyhow::Result<Self> {
+        let restart_schedule = config
+            .restart_schedule
+            .as_deref()
+            .map(|expression| {
+                CronSchedule::parse(expression).map_err(|e| {
+                    anyhow::anyhow!("Invalid restart schedule '{}': {}", expression, e)
+                })
+            })
+            .transpose()?;
+
+        let state = Arc::new(BabysitterState::new(config, config_file));
+        let registry_client = state
+            .config
+            .registry_url
+            .as_ref()
+            .map(|registry_url| {
+                BabysitterRegistryClient::new(registry_url.to_string(), state.clone())
+            })
+            .transpose()?;
+
+        // Start HTTP server
+        let handlers = BabysitterHandlers::new(state.clone());
+        let server_handle = tokio::spawn(async move {
+            if let Err(e) = handlers.start_server().await {
+                tracing::error!("HTTP server error: {}", e);
+            }
+        });
+
+        // Start process manager
+        let process_manager = Arc::new(ProcessManager::new(state.clone()));
+        let process_handle = tokio::spawn({
+            let process_manager = process_manager.clone();
+            async move { process_manager.run().await }
+        });
+
+        // Start registry client (if configured)
+        let registry_handle = registry_client
+            .clone()
+            .map(|registry_client| tokio::spawn(async move { registry_client.run().await }));
+
+        // Start scheduled restarts (if configured)
+        let schedule_handle = restart_schedule.map(|schedule| {
+            tokio::spawn(run_restart_schedule(
+                schedule,
+                state.clone(),
+                process_manager.clone(),
+                registry_client.clone(),
+            ))
+        });
+
+        Ok(Self {
+            state,
+            server_handle,
+            process_manager,
+            process_handle,
+            registry_client,
+            registry_handle,
+            schedule_handle,
+        })
+    }
+
+    /// Deregister, stop the child and tear down the tasks of the service
+    pub async fn stop(self) {
+        info!("Stopping {}", self.state.config.service_name());
+
+        if let Some(schedule_handle) = self.schedule_handle {
+            schedule_handle.abort();
+        }
+
+        // Stop registry client and remove our entries so the registry doesn't keep stale services
+        if let Some(registry_handle) = self.registry_handle {
+            registry_handle.abort();
+        }
+        if let Some(registry_client) = &self.registry_client {
+            registry_client.deregister().await;
+        }
+
+        // Stop process manager and shut the child down gracefully
+        self.process_handle.abort();
+        self.process_manager.stop_service().await;
+
+        // Stop HTTP server
+        self.server_handle.abort();
+    }
+}
+
+/// Manage one service per config until `shutdown` completes, then stop them all.
+/// Fails without starting anything further if a service port is already taken.
+pub async fn run(
+    configs: Vec<(BabysitterConfig, Option<BabysitterConfigFile>)>,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let mut services: Vec<ManagedService> = Vec::with_capacity(configs.len());
+    for (config, config_file) in configs {
+        info!("Service: {}", config.service_name());
+        if let Some(port) = config.port {
+            if let Err(e) = ensure_port_free(port, config.kill_orphans).await {
+                futures::future::join_all(services.into_iter().map(ManagedService::stop)).await;
+                anyhow::bail!("{}: {}", config.service_name(), e);
+            }
+        }
+        let service = match ManagedService::start(config, config_file) {
+            Ok(service) => service,
+            Err(e) => {
+                futures::future::join_all(services.into_iter().map(ManagedService::stop)).await;
+                return Err(e);
+            }
+        };
+        info!(
+            "Port: {} (babysitter: {})",
+            service.state.service_target_port(),
+            service.state.babysitter_port()
+        );
+        info!("Registry: {:?}", service.state.config.registry_url);
+
+        services.push(service);
+    }
+
+    shutdown.await;
+    info!("Received shutdown signal, cleaning up...");
+
+    // Stop all services concurrently so grace periods don't add up
+    futures::future::join_all(services.into_iter().map(ManagedService::stop)).await;
+    Ok(())
+}
+
+/// Gracefully restart the service whenever the schedule fires
+async fn run_restart_schedule(
+    schedule: CronSchedule,
+    state: Arc<BabysitterState>,
+    process_manager: Arc<ProcessManager>,
+    registry_client: Option<BabysitterRegistryClient>,
+) {
+    let service_name = state.config.service_name();
+    loop {
+        let now = chrono::Local::now().naive_local();
+        let Some(next) = schedule.next_after(now) else {
+            warn!("Restart schedule for {} never fires", service_name);
+            return;
+        };
+        info!("Next scheduled restart of {} at {}", service_name, next);
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        if state.control.stopped.load(Ordering::SeqCst) {
+            info!(
+                "Skipping scheduled restart of {}: service is stopped",
+                service_name
+            );
+            continue;
+        }
+
+        info!("Scheduled restart of {}", service_name);
+        process_manager
+            .planned_restart(registry_client.as_ref())
+            .await;
+    }
+}
@@ -0,0 +1,212 @@
+//! Resource telemetry for the managed service process
+
+use crate::babysitter::gpu_health::GpuReading;
+use crate::babysitter::watchdog::MemoryUsage;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Point-in-time resource usage of the managed process
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResourceUsage {
+    pub pid: u32,
+    /// CPU usage since the previous sample (100.0 = one full core)
+    pub cpu_percent: Option<f64>,
+    /// Resident set size in bytes
+    pub rss_bytes: Option<u64>,
+    /// Per-device GPU usage (requires the `nvml` feature)
+    pub gpus: Vec<GpuUsage>,
+}
+
+/// Usage of a single GPU device
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuUsage {
+    pub index: u32,
+    pub utilization_percent: u32,
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    /// Memory used on this device by the managed process, if it has a context there
+    pub process_memory_bytes: Option<u64>,
+}
+
+/// Samples CPU, memory and GPU usage for the managed process
+pub struct ResourceMonitor {
+    /// Previous (wall clock, CPU ticks) sample used to compute CPU percentage
+    last_cpu_sample: Mutex<Option<(Instant, u64)>>,
+    #[cfg(feature = "nvml")]
+    nvml: Option<nvml_wrapper::Nvml>,
+}
+
+impl Default for ResourceMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResourceMonitor {
+    pub fn new() -> Self {
+        Self {
+            last_cpu_sample: Mutex::new(None),
+            #[cfg(feature = "nvml")]
+            nvml: match nvml_wrapper::Nvml::init() {
+                Ok(nvml) => Some(nvml),
+                Err(e) => {
+                    tracing::warn!("NVML unavailable, GPU telemetry disabled: {}", e);
+                    None
+                }
+            },
+        }
+    }
+
+    /// Take a resource sample for `pid`
+    pub fn sample(&self, pid: u32) -> ResourceUsage {
+        ResourceUsage {
+            pid,
+            cpu_percent: self.cpu_percent(pid),
+            rss_bytes: read_rss_bytes(pid),
+            gpus: self.gpu_usage(pid),
+        }
+    }
+
+    /// Memory held by `pid`, without touching the CPU sample used by `sample`
+    pub fn memory_usage(&self, pid: u32) -> MemoryUsage {
+        let gpus = self.gpu_usage(pid);
+        let gpu_bytes = gpus
+            .iter()
+            .filter_map(|gpu| gpu.process_memory_bytes)
+            .reduce(|a, b| a + b);
+        MemoryUsage {
+            rss_bytes: read_rss_bytes(pid),
+            gpu_bytes,
+        }
+    }
+
+    /// Health readings of every visible GPU; None when NVML is unavailable
+    #[cfg(feature = "nvml")]
+    pub fn gpu_readings(&self) -> Option<Vec<GpuReading>> {
+        use nvml_wrapper::enum_wrappers::device::{EccCounter, MemoryError, TemperatureSensor};
+
+        let nvml = self.nvml.as_ref()?;
+        let count = nvml.device_count().ok()?;
+        Some(
+            (0..count)
+                .filter_map(|index| {
+                    let device = nvml.device_by_index(index).ok()?;
+                    Some(GpuReading {
+                        index,
+                        temperature_c: device.temperature(TemperatureSensor::Gpu).ok(),
+                        // Not supported on GPUs without ECC memory
+                        ecc_uncorrected: device
+                            .total_ecc_errors(MemoryError::Uncorrected, EccCounter::Volatile)
+                            .ok(),
+                        memory_free_bytes: device.memory_info().ok().map(|m| m.free),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    #[cfg(not(feature = "nvml"))]
+    pub fn gpu_readings(&self) -> Option<Vec<GpuReading>> {
+        None
+    }
+
+    fn cpu_percent(&self, pid: u32) -> Option<f64> {
+        let ticks = read_cpu_ticks(pid)?;
+        let now = Instant::now();
+        let mut last = self.last_cpu_sample.lock().unwrap();
+        let previous = last.replace((now, ticks));
+
+        // The first sample (or a restarted process with fewer ticks) has nothing to diff against
+        let (prev_time, prev_ticks) = previous?;
+        if ticks < prev_ticks {
+            return None;
+        }
+        let elapsed = now.duration_since(prev_time).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let cpu_secs = (ticks - prev_ticks) as f64 / clock_ticks_per_second();
+        Some(cpu_secs / elapsed * 100.0)
+    }
+
+    #[cfg(feature = "nvml")]
+    fn gpu_usage(&self, pid: u32) -> Vec<GpuUsage> {
+        use nvml_wrapper::enums::device::UsedGpuMemory;
+
+        let Some(nvml) = &self.nvml else {
+            return Vec::new();
+        };
+        let count = match nvml.device_count() {
+            Ok(count) => count,
+            Err(e) => {
+                tracing::debug!("Failed to query GPU count: {}", e);
+                return Vec::new();
+            }
+        };
+
+        (0..count)
+            .filter_map(|index| {
+                let device = nvml.device_by_index(index).ok()?;
+                let memory = device.memory_info().ok()?;
+                let utilization = device.utilization_rates().ok()?;
+                let process_memory_bytes =
+                    device
+                        .running_compute_processes()
+                        .ok()
+                        .and_then(|processes| {
+                            processes.into_iter().find(|p| p.pid == pid).and_then(|p| {
+                                match p.used_gpu_memory {
+                                    UsedGpuMemory::Used(bytes) => Some(bytes),
+                                    UsedGpuMemory::Unavailable => None,
+                                }
+                            })
+                        });
+                Some(GpuUsage {
+                    index,
+                    utilization_percent: utilization.gpu,
+                    memory_used_bytes: memory.used,
+                    memory_total_bytes: memory.total,
+                    process_memory_bytes,
+                })
+            })
+            .collect()
+    }
+
+    #[cfg(not(feature = "nvml"))]
+    fn gpu_usage(&self, _pid: u32) -> Vec<GpuUsage> {
+        Vec::new()
+    }
+}
+
+/// Total user + system CPU ticks consumed by `pid`, from /proc/<pid>/stat
+fn read_cpu_ticks(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces, so start parsing after its closing paren
+    let after_comm = &stat[stat.rfind(')')? + 1..];
+    let fields: Vec<&str> = after_comm.split_whitespace().collect();
+    // utime and stime are fields 14 and 15 of the full line (11 and 12 after the comm field)
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// Resident set size of `pid` in bytes, from /proc/<pid>/status
+fn read_rss_bytes(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+fn clock_ticks_per_second() -> f64 {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf has no preconditions
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks > 0 {
+            return ticks as f64;
+        }
+    }
+    100.0
+}
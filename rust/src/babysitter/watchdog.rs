@@ -0,0 +1,113 @@
+//! Memory watchdog: restart the managed service when its memory stays above a limit
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Memory held by the managed process
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryUsage {
+    pub rss_bytes: Option<u64>,
+    /// Summed over all GPUs the process has a context on
+    pub gpu_bytes: Option<u64>,
+}
+
+/// Tracks how long memory usage has been above the configured limits
+pub struct MemoryWatchdog {
+    rss_limit: Option<u64>,
+    gpu_limit: Option<u64>,
+    sustain: Duration,
+    over_since: Option<Instant>,
+}
+
+impl MemoryWatchdog {
+    pub fn new(rss_limit: Option<u64>, gpu_limit: Option<u64>, sustain: Duration) -> Self {
+        Self {
+            rss_limit,
+            gpu_limit,
+            sustain,
+            over_since: None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.rss_limit.is_some() || self.gpu_limit.is_some()
+    }
+
+    /// Record a sample; returns the reason to restart once usage has stayed over a limit
+    /// for the sustain period
+    pub fn observe(&mut self, usage: MemoryUsage, now: Instant) -> Option<String> {
+        let over = |used: Option<u64>, limit: Option<u64>| match (used, limit) {
+            (Some(used), Some(limit)) => used > limit,
+            _ => false,
+        };
+        let reason = if over(usage.rss_bytes, self.rss_limit) {
+            format!(
+                "RSS {} MiB above limit {} MiB",
+                usage.rss_bytes.unwrap_or_default() >> 20,
+                self.rss_limit.unwrap_or_default() >> 20
+            )
+        } else if over(usage.gpu_bytes, self.gpu_limit) {
+            format!(
+                "GPU memory {} MiB above limit {} MiB",
+                usage.gpu_bytes.unwrap_or_default() >> 20,
+                self.gpu_limit.unwrap_or_default() >> 20
+            )
+        } else {
+            self.over_since = None;
+            return None;
+        };
+
+        let since = *self.over_since.get_or_insert(now);
+        if now.duration_since(since) < self.sustain {
+            return None;
+        }
+        self.over_since = None;
+        Some(format!("{} for {}s", reason, self.sustain.as_secs()))
+    }
+}
+
+/// Watchdog restarts reported by /info
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WatchdogReport {
+    pub restarts: u32,
+    pub last_restart: Option<String>,
+    pub last_reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restarts_only_after_sustained_overage() {
+        let mut watchdog = MemoryWatchdog::new(Some(100 << 20), None, Duration::from_secs(60));
+        let start = Instant::now();
+        let usage = |rss_mib: u64| MemoryUsage {
+            rss_bytes: Some(rss_mib << 20),
+            gpu_bytes: None,
+        };
+
+        assert_eq!(watchdog.observe(usage(150), start), None);
+        // Dropping below the limit resets the clock
+        assert_eq!(
+            watchdog.observe(usage(50), start + Duration::from_secs(30)),
+            None
+        );
+        assert_eq!(
+            watchdog.observe(usage(150), start + Duration::from_secs(40)),
+            None
+        );
+        assert_eq!(
+            watchdog.observe(usage(150), start + Duration::from_secs(90)),
+            None
+        );
+        let reason = watchdog
+            .observe(usage(150), start + Duration::from_secs(100))
+            .unwrap();
+        assert!(reason.contains("RSS 150 MiB"));
+
+        let mut unlimited = MemoryWatchdog::new(None, None, Duration::ZERO);
+        assert!(!unlimited.enabled());
+        assert_eq!(unlimited.observe(usage(1 << 20), start), None);
+    }
+}
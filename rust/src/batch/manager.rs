@@ -0,0 +1,474 @@
+//! Batch bookkeeping and persistence
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{info, warn};
+
+use crate::utils::errors::RouterError;
+use crate::utils::time::current_timestamp_secs;
+
+/// One line of a batch input file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    pub body: Value,
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    InProgress,
+    Cancelling,
+    Completed,
+    Cancelled,
+}
+
+impl BatchStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, BatchStatus::Completed | BatchStatus::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestCounts {
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+/// Batch object, shaped like the OpenAI Batch API's
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Batch {
+    pub id: String,
+    pub object: String,
+    pub endpoint: String,
+    pub status: BatchStatus,
+    pub created_at: u64,
+    pub completed_at: Option<u64>,
+    pub cancelled_at: Option<u64>,
+    pub request_counts: RequestCounts,
+    /// Tenant pool the batch's requests are routed within
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool: Option<String>,
+}
+
+/// A submitted batch: its requests, state and the results collected so far
+pub struct BatchJob {
+    pub requests: Vec<BatchRequest>,
+    state: Mutex<Batch>,
+    results: Mutex<Vec<Value>>,
+    cancelled: AtomicBool,
+}
+
+impl BatchJob {
+    pub fn id(&self) -> String {
+        self.state.lock().unwrap().id.clone()
+    }
+
+    /// Snapshot of the batch object
+    pub fn batch(&self) -> Batch {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Result lines as JSONL
+    pub fn output(&self) -> String {
+        self.results
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// custom_ids that already have a result
+    pub fn finished_ids(&self) -> HashSet<String> {
+        self.results
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|line| line.get("custom_id").and_then(|v| v.as_str()))
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Endpoints batch requests may target
+pub const BATCH_ENDPOINTS: &[&str] = &["/v1/chat/completions", "/v1/completions", "/v1/embeddings"];
+
+/// Parse a JSONL batch; every request must target the same endpoint
+/// (`endpoint`, or the first request's url when not given), one of `BATCH_ENDPOINTS`
+pub fn parse_batch_input(
+    input: &str,
+    endpoint: Option<&str>,
+) -> Result<(String, Vec<BatchRequest>), String> {
+    if let Some(endpoint) = endpoint.filter(|e| !BATCH_ENDPOINTS.contains(e)) {
+        return Err(format!("Unsupported batch endpoint '{}'", endpoint));
+    }
+    let mut requests: Vec<BatchRequest> = Vec::new();
+    let mut custom_ids = HashSet::new();
+
+    for (index, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: BatchRequest = serde_json::from_str(line)
+            .map_err(|e| format!("Line {}: invalid batch request: {}", index + 1, e))?;
+        if !request.method.eq_ignore_ascii_case("POST") {
+            return Err(format!(
+                "Line {}: only POST requests are supported",
+                index + 1
+            ));
+        }
+        if !BATCH_ENDPOINTS.contains(&request.url.as_str()) {
+            return Err(format!(
+                "Line {}: unsupported url '{}' (expected one of {})",
+                index + 1,
+                request.url,
+                BATCH_ENDPOINTS.join(", ")
+            ));
+        }
+        let expected = endpoint
+            .or_else(|| requests.first().map(|r| r.url.as_str()))
+            .unwrap_or(&request.url);
+        if request.url != expected {
+            return Err(format!(
+                "Line {}: url '{}' does not match batch endpoint '{}'",
+                index + 1,
+                request.url,
+                expected
+            ));
+        }
+        if !custom_ids.insert(request.custom_id.clone()) {
+            return Err(format!(
+                "Line {}: duplicate custom_id '{}'",
+                index + 1,
+                request.custom_id
+            ));
+        }
+        requests.push(request);
+    }
+
+    match requests.first() {
+        Some(first) => Ok((first.url.clone(), requests)),
+        None => Err("Batch contains no requests".to_string()),
+    }
+}
+
+/// Registry of batches, optionally persisted to a directory
+///
+/// Each batch is stored as `<id>.json` (state), `<id>_input.jsonl` and `<id>_output.jsonl`.
+pub struct BatchManager {
+    dir: Option<PathBuf>,
+    concurrency: usize,
+    batches: RwLock<HashMap<String, Arc<BatchJob>>>,
+}
+
+impl BatchManager {
+    /// Create the manager, loading batches persisted in `dir`
+    pub fn new(dir: Option<PathBuf>, concurrency: usize) -> Result<Self, RouterError> {
+        let mut batches = HashMap::new();
+        if let Some(dir) = &dir {
+            fs::create_dir_all(dir)?;
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                match Self::load_job(dir, &path) {
+                    Ok(job) => {
+                        batches.insert(job.id(), Arc::new(job));
+                    }
+                    Err(e) => warn!("Skipping unreadable batch {:?}: {}", path, e),
+                }
+            }
+            info!(
+                "Loaded {} persisted batch(es) from {:?}",
+                batches.len(),
+                dir
+            );
+        }
+
+        Ok(Self {
+            dir,
+            concurrency: concurrency.max(1),
+            batches: RwLock::new(batches),
+        })
+    }
+
+    fn load_job(dir: &Path, state_path: &Path) -> Result<BatchJob, RouterError> {
+        let mut batch: Batch = serde_json::from_str(&fs::read_to_string(state_path)?)?;
+
+        let requests = fs::read_to_string(dir.join(format!("{}_input.jsonl", batch.id)))?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<BatchRequest>, _>>()?;
+
+        // A crash can leave a partial last line; keep only complete results
+        let output_path = dir.join(format!("{}_output.jsonl", batch.id));
+        let raw_output = fs::read_to_string(&output_path).unwrap_or_default();
+        let results: Vec<Value> = raw_output
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        if results.len() != raw_output.lines().count() {
+            let rewritten: String = results.iter().map(|r| format!("{}\n", r)).collect();
+            fs::write(&output_path, rewritten)?;
+        }
+
+        batch.request_counts = RequestCounts {
+            total: requests.len(),
+            completed: results.iter().filter(|r| is_success(r)).count(),
+            failed: results.iter().filter(|r| !is_success(r)).count(),
+        };
+        let cancelled = batch.status == BatchStatus::Cancelling;
+        if cancelled {
+            batch.status = BatchStatus::Cancelled;
+            batch.cancelled_at = Some(current_timestamp_secs());
+        }
+
+        Ok(BatchJob {
+            requests,
+            state: Mutex::new(batch),
+            results: Mutex::new(results),
+            cancelled: AtomicBool::new(cancelled),
+        })
+    }
+
+    /// Maximum requests of one batch in flight at a time
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Register a new batch
+    pub fn create(
+        &self,
+        endpoint: String,
+        requests: Vec<BatchRequest>,
+        pool: Option<String>,
+    ) -> Result<Arc<BatchJob>, RouterError> {
+        let batch = Batch {
+            id: format!("batch_{:016x}", rand::random::<u64>()),
+            object: "batch".to_string(),
+            endpoint,
+            status: BatchStatus::InProgress,
+            created_at: current_timestamp_secs(),
+            completed_at: None,
+            cancelled_at: None,
+            request_counts: RequestCounts {
+                total: requests.len(),
+                ..Default::default()
+            },
+            pool,
+        };
+
+        if let Some(dir) = &self.dir {
+            let input: String = requests
+                .iter()
+                .map(|r| serde_json::to_string(r).map(|line| line + "\n"))
+                .collect::<Result<_, _>>()?;
+            fs::write(dir.join(format!("{}_input.jsonl", batch.id)), input)?;
+            self.save_state(&batch)?;
+        }
+
+        let job = Arc::new(BatchJob {
+            requests,
+            state: Mutex::new(batch),
+            results: Mutex::new(Vec::new()),
+            cancelled: AtomicBool::new(false),
+        });
+        self.batches.write().unwrap().insert(job.id(), job.clone());
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<BatchJob>> {
+        self.batches.read().unwrap().get(id).cloned()
+    }
+
+    /// All batches, newest first
+    pub fn list(&self) -> Vec<Batch> {
+        let mut batches: Vec<Batch> = self
+            .batches
+            .read()
+            .unwrap()
+            .values()
+            .map(|job| job.batch())
+            .collect();
+        batches.sort_by_key(|batch| std::cmp::Reverse(batch.created_at));
+        batches
+    }
+
+    /// Batches that still have work to do (e.g. after a restart)
+    pub fn unfinished(&self) -> Vec<Arc<BatchJob>> {
+        self.batches
+            .read()
+            .unwrap()
+            .values()
+            .filter(|job| !job.batch().status.is_finished())
+            .cloned()
+            .collect()
+    }
+
+    /// Store one request's result line
+    pub fn record_result(&self, job: &BatchJob, result: Value) {
+        let mut results = job.results.lock().unwrap();
+        if let Some(dir) = &self.dir {
+            let path = dir.join(format!("{}_output.jsonl", job.id()));
+            let appended = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| writeln!(file, "{}", result));
+            if let Err(e) = appended {
+                warn!("Failed to persist batch result to {:?}: {}", path, e);
+            }
+        }
+
+        let mut batch = job.state.lock().unwrap();
+        if is_success(&result) {
+            batch.request_counts.completed += 1;
+        } else {
+            batch.request_counts.failed += 1;
+        }
+        results.push(result);
+        self.persist(&batch);
+    }
+
+    /// Mark a batch as done once its runner has stopped
+    pub fn finish(&self, job: &BatchJob) {
+        let mut batch = job.state.lock().unwrap();
+        if job.is_cancelled() {
+            batch.status = BatchStatus::Cancelled;
+            batch.cancelled_at = Some(current_timestamp_secs());
+        } else {
+            batch.status = BatchStatus::Completed;
+            batch.completed_at = Some(current_timestamp_secs());
+        }
+        info!(
+            "Batch {} {:?}: {} completed, {} failed of {}",
+            batch.id,
+            batch.status,
+            batch.request_counts.completed,
+            batch.request_counts.failed,
+            batch.request_counts.total
+        );
+        self.persist(&batch);
+    }
+
+    /// Stop dispatching a batch's remaining requests
+    pub fn cancel(&self, id: &str) -> Option<Batch> {
+        let job = self.get(id)?;
+        let mut batch = job.state.lock().unwrap();
+        if !batch.status.is_finished() {
+            job.cancelled.store(true, Ordering::Relaxed);
+            batch.status = BatchStatus::Cancelling;
+            self.persist(&batch);
+        }
+        Some(batch.clone())
+    }
+
+    fn persist(&self, batch: &Batch) {
+        if let Err(e) = self.save_state(batch) {
+            warn!("Failed to persist batch {}: {}", batch.id, e);
+        }
+    }
+
+    fn save_state(&self, batch: &Batch) -> Result<(), RouterError> {
+        if let Some(dir) = &self.dir {
+            let path = dir.join(format!("{}.json", batch.id));
+            let tmp_path = path.with_extension("json.tmp");
+            fs::write(&tmp_path, serde_json::to_vec(batch)?)?;
+            fs::rename(&tmp_path, &path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a result line holds a 2xx response
+fn is_success(result: &Value) -> bool {
+    result
+        .pointer("/response/status_code")
+        .and_then(|v| v.as_u64())
+        .is_some_and(|status| (200..300).contains(&status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch_input() {
+        let input = r#"{"custom_id": "a", "method": "POST", "url": "/v1/chat/completions", "body": {"model": "m"}}
+
+{"custom_id": "b", "url": "/v1/chat/completions", "body": {"model": "m"}}
+"#;
+        let (endpoint, requests) = parse_batch_input(input, None).unwrap();
+        assert_eq!(endpoint, "/v1/chat/completions");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].method, "POST");
+
+        assert!(parse_batch_input(input, Some("/v1/embeddings")).is_err());
+        assert!(parse_batch_input("", None).is_err());
+
+        let duplicate = r#"{"custom_id": "a", "url": "/v1/embeddings", "body": {}}
+{"custom_id": "a", "url": "/v1/embeddings", "body": {}}"#;
+        assert!(parse_batch_input(duplicate, None)
+            .unwrap_err()
+            .contains("duplicate custom_id"));
+    }
+
+    #[test]
+    fn test_parse_batch_input_rejects_foreign_urls() {
+        for url in ["@attacker.example/x", "//attacker.example/x", "/admin/sync"] {
+            let input = format!(r#"{{"custom_id": "a", "url": "{}", "body": {{}}}}"#, url);
+            assert!(parse_batch_input(&input, None)
+                .unwrap_err()
+                .contains("unsupported url"));
+        }
+        let input = r#"{"custom_id": "a", "url": "/v1/embeddings", "body": {}}"#;
+        assert!(parse_batch_input(input, Some("@attacker.example/x")).is_err());
+    }
+
+    #[test]
+    fn test_batch_persistence() {
+        let dir = std::env::temp_dir().join(format!("infini-batches-{}", rand::random::<u64>()));
+        let manager = BatchManager::new(Some(dir.clone()), 4).unwrap();
+        let (endpoint, requests) = parse_batch_input(
+            r#"{"custom_id": "a", "url": "/v1/embeddings", "body": {}}
+{"custom_id": "b", "url": "/v1/embeddings", "body": {}}"#,
+            None,
+        )
+        .unwrap();
+        let job = manager.create(endpoint, requests, None).unwrap();
+        manager.record_result(
+            &job,
+            serde_json::json!({"custom_id": "a", "response": {"status_code": 200, "body": {}}}),
+        );
+
+        // Reloading keeps the unfinished batch and its progress
+        let reloaded = BatchManager::new(Some(dir.clone()), 4).unwrap();
+        let pending = reloaded.unfinished();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].batch().request_counts.completed, 1);
+        assert!(pending[0].finished_ids().contains("a"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,7 @@
+//! OpenAI Batch API emulation
+//!
+//! Batches are submitted as JSONL, executed in the background across healthy services
+//! and their results served back as JSONL.
+
+pub mod manager;
+pub mod runner;
@@ -0,0 +1,192 @@
+//! Batch execution: fans a batch's requests out across healthy backends
+
+use axum::body::Bytes;
+use axum::http::HeaderMap;
+use futures::StreamExt;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::batch::manager::{BatchJob, BatchRequest};
+use crate::proxy::handler::{proxy_timeout_for, HTTP_CLIENT};
+use crate::proxy::priority::{request_client, Priority};
+use crate::router::capabilities::Requirements;
+use crate::router::load_balancer::LoadBalancer;
+use crate::router::token_estimate::text_tokens;
+
+/// Attempts per request before it is recorded as failed
+const BATCH_MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled for each further attempt
+const BATCH_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Run a batch's outstanding requests in the background
+pub fn spawn_batch(load_balancer: Arc<LoadBalancer>, job: Arc<BatchJob>) {
+    tokio::spawn(async move {
+        let manager = load_balancer.batches();
+        let finished = job.finished_ids();
+        let pending: Vec<&BatchRequest> = job
+            .requests
+            .iter()
+            .filter(|request| !finished.contains(&request.custom_id))
+            .collect();
+        info!(
+            "Running batch {}: {} of {} request(s) outstanding",
+            job.id(),
+            pending.len(),
+            job.requests.len()
+        );
+
+        let pool = job.batch().pool;
+        futures::stream::iter(pending)
+            .for_each_concurrent(manager.concurrency(), |request| {
+                let load_balancer = &load_balancer;
+                let job = &job;
+                let pool = pool.as_deref();
+                async move {
+                    if job.is_cancelled() {
+                        return;
+                    }
+                    let result = execute_request(load_balancer, request, pool).await;
+                    manager.record_result(job, result_line(request, result));
+                }
+            })
+            .await;
+
+        manager.finish(&job);
+    });
+}
+
+/// Resume batches left unfinished by a previous run
+pub fn resume_batches(load_balancer: &Arc<LoadBalancer>) {
+    for job in load_balancer.batches().unfinished() {
+        spawn_batch(load_balancer.clone(), job);
+    }
+}
+
+/// Send one request to a backend serving its model, retrying with backoff
+async fn execute_request(
+    load_balancer: &LoadBalancer,
+    request: &BatchRequest,
+    pool: Option<&str>,
+) -> Result<(u16, Value), String> {
+    let model_id = request.body.get("model").and_then(|v| v.as_str());
+
+    // Batch results are stored whole, so never ask for a stream
+    let requirements = Requirements::for_request(&request.url, &request.body);
+    let mut body = request.body.clone();
+    if let Some(fields) = body.as_object_mut() {
+        fields.remove("stream");
+    }
+    // Serialized once; every attempt sends the same shared buffer
+    let body = Bytes::from(serde_json::to_vec(&body).map_err(|e| e.to_string())?);
+    // Rough size of the request in tokens, for its share of a saturated model's queue;
+    // batches are accounted to their tenant pool, having no API key of their own
+    let cost = load_balancer
+        .token_estimator()
+        .estimate(model_id, text_tokens(&String::from_utf8_lossy(&body)));
+    let client = request_client(pool, &HeaderMap::new());
+
+    let mut last_error = String::new();
+    for attempt in 0..BATCH_MAX_ATTEMPTS {
+        if attempt > 0 {
+            sleep(BATCH_RETRY_BACKOFF * 2u32.pow(attempt - 1)).await;
+        }
+
+        // Batch work queues behind interactive traffic for saturated backends, sharing the
+        // low class fairly with the tenant's other requests
+        let admission = load_balancer
+            .admission()
+            .admit(model_id.unwrap_or(""), Priority::Low, &client, cost, || {
+                load_balancer.saturation_retry_after(model_id, &requirements, &request.url, pool)
+            })
+            .await;
+        let Ok(admission) = admission else {
+            last_error = "All services for the model are at capacity".to_string();
+            continue;
+        };
+        let service = match load_balancer
+            .get_next_healthy_service_by_model(model_id, &requirements, &request.url, pool)
+            .await
+        {
+            Some(service) => service,
+            None => {
+                last_error = match model_id {
+                    Some(model) => format!("No healthy services available for model '{}'", model),
+                    None => "No healthy services available".to_string(),
+                };
+                continue;
+            }
+        };
+        let _in_flight = service.track_in_flight();
+        drop(admission);
+
+        let target_url = match Url::parse(&service.url).and_then(|base| base.join(&request.url)) {
+            Ok(url) => url,
+            Err(e) => {
+                last_error = format!("Invalid URL for service {}: {}", service.name, e);
+                continue;
+            }
+        };
+        let response = match HTTP_CLIENT
+            .post(target_url)
+            .timeout(proxy_timeout_for(load_balancer, &service, model_id))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone())
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                warn!(
+                    "Batch request {} to service {} failed: {}",
+                    request.custom_id, service.name, e
+                );
+                service.record_last_error(format!("Batch request failed: {}", e));
+                load_balancer.report_proxy_failure(&service).await;
+                last_error = format!("Error communicating with service: {}", e);
+                continue;
+            }
+        };
+
+        let status = response.status();
+        if status.is_server_error() {
+            last_error = format!("Service {} returned {}", service.name, status);
+            service.record_last_error(last_error.clone());
+            service.increment_error_count();
+            continue;
+        }
+        service.increment_request_count();
+        load_balancer.report_proxy_success(&service).await;
+
+        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+        let body = serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+        return Ok((status.as_u16(), body));
+    }
+
+    Err(last_error)
+}
+
+/// Output line in the OpenAI batch result format
+fn result_line(request: &BatchRequest, result: Result<(u16, Value), String>) -> Value {
+    let id = format!("batch_req_{:016x}", rand::random::<u64>());
+    match result {
+        Ok((status_code, body)) => json!({
+            "id": id,
+            "custom_id": request.custom_id,
+            "response": {"status_code": status_code, "body": body},
+            "error": null
+        }),
+        Err(message) => json!({
+            "id": id,
+            "custom_id": request.custom_id,
+            "response": null,
+            "error": {"code": "backend_error", "message": message}
+        }),
+    }
+}
@@ -1,23 +1,12 @@
 //! Enhanced Babysitter for InfiniLM Services
 //! Manages service lifecycle, health monitoring, and registry integration
 
+use anyhow::Context;
 use anyhow::Result;
-use std::sync::Arc;
+use infini_router::babysitter::config::BabysitterConfig;
+use infini_router::babysitter::config_file::BabysitterConfigFile;
 use tokio::signal;
-use tokio::sync::RwLock;
-use tracing::info;
-
-// Declare babysitter modules (using path attribute to point to src/babysitter/)
-#[path = "../babysitter/mod.rs"]
-mod babysitter;
-
-use anyhow::Context;
-use babysitter::config::BabysitterConfig;
-use babysitter::config_file::BabysitterConfigFile;
-use babysitter::handlers::BabysitterHandlers;
-use babysitter::process_manager::ProcessManager;
-use babysitter::registry_client::BabysitterRegistryClient;
-use babysitter::BabysitterState;
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -33,94 +22,121 @@ async fn main() -> Result<()> {
     let cli_config = <BabysitterConfig as clap::Parser>::parse();
 
     // Load config from file if specified, otherwise use CLI config
-    let (config, config_file): (BabysitterConfig, Option<BabysitterConfigFile>) = if let Some(config_file_path) = &cli_config.config_file {
-        // Load from TOML file and merge with CLI args (CLI takes precedence)
-        let file_config = BabysitterConfigFile::from_file(config_file_path)
-            .with_context(|| format!("Failed to load config file: {:?}", config_file_path))?;
-        let mut merged = file_config.to_cli_config();
+    let configs: Vec<(BabysitterConfig, Option<BabysitterConfigFile>)> =
+        if let Some(config_file_path) = &cli_config.config_file {
+            // Load from the config file (TOML, YAML or JSON) and merge with CLI args (CLI takes precedence)
+            let file_configs = BabysitterConfigFile::from_file_all(config_file_path)
+                .with_context(|| format!("Failed to load config file: {:?}", config_file_path))?;
+            let single_service = file_configs.len() == 1;
+            if !single_service && (cli_config.name.is_some() || cli_config.port.is_some()) {
+                warn!(
+                    "--name and --port are ignored when the config file declares multiple services"
+                );
+            }
+
+            file_configs
+                .into_iter()
+                .map(|file_config| {
+                    let mut merged = file_config.to_cli_config();
+
+                    // Override with CLI values if provided (name and port identify a single service)
+                    if single_service {
+                        if cli_config.name.is_some() {
+                            merged.name = cli_config.name.clone();
+                        }
+                        if let Some(port) = cli_config.port {
+                            merged.port = Some(port);
+                        }
+                        if cli_config.babysitter_port.is_some() {
+                            merged.babysitter_port = cli_config.babysitter_port;
+                        }
+                        if cli_config.gpus.is_some() {
+                            merged.gpus = cli_config.gpus.clone();
+                        }
+                    }
+                    // Override host if provided via CLI (important for cross-server registration)
+                    // Config file may have "0.0.0.0" for binding, but we need actual IP for registration
+                    // Only override if CLI host is explicitly provided (not default "localhost")
+                    // This allows config file "0.0.0.0" to be used when CLI host is default
+                    // But if --host is explicitly passed, it overrides config
+                    // We detect explicit override by checking if host differs from default AND from config
+                    if cli_config.host != "localhost" && cli_config.host != merged.host {
+                        merged.host = cli_config.host.clone();
+                    }
+                    if !cli_config.bind.is_empty() {
+                        merged.bind = cli_config.bind.clone();
+                    }
+                    if cli_config.registry_url.is_some() {
+                        merged.registry_url = cli_config.registry_url.clone();
+                    }
+                    if cli_config.registry_ca_cert.is_some() {
+                        merged.registry_ca_cert = cli_config.registry_ca_cert.clone();
+                    }
+                    if cli_config.registry_client_cert.is_some() {
+                        merged.registry_client_cert = cli_config.registry_client_cert.clone();
+                        merged.registry_client_key = cli_config.registry_client_key.clone();
+                    }
+                    if cli_config.registry_token.is_some() {
+                        merged.registry_token = cli_config.registry_token.clone();
+                    }
+                    if cli_config.admin_token.is_some() {
+                        merged.admin_token = cli_config.admin_token.clone();
+                    }
+                    merged.allow_update_args |= cli_config.allow_update_args;
+                    if cli_config.router_url.is_some() {
+                        merged.router_url = cli_config.router_url.clone();
+                    }
+                    if cli_config.router_token.is_some() {
+                        merged.router_token = cli_config.router_token.clone();
+                    }
+                    // --env entries are merged on top of the backend env from the file
+                    merged.env = cli_config.env.clone();
+                    // ... add more overrides as needed
+
+                    // Store the loaded config file object so environment variables can be accessed
+                    (merged, Some(file_config))
+                })
+                .collect()
+        } else {
+            // Validate required CLI arguments when not using config file
+            if cli_config.port.is_none() {
+                anyhow::bail!("--port is required when --config-file is not provided");
+            }
+            vec![(cli_config, None)]
+        };
+
+    info!(
+        "Starting Enhanced Babysitter ({} managed service(s))",
+        configs.len()
+    );
+
+    infini_router::babysitter::service::run(configs, shutdown_signal()).await?;
 
-        // Override with CLI values if provided
-        if cli_config.name.is_some() {
-            merged.name = cli_config.name.clone();
-        }
-        if let Some(port) = cli_config.port {
-            merged.port = Some(port);
-        }
-        // Override host if provided via CLI (important for cross-server registration)
-        // Config file may have "0.0.0.0" for binding, but we need actual IP for registration
-        // Only override if CLI host is explicitly provided (not default "localhost")
-        // This allows config file "0.0.0.0" to be used when CLI host is default
-        // But if --host is explicitly passed, it overrides config
-        // We detect explicit override by checking if host differs from default AND from config
-        if cli_config.host != "localhost" && cli_config.host != merged.host {
-            merged.host = cli_config.host.clone();
-        }
-        if cli_config.registry_url.is_some() {
-            merged.registry_url = cli_config.registry_url.clone();
-        }
-        // ... add more overrides as needed
+    info!("Babysitter stopped");
+    Ok(())
+}
 
-        // Store the loaded config file object so environment variables can be accessed
-        (merged, Some(file_config))
-    } else {
-        // Validate required CLI arguments when not using config file
-        if cli_config.port.is_none() {
-            anyhow::bail!("--port is required when --config-file is not provided");
-        }
-        (cli_config, None)
+/// Wait for Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
     };
 
-    info!("Starting Enhanced Babysitter");
-    info!("Service: {}", config.service_name());
-    let port = config.port.expect("Port must be set");
-    info!("Port: {} (babysitter: {})", port, port + 1);
-    info!("Registry: {:?}", config.registry_url);
-
-    // Create shared state
-    let state = Arc::new(BabysitterState {
-        config: config.clone(),
-        config_file,
-        process: Arc::new(RwLock::new(None)),
-        service_port: Arc::new(RwLock::new(None)),
-        start_time: std::time::Instant::now(),
-        restart_count: Arc::new(RwLock::new(0)),
-    });
-
-    // Start HTTP server
-    let handlers = BabysitterHandlers::new(state.clone());
-    let server_handle = tokio::spawn(async move {
-        if let Err(e) = handlers.start_server().await {
-            tracing::error!("HTTP server error: {}", e);
-        }
-    });
-
-    // Start process manager
-    let process_manager = ProcessManager::new(state.clone());
-    let process_handle = tokio::spawn(async move { process_manager.run().await });
-
-    // Start registry client (if configured)
-    if let Some(registry_url) = &config.registry_url {
-        let registry_client = BabysitterRegistryClient::new(registry_url.to_string(), state.clone());
-        let registry_handle = tokio::spawn(async move { registry_client.run().await });
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
 
-        // Wait for shutdown signal
-        signal::ctrl_c().await?;
-        info!("Received shutdown signal, cleaning up...");
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-        // Stop registry client
-        registry_handle.abort();
-    } else {
-        // Wait for shutdown signal
-        signal::ctrl_c().await?;
-        info!("Received shutdown signal, cleaning up...");
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
     }
-
-    // Stop process manager
-    process_handle.abort();
-
-    // Stop HTTP server
-    server_handle.abort();
-
-    info!("Babysitter stopped");
-    Ok(())
 }
@@ -0,0 +1,261 @@
+//! Load-testing benchmark for the router
+//! Sends synthetic OpenAI-style chat traffic and reports throughput, latency and TTFT
+
+use anyhow::Result;
+use clap::Parser;
+use futures::StreamExt;
+use infini_router::router::latency::LatencyHistogram;
+use rand::Rng;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Filler words making up synthetic prompts
+const PROMPT_WORDS: &[&str] = &[
+    "the", "router", "balances", "requests", "across", "healthy", "backends", "while", "models",
+    "stream", "tokens", "back", "to", "every", "client",
+];
+
+/// Command-line arguments
+#[derive(Parser, Debug)]
+#[command(name = "infini-bench")]
+#[command(about = "Load-testing benchmark for the InfiniLM router")]
+struct Args {
+    /// Router base URL
+    #[arg(long, default_value = "http://localhost:8080")]
+    url: String,
+
+    /// Model to request; left out of the request body when unset
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Total requests to send
+    #[arg(long, default_value = "100")]
+    requests: u64,
+
+    /// Requests kept in flight at once
+    #[arg(long, default_value = "8")]
+    concurrency: usize,
+
+    /// Prompt sizes in words, one picked at random per request
+    #[arg(long, value_delimiter = ',', default_value = "32,256,1024")]
+    prompt_words: Vec<usize>,
+
+    /// max_tokens sent with each request
+    #[arg(long, default_value = "64")]
+    max_tokens: u32,
+
+    /// Fraction of requests sent with stream=true (0.0-1.0)
+    #[arg(long, default_value = "0.5")]
+    stream_ratio: f64,
+
+    /// Per-request timeout in seconds
+    #[arg(long, default_value = "120")]
+    timeout: u64,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// Outcomes collected from all workers
+#[derive(Default)]
+struct Results {
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+    bytes: AtomicU64,
+    latency: LatencyHistogram,
+    /// Time to the first chunk of streaming responses
+    first_token: LatencyHistogram,
+    last_error: Mutex<Option<String>>,
+}
+
+/// Chat completion request with a prompt of `words` words; `id` keeps prompts distinct
+/// so session affinity and prefix caches do not pin every request to one backend
+fn request_body(
+    model: Option<&str>,
+    id: u64,
+    words: usize,
+    max_tokens: u32,
+    stream: bool,
+) -> Value {
+    let mut prompt = format!("Request {}:", id);
+    for word in PROMPT_WORDS.iter().cycle().take(words) {
+        prompt.push(' ');
+        prompt.push_str(word);
+    }
+    let mut body = json!({
+        "messages": [{"role": "user", "content": prompt}],
+        "max_tokens": max_tokens,
+        "stream": stream,
+    });
+    if let Some(model) = model {
+        body["model"] = json!(model);
+    }
+    body
+}
+
+/// Send one request and read the whole response; returns the bytes received and
+/// the time from `started` to the first non-empty chunk
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    body: &Value,
+    started: Instant,
+) -> Result<(u64, Option<Duration>), String> {
+    let response = client
+        .post(url)
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status));
+    }
+
+    let mut stream = response.bytes_stream();
+    let mut bytes = 0;
+    let mut first_chunk = None;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        if !chunk.is_empty() && first_chunk.is_none() {
+            first_chunk = Some(started.elapsed());
+        }
+        bytes += chunk.len() as u64;
+    }
+    Ok((bytes, first_chunk))
+}
+
+/// Send requests until `next` reaches the total
+async fn worker(
+    client: reqwest::Client,
+    args: Arc<Args>,
+    next: Arc<AtomicU64>,
+    results: Arc<Results>,
+) {
+    let url = format!("{}/v1/chat/completions", args.url.trim_end_matches('/'));
+    loop {
+        let id = next.fetch_add(1, Ordering::Relaxed);
+        if id >= args.requests {
+            break;
+        }
+        let (words, stream) = {
+            let mut rng = rand::thread_rng();
+            (
+                args.prompt_words[rng.gen_range(0..args.prompt_words.len())],
+                rng.gen_bool(args.stream_ratio),
+            )
+        };
+        let body = request_body(args.model.as_deref(), id, words, args.max_tokens, stream);
+
+        let started = Instant::now();
+        match send(&client, &url, &body, started).await {
+            Ok((bytes, first_chunk)) => {
+                results.latency.record(started.elapsed());
+                if let Some(first_chunk) = first_chunk.filter(|_| stream) {
+                    results.first_token.record(first_chunk);
+                }
+                results.bytes.fetch_add(bytes, Ordering::Relaxed);
+                results.succeeded.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                results.failed.fetch_add(1, Ordering::Relaxed);
+                *results.last_error.lock().unwrap() = Some(e);
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.concurrency == 0 || args.prompt_words.is_empty() {
+        anyhow::bail!("--concurrency and --prompt-words must be non-zero/non-empty");
+    }
+    if !(0.0..=1.0).contains(&args.stream_ratio) {
+        anyhow::bail!("--stream-ratio must be between 0.0 and 1.0");
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(args.timeout))
+        .build()?;
+    let args = Arc::new(args);
+    let next = Arc::new(AtomicU64::new(0));
+    let results = Arc::new(Results::default());
+
+    if !args.json {
+        println!(
+            "Sending {} requests to {} ({} concurrent)...",
+            args.requests, args.url, args.concurrency
+        );
+    }
+    let started = Instant::now();
+    let workers: Vec<_> = (0..args.concurrency)
+        .map(|_| {
+            tokio::spawn(worker(
+                client.clone(),
+                args.clone(),
+                next.clone(),
+                results.clone(),
+            ))
+        })
+        .collect();
+    futures::future::join_all(workers).await;
+    let elapsed = started.elapsed().as_secs_f64().max(f64::EPSILON);
+
+    let succeeded = results.succeeded.load(Ordering::Relaxed);
+    let failed = results.failed.load(Ordering::Relaxed);
+    let bytes = results.bytes.load(Ordering::Relaxed);
+    let latency = results.latency.summary();
+    let first_token = results.first_token.summary();
+    let last_error = results.last_error.lock().unwrap().clone();
+
+    if args.json {
+        let report = json!({
+            "requests": succeeded + failed,
+            "succeeded": succeeded,
+            "failed": failed,
+            "duration_secs": elapsed,
+            "requests_per_sec": succeeded as f64 / elapsed,
+            "bytes_per_sec": bytes as f64 / elapsed,
+            "latency": latency,
+            "first_token_latency": first_token,
+            "last_error": last_error,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!(
+        "Requests:     {} ({} ok, {} failed)",
+        succeeded + failed,
+        succeeded,
+        failed
+    );
+    println!("Duration:     {:.2}s", elapsed);
+    println!(
+        "Throughput:   {:.2} req/s, {:.1} KiB/s",
+        succeeded as f64 / elapsed,
+        bytes as f64 / 1024.0 / elapsed
+    );
+    println!(
+        "Latency (ms): p50 {}  p95 {}  p99 {}  max {}",
+        latency.p50_ms, latency.p95_ms, latency.p99_ms, latency.max_ms
+    );
+    if first_token.count > 0 {
+        println!(
+            "TTFT (ms):    p50 {}  p95 {}  p99 {}  max {}  ({} streams)",
+            first_token.p50_ms,
+            first_token.p95_ms,
+            first_token.p99_ms,
+            first_token.max_ms,
+            first_token.count
+        );
+    }
+    if let Some(error) = last_error {
+        println!("Last error:   {}", error);
+    }
+    Ok(())
+}
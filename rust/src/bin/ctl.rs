@@ -0,0 +1,273 @@
+//! Operator CLI for the router, registry and babysitter admin APIs
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Client, Method};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Interval between polls of a babysitter's logs with --follow
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Command-line arguments
+#[derive(Parser, Debug)]
+#[command(name = "infini-ctl")]
+#[command(about = "Admin CLI for InfiniLM router, registry and babysitters")]
+struct Args {
+    /// Router base URL
+    #[arg(
+        long,
+        env = "INFINI_ROUTER_URL",
+        default_value = "http://localhost:8080"
+    )]
+    router: String,
+
+    /// Registry base URL
+    #[arg(
+        long,
+        env = "INFINI_REGISTRY_URL",
+        default_value = "http://localhost:8081"
+    )]
+    registry: String,
+
+    /// Token for the router's /admin endpoints (its --admin-token)
+    #[arg(long, env = "INFINI_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Token for the babysitters' /logs (their --admin-token)
+    #[arg(long, env = "INFINI_BABYSITTER_TOKEN", hide_env_values = true)]
+    babysitter_token: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List services known to the router (or to the registry with --registry-view)
+    Services {
+        /// List the registry's catalog instead of the router's view
+        #[arg(long)]
+        registry_view: bool,
+    },
+    /// Show everything the router knows about one service
+    Service { name: String },
+    /// Show router statistics
+    Stats,
+    /// Take a service out of rotation; in-flight requests finish
+    Drain { name: String },
+    /// Put a drained service back into rotation
+    Undrain { name: String },
+    /// Mark a service healthy (or unhealthy) until its next health check
+    ForceHealth {
+        name: String,
+        #[arg(long)]
+        unhealthy: bool,
+    },
+    /// Run a registry sync on the router now
+    Sync,
+    /// Print a babysitter's recent log lines
+    Logs {
+        /// Router service name, or the babysitter's base URL
+        target: String,
+        /// Number of most recent lines
+        #[arg(long, default_value = "100")]
+        lines: usize,
+        /// stdout, stderr or all
+        #[arg(long, default_value = "all")]
+        stream: String,
+        /// Keep polling for new lines
+        #[arg(long, short)]
+        follow: bool,
+    },
+}
+
+/// Send a request and return its JSON body, failing on non-success statuses
+async fn call(client: &Client, method: Method, url: &str, body: Option<Value>) -> Result<Value> {
+    let mut request = client.request(method, url);
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Request to {} failed", url))?;
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        bail!("{} returned {}: {}", url, status, text.trim());
+    }
+    Ok(serde_json::from_str(&text).unwrap_or(Value::String(text)))
+}
+
+/// Client for admin endpoints, sending the admin token when one is given
+fn admin_client(token: Option<&str>) -> Result<Client> {
+    let mut headers = HeaderMap::new();
+    if let Some(token) = token {
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).context("Invalid admin token")?,
+        );
+    }
+    Ok(Client::builder()
+        .timeout(Duration::from_secs(30))
+        .default_headers(headers)
+        .build()?)
+}
+
+fn print_json(value: &Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or("-")
+}
+
+/// One row per service from the router's /services
+fn print_router_services(services: &[Value]) {
+    println!(
+        "{:<32} {:<8} {:<9} {:>9} {:>9} {:>7}  MODELS",
+        "NAME", "HEALTHY", "DRAINING", "IN_FLIGHT", "REQUESTS", "ERRORS"
+    );
+    for service in services {
+        let models: Vec<&str> = service["models"]
+            .as_array()
+            .map(|models| models.iter().filter_map(|m| m.as_str()).collect())
+            .unwrap_or_default();
+        println!(
+            "{:<32} {:<8} {:<9} {:>9} {:>9} {:>7}  {}",
+            str_field(service, "name"),
+            service["healthy"].to_string(),
+            service["draining"].to_string(),
+            service["in_flight"].to_string(),
+            service["request_count"].to_string(),
+            service["error_count"].to_string(),
+            models.join(",")
+        );
+    }
+}
+
+/// One row per service from the registry's /services
+fn print_registry_services(services: &[Value]) {
+    println!(
+        "{:<32} {:<28} {:<10} {:<8}  TYPE",
+        "NAME", "URL", "STATUS", "HEALTHY"
+    );
+    for service in services {
+        println!(
+            "{:<32} {:<28} {:<10} {:<8}  {}",
+            str_field(service, "name"),
+            str_field(service, "url"),
+            str_field(service, "status"),
+            service["is_healthy"].to_string(),
+            service["metadata"]["type"].as_str().unwrap_or("-")
+        );
+    }
+}
+
+/// Base URL of the babysitter for `target`: used as is when it is a URL, otherwise
+/// looked up from the router's view of that service
+async fn babysitter_url(client: &Client, router: &str, target: &str) -> Result<String> {
+    if target.starts_with("http://") || target.starts_with("https://") {
+        return Ok(target.trim_end_matches('/').to_string());
+    }
+    let service = call(
+        client,
+        Method::GET,
+        &format!("{}/services/{}", router, target),
+        None,
+    )
+    .await?;
+    match service.get("babysitter_url").and_then(|v| v.as_str()) {
+        Some(url) => Ok(url.trim_end_matches('/').to_string()),
+        None => bail!("Service {} has no babysitter URL", target),
+    }
+}
+
+/// Print log lines newer than `since`; returns the newest timestamp printed
+fn print_log_lines(logs: &Value, since: f64) -> f64 {
+    let mut newest = since;
+    for entry in logs["lines"].as_array().into_iter().flatten() {
+        let timestamp = entry["timestamp"].as_f64().unwrap_or_default();
+        if timestamp <= since {
+            continue;
+        }
+        newest = newest.max(timestamp);
+        println!(
+            "[{}] {}",
+            entry["stream"].as_str().unwrap_or("?"),
+            entry["line"].as_str().unwrap_or_default()
+        );
+    }
+    newest
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let admin = admin_client(args.admin_token.as_deref())?;
+    let babysitter = admin_client(args.babysitter_token.as_deref())?;
+    let router = args.router.trim_end_matches('/');
+    let registry = args.registry.trim_end_matches('/');
+
+    match args.command {
+        Command::Services { registry_view } => {
+            let base = if registry_view { registry } else { router };
+            let response = call(&client, Method::GET, &format!("{}/services", base), None).await?;
+            let services = response["services"].as_array().cloned().unwrap_or_default();
+            if registry_view {
+                print_registry_services(&services);
+            } else {
+                print_router_services(&services);
+            }
+        }
+        Command::Service { name } => {
+            let url = format!("{}/services/{}", router, name);
+            print_json(&call(&client, Method::GET, &url, None).await?)?;
+        }
+        Command::Stats => {
+            let url = format!("{}/stats", router);
+            print_json(&call(&client, Method::GET, &url, None).await?)?;
+        }
+        Command::Drain { name } => {
+            let url = format!("{}/admin/services/{}/drain", router, name);
+            print_json(&call(&admin, Method::POST, &url, None).await?)?;
+        }
+        Command::Undrain { name } => {
+            let url = format!("{}/admin/services/{}/drain", router, name);
+            print_json(&call(&admin, Method::DELETE, &url, None).await?)?;
+        }
+        Command::ForceHealth { name, unhealthy } => {
+            let url = format!("{}/admin/services/{}/health", router, name);
+            let body = json!({"healthy": !unhealthy});
+            print_json(&call(&admin, Method::PUT, &url, Some(body)).await?)?;
+        }
+        Command::Sync => {
+            let url = format!("{}/admin/sync", router);
+            print_json(&call(&admin, Method::POST, &url, None).await?)?;
+        }
+        Command::Logs {
+            target,
+            lines,
+            stream,
+            follow,
+        } => {
+            let base = babysitter_url(&client, router, &target).await?;
+            let url = format!("{}/logs?lines={}&stream={}", base, lines, stream);
+            let mut since =
+                print_log_lines(&call(&babysitter, Method::GET, &url, None).await?, 0.0);
+            if follow {
+                loop {
+                    sleep(FOLLOW_INTERVAL).await;
+                    let logs = call(&babysitter, Method::GET, &url, None).await?;
+                    since = print_log_lines(&logs, since);
+                }
+            }
+        }
+    }
+    Ok(())
+}
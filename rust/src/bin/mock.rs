@@ -0,0 +1,332 @@
+//! Mock OpenAI-compatible backend for tests and the babysitter's "mock" service type
+//! Serves /v1/models and /v1/chat/completions (optionally streamed) with injectable
+//! latency and errors, plus vLLM-style load gauges on /metrics
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use clap::Parser;
+use futures::StreamExt;
+use infini_router::registry::tls::RegistryTls;
+use rand::Rng;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+/// Interval between heartbeats to the registry
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Command-line arguments
+#[derive(Parser, Debug)]
+#[command(name = "infini-mock")]
+#[command(about = "Mock OpenAI-compatible backend for testing")]
+struct Args {
+    /// Service name, echoed in responses
+    #[arg(long)]
+    name: String,
+
+    /// Port to listen on
+    #[arg(long)]
+    port: u16,
+
+    /// Address to bind
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Models served
+    #[arg(long, value_delimiter = ',', default_value = "test-model")]
+    models: Vec<String>,
+
+    /// Registry to register with and heartbeat to
+    #[arg(long)]
+    registry_url: Option<String>,
+
+    /// PEM bundle of extra CA certificates trusted for an https registry URL
+    #[arg(long)]
+    registry_ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate presented to the registry (mutual TLS)
+    #[arg(long, requires = "registry_client_key")]
+    registry_client_cert: Option<PathBuf>,
+
+    /// PEM private key for --registry-client-cert
+    #[arg(long, requires = "registry_client_cert")]
+    registry_client_key: Option<PathBuf>,
+
+    /// Shared secret sent to the registry with registrations and heartbeats
+    #[arg(long, env = "INFINI_REGISTRY_TOKEN", hide_env_values = true)]
+    registry_token: Option<String>,
+
+    /// Delay before answering each chat completion, in milliseconds
+    #[arg(long, default_value = "0")]
+    latency_ms: u64,
+
+    /// Delay between streamed chunks, in milliseconds
+    #[arg(long, default_value = "10")]
+    chunk_delay_ms: u64,
+
+    /// Fraction of chat completions answered with --error-status (0.0-1.0)
+    #[arg(long, default_value = "0.0")]
+    error_rate: f64,
+
+    /// Status code of injected errors
+    #[arg(long, default_value = "500")]
+    error_status: u16,
+}
+
+struct MockState {
+    args: Args,
+    requests: AtomicU64,
+    /// Chat completions not yet answered (streams count until their last chunk)
+    in_flight: Arc<AtomicU64>,
+}
+
+/// Decrements the in-flight gauge when the request (or its stream) is done
+struct InFlightGuard(Arc<AtomicU64>);
+
+impl InFlightGuard {
+    fn new(in_flight: &Arc<AtomicU64>) -> Self {
+        in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(in_flight.clone())
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn models_list(models: &[String]) -> Vec<Value> {
+    let created = unix_time();
+    models
+        .iter()
+        .map(|model| json!({"id": model, "object": "model", "created": created}))
+        .collect()
+}
+
+async fn models_handler(State(state): State<Arc<MockState>>) -> Json<Value> {
+    Json(json!({"object": "list", "data": models_list(&state.args.models)}))
+}
+
+async fn health_handler(State(state): State<Arc<MockState>>) -> Json<Value> {
+    Json(json!({
+        "status": "healthy",
+        "service": state.args.name,
+        "port": state.args.port,
+        "requests": state.requests.load(Ordering::Relaxed),
+    }))
+}
+
+/// Load gauges under vLLM's metric names, as scraped by the babysitter
+async fn metrics_handler(State(state): State<Arc<MockState>>) -> String {
+    let model = state.args.models.first().map(String::as_str);
+    format!(
+        "# TYPE vllm:num_requests_running gauge\n\
+         vllm:num_requests_running{{model_name=\"{model}\"}} {}\n\
+         # TYPE vllm:num_requests_waiting gauge\n\
+         vllm:num_requests_waiting{{model_name=\"{model}\"}} 0\n",
+        state.in_flight.load(Ordering::Relaxed),
+        model = model.unwrap_or_default(),
+    )
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({"error": {"message": message}}))).into_response()
+}
+
+async fn chat_completions_handler(
+    State(state): State<Arc<MockState>>,
+    Json(request): Json<Value>,
+) -> Response {
+    let id = state.requests.fetch_add(1, Ordering::Relaxed) + 1;
+    let guard = InFlightGuard::new(&state.in_flight);
+    let args = &state.args;
+    if args.latency_ms > 0 {
+        sleep(Duration::from_millis(args.latency_ms)).await;
+    }
+    if args.error_rate > 0.0 && rand::thread_rng().gen_bool(args.error_rate.min(1.0)) {
+        let status =
+            StatusCode::from_u16(args.error_status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        return error_response(status, format!("Injected error from {}", args.name));
+    }
+
+    let model = request
+        .get("model")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    if !args.models.contains(&model) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Model {} not available on this service", model),
+        );
+    }
+
+    let content = format!("Hello from {} (model: {})", args.name, model);
+    let created = unix_time();
+    if !request
+        .get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        return Json(json!({
+            "id": format!("chatcmpl-{}", id),
+            "object": "chat.completion",
+            "created": created,
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop",
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15},
+        }))
+        .into_response();
+    }
+
+    // One SSE event per character, then the final [DONE]
+    let mut events: Vec<String> = content
+        .chars()
+        .map(|c| {
+            let chunk = json!({
+                "id": format!("chatcmpl-{}", id),
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": model,
+                "choices": [{"index": 0, "delta": {"content": c}, "finish_reason": null}],
+            });
+            format!("data: {}\n\n", chunk)
+        })
+        .collect();
+    events.push("data: [DONE]\n\n".to_string());
+
+    let chunk_delay = Duration::from_millis(args.chunk_delay_ms);
+    let body = futures::stream::iter(events).then(move |event| {
+        // Captured by the closure so the guard lives until the stream is dropped
+        let _guard = &guard;
+        async move {
+            sleep(chunk_delay).await;
+            Ok::<_, Infallible>(event)
+        }
+    });
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .body(Body::from_stream(body))
+        .unwrap()
+}
+
+/// Register with the registry as an OpenAI API service, then heartbeat until exit
+async fn register_and_heartbeat(
+    registry_url: String,
+    client: reqwest::Client,
+    state: Arc<MockState>,
+) {
+    let args = &state.args;
+    let service = json!({
+        "name": args.name,
+        "host": args.host,
+        "hostname": "localhost",
+        "port": args.port,
+        "url": format!("http://{}:{}", args.host, args.port),
+        "status": "running",
+        "metadata": {
+            "type": "openai-api",
+            "models": args.models,
+            "models_list": models_list(&args.models),
+        },
+    });
+    let with_token = |request: reqwest::RequestBuilder| match &args.registry_token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    };
+    match with_token(client.post(format!("{}/services", registry_url)))
+        .timeout(Duration::from_secs(5))
+        .json(&service)
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => {
+            info!("Registered {} with registry at {}", args.name, registry_url)
+        }
+        Ok(response) => warn!("Registration failed: {}", response.status()),
+        Err(e) => warn!("Error registering with registry: {}", e),
+    }
+
+    let heartbeat_url = format!("{}/services/{}/heartbeat", registry_url, args.name);
+    loop {
+        sleep(HEARTBEAT_INTERVAL).await;
+        // Heartbeat failures are expected while the registry restarts
+        let _ = with_token(client.post(&heartbeat_url))
+            .timeout(Duration::from_secs(2))
+            .send()
+            .await;
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    let args = Args::parse();
+    if !(0.0..=1.0).contains(&args.error_rate) {
+        anyhow::bail!("--error-rate must be between 0.0 and 1.0");
+    }
+    let addr = format!("{}:{}", args.host, args.port);
+    let state = Arc::new(MockState {
+        args,
+        requests: AtomicU64::new(0),
+        in_flight: Arc::new(AtomicU64::new(0)),
+    });
+
+    if let Some(registry_url) = state.args.registry_url.clone() {
+        let tls = RegistryTls {
+            ca_cert: state.args.registry_ca_cert.clone(),
+            client_cert: state.args.registry_client_cert.clone(),
+            client_key: state.args.registry_client_key.clone(),
+        };
+        let client = tls.configure(reqwest::Client::builder())?.build()?;
+        tokio::spawn(register_and_heartbeat(registry_url, client, state.clone()));
+    }
+
+    let app = Router::new()
+        .route("/v1/chat/completions", post(chat_completions_handler))
+        .route("/v1/models", get(models_handler))
+        .route("/models", get(models_handler))
+        .route("/health", get(health_handler))
+        .route("/metrics", get(metrics_handler))
+        .with_state(state.clone());
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    info!(
+        "Mock service {} listening on {} (models: {})",
+        state.args.name,
+        addr,
+        state.args.models.join(", ")
+    );
+    axum::serve(listener, app).await?;
+    Ok(())
+}
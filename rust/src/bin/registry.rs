@@ -2,22 +2,42 @@
 //! Provides service discovery and registration for distributed InfiniLM deployments
 
 use axum::{
-    extract::{Path, Query},
-    http::StatusCode,
-    response::Json,
+    extract::{ConnectInfo, Path, Query, Request},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
     routing::{delete, get, post, put},
     Router,
 };
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::signal;
 use tokio::sync::RwLock;
 use tokio::time::{sleep, Instant};
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+use infini_router::utils::listen;
+
+/// Heartbeat timeout for services that do not specify one at registration
+const DEFAULT_HEARTBEAT_TIMEOUT_SECS: f64 = 120.0;
+
+/// Default time without a heartbeat before a service is removed from the registry
+const STALE_SERVICE_SECS: f64 = 300.0;
+
+/// Heartbeat metadata that changes on nearly every heartbeat (backend load, resource
+/// usage); kept out of the catalog version and sent with every delta instead
+const TELEMETRY_KEYS: &[&str] = &["load", "resources"];
+
+fn default_heartbeat_timeout() -> f64 {
+    DEFAULT_HEARTBEAT_TIMEOUT_SECS
+}
 
 /// Service information stored in registry
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,14 +49,27 @@ pub struct ServiceInfo {
     pub url: String,
     pub status: String,
     pub timestamp: String,
+    /// Seconds without a heartbeat before the service is considered unhealthy
+    #[serde(default = "default_heartbeat_timeout")]
+    pub heartbeat_timeout: f64,
     #[serde(skip)]
     pub last_heartbeat: Arc<RwLock<f64>>,
     #[serde(skip)]
     pub health_status: Arc<RwLock<String>>,
     pub metadata: HashMap<String, Value>,
+    /// Catalog version of the last change to this service, for /services/delta
+    #[serde(skip)]
+    pub modified: u64,
+    /// Health as of `modified`; a heartbeat timeout flipping it is a catalog change
+    #[serde(skip)]
+    pub reported_healthy: bool,
+    /// Latest heartbeat telemetry (`TELEMETRY_KEYS`), listed with the metadata
+    #[serde(skip)]
+    pub telemetry: HashMap<String, Value>,
 }
 
 impl ServiceInfo {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         host: String,
@@ -45,6 +78,7 @@ impl ServiceInfo {
         url: String,
         status: String,
         metadata: HashMap<String, Value>,
+        heartbeat_timeout: f64,
     ) -> Self {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -62,9 +96,13 @@ impl ServiceInfo {
             url,
             status,
             timestamp,
+            heartbeat_timeout,
             last_heartbeat: Arc::new(RwLock::new(now as f64)),
             health_status: Arc::new(RwLock::new("unknown".to_string())),
             metadata,
+            modified: 0,
+            reported_healthy: false,
+            telemetry: HashMap::new(),
         }
     }
 
@@ -79,14 +117,22 @@ impl ServiceInfo {
             .unwrap()
             .as_secs() as f64;
 
-        // Consider service unhealthy if no heartbeat for 2 minutes
-        (now - last_heartbeat) < 120.0
+        // Consider service unhealthy if no heartbeat within its heartbeat timeout
+        (now - last_heartbeat) < self.heartbeat_timeout
+    }
+
+    /// Seconds without a heartbeat before the service is removed: 5 minutes, or longer
+    /// for services whose heartbeat timeout is long enough to otherwise reach that
+    pub fn stale_after(&self) -> f64 {
+        STALE_SERVICE_SECS.max(self.heartbeat_timeout * 2.5)
     }
 
     pub async fn to_dict(&self) -> serde_json::Value {
         let last_heartbeat = *self.last_heartbeat.read().await;
         let health_status = self.health_status.read().await.clone();
         let is_healthy = self.is_healthy().await;
+        let mut metadata = self.metadata.clone();
+        metadata.extend(self.telemetry.clone());
 
         json!({
             "name": self.name,
@@ -96,13 +142,35 @@ impl ServiceInfo {
             "url": self.url,
             "status": self.status,
             "timestamp": self.timestamp,
+            "heartbeat_timeout": self.heartbeat_timeout,
             "last_heartbeat": last_heartbeat,
             "health_status": health_status,
             "is_healthy": is_healthy,
-            "metadata": self.metadata,
+            "metadata": metadata,
         })
     }
 
+    /// URL probed by health checks: the babysitter for openai-api services
+    /// (advertised `babysitter_url`/`babysitter_port`, else port + 1), the service itself
+    /// otherwise or when metadata sets `health_target` to `service`
+    pub fn health_check_url(&self) -> String {
+        if self.metadata.get("type").and_then(|v| v.as_str()) != Some("openai-api")
+            || self.metadata.get("health_target").and_then(|v| v.as_str()) == Some("service")
+        {
+            return self.url.clone();
+        }
+        if let Some(url) = self.metadata.get("babysitter_url").and_then(|v| v.as_str()) {
+            return url.trim_end_matches('/').to_string();
+        }
+        let babysitter_port = self
+            .metadata
+            .get("babysitter_port")
+            .and_then(|v| v.as_u64())
+            .and_then(|p| u16::try_from(p).ok())
+            .unwrap_or_else(|| self.port.saturating_add(1));
+        format!("http://{}:{}", self.host, babysitter_port)
+    }
+
     pub async fn update_heartbeat(&self) {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -112,30 +180,477 @@ impl ServiceInfo {
     }
 }
 
+/// Removed services remembered for /services/delta; older deltas get the full catalog
+const MAX_TOMBSTONES: usize = 1024;
+
 /// Registry state
 #[derive(Clone)]
 pub struct RegistryState {
     services: Arc<RwLock<HashMap<String, ServiceInfo>>>,
+    /// Identifies this registry run, so clients notice a restart reset the version
+    epoch: u64,
+    /// Catalog version, bumped on every registration, update, removal and health change
+    version: Arc<AtomicU64>,
+    /// (version, name) of recent removals
+    tombstones: Arc<RwLock<VecDeque<(u64, String)>>>,
+    /// Oldest version a delta can still be computed from
+    delta_floor: Arc<AtomicU64>,
     start_time: Instant,
     health_check_interval: u64,
     health_check_timeout: u64,
     cleanup_interval: u64,
+    webhooks: Arc<WebhookNotifier>,
+    audit: Arc<AuditTrail>,
+    metrics: Arc<RegistryMetrics>,
+    /// Maintenance mode: registrations, updates and removals are rejected and stale
+    /// services are kept, while reads and heartbeats are still served
+    read_only: Arc<AtomicBool>,
+    register_limiter: Arc<RateLimiter>,
+    heartbeat_limiter: Arc<RateLimiter>,
+    /// Shared secret required (as a bearer token) on registrations, updates,
+    /// removals and heartbeats
+    auth_token: Option<Arc<str>>,
 }
 
 impl RegistryState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         health_check_interval: u64,
         health_check_timeout: u64,
         cleanup_interval: u64,
+        webhook_urls: Vec<String>,
+        audit: AuditTrail,
+        read_only: bool,
+        register_limiter: RateLimiter,
+        heartbeat_limiter: RateLimiter,
+        auth_token: Option<String>,
     ) -> Self {
         Self {
             services: Arc::new(RwLock::new(HashMap::new())),
+            epoch: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            version: Arc::new(AtomicU64::new(0)),
+            tombstones: Arc::new(RwLock::new(VecDeque::new())),
+            delta_floor: Arc::new(AtomicU64::new(0)),
             start_time: Instant::now(),
             health_check_interval,
             health_check_timeout,
             cleanup_interval,
+            webhooks: Arc::new(WebhookNotifier::new(webhook_urls)),
+            audit: Arc::new(audit),
+            metrics: Arc::new(RegistryMetrics::default()),
+            read_only: Arc::new(AtomicBool::new(read_only)),
+            register_limiter: Arc::new(register_limiter),
+            heartbeat_limiter: Arc::new(heartbeat_limiter),
+            auth_token: auth_token.map(Arc::from),
+        }
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    /// Start a new catalog version and return it
+    fn bump_version(&self) -> u64 {
+        self.version.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Record that `name` left the catalog
+    async fn record_removal(&self, name: &str) {
+        let version = self.bump_version();
+        let mut tombstones = self.tombstones.write().await;
+        tombstones.push_back((version, name.to_string()));
+        while tombstones.len() > MAX_TOMBSTONES {
+            if let Some((evicted, _)) = tombstones.pop_front() {
+                self.delta_floor.store(evicted, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Turn health changes caused by heartbeats arriving or lapsing into catalog versions.
+    /// Reads only take the write lock when some service's health actually changed.
+    async fn refresh_health_versions(&self) {
+        let changed: Vec<String> = {
+            let services = self.services.read().await;
+            let mut changed = Vec::new();
+            for service in services.values() {
+                if service.is_healthy().await != service.reported_healthy {
+                    changed.push(service.name.clone());
+                }
+            }
+            changed
+        };
+        if changed.is_empty() {
+            return;
+        }
+
+        let mut services = self.services.write().await;
+        for name in &changed {
+            let Some(service) = services.get_mut(name) else {
+                continue;
+            };
+            let healthy = service.is_healthy().await;
+            if healthy != service.reported_healthy {
+                service.reported_healthy = healthy;
+                service.modified = self.bump_version();
+                self.audit.record(
+                    if healthy { "healthy" } else { "unhealthy" },
+                    &service.name,
+                    json!({
+                        "status": service.status,
+                        "last_heartbeat": *service.last_heartbeat.read().await,
+                        "heartbeat_timeout": service.heartbeat_timeout,
+                    }),
+                );
+            }
+        }
+    }
+
+    /// Weak ETag of the catalog; heartbeat timestamps, telemetry and probe results are not
+    /// part of it
+    fn etag(&self) -> String {
+        format!(
+            "W/\"{}-{}\"",
+            self.epoch,
+            self.version.load(Ordering::SeqCst)
+        )
+    }
+}
+
+/// Prometheus counters for registry activity
+#[derive(Default)]
+pub struct RegistryMetrics {
+    registrations: AtomicU64,
+    unregistrations: AtomicU64,
+    heartbeats: AtomicU64,
+    health_checks_healthy: AtomicU64,
+    health_checks_unhealthy: AtomicU64,
+    cleanup_removals: AtomicU64,
+}
+
+impl RegistryMetrics {
+    fn record_health_check(&self, health_status: &str) {
+        if health_status == "healthy" {
+            self.health_checks_healthy.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.health_checks_unhealthy.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-key token buckets (source IP and service name) guarding one registry endpoint
+pub struct RateLimiter {
+    /// Requests per second each key may sustain; 0 disables limiting
+    rate: f64,
+    burst: f64,
+    buckets: std::sync::Mutex<HashMap<String, TokenBucket>>,
+    rejected: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: f64::from(burst.max(1)),
+            buckets: std::sync::Mutex::new(HashMap::new()),
+            rejected: AtomicU64::new(0),
         }
     }
+
+    fn refill(&self, bucket: &mut TokenBucket, now: Instant) {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+    }
+
+    /// Take one token from every key's bucket, or none if any is empty; the error is how
+    /// long until the emptiest bucket has a token again
+    fn check(&self, keys: &[String]) -> Result<(), Duration> {
+        if self.rate <= 0.0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let mut shortfall: f64 = 0.0;
+        for key in keys {
+            let bucket = buckets.entry(key.clone()).or_insert(TokenBucket {
+                tokens: self.burst,
+                updated: now,
+            });
+            self.refill(bucket, now);
+            shortfall = shortfall.max(1.0 - bucket.tokens);
+        }
+        if shortfall > 0.0 {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(Duration::from_secs_f64(shortfall / self.rate));
+        }
+        for key in keys {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget keys whose buckets have refilled, so departed agents do not accumulate
+    fn prune(&self) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.retain(|_, bucket| {
+            self.refill(bucket, now);
+            bucket.tokens < self.burst
+        });
+    }
+}
+
+/// Apply `limiter` to a request from `client` about `service`, returning the 429 to send
+fn rate_limit(
+    limiter: &RateLimiter,
+    endpoint: &str,
+    client: SocketAddr,
+    service: &str,
+) -> Option<Response> {
+    let keys = [
+        format!("ip:{}", client.ip()),
+        format!("service:{}", service),
+    ];
+    let retry_after = limiter.check(&keys).err()?;
+    let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    warn!(
+        "Rate limited {} for {} from {} (Retry-After: {}s)",
+        endpoint,
+        service,
+        client.ip(),
+        retry_after_secs
+    );
+    Some(
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after_secs.to_string())],
+            Json(json!({"error": format!("Too many {} requests, retry later", endpoint)})),
+        )
+            .into_response(),
+    )
+}
+
+/// A catalog event in the registry audit trail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: String,
+    pub event: String,
+    pub service: String,
+    #[serde(default)]
+    pub details: Value,
+}
+
+/// Audit events queued for the file writer before new ones are dropped
+const AUDIT_QUEUE_SIZE: usize = 4096;
+
+/// The most recent catalog events, optionally also appended to a JSON-lines file that is
+/// read back on startup so the trail survives registry restarts
+pub struct AuditTrail {
+    events: std::sync::Mutex<VecDeque<AuditEvent>>,
+    capacity: usize,
+    /// Lines for the file writer thread, so recording never waits on disk
+    file: Option<tokio::sync::mpsc::Sender<String>>,
+}
+
+impl AuditTrail {
+    /// Keep `capacity` events; with `path`, also append them to that file, which is moved
+    /// to `<path>.1` once it would grow past `max_file_bytes` (0 never rotates)
+    pub fn new(capacity: usize, path: Option<&str>, max_file_bytes: u64) -> anyhow::Result<Self> {
+        let mut events = VecDeque::new();
+        let file = match path {
+            Some(path) => {
+                let path = std::path::Path::new(path);
+                for source in [rotated_path(path), path.to_path_buf()] {
+                    let Ok(contents) = std::fs::read_to_string(&source) else {
+                        continue;
+                    };
+                    for line in contents.lines() {
+                        if let Ok(event) = serde_json::from_str::<AuditEvent>(line) {
+                            events.push_back(event);
+                            if events.len() > capacity {
+                                events.pop_front();
+                            }
+                        }
+                    }
+                }
+                let mut file = AuditFile::open(path, max_file_bytes).map_err(|e| {
+                    anyhow::anyhow!("Cannot open audit file {}: {}", path.display(), e)
+                })?;
+                let (sender, mut receiver) = tokio::sync::mpsc::channel::<String>(AUDIT_QUEUE_SIZE);
+                std::thread::spawn(move || {
+                    while let Some(line) = receiver.blocking_recv() {
+                        if let Err(e) = file.append(&line) {
+                            warn!("Failed to persist audit event: {}", e);
+                        }
+                    }
+                });
+                Some(sender)
+            }
+            None => None,
+        };
+        Ok(Self {
+            events: std::sync::Mutex::new(events),
+            capacity,
+            file,
+        })
+    }
+
+    pub fn record(&self, event: &str, service: &str, details: Value) {
+        if self.capacity == 0 {
+            return;
+        }
+        let entry = AuditEvent {
+            timestamp: current_rfc3339(),
+            event: event.to_string(),
+            service: service.to_string(),
+            details,
+        };
+
+        if let Some(file) = &self.file {
+            if file.try_send(json!(entry).to_string()).is_err() {
+                warn!("Audit file queue full or closed, dropping audit event");
+            }
+        }
+
+        let mut events = self.events.lock().unwrap();
+        events.push_back(entry);
+        if events.len() > self.capacity {
+            events.pop_front();
+        }
+    }
+
+    /// Retained events, oldest first, optionally for one service or event type; with
+    /// `limit`, only the most recent ones
+    pub fn events(
+        &self,
+        service: Option<&str>,
+        event: Option<&str>,
+        limit: Option<usize>,
+    ) -> Vec<AuditEvent> {
+        let events = self.events.lock().unwrap();
+        let mut matching: Vec<AuditEvent> = events
+            .iter()
+            .filter(|e| service.is_none_or(|service| e.service == service))
+            .filter(|e| event.is_none_or(|event| e.event == event))
+            .cloned()
+            .collect();
+        if let Some(limit) = limit {
+            let skip = matching.len().saturating_sub(limit);
+            matching.drain(..skip);
+        }
+        matching
+    }
+}
+
+/// The audit trail's JSON-lines file; past `max_bytes` it is moved to `<path>.1`
+/// (replacing the previous one) and started over
+struct AuditFile {
+    path: std::path::PathBuf,
+    file: std::fs::File,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl AuditFile {
+    fn open(path: &std::path::Path, max_bytes: u64) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            size: file.metadata()?.len(),
+            file,
+            max_bytes,
+        })
+    }
+
+    fn append(&mut self, line: &str) -> std::io::Result<()> {
+        use std::io::Write as _;
+        let len = line.len() as u64 + 1;
+        if self.max_bytes > 0 && self.size > 0 && self.size + len > self.max_bytes {
+            std::fs::rename(&self.path, rotated_path(&self.path))?;
+            *self = Self::open(&self.path, self.max_bytes)?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+/// Where a rotated audit file is kept
+fn rotated_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    rotated.into()
+}
+
+/// Posts registry events to operator-configured webhook URLs
+pub struct WebhookNotifier {
+    urls: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(urls: Vec<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self { urls, client }
+    }
+
+    /// Fire-and-forget notification; delivery failures are logged but never block the registry
+    pub fn notify(&self, event: &str, service: &str, details: Value) {
+        if self.urls.is_empty() {
+            return;
+        }
+
+        let payload = json!({
+            "event": event,
+            "service": service,
+            "details": details,
+            "timestamp": current_rfc3339(),
+        });
+
+        for url in &self.urls {
+            let client = self.client.clone();
+            let url = url.clone();
+            let payload = payload.clone();
+            tokio::spawn(async move {
+                match client.post(&url).json(&payload).send().await {
+                    Ok(response) if !response.status().is_success() => {
+                        warn!("Webhook {} returned status {}", url, response.status());
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("Failed to deliver webhook to {}: {}", url, e);
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Current time as an RFC 3339 string
+fn current_rfc3339() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    chrono::DateTime::<chrono::Utc>::from_timestamp(now as i64, 0)
+        .unwrap()
+        .to_rfc3339()
 }
 
 /// Command-line arguments
@@ -147,6 +662,10 @@ struct Args {
     #[arg(long, default_value = "8081")]
     port: u16,
 
+    /// Address to listen on, e.g. 0.0.0.0, :: or [::] (repeatable; default 0.0.0.0)
+    #[arg(long, value_parser = listen::parse_bind_addr)]
+    bind: Vec<IpAddr>,
+
     /// Health check interval in seconds
     #[arg(long, default_value = "30")]
     health_interval: u64,
@@ -158,6 +677,44 @@ struct Args {
     /// Cleanup interval in seconds
     #[arg(long, default_value = "60")]
     cleanup_interval: u64,
+
+    /// Webhook URL to POST register/unregister/health-transition events to (repeatable)
+    #[arg(long = "webhook-url")]
+    webhook_urls: Vec<String>,
+
+    /// Catalog events (register/update/unregister/health changes) kept for GET /audit
+    #[arg(long, default_value = "1000")]
+    audit_capacity: usize,
+
+    /// File to append audit events to as JSON lines; reloaded on startup
+    #[arg(long)]
+    audit_file: Option<String>,
+
+    /// Size (MiB) past which the audit file is moved to <file>.1 and started over
+    /// (0 lets it grow without bound)
+    #[arg(long, default_value = "64")]
+    audit_file_max_mb: u64,
+
+    /// Start in read-only (maintenance) mode; toggle at runtime via PUT /admin/read-only
+    #[arg(long)]
+    read_only: bool,
+
+    /// Registrations per second allowed per source IP and per service name (0 disables)
+    #[arg(long, default_value = "2")]
+    register_rate_limit: f64,
+
+    /// Heartbeats per second allowed per source IP and per service name (0 disables)
+    #[arg(long, default_value = "10")]
+    heartbeat_rate_limit: f64,
+
+    /// Requests a source IP or service name may burst above its rate limit
+    #[arg(long, default_value = "20")]
+    rate_limit_burst: u32,
+
+    /// Shared secret babysitters must present (Authorization: Bearer) to register,
+    /// update, remove or heartbeat services
+    #[arg(long, env = "INFINI_REGISTRY_TOKEN", hide_env_values = true)]
+    auth_token: Option<String>,
 }
 
 #[tokio::main]
@@ -174,7 +731,23 @@ async fn main() -> anyhow::Result<()> {
         args.health_interval,
         args.health_timeout,
         args.cleanup_interval,
+        args.webhook_urls,
+        AuditTrail::new(
+            args.audit_capacity,
+            args.audit_file.as_deref(),
+            args.audit_file_max_mb.saturating_mul(1024 * 1024),
+        )?,
+        args.read_only,
+        RateLimiter::new(args.register_rate_limit, args.rate_limit_burst),
+        RateLimiter::new(args.heartbeat_rate_limit, args.rate_limit_burst),
+        args.auth_token,
     );
+    if state.auth_token.is_none() {
+        warn!("No --auth-token set; any client can register services");
+    }
+    if args.read_only {
+        warn!("Registry started in read-only mode; catalog mutations are rejected");
+    }
 
     // Start background tasks
     let state_clone = state.clone();
@@ -191,12 +764,13 @@ async fn main() -> anyhow::Result<()> {
     let app = create_router(state);
 
     // Start server
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", args.port)).await?;
-    info!("Service registry listening on http://0.0.0.0:{}", args.port);
+    let addrs = listen::bind_addrs(&args.bind, args.port);
+    let listeners = listen::bind_all(&addrs)?;
+    info!("Service registry listening on {}", listen::describe(&addrs));
 
     // Graceful shutdown
     tokio::select! {
-        result = axum::serve(listener, app) => {
+        result = listen::serve_all(listeners, app, std::future::pending()) => {
             if let Err(e) = result {
                 error!("Server error: {}", e);
             }
@@ -211,19 +785,134 @@ async fn main() -> anyhow::Result<()> {
 }
 
 fn create_router(state: RegistryState) -> Router {
+    let auth = middleware::from_fn_with_state(state.clone(), require_token);
     Router::new()
         .route("/health", get(health_handler))
         .route("/services", get(services_handler))
-        .route("/services", post(register_service_handler))
+        .route(
+            "/services",
+            post(register_service_handler).route_layer(auth.clone()),
+        )
+        .route("/services/delta", get(services_delta_handler))
         .route("/services/:name", get(get_service_handler))
-        .route("/services/:name", put(update_service_handler))
-        .route("/services/:name", delete(unregister_service_handler))
+        .route(
+            "/services/:name",
+            put(update_service_handler).route_layer(auth.clone()),
+        )
+        .route(
+            "/services/:name",
+            delete(unregister_service_handler).route_layer(auth.clone()),
+        )
         .route("/services/:name/health", get(service_health_handler))
-        .route("/services/:name/heartbeat", post(heartbeat_handler))
+        .route(
+            "/services/:name/heartbeat",
+            post(heartbeat_handler).route_layer(auth.clone()),
+        )
         .route("/stats", get(stats_handler))
+        .route("/audit", get(audit_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/admin/read-only", get(read_only_handler))
+        .route(
+            "/admin/read-only",
+            put(set_read_only_handler).route_layer(auth),
+        )
         .with_state(state)
 }
 
+/// Reject catalog writes that do not carry the shared token, when one is configured
+async fn require_token(
+    axum::extract::State(state): axum::extract::State<RegistryState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(token) = &state.auth_token {
+        let presented = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if presented != Some(&**token) {
+            warn!(
+                "Rejected unauthenticated {} {}",
+                request.method(),
+                request.uri().path()
+            );
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({"error": "Missing or invalid registry token"})),
+            )
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// Response for catalog mutations attempted while the registry is read-only
+fn read_only_rejection(action: &str, name: &str) -> (StatusCode, Json<Value>) {
+    warn!("Rejected {} of {}: registry is read-only", action, name);
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "error": "Registry is in read-only mode for maintenance",
+            "read_only": true
+        })),
+    )
+}
+
+async fn read_only_handler(
+    axum::extract::State(state): axum::extract::State<RegistryState>,
+) -> Json<Value> {
+    Json(json!({"read_only": state.is_read_only()}))
+}
+
+#[derive(Deserialize)]
+struct ReadOnlyRequest {
+    enabled: bool,
+}
+
+/// Freeze or unfreeze the catalog
+async fn set_read_only_handler(
+    axum::extract::State(state): axum::extract::State<RegistryState>,
+    Json(payload): Json<ReadOnlyRequest>,
+) -> Json<Value> {
+    let previous = state.read_only.swap(payload.enabled, Ordering::SeqCst);
+    if previous != payload.enabled {
+        if payload.enabled {
+            warn!("Registry switched to read-only mode");
+        } else {
+            info!("Registry left read-only mode");
+        }
+    }
+    Json(json!({"read_only": payload.enabled, "previous": previous}))
+}
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    service: Option<String>,
+    event: Option<String>,
+    /// Only the most recent matching events
+    limit: Option<usize>,
+}
+
+/// Recent catalog events, oldest first
+async fn audit_handler(
+    axum::extract::State(state): axum::extract::State<RegistryState>,
+    Query(params): Query<AuditQuery>,
+) -> Json<Value> {
+    state.refresh_health_versions().await;
+    let events = state.audit.events(
+        params.service.as_deref(),
+        params.event.as_deref(),
+        params.limit,
+    );
+    Json(json!({
+        "events": events,
+        "count": events.len(),
+        "capacity": state.audit.capacity,
+        "timestamp": current_rfc3339()
+    }))
+}
+
 async fn health_handler(
     axum::extract::State(state): axum::extract::State<RegistryState>,
 ) -> Json<Value> {
@@ -250,6 +939,7 @@ async fn health_handler(
         "registry": "running",
         "registered_services": total,
         "healthy_services": healthy_count,
+        "read_only": state.is_read_only(),
         "timestamp": timestamp
     }))
 }
@@ -258,16 +948,51 @@ async fn health_handler(
 struct ServicesQuery {
     status: Option<String>,
     healthy: Option<String>,
+    /// Maximum number of services to return
+    limit: Option<usize>,
+    /// Number of services to skip (applied after filtering)
+    #[serde(default)]
+    offset: usize,
+    /// Comma-separated list of top-level fields to include (e.g. "name,url,is_healthy")
+    fields: Option<String>,
+}
+
+/// Keep only the requested top-level fields of a service dict
+fn select_fields(service: Value, fields: &[&str]) -> Value {
+    match service {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(k, _)| fields.contains(&k.as_str()))
+                .collect(),
+        ),
+        other => other,
+    }
 }
 
 async fn services_handler(
     axum::extract::State(state): axum::extract::State<RegistryState>,
     Query(params): Query<ServicesQuery>,
-) -> Json<Value> {
+    headers: HeaderMap,
+) -> Response {
+    state.refresh_health_versions().await;
     let services = state.services.read().await;
+    let etag = state.etag();
+    let version = state.version.load(Ordering::SeqCst);
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
     let mut services_list: Vec<Value> = Vec::new();
 
-    for service in services.values() {
+    // Sort by name so that pagination is stable across requests
+    let mut sorted: Vec<_> = services.values().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for service in sorted {
         let service_dict = service.to_dict().await;
         services_list.push(service_dict);
     }
@@ -301,10 +1026,107 @@ async fn services_handler(
         .unwrap()
         .to_rfc3339();
 
+    // Paginate after filtering; "total" reports the number of matching services
+    let total = services_list.len();
+    let services_list: Vec<Value> = services_list
+        .into_iter()
+        .skip(params.offset)
+        .take(params.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    // Slim down payloads if specific fields were requested
+    let services_list: Vec<Value> = match &params.fields {
+        Some(fields) => {
+            let fields: Vec<&str> = fields
+                .split(',')
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+                .collect();
+            services_list
+                .into_iter()
+                .map(|s| select_fields(s, &fields))
+                .collect()
+        }
+        None => services_list,
+    };
+
+    (
+        [(header::ETAG, etag)],
+        Json(json!({
+            "services": services_list,
+            "total": total,
+            "count": services_list.len(),
+            "offset": params.offset,
+            "limit": params.limit,
+            "epoch": state.epoch,
+            "version": version,
+            "timestamp": timestamp
+        })),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct DeltaQuery {
+    /// Catalog version the client already has
+    since: u64,
+    /// Registry epoch that version came from
+    epoch: Option<u64>,
+}
+
+/// Services changed and removed since a catalog version, plus the heartbeat telemetry of
+/// every service. When the version cannot be served (registry restarted, or older than
+/// the remembered removals) the full catalog is returned with `full: true`.
+async fn services_delta_handler(
+    axum::extract::State(state): axum::extract::State<RegistryState>,
+    Query(params): Query<DeltaQuery>,
+) -> Json<Value> {
+    state.refresh_health_versions().await;
+    let services = state.services.read().await;
+    let version = state.version.load(Ordering::SeqCst);
+    let full = params.epoch != Some(state.epoch)
+        || params.since < state.delta_floor.load(Ordering::SeqCst)
+        || params.since > version;
+
+    let mut changed: Vec<_> = services
+        .values()
+        .filter(|service| full || service.modified > params.since)
+        .collect();
+    changed.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut services_list = Vec::with_capacity(changed.len());
+    for service in changed {
+        services_list.push(service.to_dict().await);
+    }
+
+    let mut removed: Vec<String> = if full {
+        Vec::new()
+    } else {
+        state
+            .tombstones
+            .read()
+            .await
+            .iter()
+            .filter(|(removed_at, name)| *removed_at > params.since && !services.contains_key(name))
+            .map(|(_, name)| name.clone())
+            .collect()
+    };
+    removed.sort();
+    removed.dedup();
+
+    let telemetry: HashMap<&str, &HashMap<String, Value>> = services
+        .values()
+        .filter(|service| !service.telemetry.is_empty())
+        .map(|service| (service.name.as_str(), &service.telemetry))
+        .collect();
+
     Json(json!({
+        "epoch": state.epoch,
+        "version": version,
+        "full": full,
         "services": services_list,
-        "total": services_list.len(),
-        "timestamp": timestamp
+        "removed": removed,
+        "telemetry": telemetry,
+        "timestamp": current_rfc3339()
     }))
 }
 
@@ -333,13 +1155,37 @@ struct RegisterServiceRequest {
     timestamp: Option<String>,
     #[serde(default)]
     metadata: HashMap<String, Value>,
+    /// Seconds without a heartbeat before the service is unhealthy; babysitters with
+    /// longer heartbeat intervals need a longer timeout
+    #[serde(default = "default_heartbeat_timeout")]
+    heartbeat_timeout: f64,
 }
 
 async fn register_service_handler(
     axum::extract::State(state): axum::extract::State<RegistryState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
     Json(payload): Json<RegisterServiceRequest>,
-) -> Result<(StatusCode, Json<Value>), StatusCode> {
-    let service_info = ServiceInfo::new(
+) -> Response {
+    if let Some(response) = rate_limit(
+        &state.register_limiter,
+        "registration",
+        client,
+        &payload.name,
+    ) {
+        return response;
+    }
+    if state.is_read_only() {
+        return read_only_rejection("registration", &payload.name).into_response();
+    }
+    if !(payload.heartbeat_timeout.is_finite() && payload.heartbeat_timeout > 0.0) {
+        warn!(
+            "Rejected registration of {}: invalid heartbeat_timeout {}",
+            payload.name, payload.heartbeat_timeout
+        );
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let mut service_info = ServiceInfo::new(
         payload.name.clone(),
         payload.host,
         payload.port,
@@ -347,20 +1193,34 @@ async fn register_service_handler(
         payload.url.clone(),
         payload.status,
         payload.metadata,
+        payload.heartbeat_timeout,
     );
 
     let mut services = state.services.write().await;
+    service_info.reported_healthy = service_info.is_healthy().await;
+    service_info.modified = state.bump_version();
     services.insert(payload.name.clone(), service_info.clone());
+    drop(services);
 
+    state.metrics.registrations.fetch_add(1, Ordering::Relaxed);
     info!("Registered service: {} at {}", payload.name, payload.url);
+    state.audit.record(
+        "registered",
+        &payload.name,
+        json!({"url": service_info.url, "status": service_info.status}),
+    );
+    state
+        .webhooks
+        .notify("registered", &payload.name, service_info.to_dict().await);
 
-    Ok((
+    (
         StatusCode::CREATED,
         Json(json!({
             "message": format!("Service '{}' registered successfully", payload.name),
             "service": service_info.to_dict().await
         })),
-    ))
+    )
+        .into_response()
 }
 
 #[derive(Deserialize)]
@@ -383,9 +1243,30 @@ async fn update_service_handler(
     axum::extract::State(state): axum::extract::State<RegistryState>,
     Path(name): Path<String>,
     Json(payload): Json<UpdateServiceRequest>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Response {
+    if state.is_read_only() {
+        return read_only_rejection("update", &name).into_response();
+    }
     let mut services = state.services.write().await;
-    let service = services.get_mut(&name).ok_or(StatusCode::NOT_FOUND)?;
+    let Some(service) = services.get_mut(&name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let fields: Vec<&str> = [
+        ("host", payload.host.is_some()),
+        ("port", payload.port.is_some()),
+        ("hostname", payload.hostname.is_some()),
+        ("url", payload.url.is_some()),
+        ("status", payload.status.is_some()),
+        ("metadata", payload.metadata.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, present)| *present)
+    .map(|(field, _)| field)
+    .collect();
+    state
+        .audit
+        .record("updated", &name, json!({"fields": fields}));
 
     if let Some(host) = payload.host {
         service.host = host;
@@ -407,27 +1288,46 @@ async fn update_service_handler(
     }
 
     service.update_heartbeat().await;
+    service.reported_healthy = service.is_healthy().await;
+    service.modified = state.bump_version();
 
     info!("Updated service: {}", name);
 
-    Ok(Json(json!({
+    Json(json!({
         "message": format!("Service '{}' updated successfully", name),
         "service": service.to_dict().await
-    })))
+    }))
+    .into_response()
 }
 
 async fn unregister_service_handler(
     axum::extract::State(state): axum::extract::State<RegistryState>,
     Path(name): Path<String>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Response {
+    if state.is_read_only() {
+        return read_only_rejection("unregistration", &name).into_response();
+    }
     let mut services = state.services.write().await;
     if services.remove(&name).is_some() {
+        drop(services);
+        state.record_removal(&name).await;
         info!("Unregistered service: {}", name);
-        Ok(Json(json!({
+        state
+            .metrics
+            .unregistrations
+            .fetch_add(1, Ordering::Relaxed);
+        state
+            .audit
+            .record("unregistered", &name, json!({"reason": "deregistered"}));
+        state
+            .webhooks
+            .notify("unregistered", &name, json!({"reason": "deregistered"}));
+        Json(json!({
             "message": format!("Service '{}' unregistered successfully", name)
-        })))
+        }))
+        .into_response()
     } else {
-        Err(StatusCode::NOT_FOUND)
+        StatusCode::NOT_FOUND.into_response()
     }
 }
 
@@ -438,17 +1338,15 @@ async fn service_health_handler(
     let services = state.services.read().await;
     let service = services.get(&name).ok_or(StatusCode::NOT_FOUND)?;
 
-    // Perform actual health check
-    let check_url = if service.metadata.get("type").and_then(|v| v.as_str()) == Some("openai-api") {
-        // For openai-api services, check babysitter URL (port + 1)
-        format!("http://{}:{}", service.host, service.port + 1)
-    } else {
-        service.url.clone()
+    // Perform actual health check (services in `none` mode report their last known status)
+    let health_status = match probe_service(service, state.health_check_timeout).await {
+        Some(health_status) => {
+            record_health_status(&state, service, &health_status).await;
+            health_status
+        }
+        None => service.health_status.read().await.clone(),
     };
 
-    let health_status = check_service_health(&check_url, state.health_check_timeout).await;
-    *service.health_status.write().await = health_status.clone();
-
     if health_status == "healthy" {
         service.update_heartbeat().await;
     }
@@ -472,21 +1370,49 @@ async fn service_health_handler(
 
 async fn heartbeat_handler(
     axum::extract::State(state): axum::extract::State<RegistryState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
     Path(name): Path<String>,
     payload: Option<Json<Value>>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Response {
+    if let Some(response) = rate_limit(&state.heartbeat_limiter, "heartbeat", client, &name) {
+        return response;
+    }
     let services = state.services.read().await;
-    let service = services.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+    let Some(service) = services.get(&name) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
 
     service.update_heartbeat().await;
+    state.metrics.heartbeats.fetch_add(1, Ordering::Relaxed);
 
-    // Update status if provided
+    // Update status and metadata if provided
     if let Some(Json(data)) = payload {
-        if let Some(status) = data.get("status").and_then(|v| v.as_str()) {
+        let status = data.get("status").and_then(|v| v.as_str());
+        let metadata = data.get("metadata").and_then(|v| v.as_object());
+        if status.is_some() || metadata.is_some() {
             drop(services);
             let mut services = state.services.write().await;
             if let Some(service) = services.get_mut(&name) {
-                service.status = status.to_string();
+                let mut changed = false;
+                if let Some(status) = status {
+                    changed |= service.status != status;
+                    service.status = status.to_string();
+                }
+                // Merge rather than replace so registration-time metadata is preserved;
+                // telemetry is stored apart so it does not start a new catalog version
+                if let Some(metadata) = metadata {
+                    for (key, value) in metadata {
+                        if TELEMETRY_KEYS.contains(&key.as_str()) {
+                            service.telemetry.insert(key.clone(), value.clone());
+                            continue;
+                        }
+                        changed |= service.metadata.get(key) != Some(value);
+                        service.metadata.insert(key.clone(), value.clone());
+                    }
+                }
+                if changed {
+                    service.modified = state.bump_version();
+                }
             }
         }
     }
@@ -499,10 +1425,11 @@ async fn heartbeat_handler(
         .unwrap()
         .to_rfc3339();
 
-    Ok(Json(json!({
+    Json(json!({
         "message": "Heartbeat received",
         "timestamp": timestamp
-    })))
+    }))
+    .into_response()
 }
 
 async fn stats_handler(
@@ -541,19 +1468,206 @@ async fn stats_handler(
         "status_distribution": status_counts,
         "host_distribution": host_counts,
         "uptime": state.start_time.elapsed().as_secs(),
+        "read_only": state.is_read_only(),
+        "rate_limited": {
+            "register": state.register_limiter.rejected.load(Ordering::Relaxed),
+            "heartbeat": state.heartbeat_limiter.rejected.load(Ordering::Relaxed),
+        },
         "timestamp": timestamp
     }))
 }
 
-async fn check_service_health(url: &str, timeout_secs: u64) -> String {
+/// Prometheus text exposition of registry counters and per-type service gauges
+async fn metrics_handler(
+    axum::extract::State(state): axum::extract::State<RegistryState>,
+) -> impl IntoResponse {
+    let metrics = &state.metrics;
+
+    // Per-type totals: type -> (total, healthy)
+    let mut per_type: std::collections::BTreeMap<String, (usize, usize)> =
+        std::collections::BTreeMap::new();
+    {
+        let services = state.services.read().await;
+        for service in services.values() {
+            let service_type = service
+                .metadata
+                .get("type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string();
+            let healthy = service.is_healthy().await;
+            let entry = per_type.entry(service_type).or_insert((0, 0));
+            entry.0 += 1;
+            if healthy {
+                entry.1 += 1;
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let counters = [
+        (
+            "infini_registry_registrations_total",
+            "Total number of service registrations",
+            metrics.registrations.load(Ordering::Relaxed),
+        ),
+        (
+            "infini_registry_unregistrations_total",
+            "Total number of explicit service unregistrations",
+            metrics.unregistrations.load(Ordering::Relaxed),
+        ),
+        (
+            "infini_registry_heartbeats_total",
+            "Total number of heartbeats received",
+            metrics.heartbeats.load(Ordering::Relaxed),
+        ),
+        (
+            "infini_registry_cleanup_removals_total",
+            "Total number of services removed for missing heartbeats",
+            metrics.cleanup_removals.load(Ordering::Relaxed),
+        ),
+    ];
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP infini_registry_health_checks_total Total number of health checks by result"
+    );
+    let _ = writeln!(out, "# TYPE infini_registry_health_checks_total counter");
+    let _ = writeln!(
+        out,
+        "infini_registry_health_checks_total{{result=\"healthy\"}} {}",
+        metrics.health_checks_healthy.load(Ordering::Relaxed)
+    );
+    let _ = writeln!(
+        out,
+        "infini_registry_health_checks_total{{result=\"unhealthy\"}} {}",
+        metrics.health_checks_unhealthy.load(Ordering::Relaxed)
+    );
+
+    let _ = writeln!(
+        out,
+        "# HELP infini_registry_rate_limited_total Total number of requests rejected by rate limiting"
+    );
+    let _ = writeln!(out, "# TYPE infini_registry_rate_limited_total counter");
+    for (endpoint, limiter) in [
+        ("register", &state.register_limiter),
+        ("heartbeat", &state.heartbeat_limiter),
+    ] {
+        let _ = writeln!(
+            out,
+            "infini_registry_rate_limited_total{{endpoint=\"{}\"}} {}",
+            endpoint,
+            limiter.rejected.load(Ordering::Relaxed)
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP infini_registry_services Number of registered services by type"
+    );
+    let _ = writeln!(out, "# TYPE infini_registry_services gauge");
+    for (service_type, (total, _)) in &per_type {
+        let _ = writeln!(
+            out,
+            "infini_registry_services{{type=\"{}\"}} {}",
+            service_type, total
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP infini_registry_healthy_services Number of healthy services by type"
+    );
+    let _ = writeln!(out, "# TYPE infini_registry_healthy_services gauge");
+    for (service_type, (_, healthy)) in &per_type {
+        let _ = writeln!(
+            out,
+            "infini_registry_healthy_services{{type=\"{}\"}} {}",
+            service_type, healthy
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP infini_registry_uptime_seconds Seconds since the registry started"
+    );
+    let _ = writeln!(out, "# TYPE infini_registry_uptime_seconds gauge");
+    let _ = writeln!(
+        out,
+        "infini_registry_uptime_seconds {}",
+        state.start_time.elapsed().as_secs()
+    );
+
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4",
+        )],
+        out,
+    )
+}
+
+/// Probe a service according to its `health_check` metadata: `http` (default), `tcp`
+/// (the port accepts connections) or `none` (heartbeats only, never probed)
+async fn probe_service(service: &ServiceInfo, timeout_secs: u64) -> Option<String> {
+    match service
+        .metadata
+        .get("health_check")
+        .and_then(|v| v.as_str())
+    {
+        Some("none") => None,
+        Some("tcp") => {
+            let connect = tokio::net::TcpStream::connect((service.host.as_str(), service.port));
+            let healthy = matches!(
+                tokio::time::timeout(Duration::from_secs(timeout_secs), connect).await,
+                Ok(Ok(_))
+            );
+            Some(if healthy { "healthy" } else { "unhealthy" }.to_string())
+        }
+        _ => {
+            // `health_path` and `health_expect_status` override /health and "any 2xx"
+            let path = service
+                .metadata
+                .get("health_path")
+                .and_then(|v| v.as_str())
+                .unwrap_or("/health");
+            let url = format!(
+                "{}/{}",
+                service.health_check_url(),
+                path.trim_start_matches('/')
+            );
+            let expected = service
+                .metadata
+                .get("health_expect_status")
+                .and_then(|v| v.as_u64());
+            Some(check_service_health(&url, expected, timeout_secs).await)
+        }
+    }
+}
+
+async fn check_service_health(
+    url: &str,
+    expected_status: Option<u64>,
+    timeout_secs: u64,
+) -> String {
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(timeout_secs))
         .build()
         .unwrap_or_default();
 
-    match client.get(format!("{}/health", url)).send().await {
+    match client.get(url).send().await {
         Ok(response) => {
-            if response.status().is_success() {
+            let status = response.status();
+            let healthy = match expected_status {
+                Some(expected) => u64::from(status.as_u16()) == expected,
+                None => status.is_success(),
+            };
+            if healthy {
                 "healthy".to_string()
             } else {
                 "unhealthy".to_string()
@@ -563,9 +1677,39 @@ async fn check_service_health(url: &str, timeout_secs: u64) -> String {
     }
 }
 
+/// Store a health check result and emit a webhook event if the status changed
+async fn record_health_status(state: &RegistryState, service: &ServiceInfo, health_status: &str) {
+    state.metrics.record_health_check(health_status);
+
+    let previous = {
+        let mut current = service.health_status.write().await;
+        std::mem::replace(&mut *current, health_status.to_string())
+    };
+
+    // "unknown" is the initial state, so the first check is not a transition
+    if previous != health_status && previous != "unknown" {
+        info!(
+            "Service {} health changed: {} -> {}",
+            service.name, previous, health_status
+        );
+        state.audit.record(
+            "health_changed",
+            &service.name,
+            json!({"previous": previous, "current": health_status}),
+        );
+        state.webhooks.notify(
+            "health_changed",
+            &service.name,
+            json!({"previous": previous, "current": health_status}),
+        );
+    }
+}
+
 async fn perform_health_checks(state: RegistryState) {
     loop {
         sleep(Duration::from_secs(state.health_check_interval)).await;
+        // Notice lapsed heartbeats even when nobody is reading the catalog
+        state.refresh_health_versions().await;
 
         let services = {
             let services_guard = state.services.read().await;
@@ -575,17 +1719,15 @@ async fn perform_health_checks(state: RegistryState) {
         if !services.is_empty() {
             let mut healthy_count = 0;
             for service in &services {
-                let check_url = if service.metadata.get("type").and_then(|v| v.as_str())
-                    == Some("openai-api")
-                {
-                    format!("http://{}:{}", service.host, service.port + 1)
-                } else {
-                    service.url.clone()
+                let Some(health_status) = probe_service(service, state.health_check_timeout).await
+                else {
+                    // Heartbeat-only services count by their heartbeat
+                    if service.is_healthy().await {
+                        healthy_count += 1;
+                    }
+                    continue;
                 };
-
-                let health_status =
-                    check_service_health(&check_url, state.health_check_timeout).await;
-                *service.health_status.write().await = health_status.clone();
+                record_health_status(&state, service, &health_status).await;
 
                 if health_status == "healthy" {
                     healthy_count += 1;
@@ -604,6 +1746,12 @@ async fn perform_health_checks(state: RegistryState) {
 async fn cleanup_stale_services(state: RegistryState) {
     loop {
         sleep(Duration::from_secs(state.cleanup_interval)).await;
+        // A frozen catalog keeps nodes that are down for the maintenance window
+        state.register_limiter.prune();
+        state.heartbeat_limiter.prune();
+        if state.is_read_only() {
+            continue;
+        }
 
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -616,8 +1764,8 @@ async fn cleanup_stale_services(state: RegistryState) {
             let mut stale = Vec::new();
             for (name, service) in services.iter() {
                 let last_heartbeat = *service.last_heartbeat.read().await;
-                // Remove services that haven't sent heartbeat for 5 minutes
-                if (now - last_heartbeat) > 300.0 {
+                // Remove services that haven't sent a heartbeat for a long time
+                if (now - last_heartbeat) > service.stale_after() {
                     stale.push(name.clone());
                 }
             }
@@ -629,9 +1777,490 @@ async fn cleanup_stale_services(state: RegistryState) {
             let mut services = state.services.write().await;
             for name in &stale_services {
                 services.remove(name);
+                state.record_removal(name).await;
+                state
+                    .metrics
+                    .cleanup_removals
+                    .fetch_add(1, Ordering::Relaxed);
                 info!("Removed stale service: {}", name);
+                state
+                    .audit
+                    .record("unregistered", name, json!({"reason": "stale"}));
+                state
+                    .webhooks
+                    .notify("unregistered", name, json!({"reason": "stale"}));
             }
             info!("Cleaned up {} stale services", stale_services.len());
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn test_state(auth_token: Option<&str>, read_only: bool) -> RegistryState {
+        RegistryState::new(
+            30,
+            5,
+            60,
+            Vec::new(),
+            AuditTrail::new(100, None, 0).unwrap(),
+            read_only,
+            RateLimiter::new(0.0, 1),
+            RateLimiter::new(0.0, 1),
+            auth_token.map(str::to_string),
+        )
+    }
+
+    fn registration(name: &str) -> Value {
+        json!({
+            "name": name,
+            "host": "127.0.0.1",
+            "port": 8100,
+            "hostname": "node-1",
+            "url": "http://127.0.0.1:8100",
+            "status": "running",
+            "metadata": {"type": "openai-api"}
+        })
+    }
+
+    /// Send a request from a local client through the registry's router
+    async fn send(
+        state: &RegistryState,
+        method: &str,
+        uri: &str,
+        headers: &[(header::HeaderName, &str)],
+        body: Option<Value>,
+    ) -> Response {
+        let mut builder = axum::http::Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(name, *value);
+        }
+        let mut request = match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        create_router(state.clone()).oneshot(request).await.unwrap()
+    }
+
+    async fn json_body(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    async fn delta(state: &RegistryState, since: u64, epoch: u64) -> Value {
+        let uri = format!("/services/delta?since={}&epoch={}", since, epoch);
+        json_body(send(state, "GET", &uri, &[], None).await).await
+    }
+
+    fn names(services: &Value) -> Vec<&str> {
+        services
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|service| service["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_services_delta() {
+        let state = test_state(None, false);
+        for name in ["a", "b"] {
+            let response = send(&state, "POST", "/services", &[], Some(registration(name))).await;
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        let unchanged = delta(&state, 2, state.epoch).await;
+        assert_eq!(unchanged["version"], 2);
+        assert_eq!(unchanged["full"], false);
+        assert!(names(&unchanged["services"]).is_empty());
+
+        let update = json!({"metadata": {"type": "openai-api", "models": ["m"]}});
+        send(&state, "PUT", "/services/a", &[], Some(update)).await;
+        let updated = delta(&state, 2, state.epoch).await;
+        assert_eq!(updated["version"], 3);
+        assert_eq!(names(&updated["services"]), ["a"]);
+        assert_eq!(updated["removed"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_services_delta_tombstones() {
+        let state = test_state(None, false);
+        for name in ["a", "b"] {
+            send(&state, "POST", "/services", &[], Some(registration(name))).await;
+        }
+        let response = send(&state, "DELETE", "/services/b", &[], None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let removal = delta(&state, 2, state.epoch).await;
+        assert!(names(&removal["services"]).is_empty());
+        assert_eq!(removal["removed"], json!(["b"]));
+
+        // A service registered again after its removal is a change, not a removal
+        send(&state, "POST", "/services", &[], Some(registration("b"))).await;
+        let since_start = delta(&state, 0, state.epoch).await;
+        assert_eq!(names(&since_start["services"]), ["a", "b"]);
+        assert_eq!(since_start["removed"], json!([]));
+
+        // Versions older than the remembered removals get the full catalog
+        state.delta_floor.store(3, Ordering::SeqCst);
+        assert_eq!(delta(&state, 2, state.epoch).await["full"], true);
+    }
+
+    #[tokio::test]
+    async fn test_services_delta_epoch_mismatch() {
+        let state = test_state(None, false);
+        for name in ["a", "b"] {
+            send(&state, "POST", "/services", &[], Some(registration(name))).await;
+        }
+
+        let restarted = delta(&state, 2, state.epoch + 1).await;
+        assert_eq!(restarted["full"], true);
+        assert_eq!(restarted["epoch"], state.epoch);
+        assert_eq!(names(&restarted["services"]), ["a", "b"]);
+
+        // A version from the future also means the client's copy is not ours
+        assert_eq!(delta(&state, 9, state.epoch).await["full"], true);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_telemetry_is_unversioned() {
+        const HEARTBEAT: &str = "/services/a/heartbeat";
+        let state = test_state(None, false);
+        send(&state, "POST", "/services", &[], Some(registration("a"))).await;
+
+        let heartbeat = json!({"metadata": {"load": {"queue_depth": 3}}});
+        let response = send(&state, "POST", HEARTBEAT, &[], Some(heartbeat)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.version.load(Ordering::SeqCst), 1);
+
+        let unchanged = delta(&state, 1, state.epoch).await;
+        assert!(names(&unchanged["services"]).is_empty());
+        assert_eq!(unchanged["telemetry"]["a"]["load"]["queue_depth"], 3);
+        let service = json_body(send(&state, "GET", "/services/a", &[], None).await).await;
+        assert_eq!(service["metadata"]["load"]["queue_depth"], 3);
+
+        // Other heartbeat metadata still changes the catalog
+        let heartbeat = json!({"metadata": {"restart_count": 1}});
+        send(&state, "POST", HEARTBEAT, &[], Some(heartbeat)).await;
+        assert_eq!(
+            names(&delta(&state, 1, state.epoch).await["services"]),
+            ["a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lapsed_heartbeat_is_a_catalog_change() {
+        let state = test_state(None, false);
+        send(&state, "POST", "/services", &[], Some(registration("a"))).await;
+        let last_heartbeat = state.services.read().await["a"].last_heartbeat.clone();
+        *last_heartbeat.write().await = 0.0;
+
+        let lapsed = delta(&state, 1, state.epoch).await;
+        assert_eq!(lapsed["version"], 2);
+        assert_eq!(lapsed["services"][0]["is_healthy"], false);
+        // Seen once; later reads do not bump the version again
+        assert_eq!(delta(&state, 2, state.epoch).await["version"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_delta_since_skips_telemetry_heartbeats() {
+        let state = test_state(None, false);
+        for name in ["a", "b"] {
+            send(&state, "POST", "/services", &[], Some(registration(name))).await;
+        }
+        let etag = send(&state, "GET", "/services", &[], None).await.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let heartbeat = json!({"metadata": {"load": {"kv_cache_utilization": 0.5}}});
+        send(
+            &state,
+            "POST",
+            "/services/a/heartbeat",
+            &[],
+            Some(heartbeat),
+        )
+        .await;
+
+        // Only b changed since version 1; a's load rides along as telemetry
+        let since_a = delta(&state, 1, state.epoch).await;
+        assert_eq!(since_a["version"], 2);
+        assert_eq!(names(&since_a["services"]), ["b"]);
+        assert_eq!(
+            since_a["telemetry"]["a"]["load"]["kv_cache_utilization"],
+            0.5
+        );
+        let current = delta(&state, 2, state.epoch).await;
+        assert!(names(&current["services"]).is_empty());
+
+        let cached = send(
+            &state,
+            "GET",
+            "/services",
+            &[(header::IF_NONE_MATCH, &etag)],
+            None,
+        )
+        .await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[test]
+    fn test_rate_limiter_burst_and_refill() {
+        let limiter = RateLimiter::new(1.0, 2);
+        let ip = ["ip:10.0.0.1".to_string()];
+        assert!(limiter.check(&ip).is_ok());
+        assert!(limiter.check(&ip).is_ok());
+        let retry_after = limiter.check(&ip).unwrap_err();
+        assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(1));
+        assert_eq!(limiter.rejected.load(Ordering::Relaxed), 1);
+
+        // Other keys have their own bucket, but every key must have a token
+        let other = ["ip:10.0.0.2".to_string()];
+        assert!(limiter.check(&other).is_ok());
+        let both = [ip[0].clone(), other[0].clone()];
+        assert!(limiter.check(&both).is_err());
+        assert!(limiter.check(&other).is_ok());
+
+        // A second refills one token, never beyond the burst
+        for bucket in limiter.buckets.lock().unwrap().values_mut() {
+            bucket.updated -= Duration::from_secs(1);
+        }
+        assert!(limiter.check(&ip).is_ok());
+        assert!(limiter.check(&ip).is_err());
+        for bucket in limiter.buckets.lock().unwrap().values_mut() {
+            bucket.updated -= Duration::from_secs(60);
+        }
+        assert!(limiter.check(&ip).is_ok());
+        assert!(limiter.check(&ip).is_ok());
+        assert!(limiter.check(&ip).is_err());
+
+        let disabled = RateLimiter::new(0.0, 1);
+        assert!((0..100).all(|_| disabled.check(&ip).is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_registration() {
+        let state = RegistryState::new(
+            30,
+            5,
+            60,
+            Vec::new(),
+            AuditTrail::new(100, None, 0).unwrap(),
+            false,
+            RateLimiter::new(0.5, 1),
+            RateLimiter::new(0.0, 1),
+            None,
+        );
+        let response = send(&state, "POST", "/services", &[], Some(registration("a"))).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(&state, "POST", "/services", &[], Some(registration("a"))).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+        let stats = json_body(send(&state, "GET", "/stats", &[], None).await).await;
+        assert_eq!(stats["rate_limited"]["register"], 1);
+    }
+
+    #[test]
+    fn test_audit_trail_events() {
+        let trail = AuditTrail::new(3, None, 0).unwrap();
+        trail.record("registered", "a", json!({}));
+        trail.record("registered", "b", json!({}));
+        trail.record("unhealthy", "a", json!({}));
+        trail.record("unregistered", "a", json!({"reason": "stale"}));
+
+        let events = |service, event, limit| -> Vec<(String, String)> {
+            trail
+                .events(service, event, limit)
+                .into_iter()
+                .map(|e| (e.service, e.event))
+                .collect()
+        };
+        // The oldest event fell out of the capacity
+        assert_eq!(events(None, None, None).len(), 3);
+        assert_eq!(
+            events(Some("a"), None, None),
+            [
+                ("a".into(), "unhealthy".into()),
+                ("a".into(), "unregistered".into())
+            ]
+        );
+        assert_eq!(
+            events(None, Some("registered"), None),
+            [("b".into(), "registered".into())]
+        );
+        assert_eq!(
+            events(None, None, Some(1)),
+            [("a".into(), "unregistered".into())]
+        );
+
+        let disabled = AuditTrail::new(0, None, 0).unwrap();
+        disabled.record("registered", "a", json!({}));
+        assert!(disabled.events(None, None, None).is_empty());
+    }
+
+    #[test]
+    fn test_audit_file_rotation() {
+        let path = std::env::temp_dir().join("infini-registry-audit-test.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(rotated_path(&path));
+        let event = |n: usize| {
+            json!(AuditEvent {
+                timestamp: current_rfc3339(),
+                event: "registered".to_string(),
+                service: format!("svc-{}", n),
+                details: json!({}),
+            })
+            .to_string()
+        };
+        let max_bytes = 2 * (event(0).len() as u64 + 1);
+
+        // Two lines fit: svc-0 and svc-1 are rotated out by svc-2, then dropped by svc-4
+        let mut file = AuditFile::open(&path, max_bytes).unwrap();
+        for n in 0..5 {
+            file.append(&event(n)).unwrap();
+        }
+        let lines = |path: &std::path::Path| std::fs::read_to_string(path).unwrap().lines().count();
+        assert_eq!(lines(&path), 1);
+        assert_eq!(lines(&rotated_path(&path)), 2);
+
+        // Both files are read back on startup, oldest first
+        let trail = AuditTrail::new(10, path.to_str(), max_bytes).unwrap();
+        let services: Vec<String> = trail
+            .events(None, None, None)
+            .into_iter()
+            .map(|e| e.service)
+            .collect();
+        assert_eq!(services, ["svc-2", "svc-3", "svc-4"]);
+
+        drop(trail);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(rotated_path(&path));
+    }
+
+    #[tokio::test]
+    async fn test_require_token() {
+        let state = test_state(Some("secret"), false);
+        let wrong = [(header::AUTHORIZATION, "Bearer guess")];
+        let right = [(header::AUTHORIZATION, "Bearer secret")];
+
+        let response = send(&state, "POST", "/services", &[], Some(registration("a"))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send(&state, "POST", "/services", &wrong, Some(registration("a"))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(state.services.read().await.is_empty());
+        let response = send(&state, "POST", "/services", &right, Some(registration("a"))).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        for (method, uri) in [("POST", "/services/a/heartbeat"), ("DELETE", "/services/a")] {
+            let response = send(&state, method, uri, &[], None).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::UNAUTHORIZED,
+                "{} {}",
+                method,
+                uri
+            );
+        }
+        let response = send(
+            &state,
+            "PUT",
+            "/admin/read-only",
+            &[],
+            Some(json!({"enabled": true})),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!state.is_read_only());
+
+        // Reads stay open
+        for uri in [
+            "/services",
+            "/services/a",
+            "/services/delta?since=0",
+            "/stats",
+        ] {
+            let response = send(&state, "GET", uri, &[], None).await;
+            assert_eq!(response.status(), StatusCode::OK, "GET {}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode() {
+        let state = test_state(None, false);
+        send(&state, "POST", "/services", &[], Some(registration("a"))).await;
+        let enable = json!({"enabled": true});
+        let response = send(&state, "PUT", "/admin/read-only", &[], Some(enable)).await;
+        assert_eq!(json_body(response).await["previous"], false);
+
+        let response = send(&state, "POST", "/services", &[], Some(registration("b"))).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json_body(response).await["read_only"], true);
+        let update = json!({"status": "stopped"});
+        let response = send(&state, "PUT", "/services/a", &[], Some(update)).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let response = send(&state, "DELETE", "/services/a", &[], None).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // Reads and heartbeats are still served
+        let response = send(&state, "POST", "/services/a/heartbeat", &[], None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let services = json_body(send(&state, "GET", "/services", &[], None).await).await;
+        assert_eq!(names(&services["services"]), ["a"]);
+        assert_eq!(services["services"][0]["status"], "running");
+
+        let disable = json!({"enabled": false});
+        send(&state, "PUT", "/admin/read-only", &[], Some(disable)).await;
+        let response = send(&state, "POST", "/services", &[], Some(registration("b"))).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_services_etag() {
+        let state = test_state(None, false);
+        send(&state, "POST", "/services", &[], Some(registration("a"))).await;
+
+        let response = send(&state, "GET", "/services", &[], None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let cached = send(
+            &state,
+            "GET",
+            "/services",
+            &[(header::IF_NONE_MATCH, &etag)],
+            None,
+        )
+        .await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(cached.headers()[header::ETAG], etag.as_str());
+
+        send(&state, "POST", "/services", &[], Some(registration("b"))).await;
+        let changed = send(
+            &state,
+            "GET",
+            "/services",
+            &[(header::IF_NONE_MATCH, &etag)],
+            None,
+        )
+        .await;
+        assert_eq!(changed.status(), StatusCode::OK);
+        assert_ne!(changed.headers()[header::ETAG], etag.as_str());
+    }
+}
@@ -1,21 +1,478 @@
 //! Configuration management for the router service
 
 use anyhow::{Context, Result};
+use clap::Parser;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::proxy::forwarded::TrustedProxies;
+use crate::proxy::header_rules::HeaderRules;
+use crate::proxy::priority::Priority;
+use crate::registry::tls::RegistryTls;
+use crate::router::backend_load::LoadPolicy;
+use crate::router::context_window::ContextOverflow;
+use crate::router::flapping::FlapPolicy;
+use crate::router::outlier::OutlierDetection;
+use crate::router::slow_backends::SlowBackendPolicy;
+use crate::router::throttle::ThrottlePolicy;
+use crate::utils::listen::parse_bind_addr;
+
+/// Command-line arguments of the router
+#[derive(Parser, Debug)]
+#[command(name = "infini-router")]
+#[command(about = "High-performance distributed router for InfiniLM services", long_about = None)]
+pub struct RouterArgs {
+    /// Router port
+    #[arg(long, default_value = "8080")]
+    pub router_port: u16,
+
+    /// Address to listen on, e.g. 0.0.0.0, :: or [::] (repeatable; default 0.0.0.0)
+    #[arg(long, value_parser = parse_bind_addr)]
+    pub bind: Vec<IpAddr>,
+
+    /// Service registry URL for dynamic service discovery
+    #[arg(long)]
+    pub registry_url: Option<String>,
+
+    /// PEM bundle of extra CA certificates trusted for an https registry URL
+    #[arg(long)]
+    pub registry_ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate presented to the registry (mutual TLS)
+    #[arg(long, requires = "registry_client_key")]
+    pub registry_client_cert: Option<PathBuf>,
+
+    /// PEM private key for --registry-client-cert
+    #[arg(long, requires = "registry_client_cert")]
+    pub registry_client_key: Option<PathBuf>,
+
+    /// JSON file with static service configurations
+    #[arg(long)]
+    pub static_services: Option<String>,
+
+    /// Health check interval in seconds
+    #[arg(long, default_value = "30")]
+    pub health_interval: u64,
+
+    /// Health check timeout in seconds
+    #[arg(long, default_value = "5")]
+    pub health_timeout: u64,
+
+    /// Max errors before marking service unhealthy
+    #[arg(long, default_value = "3")]
+    pub max_errors: u32,
+
+    /// Registry sync interval in seconds
+    #[arg(long, default_value = "10")]
+    pub registry_sync_interval: u64,
+
+    /// Grace period in seconds before removing services that disappear from registry
+    #[arg(long, default_value = "60")]
+    pub service_removal_grace_period: u64,
+
+    /// Maximum number of session affinity entries (least recently used are evicted)
+    #[arg(long, default_value = "100000")]
+    pub session_max_entries: usize,
+
+    /// Idle time in seconds after which a session affinity entry expires
+    #[arg(long, default_value = "3600")]
+    pub session_ttl: u64,
+
+    /// Redis URL for sharing session affinity across router replicas and restarts
+    /// (requires the `redis` feature)
+    #[arg(long)]
+    pub session_redis_url: Option<String>,
+
+    /// Base URL of another router replica to share session affinity and backend
+    /// ejections with (repeatable)
+    #[arg(long = "peer-router")]
+    pub peer_routers: Vec<String>,
+
+    /// Shared secret peer routers present (Authorization: Bearer) on the /internal
+    /// replication endpoints; without it only the peer routers' addresses are accepted
+    #[arg(long, env = "INFINI_PEER_TOKEN", hide_env_values = true)]
+    pub peer_token: Option<String>,
+
+    /// Bearer token operators present on the /admin endpoints; without it they are
+    /// disabled (peer routers can still forward session flushes)
+    #[arg(long, env = "INFINI_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    /// Maximum in-flight requests per service before the router answers 429 (0 = unlimited);
+    /// a service's `max_concurrency` metadata overrides it
+    #[arg(long, default_value = "0")]
+    pub max_concurrency_per_service: u32,
+
+    /// Seconds to cache the aggregated /models list (0 disables caching)
+    #[arg(long, default_value = "5")]
+    pub models_cache_ttl: u64,
+
+    /// Directory where /v1/batches state and results are persisted
+    /// (unfinished batches resume on restart); batches are kept in memory only if unset
+    #[arg(long)]
+    pub batch_dir: Option<String>,
+
+    /// Maximum concurrent requests per batch
+    #[arg(long, default_value = "8")]
+    pub batch_concurrency: usize,
+
+    /// Built-in request/response hook, applied in order (repeatable):
+    /// `system_prompt=<text>`, `strip_fields=a,b` or `strip_response_fields=a,b`
+    #[arg(long = "proxy-hook")]
+    pub proxy_hooks: Vec<String>,
+
+    /// Audit log sink for proxied requests: a JSONL file path or an http(s) URL to POST to
+    #[arg(long)]
+    pub audit_sink: Option<String>,
+
+    /// How prompts appear in audit records: none, hash, redact or full
+    #[arg(long, default_value = "hash")]
+    pub audit_prompts: String,
+
+    /// Fraction of requests recorded in the audit log (0.0-1.0)
+    #[arg(long, default_value = "1.0")]
+    pub audit_sample_rate: f64,
+
+    /// Regex replaced with [REDACTED] in `redact` mode (repeatable; defaults cover
+    /// e-mail addresses, API keys and long digit sequences)
+    #[arg(long = "audit-redact-pattern")]
+    pub audit_redact_patterns: Vec<String>,
+
+    /// Origin allowed to call the router from a browser (repeatable, "*" for any);
+    /// CORS is disabled when unset
+    #[arg(long = "cors-allowed-origin")]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// Methods allowed in CORS requests (comma-separated)
+    #[arg(long, value_delimiter = ',', default_value = "GET,POST,OPTIONS")]
+    pub cors_allowed_methods: Vec<String>,
+
+    /// Request headers allowed in CORS requests (comma-separated, "*" for any)
+    #[arg(long, value_delimiter = ',', default_value = "*")]
+    pub cors_allowed_headers: Vec<String>,
+
+    /// Seconds browsers may cache CORS preflight responses
+    #[arg(long, default_value = "600")]
+    pub cors_max_age: u64,
+
+    /// Gzip JSON responses of at least this many bytes for clients that accept it
+    /// (0 disables; responses the backend already compressed are passed through)
+    #[arg(long, default_value = "0")]
+    pub compress_min_bytes: u64,
+
+    /// Proxy timeout for a model as MODEL=SECONDS (repeatable); overrides a service's
+    /// `proxy_timeout_seconds` metadata and PROXY_TIMEOUT_SECONDS
+    #[arg(long = "model-timeout")]
+    pub model_timeouts: Vec<String>,
+
+    /// Methods whose failed requests are retried on another service (comma-separated; default: all)
+    #[arg(long, value_delimiter = ',')]
+    pub retry_methods: Vec<String>,
+
+    /// Path prefix whose failed requests are retried (repeatable; default: all paths)
+    #[arg(long = "retry-path")]
+    pub retry_paths: Vec<String>,
+
+    /// Largest request body (bytes) that is retried; larger requests get one attempt (0 = no limit)
+    #[arg(long, default_value = "0")]
+    pub retry_max_body_bytes: usize,
+
+    /// Upstream response statuses retried on another service like connection errors
+    /// (comma-separated, e.g. 500,502,504; default: none)
+    #[arg(long, value_delimiter = ',')]
+    pub retry_statuses: Vec<u16>,
+
+    /// Latency objective (ms) for /stats/slo; streaming requests are timed to the first response byte
+    #[arg(long, default_value = "30000")]
+    pub slo_latency_ms: u64,
+
+    /// Availability target for /stats/slo: share of requests that must not fail with a 5xx
+    #[arg(long, default_value = "0.99")]
+    pub slo_availability_target: f64,
+
+    /// Share of successful requests that must meet the latency objective
+    #[arg(long, default_value = "0.95")]
+    pub slo_latency_target: f64,
+
+    /// Windows reported in /stats/slo (comma-separated, e.g. 30m,1h,24h; at most 24h)
+    #[arg(long, value_delimiter = ',', default_value = "1h,24h")]
+    pub slo_windows: Vec<String>,
+
+    /// Fraction of the health check interval over which each round of checks is spread
+    /// (0 checks every service at once)
+    #[arg(long, default_value = "0.5")]
+    pub health_check_spread: f64,
+
+    /// Maximum health checks in flight at once
+    #[arg(long, default_value = "32")]
+    pub health_check_concurrency: usize,
+
+    /// Consecutive proxy connection failures before a service is taken out of rotation
+    #[arg(long, default_value = "1")]
+    pub passive_failure_threshold: u32,
+
+    /// Seconds between recovery probes of a service ejected by proxy failures (0 waits
+    /// for the periodic health check)
+    #[arg(long, default_value = "2")]
+    pub recovery_probe_interval: u64,
+
+    /// Seconds between live requests let through to a service ejected by proxy failures,
+    /// so recovery shows in real traffic (0 waits for health checks)
+    #[arg(long, default_value = "10")]
+    pub half_open_interval: u64,
+
+    /// Eject services whose error rate exceeds this multiple of the pool average (0 disables
+    /// outlier detection)
+    #[arg(long, default_value = "0")]
+    pub outlier_error_ratio: f64,
+
+    /// Seconds between outlier detection passes
+    #[arg(long, default_value = "10")]
+    pub outlier_interval: u64,
+
+    /// Seconds of traffic the outlier error rate is computed over (at most 3600)
+    #[arg(long, default_value = "60")]
+    pub outlier_window: u64,
+
+    /// Requests a service needs in the window before it can be ejected as an outlier
+    #[arg(long, default_value = "20")]
+    pub outlier_min_requests: u64,
+
+    /// Largest percentage of services ejected as outliers at the same time
+    #[arg(long, default_value = "10")]
+    pub outlier_max_ejection_percent: f64,
+
+    /// Seconds a first outlier ejection lasts; repeated ejections last proportionally longer
+    #[arg(long, default_value = "30")]
+    pub outlier_ejection_seconds: u64,
+
+    /// Reduce the weight of services whose p95 latency exceeds this multiple of the pool
+    /// median (0 disables)
+    #[arg(long, default_value = "0")]
+    pub slow_backend_ratio: f64,
+
+    /// Seconds between slow-backend latency checks
+    #[arg(long, default_value = "30")]
+    pub slow_backend_interval: u64,
+
+    /// Requests a service needs since its last latency check before it is judged again
+    #[arg(long, default_value = "20")]
+    pub slow_backend_min_samples: u64,
+
+    /// Crash restarts (reported by babysitters) after which a service only gets requests
+    /// no stable service can take (0 disables)
+    #[arg(long, default_value = "3")]
+    pub flap_restart_threshold: u64,
+
+    /// Seconds since its last restart during which a service can count as flapping
+    #[arg(long, default_value = "600")]
+    pub flap_window: u64,
+
+    /// Queue depth (reported by babysitters) at which a backend only gets requests no
+    /// less loaded service can take (0 disables)
+    #[arg(long, default_value = "16")]
+    pub load_max_queue_depth: u64,
+
+    /// KV-cache utilization (0.0-1.0, reported by babysitters) at which a backend only
+    /// gets requests no less loaded service can take (0 disables)
+    #[arg(long, default_value = "0.95")]
+    pub load_max_kv_cache: f64,
+
+    /// Requests estimated to exceed the model's context length (reported by backends or
+    /// declared in `model_capabilities` metadata): off, reject (400 when over the smallest
+    /// window of the model's services) or route (send them to services with a large enough
+    /// window, 400 when there is none)
+    #[arg(long, default_value = "reject")]
+    pub context_overflow: String,
+
+    /// Seconds to back off from a service that answers 429 without Retry-After (a 429,
+    /// or a 503 with Retry-After, sends its traffic to other services meanwhile)
+    #[arg(long, default_value = "5")]
+    pub throttle_backoff: u64,
+
+    /// Longest Retry-After (seconds) honoured when backing off from a service
+    #[arg(long, default_value = "60")]
+    pub throttle_max_backoff: u64,
+
+    /// Pass 429/503 responses to the client instead of retrying them on another service
+    #[arg(long)]
+    pub no_throttle_retry: bool,
+
+    /// Zone this router runs in; services whose metadata `zone` matches are preferred and
+    /// other zones are only used when no local service can take the request
+    #[arg(long)]
+    pub zone: Option<String>,
+
+    /// Map an API key (Authorization: Bearer) to a tenant pool as API_KEY=POOL (repeatable);
+    /// tenants reach services whose metadata `pool` matches plus unpooled services
+    #[arg(long = "tenant-key")]
+    pub tenant_keys: Vec<String>,
+
+    /// Header carrying the tenant pool name, for deployments where a gateway authenticates
+    /// clients (checked when the API key maps to no pool)
+    #[arg(long)]
+    pub tenant_header: Option<String>,
+
+    /// Map an API key (Authorization: Bearer) to a priority class as API_KEY=CLASS
+    /// (high, normal or low; repeatable). The X-Priority header can lower it but not raise it
+    #[arg(long = "key-priority")]
+    pub key_priorities: Vec<String>,
+
+    /// Seconds a request waits, in priority order, for a slot on backends at their
+    /// concurrency limit before the router answers 429 (0 answers 429 at once)
+    #[arg(long, default_value = "30")]
+    pub priority_queue_timeout: u64,
+
+    /// Most requests waiting for a backend slot at once; more are answered 429
+    #[arg(long, default_value = "256")]
+    pub priority_queue_size: usize,
+
+    /// Honour the X-InfiniLM-Target debug header for requests carrying this token in
+    /// X-InfiniLM-Target-Token (without it the header is ignored)
+    #[arg(long)]
+    pub target_token: Option<String>,
+
+    /// Proxies (CIDRs or addresses, comma-separated) whose X-Forwarded-For is trusted; from
+    /// other peers the socket address is the client address
+    #[arg(long, value_delimiter = ',')]
+    pub trusted_proxies: Vec<String>,
+
+    /// Request header to remove before forwarding upstream (repeatable)
+    #[arg(long = "strip-request-header")]
+    pub strip_request_headers: Vec<String>,
+
+    /// Request header to set on upstream requests as NAME=VALUE (repeatable)
+    #[arg(long = "set-request-header")]
+    pub set_request_headers: Vec<String>,
+
+    /// Response header to remove before replying to clients (repeatable)
+    #[arg(long = "strip-response-header")]
+    pub strip_response_headers: Vec<String>,
+
+    /// Response header to set on replies to clients as NAME=VALUE (repeatable)
+    #[arg(long = "set-response-header")]
+    pub set_response_headers: Vec<String>,
+
+    /// Add an X-Served-By response header naming the service, its cache type and the
+    /// attempt that served each proxied request
+    #[arg(long)]
+    pub served_by_header: bool,
+}
 
 /// Router configuration
 #[derive(Debug, Clone)]
 pub struct Config {
     pub router_port: u16,
+    /// Addresses to listen on (all IPv4 interfaces when empty)
+    pub bind: Vec<IpAddr>,
     pub registry_url: Option<String>,
+    /// CA bundle and client certificate for https registry URLs
+    pub registry_tls: RegistryTls,
     pub static_services: Option<Vec<StaticService>>,
     pub health_check_interval: u64,
     pub health_check_timeout: u64,
     pub max_errors: u32,
     pub registry_sync_interval: u64,
     pub service_removal_grace_period: u64,
+    pub session_max_entries: usize,
+    pub session_ttl: u64,
+    pub session_redis_url: Option<String>,
+    pub peer_routers: Vec<String>,
+    /// Shared secret peers present on /internal endpoints (unset: peer addresses are trusted)
+    pub peer_token: Option<String>,
+    /// Resolved addresses of the peer routers
+    pub peer_addrs: Vec<IpAddr>,
+    /// Bearer token required on /admin endpoints (unset: only peers)
+    pub admin_token: Option<String>,
+    pub max_concurrency_per_service: u32,
+    pub models_cache_ttl: u64,
+    pub batch_dir: Option<String>,
+    pub batch_concurrency: usize,
+    pub proxy_hooks: Vec<String>,
+    pub audit_sink: Option<String>,
+    pub audit_prompts: String,
+    pub audit_sample_rate: f64,
+    pub audit_redact_patterns: Vec<String>,
+    pub cors_allowed_origins: Vec<String>,
+    pub cors_allowed_methods: Vec<String>,
+    pub cors_allowed_headers: Vec<String>,
+    pub cors_max_age: u64,
+    pub compress_min_bytes: u64,
+    /// Proxy timeout overrides in seconds, by model id
+    pub model_timeouts: HashMap<String, u64>,
+    pub retry_policy: RetryPolicy,
+    pub slo_latency_ms: u64,
+    pub slo_availability_target: f64,
+    pub slo_latency_target: f64,
+    pub slo_windows: Vec<String>,
+    pub health_check_spread: f64,
+    pub health_check_concurrency: usize,
+    pub passive_failure_threshold: u32,
+    pub recovery_probe_interval: u64,
+    pub half_open_interval: u64,
+    pub outlier_detection: OutlierDetection,
+    pub slow_backends: SlowBackendPolicy,
+    pub flapping: FlapPolicy,
+    pub backend_load: LoadPolicy,
+    /// Handling of requests larger than a backend's context window
+    pub context_overflow: ContextOverflow,
+    pub throttle: ThrottlePolicy,
+    pub zone: Option<String>,
+    /// API key -> tenant pool
+    pub tenant_keys: HashMap<String, String>,
+    /// Header naming the tenant pool directly (for deployments behind an authenticating gateway)
+    pub tenant_header: Option<String>,
+    /// API key -> priority class
+    pub key_priorities: HashMap<String, Priority>,
+    /// Seconds a request waits for a slot on saturated backends before 429 (0 = no queue)
+    pub priority_queue_timeout: u64,
+    /// Most requests waiting for a slot at once
+    pub priority_queue_size: usize,
+    /// Token required to use the X-InfiniLM-Target debug header (unset: header ignored)
+    pub target_token: Option<String>,
+    pub trusted_proxies: TrustedProxies,
+    pub header_rules: HeaderRules,
+    pub served_by_header: bool,
+}
+
+/// Which failed requests may be retried on another service
+#[derive(Debug, Clone, Default)]
+pub struct RetryPolicy {
+    /// Retryable methods (empty allows all)
+    pub methods: Vec<String>,
+    /// Retryable path prefixes (empty allows all)
+    pub paths: Vec<String>,
+    /// Largest retryable body in bytes (0 = no limit)
+    pub max_body_bytes: usize,
+    /// Upstream response statuses that fail the attempt like a transport error
+    pub statuses: Vec<u16>,
+}
+
+impl RetryPolicy {
+    pub fn is_retryable(&self, method: &str, path: &str, body_len: usize) -> bool {
+        let method_allowed = self.methods.is_empty()
+            || self
+                .methods
+                .iter()
+                .any(|m| m.trim().eq_ignore_ascii_case(method));
+        let path_allowed = self.paths.is_empty()
+            || self
+                .paths
+                .iter()
+                .any(|prefix| path.starts_with(prefix.as_str()));
+        let size_allowed = self.max_body_bytes == 0 || body_len <= self.max_body_bytes;
+        method_allowed && path_allowed && size_allowed
+    }
+
+    /// Whether a response with `status` fails the attempt and moves on to another service
+    pub fn retries_status(&self, status: u16) -> bool {
+        self.statuses.contains(&status)
+    }
 }
 
 /// Static service configuration
@@ -35,36 +492,182 @@ fn default_weight() -> u32 {
 }
 
 impl Config {
-    /// Create a new configuration from command-line arguments
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        router_port: u16,
-        registry_url: Option<String>,
-        static_services_file: Option<String>,
-        health_check_interval: u64,
-        health_check_timeout: u64,
-        max_errors: u32,
-        registry_sync_interval: u64,
-        service_removal_grace_period: u64,
-    ) -> Result<Self> {
-        let static_services = if let Some(file_path) = static_services_file {
-            Some(Self::load_static_services(&file_path)?)
-        } else {
-            None
+    /// Create the configuration from command-line arguments
+    pub fn from_args(args: RouterArgs) -> Result<Self> {
+        let static_services = match &args.static_services {
+            Some(file_path) => Some(Self::load_static_services(file_path)?),
+            None => None,
         };
+        let header_rules = HeaderRules::new(
+            &args.strip_request_headers,
+            &args.set_request_headers,
+            &args.strip_response_headers,
+            &args.set_response_headers,
+        )?;
 
         Ok(Config {
-            router_port,
-            registry_url,
+            router_port: args.router_port,
+            bind: args.bind,
+            registry_url: args.registry_url,
+            registry_tls: RegistryTls {
+                ca_cert: args.registry_ca_cert,
+                client_cert: args.registry_client_cert,
+                client_key: args.registry_client_key,
+            },
             static_services,
-            health_check_interval,
-            health_check_timeout,
-            max_errors,
-            registry_sync_interval,
-            service_removal_grace_period,
+            health_check_interval: args.health_interval,
+            health_check_timeout: args.health_timeout,
+            max_errors: args.max_errors,
+            registry_sync_interval: args.registry_sync_interval,
+            service_removal_grace_period: args.service_removal_grace_period,
+            session_max_entries: args.session_max_entries,
+            session_ttl: args.session_ttl,
+            session_redis_url: args.session_redis_url,
+            peer_addrs: Self::resolve_peer_addrs(&args.peer_routers),
+            peer_routers: args.peer_routers,
+            peer_token: args.peer_token,
+            admin_token: args.admin_token,
+            max_concurrency_per_service: args.max_concurrency_per_service,
+            models_cache_ttl: args.models_cache_ttl,
+            batch_dir: args.batch_dir,
+            batch_concurrency: args.batch_concurrency,
+            proxy_hooks: args.proxy_hooks,
+            audit_sink: args.audit_sink,
+            audit_prompts: args.audit_prompts,
+            audit_sample_rate: args.audit_sample_rate,
+            audit_redact_patterns: args.audit_redact_patterns,
+            cors_allowed_origins: args.cors_allowed_origins,
+            cors_allowed_methods: args.cors_allowed_methods,
+            cors_allowed_headers: args.cors_allowed_headers,
+            cors_max_age: args.cors_max_age,
+            compress_min_bytes: args.compress_min_bytes,
+            model_timeouts: Self::parse_model_timeouts(&args.model_timeouts)?,
+            retry_policy: RetryPolicy {
+                methods: args.retry_methods,
+                paths: args.retry_paths,
+                max_body_bytes: args.retry_max_body_bytes,
+                statuses: args.retry_statuses,
+            },
+            slo_latency_ms: args.slo_latency_ms,
+            slo_availability_target: args.slo_availability_target,
+            slo_latency_target: args.slo_latency_target,
+            slo_windows: args.slo_windows,
+            health_check_spread: args.health_check_spread,
+            health_check_concurrency: args.health_check_concurrency,
+            passive_failure_threshold: args.passive_failure_threshold,
+            recovery_probe_interval: args.recovery_probe_interval,
+            half_open_interval: args.half_open_interval,
+            outlier_detection: OutlierDetection {
+                error_ratio: args.outlier_error_ratio,
+                interval: args.outlier_interval,
+                window: Duration::from_secs(args.outlier_window),
+                min_requests: args.outlier_min_requests,
+                max_ejection_percent: args.outlier_max_ejection_percent,
+                base_ejection: Duration::from_secs(args.outlier_ejection_seconds),
+            },
+            slow_backends: SlowBackendPolicy {
+                ratio: args.slow_backend_ratio,
+                interval: Duration::from_secs(args.slow_backend_interval),
+                min_samples: args.slow_backend_min_samples,
+            },
+            flapping: FlapPolicy {
+                restart_threshold: args.flap_restart_threshold,
+                window: Duration::from_secs(args.flap_window),
+            },
+            backend_load: LoadPolicy {
+                max_queue_depth: args.load_max_queue_depth,
+                max_kv_cache_utilization: args.load_max_kv_cache,
+            },
+            context_overflow: ContextOverflow::parse(&args.context_overflow)?,
+            throttle: ThrottlePolicy {
+                retry: !args.no_throttle_retry,
+                default_backoff: Duration::from_secs(args.throttle_backoff),
+                max_backoff: Duration::from_secs(args.throttle_max_backoff),
+            },
+            zone: args.zone,
+            tenant_keys: Self::parse_tenant_keys(&args.tenant_keys)?,
+            tenant_header: args.tenant_header,
+            key_priorities: Self::parse_key_priorities(&args.key_priorities)?,
+            priority_queue_timeout: args.priority_queue_timeout,
+            priority_queue_size: args.priority_queue_size,
+            target_token: args.target_token,
+            trusted_proxies: TrustedProxies::parse(&args.trusted_proxies)
+                .map_err(anyhow::Error::msg)?,
+            header_rules,
+            served_by_header: args.served_by_header,
         })
     }
 
+    /// Parse `MODEL=SECONDS` timeout overrides
+    fn parse_model_timeouts(entries: &[String]) -> Result<HashMap<String, u64>> {
+        entries
+            .iter()
+            .map(|entry| {
+                let (model, seconds) = entry.rsplit_once('=').with_context(|| {
+                    format!("Invalid model timeout (expected MODEL=SECONDS): {}", entry)
+                })?;
+                let seconds = seconds
+                    .trim()
+                    .parse::<u64>()
+                    .with_context(|| format!("Invalid timeout seconds in: {}", entry))?;
+                Ok((model.trim().to_string(), seconds))
+            })
+            .collect()
+    }
+
+    /// Parse KEY=POOL tenant mappings
+    fn parse_tenant_keys(entries: &[String]) -> Result<HashMap<String, String>> {
+        entries
+            .iter()
+            .map(|entry| {
+                let (key, pool) = entry
+                    .rsplit_once('=')
+                    .filter(|(key, pool)| !key.trim().is_empty() && !pool.trim().is_empty())
+                    .with_context(|| {
+                        format!("Invalid tenant key (expected API_KEY=POOL): {}", entry)
+                    })?;
+                Ok((key.trim().to_string(), pool.trim().to_string()))
+            })
+            .collect()
+    }
+
+    /// Addresses the peer router URLs resolve to
+    fn resolve_peer_addrs(peers: &[String]) -> Vec<IpAddr> {
+        peers
+            .iter()
+            .flat_map(|peer| {
+                let addrs = reqwest::Url::parse(peer)
+                    .ok()
+                    .and_then(|url| url.socket_addrs(|| None).ok())
+                    .unwrap_or_default();
+                if addrs.is_empty() {
+                    warn!("Could not resolve the address of peer router {}", peer);
+                }
+                addrs.into_iter().map(|addr| addr.ip().to_canonical())
+            })
+            .collect()
+    }
+
+    /// Parse KEY=CLASS priority mappings
+    fn parse_key_priorities(entries: &[String]) -> Result<HashMap<String, Priority>> {
+        entries
+            .iter()
+            .map(|entry| {
+                let (key, priority) = entry
+                    .rsplit_once('=')
+                    .and_then(|(key, class)| Some((key.trim(), Priority::parse(class)?)))
+                    .filter(|(key, _)| !key.is_empty())
+                    .with_context(|| {
+                        format!(
+                            "Invalid key priority (expected API_KEY=high|normal|low): {}",
+                            entry
+                        )
+                    })?;
+                Ok((key.to_string(), priority))
+            })
+            .collect()
+    }
+
     /// Load static services from a JSON file
     fn load_static_services<P: AsRef<Path>>(file_path: P) -> Result<Vec<StaticService>> {
         let content = fs::read_to_string(&file_path).with_context(|| {
@@ -133,4 +736,115 @@ mod tests {
 
         std::fs::remove_file(&temp_file).unwrap();
     }
+
+    #[test]
+    fn test_parse_model_timeouts() {
+        let timeouts = Config::parse_model_timeouts(&[
+            "llama-70b=3600".to_string(),
+            "org/model=v2 = 300".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(timeouts["llama-70b"], 3600);
+        assert_eq!(timeouts["org/model=v2"], 300);
+
+        assert!(Config::parse_model_timeouts(&["llama-70b".to_string()]).is_err());
+        assert!(Config::parse_model_timeouts(&["llama-70b=soon".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_tenant_keys() {
+        let keys =
+            Config::parse_tenant_keys(&["sk-a=team-a".to_string(), "c2s==team-b".to_string()])
+                .unwrap();
+        assert_eq!(keys["sk-a"], "team-a");
+        assert_eq!(keys["c2s="], "team-b");
+
+        assert!(Config::parse_tenant_keys(&["sk-a".to_string()]).is_err());
+        assert!(Config::parse_tenant_keys(&["sk-a=".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_peer_addrs() {
+        let addrs = Config::resolve_peer_addrs(&[
+            "http://10.0.0.2:8000".to_string(),
+            "http://[::1]:8000/".to_string(),
+            "not a url".to_string(),
+        ]);
+        assert_eq!(
+            addrs,
+            vec![
+                "10.0.0.2".parse::<IpAddr>().unwrap(),
+                "::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_key_priorities() {
+        let priorities =
+            Config::parse_key_priorities(&["sk-chat=high".to_string(), "sk-etl=batch".to_string()])
+                .unwrap();
+        assert_eq!(priorities["sk-chat"], Priority::High);
+        assert_eq!(priorities["sk-etl"], Priority::Low);
+
+        assert!(Config::parse_key_priorities(&["sk-chat=urgent".to_string()]).is_err());
+        assert!(Config::parse_key_priorities(&["=high".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_from_args() {
+        let config = Config::from_args(RouterArgs::parse_from(["infini-router"])).unwrap();
+        assert_eq!(config.router_port, 8080);
+        assert_eq!(config.health_check_interval, 30);
+        assert_eq!(config.health_check_timeout, 5);
+        assert!(config.admin_token.is_none());
+
+        let args = RouterArgs::parse_from([
+            "infini-router",
+            "--health-interval",
+            "7",
+            "--health-timeout",
+            "3",
+            "--outlier-window",
+            "120",
+            "--outlier-ejection-seconds",
+            "45",
+            "--tenant-key",
+            "sk-a=team-a",
+            "--retry-statuses",
+            "502,504",
+        ]);
+        let config = Config::from_args(args).unwrap();
+        assert_eq!(config.health_check_interval, 7);
+        assert_eq!(config.health_check_timeout, 3);
+        assert_eq!(config.outlier_detection.window, Duration::from_secs(120));
+        assert_eq!(
+            config.outlier_detection.base_ejection,
+            Duration::from_secs(45)
+        );
+        assert_eq!(config.tenant_keys["sk-a"], "team-a");
+        assert_eq!(config.retry_policy.statuses, [502, 504]);
+
+        let args = RouterArgs::parse_from(["infini-router", "--context-overflow", "maybe"]);
+        assert!(Config::from_args(args).is_err());
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        assert!(RetryPolicy::default().is_retryable("POST", "/v1/chat/completions", 1 << 30));
+
+        let policy = RetryPolicy {
+            methods: vec!["GET".to_string()],
+            paths: vec!["/v1/".to_string()],
+            max_body_bytes: 1024,
+            statuses: vec![502, 504],
+        };
+        assert!(policy.is_retryable("get", "/v1/models", 0));
+        assert!(!policy.is_retryable("POST", "/v1/completions", 10));
+        assert!(!policy.is_retryable("GET", "/health", 10));
+        assert!(!policy.is_retryable("GET", "/v1/models", 2048));
+        assert!(policy.retries_status(502));
+        assert!(!policy.retries_status(500));
+        assert!(!RetryPolicy::default().retries_status(502));
+    }
 }
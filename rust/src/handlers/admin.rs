@@ -0,0 +1,355 @@
+//! Operator endpoints (/admin/*)
+
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Path, Query, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::sessions::{bearer_token, is_peer};
+use crate::proxy::handler::explain_route;
+use crate::router::load_balancer::LoadBalancer;
+use crate::utils::errors::RouterError;
+
+/// Accept operator calls carrying the admin token and calls from peer routers, which
+/// forward session flushes. Without a token configured operator calls are refused: a
+/// loopback peer may be a local reverse proxy relaying anyone's request.
+pub async fn require_admin(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = load_balancer.config();
+    if is_peer(config, &request) {
+        return next.run(request).await;
+    }
+    let Some(token) = &config.admin_token else {
+        warn!(
+            "Rejected {} {} (no admin token configured)",
+            request.method(),
+            request.uri().path()
+        );
+        return (
+            StatusCode::FORBIDDEN,
+            Json(
+                json!({"error": "Admin endpoints are disabled until an admin token is configured"}),
+            ),
+        )
+            .into_response();
+    };
+    if bearer_token(request.headers()) != Some(token.as_str()) {
+        warn!(
+            "Rejected unauthorized {} {}",
+            request.method(),
+            request.uri().path()
+        );
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Missing or invalid admin token"})),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Run one registry sync now and return the services it added, updated or removed
+pub async fn sync_handler(State(load_balancer): State<Arc<LoadBalancer>>) -> Response {
+    match load_balancer.sync_registry().await {
+        Ok(diff) => {
+            info!(
+                "On-demand registry sync: {} added, {} updated, {} removed",
+                diff.added.len(),
+                diff.updated.len(),
+                diff.removed.len()
+            );
+            Json(diff).into_response()
+        }
+        Err(RouterError::ConfigError(message)) => {
+            (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+        }
+        Err(e) => {
+            warn!("On-demand registry sync failed: {}", e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({"error": format!("Registry sync failed: {}", e)})),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FlushSessionsQuery {
+    /// Only sessions pinned to services serving this model
+    model: Option<String>,
+    /// Only sessions pinned to this service
+    service: Option<String>,
+    /// Set on flushes forwarded by a peer router, which are not forwarded again
+    #[serde(default)]
+    local: bool,
+}
+
+/// Clear session → service pins, optionally only those pointing at one backend or at
+/// the backends serving one model (e.g. after a redeploy wiped their prompt cache)
+pub async fn flush_sessions_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Query(query): Query<FlushSessionsQuery>,
+) -> Json<serde_json::Value> {
+    let scope: Option<HashSet<String>> = if query.model.is_none() && query.service.is_none() {
+        None
+    } else {
+        let mut services = HashSet::new();
+        for service in load_balancer.get_all_services().await {
+            let service_matches = query
+                .service
+                .as_ref()
+                .is_none_or(|name| *name == service.name);
+            let model_matches = match &query.model {
+                Some(model) => service.supports_model(model),
+                None => true,
+            };
+            if service_matches && model_matches {
+                services.insert(service.name);
+            }
+        }
+        // A named backend may already be gone from the service map but still have pins
+        if query.model.is_none() {
+            services.extend(query.service.clone());
+        }
+        Some(services)
+    };
+
+    let flushed = load_balancer.sessions().flush(scope.as_ref()).await;
+    if !query.local {
+        load_balancer
+            .sessions()
+            .flush_peers(query.model.as_deref(), query.service.as_deref())
+            .await;
+    }
+    info!(
+        "Flushed {} session(s) (model: {:?}, service: {:?})",
+        flushed, query.model, query.service
+    );
+
+    Json(json!({
+        "flushed": flushed,
+        "model": query.model,
+        "service": query.service,
+        "services": scope.map(|services| {
+            let mut services: Vec<_> = services.into_iter().collect();
+            services.sort();
+            services
+        }),
+    }))
+}
+
+/// Drain state of one service: whether it is out of rotation and how many requests
+/// are still in flight to it
+async fn drain_status(
+    load_balancer: &LoadBalancer,
+    name: &str,
+    draining: Option<bool>,
+) -> Response {
+    let Some(service) = load_balancer.get_service(name).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": format!("Service not found: {}", name)})),
+        )
+            .into_response();
+    };
+    if let Some(draining) = draining {
+        if service.draining.swap(draining, Ordering::Relaxed) != draining {
+            info!(
+                "Service {} {}",
+                name,
+                if draining {
+                    "is draining"
+                } else {
+                    "is back in rotation"
+                }
+            );
+        }
+    }
+    Json(json!({
+        "service": service.name,
+        "draining": service.is_draining(),
+        "in_flight": service.in_flight_count(),
+    }))
+    .into_response()
+}
+
+/// Report whether a service is draining and its in-flight requests
+pub async fn drain_status_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Path(name): Path<String>,
+) -> Response {
+    drain_status(&load_balancer, &name, None).await
+}
+
+/// Take a service out of rotation ahead of a planned restart; requests already in
+/// flight finish normally
+pub async fn drain_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Path(name): Path<String>,
+) -> Response {
+    drain_status(&load_balancer, &name, Some(true)).await
+}
+
+/// Put a drained service back into rotation
+pub async fn undrain_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Path(name): Path<String>,
+) -> Response {
+    drain_status(&load_balancer, &name, Some(false)).await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForceHealthRequest {
+    healthy: bool,
+}
+
+/// Mark a service healthy or unhealthy by hand; the next health check result
+/// overrides it
+pub async fn force_health_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Path(name): Path<String>,
+    Json(request): Json<ForceHealthRequest>,
+) -> Response {
+    let Some(service) = load_balancer.get_service(&name).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": format!("Service not found: {}", name)})),
+        )
+            .into_response();
+    };
+    if request.healthy {
+        service.consecutive_failures.store(0, Ordering::Relaxed);
+        service.end_passive_ejection();
+    }
+    service.set_healthy(request.healthy);
+    info!(
+        "Service {} forced {} by an operator",
+        name,
+        if request.healthy {
+            "healthy"
+        } else {
+            "unhealthy"
+        }
+    );
+    Json(json!({"service": service.name, "healthy": service.is_healthy()})).into_response()
+}
+
+fn default_explain_path() -> String {
+    "/v1/chat/completions".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExplainRouteRequest {
+    /// Path the sample request would be sent to
+    #[serde(default = "default_explain_path")]
+    path: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    /// Sample request body, e.g. a chat completion request
+    #[serde(default)]
+    body: serde_json::Value,
+    /// Address the request would come from; defaults to the caller's
+    client_ip: Option<IpAddr>,
+}
+
+/// Report where a sample request would be routed (model, size, cache type, session,
+/// candidates and chosen backend) without proxying it
+pub async fn explain_route_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(request): Json<ExplainRouteRequest>,
+) -> Response {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &request.headers {
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.append(name, value);
+            }
+            _ => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": format!("Invalid header: {}", name)})),
+                )
+                    .into_response()
+            }
+        }
+    }
+    let body = if request.body.is_null() {
+        Bytes::new()
+    } else {
+        Bytes::from(request.body.to_string())
+    };
+    let peer = request
+        .client_ip
+        .or(connect_info.map(|ConnectInfo(addr)| addr.ip().to_canonical()));
+
+    match explain_route(&load_balancer, &request.path, headers, body, peer).await {
+        Ok(explanation) => Json(explanation).into_response(),
+        Err((status, message)) => (status, Json(json!({"error": message}))).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, RouterArgs};
+    use crate::handlers::create_router;
+    use axum::body::Body;
+    use clap::Parser;
+    use tower::ServiceExt;
+
+    async fn drain_status(args: &[&str], token: Option<&str>) -> StatusCode {
+        let config =
+            Config::from_args(RouterArgs::parse_from([&["infini-router"], args].concat())).unwrap();
+        let load_balancer = Arc::new(LoadBalancer::new(&config).await.unwrap());
+        let mut request = Request::get("/admin/services/missing/drain");
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {}", token));
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        create_router(load_balancer, &config)
+            .unwrap()
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_require_admin() {
+        // Without a token even local clients are refused
+        assert_eq!(drain_status(&[], None).await, StatusCode::FORBIDDEN);
+
+        let args = ["--admin-token", "secret"];
+        assert_eq!(drain_status(&args, None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            drain_status(&args, Some("wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        // Authorized: the handler answers for the unknown service
+        assert_eq!(
+            drain_status(&args, Some("secret")).await,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
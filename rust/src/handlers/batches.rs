@@ -0,0 +1,123 @@
+//! Batch API handlers (/v1/batches)
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::batch::manager::parse_batch_input;
+use crate::batch::runner::spawn_batch;
+use crate::proxy::tenant::tenant_pool;
+use crate::router::load_balancer::LoadBalancer;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBatchQuery {
+    /// Endpoint every request must target (default: the first request's url)
+    endpoint: Option<String>,
+}
+
+fn batch_not_found(batch_id: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({"error": format!("Batch not found: {}", batch_id)})),
+    )
+        .into_response()
+}
+
+/// Create a batch from a JSONL body and start running it
+pub async fn create_batch_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Query(query): Query<CreateBatchQuery>,
+    request: Request,
+) -> Response {
+    // Batches run within the submitting tenant's pool
+    let config = load_balancer.config();
+    let pool = tenant_pool(
+        &config.tenant_keys,
+        config.tenant_header.as_deref(),
+        request.headers(),
+    );
+
+    let body_bytes = match axum::body::to_bytes(request.into_body(), usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to read batch body: {}", e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Failed to read request body"})),
+            )
+                .into_response();
+        }
+    };
+
+    let parsed = std::str::from_utf8(&body_bytes)
+        .map_err(|_| "Batch body must be UTF-8 JSONL".to_string())
+        .and_then(|input| parse_batch_input(input, query.endpoint.as_deref()));
+    let (endpoint, requests) = match parsed {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+        }
+    };
+
+    let job = match load_balancer.batches().create(endpoint, requests, pool) {
+        Ok(job) => job,
+        Err(e) => return e.into_response(),
+    };
+    let batch = job.batch();
+    info!(
+        "Created batch {} with {} request(s) for {}",
+        batch.id, batch.request_counts.total, batch.endpoint
+    );
+    spawn_batch(load_balancer.clone(), job);
+
+    Json(batch).into_response()
+}
+
+/// List all batches, newest first
+pub async fn list_batches_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+) -> Json<serde_json::Value> {
+    Json(json!({
+        "object": "list",
+        "data": load_balancer.batches().list()
+    }))
+}
+
+/// Batch status and request counts
+pub async fn get_batch_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Path(batch_id): Path<String>,
+) -> Response {
+    match load_balancer.batches().get(&batch_id) {
+        Some(job) => Json(job.batch()).into_response(),
+        None => batch_not_found(&batch_id),
+    }
+}
+
+/// Results collected so far, as JSONL
+pub async fn batch_output_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Path(batch_id): Path<String>,
+) -> Response {
+    match load_balancer.batches().get(&batch_id) {
+        Some(job) => ([(header::CONTENT_TYPE, "application/jsonl")], job.output()).into_response(),
+        None => batch_not_found(&batch_id),
+    }
+}
+
+/// Cancel a batch; requests already in flight still complete
+pub async fn cancel_batch_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Path(batch_id): Path<String>,
+) -> Response {
+    match load_balancer.batches().cancel(&batch_id) {
+        Some(batch) => Json(batch).into_response(),
+        None => batch_not_found(&batch_id),
+    }
+}
@@ -0,0 +1,46 @@
+//! Optional gzip compression of large JSON responses
+
+use axum::{
+    body::HttpBody,
+    http::{header, Response},
+};
+use tower_http::compression::{predicate::Predicate, CompressionLayer};
+
+use crate::config::Config;
+
+/// Compress only JSON bodies of known size at or above the threshold.
+/// Streams (SSE, chunked) and bodies the backend already encoded pass through untouched.
+#[derive(Debug, Clone, Copy)]
+pub struct LargeJson {
+    min_bytes: u64,
+}
+
+impl Predicate for LargeJson {
+    fn should_compress<B>(&self, response: &Response<B>) -> bool
+    where
+        B: HttpBody,
+    {
+        let is_json = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("application/json"));
+        is_json
+            && response
+                .body()
+                .size_hint()
+                .exact()
+                .is_some_and(|size| size >= self.min_bytes)
+    }
+}
+
+/// Build the compression layer, or None when response compression is disabled
+pub fn compression_layer(config: &Config) -> Option<CompressionLayer<LargeJson>> {
+    if config.compress_min_bytes == 0 {
+        return None;
+    }
+
+    Some(CompressionLayer::new().gzip(true).compress_when(LargeJson {
+        min_bytes: config.compress_min_bytes,
+    }))
+}
@@ -0,0 +1,61 @@
+//! CORS configuration for browser clients
+
+use anyhow::{Context, Result};
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+
+use crate::config::Config;
+
+/// Build the CORS layer, or None when no allowed origins are configured
+pub fn cors_layer(config: &Config) -> Result<Option<CorsLayer>> {
+    if config.cors_allowed_origins.is_empty() {
+        return Ok(None);
+    }
+
+    let origins = if config.cors_allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = config
+            .cors_allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .with_context(|| format!("Invalid CORS origin: {}", origin))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    let methods = config
+        .cors_allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.trim().to_uppercase().as_bytes())
+                .with_context(|| format!("Invalid CORS method: {}", method))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let headers = if config.cors_allowed_headers.iter().any(|h| h == "*") {
+        AllowHeaders::any()
+    } else {
+        let headers = config
+            .cors_allowed_headers
+            .iter()
+            .map(|name| {
+                HeaderName::from_bytes(name.trim().as_bytes())
+                    .with_context(|| format!("Invalid CORS header: {}", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        AllowHeaders::list(headers)
+    };
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(methods)
+            .allow_headers(headers)
+            .expose_headers([header::RETRY_AFTER])
+            .max_age(Duration::from_secs(config.cors_max_age)),
+    ))
+}
@@ -13,10 +13,7 @@ pub async fn health_handler(
     let services = load_balancer.get_all_services().await;
 
     // Check health status for all services
-    let health_statuses: Vec<bool> =
-        futures::future::join_all(services.iter().map(|s| s.is_healthy())).await;
-
-    let healthy_count = health_statuses.iter().filter(|&&h| h).count();
+    let healthy_count = services.iter().filter(|s| s.is_healthy()).count();
     let total_count = services.len();
 
     Json(json!({
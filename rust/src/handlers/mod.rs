@@ -1,24 +1,107 @@
 //! HTTP request handlers
 
-use axum::{routing::get, Router};
+use anyhow::Result;
+use axum::{
+    handler::Handler,
+    middleware,
+    routing::{get, post, put},
+    Router,
+};
 use std::sync::Arc;
 
+use crate::config::Config;
+use crate::proxy::audit::audit_middleware;
 use crate::proxy::handler::proxy_handler;
+use crate::proxy::slo::slo_middleware;
 use crate::router::load_balancer::LoadBalancer;
 
+mod admin;
+mod batches;
+mod compression;
+mod cors;
 mod health;
 mod models;
 mod services;
+mod sessions;
 mod stats;
 
 /// Create the main router
-pub fn create_router(load_balancer: Arc<LoadBalancer>) -> Router {
-    Router::new()
+pub fn create_router(load_balancer: Arc<LoadBalancer>, config: &Config) -> Result<Router> {
+    let peer_only = middleware::from_fn_with_state(load_balancer.clone(), sessions::require_peer);
+    let admin_only = middleware::from_fn_with_state(load_balancer.clone(), admin::require_admin);
+    let router = Router::new()
         .route("/health", get(health::health_handler))
         .route("/status", get(health::health_handler)) // Alias for /health
         .route("/stats", get(stats::stats_handler))
+        .route("/stats/slo", get(stats::slo_handler))
         .route("/services", get(services::services_handler))
+        .route("/services/:name", get(services::service_detail_handler))
         .route("/models", get(models::models_handler))
-        .fallback(proxy_handler)
-        .with_state(load_balancer)
+        .route(
+            "/internal/sessions",
+            post(sessions::session_pin_handler).route_layer(peer_only.clone()),
+        )
+        .route(
+            "/internal/health",
+            post(sessions::health_observation_handler).route_layer(peer_only),
+        )
+        .route(
+            "/admin/sync",
+            post(admin::sync_handler).route_layer(admin_only.clone()),
+        )
+        .route(
+            "/admin/sessions/flush",
+            post(admin::flush_sessions_handler).route_layer(admin_only.clone()),
+        )
+        .route(
+            "/admin/route/explain",
+            post(admin::explain_route_handler).route_layer(admin_only.clone()),
+        )
+        .route(
+            "/admin/services/:name/drain",
+            get(admin::drain_status_handler)
+                .post(admin::drain_handler)
+                .delete(admin::undrain_handler)
+                .route_layer(admin_only.clone()),
+        )
+        .route(
+            "/admin/services/:name/health",
+            put(admin::force_health_handler).route_layer(admin_only),
+        )
+        .route(
+            "/v1/batches",
+            get(batches::list_batches_handler).post(batches::create_batch_handler),
+        )
+        .route("/v1/batches/:batch_id", get(batches::get_batch_handler))
+        .route(
+            "/v1/batches/:batch_id/output",
+            get(batches::batch_output_handler),
+        )
+        .route(
+            "/v1/batches/:batch_id/cancel",
+            post(batches::cancel_batch_handler),
+        )
+        .fallback(
+            proxy_handler
+                .layer(middleware::from_fn_with_state(
+                    load_balancer.clone(),
+                    audit_middleware,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    load_balancer.clone(),
+                    slo_middleware,
+                )),
+        )
+        .with_state(load_balancer);
+
+    let router = match compression::compression_layer(config) {
+        Some(compression) => router.layer(compression),
+        None => router,
+    };
+
+    // CORS wraps every route so preflight requests are answered before routing
+    Ok(match cors::cors_layer(config)? {
+        Some(cors) => router.layer(cors),
+        None => router,
+    })
 }
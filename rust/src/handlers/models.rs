@@ -15,6 +15,6 @@ pub async fn models_handler(
 
     Json(json!({
         "object": "list",
-        "data": models
+        "data": *models
     }))
 }
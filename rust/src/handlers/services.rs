@@ -1,23 +1,181 @@
 //! Services endpoint handler
 
-use axum::{extract::State, response::Json};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::cmp::Reverse;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use crate::router::load_balancer::LoadBalancer;
+use crate::router::service_instance::{HealthCheckRecord, ServiceError, ServiceInfo};
+
+/// Filters and ordering for /services and /stats
+#[derive(Debug, Default, Deserialize)]
+pub struct ServiceQuery {
+    /// Only services whose name contains this string
+    service: Option<String>,
+    /// Only services serving this model
+    model: Option<String>,
+    /// Only healthy (true) or unhealthy (false) services
+    healthy: Option<bool>,
+    /// name, requests, errors, in_flight or latency (p95); numeric keys sort descending
+    sort: Option<String>,
+}
+
+impl ServiceQuery {
+    /// Filter and sort service infos, or explain why the query is invalid
+    pub fn apply(&self, mut services: Vec<ServiceInfo>) -> Result<Vec<ServiceInfo>, String> {
+        services.retain(|info| {
+            self.service
+                .as_deref()
+                .is_none_or(|name| info.name.contains(name))
+                && self
+                    .model
+                    .as_deref()
+                    .is_none_or(|model| info.models.iter().any(|m| m == model))
+                && self.healthy.is_none_or(|healthy| info.healthy == healthy)
+        });
+
+        match self.sort.as_deref() {
+            None | Some("name") => services.sort_by(|a, b| a.name.cmp(&b.name)),
+            Some("requests") => services.sort_by_key(|info| Reverse(info.request_count)),
+            Some("errors") => services.sort_by_key(|info| Reverse(info.error_count)),
+            Some("in_flight") => services.sort_by_key(|info| Reverse(info.in_flight)),
+            Some("latency") => services.sort_by_key(|info| Reverse(info.request_latency.p95_ms)),
+            Some(other) => {
+                return Err(format!(
+                    "Unknown sort key '{}': expected name, requests, errors, in_flight or latency",
+                    other
+                ))
+            }
+        }
+        Ok(services)
+    }
+}
+
+/// Everything known about one service, for debugging a single backend
+#[derive(Debug, Serialize)]
+pub struct ServiceDetail {
+    #[serde(flatten)]
+    info: ServiceInfo,
+    last_seen: f64,
+    last_check: f64,
+    /// Sessions pinned to this service in the local affinity table
+    pinned_sessions: usize,
+    /// Proxy failures since the last success (passive health)
+    consecutive_failures: u32,
+    last_error: Option<ServiceError>,
+    health_history: Vec<HealthCheckRecord>,
+}
+
+/// 400 response for an invalid filter/sort query
+pub fn invalid_query(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({"error": message}))).into_response()
+}
 
 /// Services information endpoint
 pub async fn services_handler(
     State(load_balancer): State<Arc<LoadBalancer>>,
-) -> Json<serde_json::Value> {
+    Query(query): Query<ServiceQuery>,
+) -> Response {
     let services = load_balancer.get_all_services().await;
 
-    let services_info: Vec<_> =
-        futures::future::join_all(services.iter().map(|s| s.to_info())).await;
+    let services_info: Vec<_> = services.iter().map(|s| s.to_info()).collect();
+    let services_info = match query.apply(services_info) {
+        Ok(services_info) => services_info,
+        Err(message) => return invalid_query(message),
+    };
 
     Json(json!({
         "services": services_info,
         "total": services_info.len(),
         "registry_url": load_balancer.registry_url
     }))
+    .into_response()
+}
+
+/// Detail for a single service
+pub async fn service_detail_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Path(name): Path<String>,
+) -> Response {
+    let service = match load_balancer.get_service(&name).await {
+        Some(service) => service,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({"error": format!("Service not found: {}", name)})),
+            )
+                .into_response()
+        }
+    };
+
+    let detail = ServiceDetail {
+        info: service.to_info(),
+        last_seen: service.last_seen.load(),
+        last_check: service.last_check.load(),
+        pinned_sessions: load_balancer.sessions().local().count_for(&service.name),
+        consecutive_failures: service.consecutive_failures.load(Ordering::Relaxed),
+        last_error: service.last_error.lock().unwrap().clone(),
+        health_history: service
+            .health_history
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect(),
+    };
+    Json(detail).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::service_instance::ServiceInstance;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_service_query() {
+        let mut infos = Vec::new();
+        for (name, model, requests) in [("a-1", "m1", 5), ("a-2", "m2", 9), ("b-1", "m1", 7)] {
+            let service =
+                ServiceInstance::new(name.into(), "localhost".into(), 8000, 1, HashMap::new());
+            service.set_models(vec![model.to_string()]);
+            for _ in 0..requests {
+                service.increment_request_count();
+            }
+            infos.push(service.to_info());
+        }
+
+        let query = ServiceQuery {
+            model: Some("m1".into()),
+            sort: Some("requests".into()),
+            ..Default::default()
+        };
+        let names: Vec<_> = query
+            .apply(infos.clone())
+            .unwrap()
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(names, ["b-1", "a-1"]);
+
+        let query = ServiceQuery {
+            service: Some("a-".into()),
+            healthy: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(query.apply(infos.clone()).unwrap().len(), 2);
+
+        let query = ServiceQuery {
+            sort: Some("bogus".into()),
+            ..Default::default()
+        };
+        assert!(query.apply(infos).is_err());
+    }
 }
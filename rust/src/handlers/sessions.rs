@@ -0,0 +1,82 @@
+//! Peer replication endpoint handlers
+
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::proxy::forwarded::peer_addr;
+
+use crate::router::gossip::HealthObservation;
+use crate::router::load_balancer::LoadBalancer;
+use crate::router::session_store::SessionPin;
+
+/// The bearer token of a request, if any
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Whether a request comes from a peer router: it carries the peer token when one is
+/// configured, else it comes from the address of a configured peer
+pub fn is_peer(config: &Config, request: &Request) -> bool {
+    match &config.peer_token {
+        Some(token) => bearer_token(request.headers()) == Some(token.as_str()),
+        None => {
+            peer_addr(request.extensions()).is_some_and(|addr| config.peer_addrs.contains(&addr))
+        }
+    }
+}
+
+/// Accept replication requests only from peer routers
+pub async fn require_peer(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_peer(load_balancer.config(), &request) {
+        warn!(
+            "Rejected {} {} from a client that is not a peer router",
+            request.method(),
+            request.uri().path()
+        );
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": "Only peer routers may call this endpoint"})),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Accept a session pin pushed by a peer router
+pub async fn session_pin_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Json(pin): Json<SessionPin>,
+) -> Json<serde_json::Value> {
+    load_balancer.sessions().insert_from_peer(&pin);
+
+    Json(json!({
+        "status": "ok"
+    }))
+}
+
+/// Accept a backend ejection gossiped by a peer router
+pub async fn health_observation_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    Json(observation): Json<HealthObservation>,
+) -> Json<serde_json::Value> {
+    load_balancer.apply_peer_observation(&observation).await;
+
+    Json(json!({
+        "status": "ok"
+    }))
+}
@@ -1,30 +1,62 @@
 //! Statistics endpoint handler
 
-use axum::{extract::State, response::Json};
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Json, Response},
+};
 use serde_json::json;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use super::services::{invalid_query, ServiceQuery};
 use crate::router::load_balancer::LoadBalancer;
 
-/// Statistics endpoint
+/// Statistics endpoint; totals cover every service, the `services` list honours the query
 pub async fn stats_handler(
     State(load_balancer): State<Arc<LoadBalancer>>,
-) -> Json<serde_json::Value> {
+    Query(query): Query<ServiceQuery>,
+) -> Response {
     let services = load_balancer.get_all_services().await;
 
     // Check health status for all services
-    let health_statuses: Vec<bool> =
-        futures::future::join_all(services.iter().map(|s| s.is_healthy())).await;
+    let healthy_count = services.iter().filter(|s| s.is_healthy()).count();
 
-    let healthy_count = health_statuses.iter().filter(|&&h| h).count();
+    let services_info: Vec<_> = services.iter().map(|s| s.to_info()).collect();
+    let services_info = match query.apply(services_info) {
+        Ok(services_info) => services_info,
+        Err(message) => return invalid_query(message),
+    };
 
-    let services_info: Vec<_> =
-        futures::future::join_all(services.iter().map(|s| s.to_info())).await;
+    let fallbacks = load_balancer.cache_type_fallbacks();
 
     Json(json!({
         "total_services": services.len(),
         "healthy_services": healthy_count,
         "registry_url": load_balancer.registry_url,
+        "sessions": {
+            "active": load_balancer.sessions().local().size(),
+            "max_entries": load_balancer.sessions().local().capacity(),
+            "backend": load_balancer.sessions().backend(),
+            "peers": load_balancer.sessions().peers(),
+        },
+        "routing": {
+            "cache_type_fallbacks": {
+                "static_to_paged": fallbacks.static_to_paged.load(Ordering::Relaxed),
+                "paged_to_static": fallbacks.paged_to_static.load(Ordering::Relaxed),
+            },
+            "token_estimate_factors": load_balancer.token_estimator().factors(),
+            "queued": load_balancer.admission().waiting(),
+        },
+        "first_token_latency": load_balancer.first_token_latency().summaries(),
         "services": services_info
     }))
+    .into_response()
+}
+
+/// SLO / error-budget endpoint: availability and latency-objective compliance
+/// per model and per service over the configured windows
+pub async fn slo_handler(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+) -> Json<serde_json::Value> {
+    Json(load_balancer.slo().report())
 }
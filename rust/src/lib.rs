@@ -2,6 +2,7 @@
 //! Shared modules for router, registry, and babysitter binaries
 
 // Router modules (used by infini-router binary)
+pub mod batch;
 pub mod config;
 pub mod handlers;
 pub mod models;
@@ -6,8 +6,9 @@ use anyhow::Result;
 use clap::Parser;
 use std::sync::Arc;
 use tokio::signal;
-use tracing::info;
+use tracing::{info, warn};
 
+mod batch;
 mod config;
 mod handlers;
 mod models;
@@ -16,47 +17,9 @@ mod registry;
 mod router;
 mod utils;
 
-use config::Config;
+use config::{Config, RouterArgs};
 use router::load_balancer::LoadBalancer;
 
-/// InfiniLM Distributed Router Service
-#[derive(Parser, Debug)]
-#[command(name = "infini-router")]
-#[command(about = "High-performance distributed router for InfiniLM services", long_about = None)]
-struct Args {
-    /// Router port
-    #[arg(long, default_value = "8080")]
-    router_port: u16,
-
-    /// Service registry URL for dynamic service discovery
-    #[arg(long)]
-    registry_url: Option<String>,
-
-    /// JSON file with static service configurations
-    #[arg(long)]
-    static_services: Option<String>,
-
-    /// Health check interval in seconds
-    #[arg(long, default_value = "30")]
-    health_interval: u64,
-
-    /// Health check timeout in seconds
-    #[arg(long, default_value = "5")]
-    health_timeout: u64,
-
-    /// Max errors before marking service unhealthy
-    #[arg(long, default_value = "3")]
-    max_errors: u32,
-
-    /// Registry sync interval in seconds
-    #[arg(long, default_value = "10")]
-    registry_sync_interval: u64,
-
-    /// Grace period in seconds before removing services that disappear from registry
-    #[arg(long, default_value = "60")]
-    service_removal_grace_period: u64,
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize tracing
@@ -64,26 +27,21 @@ async fn main() -> Result<()> {
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
-    let args = Args::parse();
+    let args = RouterArgs::parse();
 
     info!("Starting InfiniLM Distributed Router Service");
     info!("Router port: {}", args.router_port);
     info!("Registry URL: {:?}", args.registry_url);
 
     // Create configuration
-    let config = Config::new(
-        args.router_port,
-        args.registry_url,
-        args.static_services,
-        args.health_interval,
-        args.health_timeout,
-        args.max_errors,
-        args.registry_sync_interval,
-        args.service_removal_grace_period,
-    )?;
+    let config = Config::from_args(args)?;
+    if config.admin_token.is_none() {
+        warn!("No --admin-token set: the /admin endpoints are disabled");
+    }
 
     // Create load balancer
     let load_balancer = Arc::new(LoadBalancer::new(&config).await?);
+    batch::runner::resume_batches(&load_balancer);
 
     // Start background tasks
     let health_checker = load_balancer.clone();
@@ -91,6 +49,16 @@ async fn main() -> Result<()> {
         health_checker.start_health_checks().await;
     });
 
+    let outlier_detection = load_balancer.clone();
+    tokio::spawn(async move {
+        outlier_detection.start_outlier_detection().await;
+    });
+
+    let slow_backends = load_balancer.clone();
+    tokio::spawn(async move {
+        slow_backends.start_slow_backend_checks().await;
+    });
+
     let registry_sync = load_balancer.clone();
     if config.registry_url.is_some() {
         tokio::spawn(async move {
@@ -99,11 +67,12 @@ async fn main() -> Result<()> {
     }
 
     // Build router
-    let app = handlers::create_router(load_balancer.clone());
+    let app = handlers::create_router(load_balancer.clone(), &config)?;
 
     // Start server
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", config.router_port)).await?;
-    info!("Router listening on http://0.0.0.0:{}", config.router_port);
+    let addrs = utils::listen::bind_addrs(&config.bind, config.router_port);
+    let listeners = utils::listen::bind_all(&addrs)?;
+    info!("Router listening on {}", utils::listen::describe(&addrs));
 
     // Handle graceful shutdown
     let shutdown_signal = async {
@@ -131,9 +100,8 @@ async fn main() -> Result<()> {
     };
 
     // Run server with graceful shutdown
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal)
-        .await?;
+    // Peer addresses are recorded for X-Forwarded-For
+    utils::listen::serve_all(listeners, app, shutdown_signal).await?;
 
     info!("Router shutdown complete");
     Ok(())
@@ -3,22 +3,72 @@
 use crate::router::load_balancer::LoadBalancer;
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tracing::debug;
 
+/// Short-lived cache of the aggregated model list
+///
+/// Entries expire after the TTL (which also covers health flips) and are dropped
+/// immediately when registry sync adds, removes or changes services.
+pub struct ModelListCache {
+    ttl: Duration,
+    entry: RwLock<Option<(Instant, Arc<Vec<Value>>)>>,
+}
+
+impl ModelListCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: RwLock::new(None),
+        }
+    }
+
+    fn get(&self) -> Option<Arc<Vec<Value>>> {
+        let entry = self.entry.read().unwrap();
+        entry
+            .as_ref()
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.ttl)
+            .map(|(_, models)| models.clone())
+    }
+
+    fn store(&self, models: Arc<Vec<Value>>) {
+        if !self.ttl.is_zero() {
+            *self.entry.write().unwrap() = Some((Instant::now(), models));
+        }
+    }
+
+    /// Drop the cached list so the next request re-aggregates
+    pub fn invalidate(&self) {
+        *self.entry.write().unwrap() = None;
+    }
+}
+
 /// Model aggregator
 pub struct ModelAggregator;
 
 impl ModelAggregator {
+    /// Aggregated models from all healthy services, served from cache when fresh
+    pub async fn aggregate_models(load_balancer: &Arc<LoadBalancer>) -> Arc<Vec<Value>> {
+        let cache = load_balancer.model_cache();
+        if let Some(models) = cache.get() {
+            return models;
+        }
+
+        let models = Arc::new(Self::collect_models(load_balancer).await);
+        cache.store(models.clone());
+        models
+    }
+
     /// Aggregate models from all healthy services
-    pub async fn aggregate_models(load_balancer: &Arc<LoadBalancer>) -> Vec<Value> {
+    async fn collect_models(load_balancer: &Arc<LoadBalancer>) -> Vec<Value> {
         let services = load_balancer.get_all_services().await;
         let services_count = services.len();
         let mut aggregated_models: HashMap<String, Value> = HashMap::new();
 
         for service in services {
             // Only aggregate from healthy openai-api services
-            let is_healthy = service.is_healthy().await;
+            let is_healthy = service.is_healthy();
             let service_type = service
                 .metadata
                 .get("type")
@@ -49,7 +99,7 @@ impl ModelAggregator {
                 }
             } else {
                 // Fallback to model IDs from service.models
-                let models = service.models.read().await;
+                let models = service.models.load();
                 for model_id in models.iter() {
                     if !aggregated_models.contains_key(model_id) {
                         // Create minimal model info
@@ -80,3 +130,22 @@ impl ModelAggregator {
         models_vec
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_list_cache() {
+        let cache = ModelListCache::new(Duration::from_secs(60));
+        assert!(cache.get().is_none());
+        cache.store(Arc::new(vec![json!({"id": "model-a"})]));
+        assert_eq!(cache.get().unwrap().len(), 1);
+        cache.invalidate();
+        assert!(cache.get().is_none());
+
+        let disabled = ModelListCache::new(Duration::ZERO);
+        disabled.store(Arc::new(vec![]));
+        assert!(disabled.get().is_none());
+    }
+}
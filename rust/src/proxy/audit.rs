@@ -0,0 +1,289 @@
+//! Prompt audit logging
+//!
+//! Sampled proxied requests are recorded as JSON lines to a file or POSTed to an HTTP sink.
+//! Prompt text is omitted, hashed, redacted or kept in full depending on the configured mode.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use regex::Regex;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+
+use crate::proxy::forwarded::peer_addr;
+use crate::proxy::handler::ServedBy;
+use crate::router::load_balancer::LoadBalancer;
+use crate::utils::errors::RouterError;
+use crate::utils::time::current_timestamp;
+
+/// Audit records buffered before new ones are dropped
+const AUDIT_QUEUE_SIZE: usize = 4096;
+
+/// Patterns redacted in `redact` mode when none are configured:
+/// e-mail addresses, API keys and long digit runs (card/phone/account numbers)
+const DEFAULT_REDACT_PATTERNS: &[&str] = &[
+    r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+    r"\bsk-[A-Za-z0-9_-]{16,}",
+    r"\b\d(?:[ -]?\d){8,18}\b",
+];
+
+/// How prompt contents appear in audit records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptMode {
+    /// Omit prompt contents
+    None,
+    /// SHA-256 of the prompt text
+    Hash,
+    /// Prompt text with sensitive patterns replaced
+    Redact,
+    /// Prompt text as sent
+    Full,
+}
+
+impl PromptMode {
+    fn parse(mode: &str) -> Result<Self, RouterError> {
+        match mode {
+            "none" => Ok(PromptMode::None),
+            "hash" => Ok(PromptMode::Hash),
+            "redact" => Ok(PromptMode::Redact),
+            "full" => Ok(PromptMode::Full),
+            other => Err(RouterError::ConfigError(format!(
+                "Invalid audit prompt mode '{}': expected none, hash, redact or full",
+                other
+            ))),
+        }
+    }
+}
+
+/// Audit subsystem: builds records and hands them to a background sink writer
+pub struct AuditLog {
+    prompt_mode: PromptMode,
+    sample_rate: f64,
+    redact_patterns: Vec<Regex>,
+    sender: mpsc::Sender<Value>,
+}
+
+impl AuditLog {
+    /// Start an audit log writing to `sink` (a file path or http(s) URL)
+    pub fn new(
+        sink: &str,
+        prompt_mode: &str,
+        sample_rate: f64,
+        redact_patterns: &[String],
+    ) -> Result<Self, RouterError> {
+        let prompt_mode = PromptMode::parse(prompt_mode)?;
+        let patterns: Vec<&str> = if redact_patterns.is_empty() {
+            DEFAULT_REDACT_PATTERNS.to_vec()
+        } else {
+            redact_patterns.iter().map(String::as_str).collect()
+        };
+        let redact_patterns = patterns
+            .into_iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    RouterError::ConfigError(format!(
+                        "Invalid audit redact pattern '{}': {}",
+                        pattern, e
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let (sender, receiver) = mpsc::channel(AUDIT_QUEUE_SIZE);
+        tokio::spawn(run_sink(sink.to_string(), receiver));
+
+        Ok(Self {
+            prompt_mode,
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            redact_patterns,
+            sender,
+        })
+    }
+
+    fn sampled(&self) -> bool {
+        self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate
+    }
+
+    /// Prompt fields of an audit record
+    fn prompt_fields(&self, body: &[u8]) -> Value {
+        let text = match serde_json::from_slice::<Value>(body) {
+            Ok(request) => prompt_text(&request),
+            Err(_) => return json!({}),
+        };
+        let mut fields = json!({ "prompt_chars": text.chars().count() });
+        match self.prompt_mode {
+            PromptMode::None => {}
+            PromptMode::Hash => {
+                fields["prompt_sha256"] = json!(format!("{:x}", Sha256::digest(text.as_bytes())));
+            }
+            PromptMode::Redact => {
+                let redacted = self.redact_patterns.iter().fold(text, |text, pattern| {
+                    pattern.replace_all(&text, "[REDACTED]").into_owned()
+                });
+                fields["prompt"] = json!(redacted);
+            }
+            PromptMode::Full => fields["prompt"] = json!(text),
+        }
+        fields
+    }
+
+    fn record(&self, entry: Value) {
+        if self.sender.try_send(entry).is_err() {
+            warn!("Audit queue full or closed, dropping audit record");
+        }
+    }
+}
+
+/// Concatenated prompt text of a chat (`messages`) or completion (`prompt`) request
+fn prompt_text(request: &Value) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    if let Some(messages) = request.get("messages").and_then(|m| m.as_array()) {
+        for message in messages {
+            match message.get("content") {
+                Some(Value::String(text)) => parts.push(text),
+                Some(Value::Array(items)) => parts.extend(
+                    items
+                        .iter()
+                        .filter_map(|item| item.get("text").and_then(|t| t.as_str())),
+                ),
+                _ => {}
+            }
+        }
+    }
+    match request.get("prompt") {
+        Some(Value::String(text)) => parts.push(text),
+        Some(Value::Array(items)) => parts.extend(items.iter().filter_map(|i| i.as_str())),
+        _ => {}
+    }
+    parts.join("\n")
+}
+
+/// Write audit records to the sink until the log is dropped
+async fn run_sink(sink: String, mut receiver: mpsc::Receiver<Value>) {
+    if sink.starts_with("http://") || sink.starts_with("https://") {
+        let client = reqwest::Client::new();
+        while let Some(entry) = receiver.recv().await {
+            if let Err(e) = client.post(&sink).json(&entry).send().await {
+                warn!("Failed to send audit record to {}: {}", sink, e);
+            }
+        }
+        return;
+    }
+
+    let mut file = match tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&sink)
+        .await
+    {
+        Ok(file) => file,
+        Err(e) => {
+            error!("Failed to open audit log {}: {}", sink, e);
+            return;
+        }
+    };
+    while let Some(entry) = receiver.recv().await {
+        let line = format!("{}\n", entry);
+        if let Err(e) = file.write_all(line.as_bytes()).await {
+            warn!("Failed to write audit record to {}: {}", sink, e);
+        }
+    }
+}
+
+/// Middleware recording sampled proxied requests in the audit log
+pub async fn audit_middleware(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let audit = match load_balancer.audit() {
+        Some(audit) if audit.sampled() => audit,
+        _ => return next.run(request).await,
+    };
+
+    let (parts, body) = request.into_parts();
+    let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to read request body: {}", e);
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                axum::Json(json!({"error": "Failed to read request body"})),
+            )
+                .into_response();
+        }
+    };
+
+    let mut entry = json!({
+        "timestamp": current_timestamp(),
+        "method": parts.method.as_str(),
+        "path": parts.uri.path(),
+        "client": load_balancer
+            .config()
+            .trusted_proxies
+            .client_ip(&parts.headers, peer_addr(&parts.extensions))
+            .map(|ip| ip.to_string()),
+    });
+    if let Ok(request) = serde_json::from_slice::<Value>(&body_bytes) {
+        entry["model"] = request.get("model").cloned().unwrap_or(Value::Null);
+        entry["user"] = request.get("user").cloned().unwrap_or(Value::Null);
+        entry["stream"] = json!(request
+            .get("stream")
+            .and_then(|s| s.as_bool())
+            .unwrap_or(false));
+    }
+    if let (Some(entry), Value::Object(prompt)) =
+        (entry.as_object_mut(), audit.prompt_fields(&body_bytes))
+    {
+        entry.extend(prompt);
+    }
+
+    let started = Instant::now();
+    let response = next
+        .run(Request::from_parts(parts, Body::from(body_bytes)))
+        .await;
+
+    entry["status"] = json!(response.status().as_u16());
+    entry["duration_ms"] = json!(started.elapsed().as_millis() as u64);
+    entry["service"] = json!(response.extensions().get::<ServedBy>().map(|s| &s.0));
+    audit.record(entry);
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prompt_fields() {
+        let body = br#"{"model": "m", "messages": [
+            {"role": "system", "content": "Mail me at jane.doe@example.com"},
+            {"role": "user", "content": [{"type": "text", "text": "card 4111 1111 1111 1111"}]}
+        ]}"#;
+        let sink = std::env::temp_dir().join("infini-audit-test.jsonl");
+        let sink = sink.to_str().unwrap();
+
+        let redact = AuditLog::new(sink, "redact", 1.0, &[]).unwrap();
+        assert_eq!(
+            redact.prompt_fields(body)["prompt"],
+            "Mail me at [REDACTED]\ncard [REDACTED]"
+        );
+
+        let hash = AuditLog::new(sink, "hash", 1.0, &[]).unwrap();
+        let fields = hash.prompt_fields(body);
+        assert!(fields.get("prompt").is_none());
+        assert_eq!(fields["prompt_sha256"].as_str().unwrap().len(), 64);
+
+        assert!(AuditLog::new(sink, "verbose", 1.0, &[]).is_err());
+        let _ = std::fs::remove_file(sink);
+    }
+}
@@ -0,0 +1,215 @@
+//! X-Forwarded-* / Forwarded headers for upstream requests
+//!
+//! The router appends the peer it accepted the connection from to X-Forwarded-For and
+//! Forwarded, and fills in X-Forwarded-Proto/Host unless an earlier proxy already did.
+//! Forwarding headers are only believed when the peer is a trusted proxy; from anyone
+//! else they are dropped, so clients cannot spoof their address.
+
+use axum::extract::ConnectInfo;
+use axum::http::{header::FORWARDED, header::HOST, Extensions, HeaderMap, HeaderValue};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+
+/// Networks of proxies whose forwarding headers are trusted
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    /// Parse CIDRs ("10.0.0.0/8") or single addresses ("127.0.0.1", "::1")
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        let networks = entries
+            .iter()
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| format!("Invalid trusted proxy (expected CIDR or IP): {}", entry))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { networks })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(&ip))
+    }
+
+    /// The client's address: the peer, or, while hops are trusted proxies, the address
+    /// they forwarded for (X-Forwarded-For read right to left)
+    pub fn client_ip(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+        let mut client = peer?;
+        let forwarded_for: Vec<&str> = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .collect();
+        for hop in forwarded_for.into_iter().rev() {
+            if !self.contains(client) {
+                break;
+            }
+            match hop.trim().parse::<IpAddr>() {
+                Ok(ip) => client = ip.to_canonical(),
+                Err(_) => break,
+            }
+        }
+        Some(client)
+    }
+}
+
+/// Address of the peer that opened the connection, when the server records it. IPv4
+/// peers of a dual-stack listener arrive as `::ffff:a.b.c.d` and are mapped back to IPv4.
+pub fn peer_addr(extensions: &Extensions) -> Option<IpAddr> {
+    extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_canonical())
+}
+
+/// Record `peer` as the next hop in the forwarding headers sent upstream. Headers set by
+/// an untrusted peer are discarded first.
+pub fn append_forwarded_headers(
+    headers: &mut HeaderMap,
+    peer: Option<IpAddr>,
+    trusted: &TrustedProxies,
+) {
+    if !peer.is_some_and(|peer| trusted.contains(peer)) {
+        for name in [X_FORWARDED_FOR, X_FORWARDED_PROTO, X_FORWARDED_HOST] {
+            headers.remove(name);
+        }
+        headers.remove(FORWARDED);
+    }
+
+    let host = headers
+        .get(HOST)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+
+    if let Some(peer) = peer {
+        let forwarded_for = match headers.get(X_FORWARDED_FOR).and_then(|v| v.to_str().ok()) {
+            Some(existing) if !existing.trim().is_empty() => format!("{}, {}", existing, peer),
+            _ => peer.to_string(),
+        };
+        if let Ok(value) = HeaderValue::from_str(&forwarded_for) {
+            headers.insert(X_FORWARDED_FOR, value);
+        }
+
+        // RFC 7239: IPv6 addresses are bracketed and quoted
+        let node = match peer {
+            IpAddr::V4(ip) => ip.to_string(),
+            IpAddr::V6(ip) => format!("\"[{}]\"", ip),
+        };
+        let mut element = format!("for={};proto=http", node);
+        if let Some(host) = &host {
+            element.push_str(&format!(";host=\"{}\"", host));
+        }
+        let forwarded = match headers.get(FORWARDED).and_then(|v| v.to_str().ok()) {
+            Some(existing) if !existing.trim().is_empty() => format!("{}, {}", existing, element),
+            _ => element,
+        };
+        if let Ok(value) = HeaderValue::from_str(&forwarded) {
+            headers.insert(FORWARDED, value);
+        }
+    }
+
+    if !headers.contains_key(X_FORWARDED_PROTO) {
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static("http"));
+    }
+    if !headers.contains_key(X_FORWARDED_HOST) {
+        if let Some(value) = host.and_then(|host| HeaderValue::from_str(&host).ok()) {
+            headers.insert(X_FORWARDED_HOST, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_forwarded_headers() {
+        let trusted = TrustedProxies::parse(&["::1".to_string()]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, HeaderValue::from_static("router:8000"));
+        append_forwarded_headers(&mut headers, Some("10.0.0.5".parse().unwrap()), &trusted);
+        assert_eq!(headers[X_FORWARDED_FOR], "10.0.0.5");
+        assert_eq!(headers[X_FORWARDED_PROTO], "http");
+        assert_eq!(headers[X_FORWARDED_HOST], "router:8000");
+        assert_eq!(
+            headers[FORWARDED],
+            "for=10.0.0.5;proto=http;host=\"router:8000\""
+        );
+
+        // Behind a trusted proxy: append to its chain and keep its proto/host
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_static("203.0.113.7"));
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static("https"));
+        let mut spoofed = headers.clone();
+        append_forwarded_headers(&mut headers, Some("::1".parse().unwrap()), &trusted);
+        assert_eq!(headers[X_FORWARDED_FOR], "203.0.113.7, ::1");
+        assert_eq!(headers[X_FORWARDED_PROTO], "https");
+        assert_eq!(headers[FORWARDED], "for=\"[::1]\";proto=http");
+        assert!(!headers.contains_key(X_FORWARDED_HOST));
+
+        // The same headers from an untrusted client are replaced
+        append_forwarded_headers(&mut spoofed, Some("10.0.0.5".parse().unwrap()), &trusted);
+        assert_eq!(spoofed[X_FORWARDED_FOR], "10.0.0.5");
+        assert_eq!(spoofed[X_FORWARDED_PROTO], "http");
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let trusted =
+            TrustedProxies::parse(&["10.0.0.0/8".to_string(), "127.0.0.1".to_string()]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            X_FORWARDED_FOR,
+            HeaderValue::from_static("1.1.1.1, 203.0.113.7, 10.1.2.3"),
+        );
+        let ip = |s: &str| Some(s.parse::<IpAddr>().unwrap());
+
+        // Through two trusted hops to the first untrusted address
+        assert_eq!(
+            trusted.client_ip(&headers, ip("127.0.0.1")),
+            ip("203.0.113.7")
+        );
+        // An untrusted peer's X-Forwarded-For is ignored
+        assert_eq!(
+            trusted.client_ip(&headers, ip("198.51.100.1")),
+            ip("198.51.100.1")
+        );
+        assert_eq!(
+            TrustedProxies::default().client_ip(&headers, ip("127.0.0.1")),
+            ip("127.0.0.1")
+        );
+        assert_eq!(trusted.client_ip(&headers, None), None);
+        assert!(TrustedProxies::parse(&["10.0.0.0/33".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_peer_addr_maps_dual_stack_ipv4() {
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(
+            "[::ffff:10.0.0.1]:40000".parse::<SocketAddr>().unwrap(),
+        ));
+        let peer = peer_addr(&extensions);
+        assert_eq!(peer, Some("10.0.0.1".parse().unwrap()));
+
+        // An IPv4 proxy network matches the mapped peer, so its X-Forwarded-For is used
+        let trusted = TrustedProxies::parse(&["10.0.0.0/8".to_string()]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_static("203.0.113.7"));
+        assert_eq!(
+            trusted.client_ip(&headers, peer),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        append_forwarded_headers(&mut headers, peer, &trusted);
+        assert_eq!(headers[X_FORWARDED_FOR], "203.0.113.7, 10.0.0.1");
+    }
+}
@@ -1,23 +1,35 @@
 //! Request/response proxy handler
 
 use axum::{
-    body::Body,
+    body::{Body, Bytes},
     extract::{Request, State},
-    http::{Method, StatusCode},
+    http::{HeaderMap, Method, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use reqwest::Client;
-use serde::Deserialize;
-use std::borrow::Cow;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::borrow::Cow;
+use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::Duration;
-use tracing::{error, info};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
 
-use crate::proxy::session_extractor::generate_session_from_ip;
-use crate::proxy::streaming::handle_streaming_response;
+use crate::proxy::forwarded::{append_forwarded_headers, peer_addr};
+use crate::proxy::priority::{request_client, request_priority};
+use crate::proxy::session_extractor::{generate_session_from_client, generate_session_from_prefix};
+use crate::proxy::streaming::{await_first_chunk, handle_streaming_response, FirstTokenTimer};
+use crate::proxy::tenant::tenant_pool;
+use crate::router::capabilities::{is_embeddings_endpoint, Requirements, IMAGE_PART_TYPES};
+use crate::router::context_window::ContextOverflow;
 use crate::router::load_balancer::LoadBalancer;
+use crate::router::service_instance::ServiceInstance;
+use crate::router::token_estimate::{text_tokens, TokenEstimator};
+use crate::utils::egress::with_upstream_proxy;
+use crate::utils::time::current_timestamp_secs;
 
 /// Get proxy timeout from environment variable or use default (30 minutes)
 fn get_proxy_timeout() -> Duration {
@@ -28,14 +40,55 @@ fn get_proxy_timeout() -> Duration {
         .unwrap_or(Duration::from_secs(1800)) // Default: 30 minutes
 }
 
+/// Timeout for a request to `service` for `model_id`:
+/// the configured per-model override, then the service's `proxy_timeout_seconds` metadata,
+/// then PROXY_TIMEOUT_SECONDS
+pub(crate) fn proxy_timeout_for(
+    load_balancer: &LoadBalancer,
+    service: &ServiceInstance,
+    model_id: Option<&str>,
+) -> Duration {
+    model_id
+        .and_then(|model| load_balancer.config().model_timeouts.get(model).copied())
+        .or_else(|| {
+            service
+                .metadata
+                .get("proxy_timeout_seconds")
+                .and_then(|v| v.as_u64())
+        })
+        .map(Duration::from_secs)
+        .unwrap_or_else(get_proxy_timeout)
+}
+
+// reqwest is built without its decompression features: Accept-Encoding from the client is
+// forwarded as-is and compressed upstream bodies are passed through without decoding.
+// Backends are reached through UPSTREAM_PROXY (or the standard *_PROXY variables) if set.
 lazy_static::lazy_static! {
-    static ref HTTP_CLIENT: Client = Client::builder()
+    pub(crate) static ref HTTP_CLIENT: Client = with_upstream_proxy(Client::builder())
         .timeout(get_proxy_timeout())
         .connect_timeout(Duration::from_secs(5)) // 5 seconds connection timeout
         .build()
         .expect("Failed to create HTTP client");
 }
 
+/// Response extension naming the service that handled a proxied request
+#[derive(Debug, Clone)]
+pub struct ServedBy(pub String);
+
+/// Optional response header describing where a request was served
+const SERVED_BY_HEADER: &str = "x-served-by";
+
+/// X-Served-By value: service name, its cache type (if any) and the 1-based attempt
+fn served_by_value(service: &ServiceInstance, attempt: u32) -> String {
+    match service.metadata.get("cache_type").and_then(|v| v.as_str()) {
+        Some(cache_type) => format!(
+            "{}; cache_type={}; attempt={}",
+            service.name, cache_type, attempt
+        ),
+        None => format!("{}; attempt={}", service.name, attempt),
+    }
+}
+
 /// Headers that should not be forwarded (hop-by-hop headers)
 const HOP_BY_HOP_HEADERS: &[&str] = &[
     "connection",
@@ -50,6 +103,12 @@ const HOP_BY_HOP_HEADERS: &[&str] = &[
     "content-length", // Will be recalculated
 ];
 
+/// Debug header naming the service to proxy to, bypassing load balancing
+const TARGET_HEADER: &str = "x-infinilm-target";
+
+/// Header carrying the token that unlocks TARGET_HEADER (honoured only with `--target-token`)
+const TARGET_TOKEN_HEADER: &str = "x-infinilm-target-token";
+
 /// Default routing threshold in bytes (50KB)
 const DEFAULT_CACHE_TYPE_ROUTING_THRESHOLD: usize = 51200;
 
@@ -61,6 +120,36 @@ fn get_routing_threshold() -> usize {
         .unwrap_or(DEFAULT_CACHE_TYPE_ROUTING_THRESHOLD)
 }
 
+/// Get routing threshold in estimated prompt tokens from environment variable, if set;
+/// it replaces the byte threshold
+fn get_routing_threshold_tokens() -> Option<u64> {
+    std::env::var("CACHE_TYPE_ROUTING_THRESHOLD_TOKENS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|tokens| *tokens > 0)
+}
+
+/// How often a queued request re-checks for a healthy backend
+const NO_BACKEND_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long to queue a request while no healthy backend is available (0 fails immediately)
+fn get_no_backend_queue_timeout() -> Duration {
+    std::env::var("NO_BACKEND_QUEUE_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .map(Duration::from_secs_f64)
+        .unwrap_or(Duration::ZERO)
+}
+
+/// Number of leading prompt bytes hashed for prefix-cache-aware routing (0 disables it)
+fn get_prefix_routing_bytes() -> usize {
+    std::env::var("PREFIX_ROUTING_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
 /// Routing-relevant fields extracted from a request body.
 /// We intentionally do NOT deserialize the full JSON into `serde_json::Value` for efficiency.
 #[derive(Debug, Clone)]
@@ -68,6 +157,23 @@ struct RoutingFields {
     model_id: Option<String>,
     prompt_cache_key: Option<String>,
     message_size: Option<usize>,
+    /// Estimated prompt tokens, calibrated for the model
+    prompt_tokens: Option<u64>,
+    /// Leading prompt bytes (up to PREFIX_ROUTING_BYTES), for prefix-cache-aware routing
+    prompt_prefix: Vec<u8>,
+    /// Image content parts were sent
+    has_images: bool,
+    /// Tools (or legacy functions) were offered to the model
+    has_tools: bool,
+    /// Completion tokens asked for (`max_completion_tokens`, else `max_tokens`)
+    max_tokens: Option<u64>,
+}
+
+impl RoutingFields {
+    /// Estimated tokens the request needs: prompt plus requested completion
+    fn context_tokens(&self) -> u64 {
+        self.prompt_tokens.unwrap_or(0) + self.max_tokens.unwrap_or(0)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -79,6 +185,8 @@ enum Content<'a> {
 
 #[derive(Debug, Deserialize)]
 struct ContentPart<'a> {
+    #[serde(default, borrow, rename = "type")]
+    kind: Option<Cow<'a, str>>,
     #[serde(default, borrow)]
     text: Option<Cow<'a, str>>,
     #[serde(default, borrow)]
@@ -86,13 +194,51 @@ struct ContentPart<'a> {
 }
 
 impl<'a> Content<'a> {
+    fn append_prefix(&self, prefix: &mut Vec<u8>, limit: usize) {
+        match self {
+            Content::Str(s) => append_prefix(prefix, s, limit),
+            Content::Parts(parts) => {
+                for part in parts {
+                    for text in [&part.text, &part.content].into_iter().flatten() {
+                        append_prefix(prefix, text, limit);
+                    }
+                }
+            }
+        }
+    }
+
+    fn has_images(&self) -> bool {
+        match self {
+            Content::Str(_) => false,
+            Content::Parts(parts) => parts.iter().any(|p| {
+                p.kind
+                    .as_deref()
+                    .is_some_and(|kind| IMAGE_PART_TYPES.contains(&kind))
+            }),
+        }
+    }
+
     fn text_len(&self) -> usize {
         match self {
             Content::Str(s) => s.len(),
             Content::Parts(parts) => parts
                 .iter()
-                .map(|p| p.text.as_ref().map(|s| s.len()).unwrap_or(0)
-                    + p.content.as_ref().map(|s| s.len()).unwrap_or(0))
+                .map(|p| {
+                    p.text.as_ref().map(|s| s.len()).unwrap_or(0)
+                        + p.content.as_ref().map(|s| s.len()).unwrap_or(0)
+                })
+                .sum(),
+        }
+    }
+
+    fn text_tokens(&self) -> f64 {
+        match self {
+            Content::Str(s) => text_tokens(s),
+            Content::Parts(parts) => parts
+                .iter()
+                .flat_map(|p| [&p.text, &p.content])
+                .flatten()
+                .map(|s| text_tokens(s))
                 .sum(),
         }
     }
@@ -112,12 +258,30 @@ enum Prompt<'a> {
 }
 
 impl<'a> Prompt<'a> {
+    fn append_prefix(&self, prefix: &mut Vec<u8>, limit: usize) {
+        match self {
+            Prompt::Str(s) => append_prefix(prefix, s, limit),
+            Prompt::Arr(arr) => {
+                for s in arr {
+                    append_prefix(prefix, s, limit);
+                }
+            }
+        }
+    }
+
     fn text_len(&self) -> usize {
         match self {
             Prompt::Str(s) => s.len(),
             Prompt::Arr(arr) => arr.iter().map(|s| s.len()).sum(),
         }
     }
+
+    fn text_tokens(&self) -> f64 {
+        match self {
+            Prompt::Str(s) => text_tokens(s),
+            Prompt::Arr(arr) => arr.iter().map(|s| text_tokens(s)).sum(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -130,31 +294,493 @@ struct RoutingRequest<'a> {
     messages: Option<Vec<Message<'a>>>,
     #[serde(default)]
     prompt: Option<Prompt<'a>>,
+    #[serde(default)]
+    tools: Option<Vec<IgnoredAny>>,
+    #[serde(default)]
+    functions: Option<Vec<IgnoredAny>>,
+    #[serde(default)]
+    max_tokens: Option<serde_json::Value>,
+    #[serde(default)]
+    max_completion_tokens: Option<serde_json::Value>,
 }
 
-fn extract_routing_fields(body_bytes: &[u8]) -> Option<RoutingFields> {
+/// Append as much of `text` as fits under `limit` bytes
+fn append_prefix(prefix: &mut Vec<u8>, text: &str, limit: usize) {
+    let remaining = limit.saturating_sub(prefix.len());
+    let bytes = text.as_bytes();
+    prefix.extend_from_slice(&bytes[..bytes.len().min(remaining)]);
+}
+
+fn extract_routing_fields(
+    body_bytes: &[u8],
+    prefix_bytes: usize,
+    token_estimator: &TokenEstimator,
+) -> Option<RoutingFields> {
     let req: RoutingRequest<'_> = serde_json::from_slice(body_bytes).ok()?;
 
-    let message_size = if let Some(messages) = req.messages {
+    let mut prompt_prefix = Vec::new();
+    if prefix_bytes > 0 {
+        if let Some(messages) = &req.messages {
+            for content in messages.iter().filter_map(|m| m.content.as_ref()) {
+                content.append_prefix(&mut prompt_prefix, prefix_bytes);
+            }
+        } else if let Some(prompt) = &req.prompt {
+            prompt.append_prefix(&mut prompt_prefix, prefix_bytes);
+        }
+    }
+
+    let has_images = req.messages.iter().flatten().any(|m| {
+        m.content
+            .as_ref()
+            .is_some_and(|content| content.has_images())
+    });
+    let has_tools = [&req.tools, &req.functions]
+        .into_iter()
+        .flatten()
+        .any(|tools| !tools.is_empty());
+
+    let message_size = if let Some(messages) = &req.messages {
         Some(
             messages
                 .iter()
                 .map(|m| m.content.as_ref().map(|c| c.text_len()).unwrap_or(0))
                 .sum(),
         )
-    } else if let Some(prompt) = req.prompt {
-        Some(prompt.text_len())
     } else {
-        None
+        req.prompt.as_ref().map(|prompt| prompt.text_len())
     };
+    let prompt_text_tokens = if let Some(messages) = &req.messages {
+        Some(
+            messages
+                .iter()
+                .filter_map(|m| m.content.as_ref())
+                .map(|c| c.text_tokens())
+                .sum(),
+        )
+    } else {
+        req.prompt.as_ref().map(|prompt| prompt.text_tokens())
+    };
+    let prompt_tokens =
+        prompt_text_tokens.map(|tokens| token_estimator.estimate(req.model.as_deref(), tokens));
 
     Some(RoutingFields {
         model_id: req.model.map(|c| c.to_string()),
         prompt_cache_key: req.prompt_cache_key.map(|c| c.to_string()),
         message_size,
+        prompt_tokens,
+        prompt_prefix,
+        has_images,
+        has_tools,
+        max_tokens: [req.max_completion_tokens, req.max_tokens]
+            .into_iter()
+            .flatten()
+            .find_map(|v| v.as_u64()),
+    })
+}
+
+/// `usage.prompt_tokens` of an OpenAI-style response body
+fn reported_prompt_tokens(body: &[u8]) -> Option<u64> {
+    #[derive(Deserialize)]
+    struct Usage {
+        prompt_tokens: Option<u64>,
+    }
+    #[derive(Deserialize)]
+    struct UsageResponse {
+        usage: Option<Usage>,
+    }
+    serde_json::from_slice::<UsageResponse>(body)
+        .ok()?
+        .usage?
+        .prompt_tokens
+}
+
+/// Capabilities a request to `path` needs from its backend; in `route` overflow mode that
+/// includes a context window it fits
+fn request_requirements(
+    path: &str,
+    routing_fields: Option<&RoutingFields>,
+    context_overflow: ContextOverflow,
+) -> Requirements {
+    Requirements {
+        vision: routing_fields.is_some_and(|r| r.has_images),
+        tool_calling: routing_fields.is_some_and(|r| r.has_tools),
+        embeddings: is_embeddings_endpoint(path),
+        context_tokens: routing_fields
+            .filter(|_| context_overflow == ContextOverflow::Route)
+            .map(|r| r.context_tokens()),
+    }
+}
+
+/// Reject a request estimated to need more tokens than `model_id` accepts, with the status
+/// and message to reply with
+fn check_context_fit(
+    load_balancer: &LoadBalancer,
+    routing_fields: Option<&RoutingFields>,
+    model_id: Option<&str>,
+    endpoint: &str,
+    pool: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let (Some(rf), Some(model_id)) = (routing_fields, model_id) else {
+        return Ok(());
+    };
+    let Some(limit) = load_balancer.context_limit(model_id, endpoint, pool) else {
+        return Ok(());
+    };
+    let tokens = rf.context_tokens();
+    if tokens <= limit {
+        return Ok(());
+    }
+    let message = format!(
+        "Request needs about {} tokens ({} prompt, {} completion) but model '{}' accepts at most {}",
+        tokens,
+        tokens - rf.max_tokens.unwrap_or(0),
+        rf.max_tokens.unwrap_or(0),
+        model_id,
+        limit
+    );
+    info!("Rejecting oversize request: {}", message);
+    Err((StatusCode::BAD_REQUEST, message))
+}
+
+/// 429 response telling the client when to retry
+fn too_many_requests(retry_after: Duration) -> Response {
+    let retry_after_secs = retry_after.as_secs().max(1);
+    info!(
+        "All healthy services are at their concurrency limit, rejecting request (Retry-After: {}s)",
+        retry_after_secs
+    );
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(
+            axum::http::header::RETRY_AFTER,
+            retry_after_secs.to_string(),
+        )],
+        Json(json!({"error": "All services are at capacity, retry later"})),
+    )
+        .into_response()
+}
+
+/// Resolve the X-InfiniLM-Target debug header to the named healthy service, or the error
+/// status and message to reply with. Ok(None) when the header is absent, or ignored
+/// because no `--target-token` is configured: pinning bypasses ejection, tenant pools and
+/// admission, so it is never open to every client.
+async fn pinned_target(
+    load_balancer: &LoadBalancer,
+    headers: &axum::http::HeaderMap,
+    pool: Option<&str>,
+    endpoint: &str,
+) -> Result<Option<ServiceInstance>, (StatusCode, String)> {
+    let Some(name) = headers.get(TARGET_HEADER).and_then(|v| v.to_str().ok()) else {
+        return Ok(None);
+    };
+    let Some(token) = &load_balancer.config().target_token else {
+        debug!("Ignoring {} (no target token configured)", TARGET_HEADER);
+        return Ok(None);
+    };
+    let presented = headers
+        .get(TARGET_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok());
+    if presented != Some(token.as_str()) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("{} requires a valid {}", TARGET_HEADER, TARGET_TOKEN_HEADER),
+        ));
+    }
+
+    let service = match load_balancer.get_service(name.trim()).await {
+        Some(service) if service.serves_pool(pool) => service,
+        _ => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Service not found: {}", name),
+            ))
+        }
+    };
+    if !service.is_healthy()
+        || service.is_passively_ejected()
+        || !service.supports_endpoint(endpoint)
+    {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Service {} is not available for {}", service.name, endpoint),
+        ));
+    }
+    info!(
+        "Request pinned to service {} by {}",
+        service.name, TARGET_HEADER
+    );
+    Ok(Some(service))
+}
+
+/// Session ID for affinity: prompt_cache_key, then the prompt prefix hash, then the client IP
+fn request_session_id(
+    model_id: Option<&str>,
+    routing_fields: Option<&RoutingFields>,
+    client_ip: Option<IpAddr>,
+    headers: &HeaderMap,
+) -> Option<String> {
+    let model_prefix = model_id.unwrap_or("default");
+    if let Some(key) = routing_fields.and_then(|r| r.prompt_cache_key.as_deref()) {
+        return Some(format!("{}:prompt_cache:{}", model_prefix, key));
+    }
+    if let Some(prefix_hash) =
+        routing_fields.and_then(|r| generate_session_from_prefix(&r.prompt_prefix))
+    {
+        return Some(format!("{}:prefix:{}", model_prefix, prefix_hash));
+    }
+    client_ip.map(|client_ip| {
+        let ip_hash = generate_session_from_client(&client_ip.to_string(), headers);
+        format!("{}:ip:{}", model_prefix, ip_hash)
     })
 }
 
+/// Size-based cache_type for a request of `message_size` bytes and `prompt_tokens`
+/// estimated prompt tokens: large requests -> static cache, small requests -> paged cache.
+/// Sizes are compared in tokens when CACHE_TYPE_ROUTING_THRESHOLD_TOKENS is set
+fn cache_type_for_size(message_size: usize, prompt_tokens: u64) -> &'static str {
+    let large = match get_routing_threshold_tokens() {
+        Some(threshold) => prompt_tokens > threshold,
+        None => message_size > get_routing_threshold(),
+    };
+    if large {
+        "static"
+    } else {
+        "paged"
+    }
+}
+
+/// The routing decision for a sample request, as reported by /admin/route/explain
+#[derive(Debug, Serialize)]
+pub struct RouteExplanation {
+    pub model: Option<String>,
+    pub message_size: Option<usize>,
+    pub prompt_tokens: Option<u64>,
+    /// Capabilities the request needs (vision, tool_calling, embeddings)
+    pub requirements: Requirements,
+    pub cache_type: Option<String>,
+    pub session_id: Option<String>,
+    pub pool: Option<String>,
+    /// pinned, cache_type, cache_type_fallback, session, round_robin or none
+    pub strategy: &'static str,
+    /// Services the strategy chose among
+    pub candidates: Vec<String>,
+    pub chosen: Option<String>,
+}
+
+/// Work out where a POST to `path` would be routed, following the same steps as
+/// `proxy_handler`, without proxying it or advancing round-robin and session state
+pub async fn explain_route(
+    load_balancer: &LoadBalancer,
+    path: &str,
+    mut headers: HeaderMap,
+    mut body: Bytes,
+    peer: Option<IpAddr>,
+) -> Result<RouteExplanation, (StatusCode, String)> {
+    load_balancer
+        .hooks()
+        .apply_request(path, &mut headers, &mut body);
+
+    let routing_fields = extract_routing_fields(
+        &body,
+        get_prefix_routing_bytes(),
+        load_balancer.token_estimator(),
+    );
+    let model_id = routing_fields.as_ref().and_then(|r| r.model_id.clone());
+    let requirements = request_requirements(
+        path,
+        routing_fields.as_ref(),
+        load_balancer.config().context_overflow,
+    );
+    let client_ip = load_balancer
+        .config()
+        .trusted_proxies
+        .client_ip(&headers, peer);
+    let session_id = request_session_id(
+        model_id.as_deref(),
+        routing_fields.as_ref(),
+        client_ip,
+        &headers,
+    );
+    let pool = tenant_pool(
+        &load_balancer.config().tenant_keys,
+        load_balancer.config().tenant_header.as_deref(),
+        &headers,
+    );
+
+    let mut explanation = RouteExplanation {
+        model: model_id.clone(),
+        message_size: routing_fields.as_ref().map(|r| r.message_size.unwrap_or(0)),
+        prompt_tokens: routing_fields
+            .as_ref()
+            .map(|r| r.prompt_tokens.unwrap_or(0)),
+        requirements,
+        cache_type: None,
+        session_id: session_id.clone(),
+        pool: pool.clone(),
+        strategy: "none",
+        candidates: Vec::new(),
+        chosen: None,
+    };
+    let names = |services: &[ServiceInstance]| services.iter().map(|s| s.name.clone()).collect();
+
+    check_context_fit(
+        load_balancer,
+        routing_fields.as_ref(),
+        model_id.as_deref(),
+        path,
+        pool.as_deref(),
+    )?;
+
+    if let Some(target) = pinned_target(load_balancer, &headers, pool.as_deref(), path).await? {
+        explanation.strategy = "pinned";
+        explanation.candidates = vec![target.name.clone()];
+        explanation.chosen = Some(target.name);
+        return Ok(explanation);
+    }
+
+    if let Some(message_size) = explanation.message_size {
+        let cache_type = cache_type_for_size(message_size, explanation.prompt_tokens.unwrap_or(0));
+        let fallback_cache_type = if cache_type == "static" {
+            "paged"
+        } else {
+            "static"
+        };
+        for (cache_type, strategy) in [
+            (cache_type, "cache_type"),
+            (fallback_cache_type, "cache_type_fallback"),
+        ] {
+            let candidates = load_balancer.route_candidates(
+                model_id.as_deref(),
+                &requirements,
+                path,
+                pool.as_deref(),
+                Some(cache_type),
+            );
+            if let Some(chosen) = load_balancer.peek_weighted(&candidates) {
+                explanation.cache_type = Some(cache_type.to_string());
+                explanation.strategy = strategy;
+                explanation.candidates = names(&candidates);
+                explanation.chosen = Some(chosen.name);
+                return Ok(explanation);
+            }
+        }
+    }
+
+    let candidates = load_balancer.route_candidates(
+        model_id.as_deref(),
+        &requirements,
+        path,
+        pool.as_deref(),
+        None,
+    );
+    let chosen = match &session_id {
+        Some(session_key) => {
+            explanation.strategy = "session";
+            load_balancer
+                .session_target(session_key, &candidates)
+                .await
+                .map(|(service, _)| service)
+        }
+        None => {
+            explanation.strategy = "round_robin";
+            load_balancer.peek_weighted(&candidates)
+        }
+    };
+    match chosen {
+        Some(chosen) => {
+            explanation.candidates = names(&candidates);
+            explanation.chosen = Some(chosen.name);
+        }
+        None => explanation.strategy = "none",
+    }
+    Ok(explanation)
+}
+
+/// Pick a backend: a due half-open probe, then size-based cache_type routing, then
+/// session-aware routing, then round-robin
+async fn select_service(
+    load_balancer: &LoadBalancer,
+    routing_fields: Option<&RoutingFields>,
+    model_id: Option<&str>,
+    endpoint: &str,
+    pool: Option<&str>,
+    session_id: Option<&str>,
+    log_routing: bool,
+) -> Option<ServiceInstance> {
+    let requirements = &request_requirements(
+        endpoint,
+        routing_fields,
+        load_balancer.config().context_overflow,
+    );
+
+    // A service taken out by proxy failures gets an occasional live request as a probe
+    if let Some(s) = load_balancer
+        .half_open_probe(model_id, requirements, endpoint, pool)
+        .await
+    {
+        return Some(s);
+    }
+
+    if let Some(rf) = routing_fields {
+        // Calculate message body size for size-based routing
+        let message_size = rf.message_size.unwrap_or(0);
+        let prompt_tokens = rf.prompt_tokens.unwrap_or(0);
+
+        // Size-based routing: large requests -> static cache, small requests -> paged cache
+        let cache_type = cache_type_for_size(message_size, prompt_tokens);
+
+        if let Some(s) = load_balancer
+            .get_service_by_cache_type(cache_type, model_id, requirements, endpoint, pool)
+            .await
+        {
+            if log_routing {
+                let threshold = match get_routing_threshold_tokens() {
+                    Some(tokens) => format!("{} tokens", tokens),
+                    None => format!("{} bytes", get_routing_threshold()),
+                };
+                info!(
+                    "Size-based routing: message_size={} bytes, prompt_tokens~{}, threshold={}, cache_type={}, service={}",
+                    message_size, prompt_tokens, threshold, cache_type, s.name
+                );
+            }
+            return Some(s);
+        }
+
+        // No backend of the preferred cache type: try the other one for the same model
+        let fallback_cache_type = if cache_type == "static" {
+            "paged"
+        } else {
+            "static"
+        };
+        if let Some(s) = load_balancer
+            .get_service_by_cache_type(fallback_cache_type, model_id, requirements, endpoint, pool)
+            .await
+        {
+            load_balancer.record_cache_type_fallback(cache_type);
+            info!(
+                "Cache-type fallback: no '{}' service available (message_size={} bytes), routed to '{}' service {}",
+                cache_type, message_size, fallback_cache_type, s.name
+            );
+            return Some(s);
+        }
+    }
+
+    // Fallback to session-aware routing if size-based routing fails
+    if let Some(session_key) = session_id {
+        if let Some(s) = load_balancer
+            .get_service_by_session(session_key, model_id, requirements, endpoint, pool)
+            .await
+        {
+            return Some(s);
+        }
+    }
+
+    // Fallback to round-robin
+    load_balancer
+        .get_next_healthy_service_by_model(model_id, requirements, endpoint, pool)
+        .await
+}
+
 /// Proxy handler - forwards requests to backend services
 pub async fn proxy_handler(
     State(load_balancer): State<Arc<LoadBalancer>>,
@@ -162,10 +788,11 @@ pub async fn proxy_handler(
 ) -> Response {
     let method = request.method().clone();
     let uri = request.uri().clone();
-    let headers = request.headers().clone();
+    let mut headers = request.headers().clone();
+    let peer = peer_addr(request.extensions());
 
     // Read request body first (needed for model extraction and forwarding)
-    let body_bytes = match axum::body::to_bytes(request.into_body(), usize::MAX).await {
+    let mut body_bytes = match axum::body::to_bytes(request.into_body(), usize::MAX).await {
         Ok(bytes) => bytes,
         Err(e) => {
             error!("Failed to read request body: {}", e);
@@ -177,32 +804,121 @@ pub async fn proxy_handler(
         }
     };
 
+    // Transformation hooks see the request before routing
+    load_balancer
+        .hooks()
+        .apply_request(uri.path(), &mut headers, &mut body_bytes);
+
     // Extract only routing-relevant fields; avoid building full JSON DOM.
     let routing_fields = if method == Method::POST {
-        extract_routing_fields(&body_bytes)
+        extract_routing_fields(
+            &body_bytes,
+            get_prefix_routing_bytes(),
+            load_balancer.token_estimator(),
+        )
     } else {
         None
     };
     let model_id = routing_fields.as_ref().and_then(|r| r.model_id.clone());
-    let prompt_cache_key = routing_fields
-        .as_ref()
-        .and_then(|r| r.prompt_cache_key.clone());
-
-    // Extract session ID (prompt_cache_key or IP-based)
-    // Note: remote_addr is None here since we don't have direct access to it in axum Request.
-    // We still use X-Forwarded-For as a fallback via generate_session_from_ip.
-    let session_id = if let Some(key) = prompt_cache_key {
-        let model_prefix = model_id.as_deref().unwrap_or("default");
-        Some(format!("{}:prompt_cache:{}", model_prefix, key))
-    } else if let Some(ip_hash) = generate_session_from_ip(&headers, None) {
-        let model_prefix = model_id.as_deref().unwrap_or("default");
-        Some(format!("{}:ip:{}", model_prefix, ip_hash))
-    } else {
-        None
+    let requirements = request_requirements(
+        uri.path(),
+        routing_fields.as_ref(),
+        load_balancer.config().context_overflow,
+    );
+
+    // Extract session ID (prompt_cache_key, prompt prefix hash, or IP-based)
+    // IP-based sessions use the client address, read from X-Forwarded-For only when the
+    // peer is a trusted proxy
+    let client_ip = load_balancer
+        .config()
+        .trusted_proxies
+        .client_ip(&headers, peer);
+    let session_id = request_session_id(
+        model_id.as_deref(),
+        routing_fields.as_ref(),
+        client_ip,
+        &headers,
+    );
+
+    // Tenants are routed within their own pool plus the shared services
+    let pool = tenant_pool(
+        &load_balancer.config().tenant_keys,
+        load_balancer.config().tenant_header.as_deref(),
+        &headers,
+    );
+
+    // Requests that cannot fit the model's context window never take a backend slot
+    if let Err((status, message)) = check_context_fit(
+        &load_balancer,
+        routing_fields.as_ref(),
+        model_id.as_deref(),
+        uri.path(),
+        pool.as_deref(),
+    ) {
+        return (status, Json(json!({"error": message}))).into_response();
+    }
+
+    // A debug-pinned request goes to its service only, without load balancing or retries
+    let target = match pinned_target(&load_balancer, &headers, pool.as_deref(), uri.path()).await {
+        Ok(target) => target,
+        Err((status, message)) => {
+            return (status, Json(json!({"error": message}))).into_response();
+        }
     };
+    headers.remove(TARGET_HEADER);
+    headers.remove(TARGET_TOKEN_HEADER);
+
+    // Requests for saturated backends wait their turn by priority class, shared fairly
+    // between clients, instead of getting 429 at once; the place in the queue is held
+    // until a slot is taken
+    let mut admission = None;
+    if target.is_none() {
+        let priority = request_priority(&load_balancer.config().key_priorities, &headers);
+        let client = request_client(pool.as_deref(), &headers);
+        let cost = routing_fields
+            .as_ref()
+            .map_or(0, |fields| fields.context_tokens());
+        let saturation = || {
+            load_balancer.saturation_retry_after(
+                model_id.as_deref(),
+                &requirements,
+                uri.path(),
+                pool.as_deref(),
+            )
+        };
+        match load_balancer
+            .admission()
+            .admit(
+                model_id.as_deref().unwrap_or(""),
+                priority,
+                &client,
+                cost,
+                saturation,
+            )
+            .await
+        {
+            Ok(ticket) => admission = ticket,
+            Err(retry_after) => return too_many_requests(retry_after),
+        }
+    }
+    append_forwarded_headers(&mut headers, peer, &load_balancer.config().trusted_proxies);
+    load_balancer
+        .config()
+        .header_rules
+        .apply_request(&mut headers);
 
     // Try multiple services if one fails (retry logic for multi-server scenarios)
-    let max_retries = 3;
+    // Requests that may not be retried get a single attempt
+    let max_retries = if target.is_none()
+        && load_balancer.config().retry_policy.is_retryable(
+            method.as_str(),
+            uri.path(),
+            body_bytes.len(),
+        ) {
+        3
+    } else {
+        1
+    };
     let mut last_error: Option<(StatusCode, String)> = None;
 
     // Convert axum Method to reqwest Method (only need to do this once)
@@ -218,129 +934,96 @@ pub async fn proxy_handler(
         }
     };
 
-    for attempt in 0..max_retries {
-        // Get service: size-based routing if enabled, else session-aware routing, else round-robin
-        let service = if let Some(ref rf) = routing_fields {
-            // Calculate message body size for size-based routing
-            let message_size = rf.message_size.unwrap_or(0);
-            let threshold = get_routing_threshold();
-
-            // Size-based routing: large requests -> static cache, small requests -> paged cache
-            let cache_type = if message_size > threshold {
-                "static"
-            } else {
-                "paged"
-            };
+    // Deadline for waiting on a healthy backend (set when we first have to wait)
+    let mut queue_deadline: Option<Instant> = None;
 
-            match load_balancer
-                .get_service_by_cache_type(cache_type, model_id.as_deref())
+    for attempt in 0..max_retries {
+        let mut selected = match &target {
+            Some(service) => Some(service.clone()),
+            None => {
+                select_service(
+                    &load_balancer,
+                    routing_fields.as_ref(),
+                    model_id.as_deref(),
+                    uri.path(),
+                    pool.as_deref(),
+                    session_id.as_deref(),
+                    attempt == 0,
+                )
                 .await
-            {
-                Some(s) => {
-                    if attempt == 0 {
-                        info!(
-                            "Size-based routing: message_size={} bytes, threshold={} bytes, cache_type={}, service={}",
-                            message_size, threshold, cache_type, s.name
-                        );
-                    }
-                    s
-                }
-                None => {
-                    // Fallback to session-aware routing if size-based routing fails
-                    if let Some(ref session_key) = session_id {
-                        match load_balancer
-                            .get_service_by_session(session_key, model_id.as_deref())
-                            .await
-                        {
-                            Some(s) => s,
-                            None => {
-                                // Fallback to round-robin
-                                match load_balancer
-                                    .get_next_healthy_service_by_model(model_id.as_deref())
-                                    .await
-                                {
-                                    Some(s) => s,
-                                    None => {
-                                        let error_msg = if let Some(model) = &model_id {
-                                            format!("No healthy services available for model '{}'", model)
-                                        } else {
-                                            "No healthy services available".to_string()
-                                        };
-                                        return (
-                                            StatusCode::SERVICE_UNAVAILABLE,
-                                            Json(json!({"error": error_msg})),
-                                        )
-                                            .into_response();
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        // Fallback to round-robin
-                        match load_balancer
-                            .get_next_healthy_service_by_model(model_id.as_deref())
-                            .await
-                        {
-                            Some(s) => s,
-                            None => {
-                                let error_msg = if let Some(model) = &model_id {
-                                    format!("No healthy services available for model '{}'", model)
-                                } else {
-                                    "No healthy services available".to_string()
-                                };
-                                return (
-                                    StatusCode::SERVICE_UNAVAILABLE,
-                                    Json(json!({"error": error_msg})),
-                                )
-                                    .into_response();
-                            }
-                        }
-                    }
-                }
             }
-        } else if let Some(ref session_key) = session_id {
-            // Session-aware routing (fallback when body_json is not available)
-            match load_balancer
-                .get_service_by_session(session_key, model_id.as_deref())
-                .await
-            {
-                Some(s) => s,
-                None => {
-                    // No more healthy services available
-                    let error_msg = if let Some(model) = &model_id {
-                        format!("No healthy services available for model '{}'", model)
-                    } else {
-                        "No healthy services available".to_string()
-                    };
-                    return (
-                        StatusCode::SERVICE_UNAVAILABLE,
-                        Json(json!({"error": error_msg})),
-                    )
-                        .into_response();
-                }
+        };
+
+        // Briefly queue the request instead of failing during health/registry blips
+        if selected.is_none() {
+            // Overloaded rather than down: push back on the client instead of queueing
+            if let Some(retry_after) = load_balancer.saturation_retry_after(
+                model_id.as_deref(),
+                &requirements,
+                uri.path(),
+                pool.as_deref(),
+            ) {
+                return too_many_requests(retry_after);
             }
-        } else {
-            // Fallback to existing round-robin logic (no session identifier available)
-            match load_balancer
-                .get_next_healthy_service_by_model(model_id.as_deref())
-                .await
-            {
-                Some(s) => s,
-                None => {
-                    // No more healthy services available
-                    let error_msg = if let Some(model) = &model_id {
+
+            let deadline = *queue_deadline
+                .get_or_insert_with(|| Instant::now() + get_no_backend_queue_timeout());
+            if Instant::now() < deadline {
+                info!(
+                    "No healthy services available, queueing request for up to {:?}",
+                    deadline.saturating_duration_since(Instant::now())
+                );
+            }
+            while selected.is_none() && Instant::now() < deadline {
+                sleep(NO_BACKEND_POLL_INTERVAL).await;
+                selected = select_service(
+                    &load_balancer,
+                    routing_fields.as_ref(),
+                    model_id.as_deref(),
+                    uri.path(),
+                    pool.as_deref(),
+                    session_id.as_deref(),
+                    false,
+                )
+                .await;
+            }
+        }
+
+        let service = match selected {
+            Some(s) => s,
+            None => {
+                if let Some(retry_after) = load_balancer.saturation_retry_after(
+                    model_id.as_deref(),
+                    &requirements,
+                    uri.path(),
+                    pool.as_deref(),
+                ) {
+                    return too_many_requests(retry_after);
+                }
+
+                // No more healthy services available
+                let error_msg = if let Some(model) = &model_id {
+                    if requirements.is_empty() {
                         format!("No healthy services available for model '{}'", model)
                     } else {
-                        "No healthy services available".to_string()
-                    };
-                    return (
-                        StatusCode::SERVICE_UNAVAILABLE,
-                        Json(json!({"error": error_msg})),
-                    )
-                        .into_response();
-                }
+                        format!(
+                            "No healthy services available for model '{}' with capabilities: {}",
+                            model, requirements
+                        )
+                    }
+                } else {
+                    "No healthy services available".to_string()
+                };
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(json!({"error": error_msg})),
+                )
+                    .into_response();
             }
         };
+        let in_flight = service.track_in_flight();
+        drop(admission.take());
+        let started = Instant::now();
 
         // Build target URL
         let target_url = format!(
@@ -387,6 +1070,12 @@ pub async fn proxy_handler(
         // Build upstream request
         let mut upstream_request = HTTP_CLIENT
             .request(reqwest_method.clone(), &target_url)
+            .timeout(proxy_timeout_for(
+                &load_balancer,
+                &service,
+                model_id.as_deref(),
+            ))
+            // Bytes clones share one buffer, so retries never copy the request body
             .body(body_bytes.clone());
 
         // Copy headers (excluding hop-by-hop headers)
@@ -410,9 +1099,9 @@ pub async fn proxy_handler(
                     service.name, target_url, e
                 );
 
-                // Mark service as unhealthy on connection errors
-                service.increment_error_count().await;
-                service.set_healthy(false).await;
+                // Connection errors count towards passive ejection
+                service.record_last_error(format!("Proxy request failed: {}", e));
+                load_balancer.report_proxy_failure(&service).await;
 
                 // Store error for potential retry
                 let (status, error_msg) = if e.is_timeout() {
@@ -442,13 +1131,59 @@ pub async fn proxy_handler(
 
         // Success! Break out of retry loop
         // Increment request count on success
-        service.increment_request_count().await;
+        service.increment_request_count();
 
         let status = StatusCode::from_u16(upstream_response.status().as_u16())
             .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
 
+        // A backend pushing back with 429/503 gets its traffic sent elsewhere for a while
+        let retry_after = upstream_response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok());
+        let throttle = &load_balancer.config().throttle;
+        let backoff = throttle.backoff(status.as_u16(), retry_after, current_timestamp_secs());
+        if let Some(backoff) = backoff {
+            warn!(
+                "Service {} returned {}, backing off for {}s",
+                service.name,
+                status,
+                backoff.as_secs()
+            );
+            service.throttle(backoff);
+            if throttle.retry && attempt < max_retries - 1 {
+                last_error = Some((status, format!("Service {} is throttled", service.name)));
+                continue;
+            }
+        }
+
+        // Configured statuses fail the attempt and move on to the next service; only
+        // other responses count as passive health successes
+        let failed = load_balancer
+            .config()
+            .retry_policy
+            .retries_status(status.as_u16());
+        if failed {
+            service.record_last_error(format!("Upstream returned {}", status));
+            load_balancer.report_proxy_failure(&service).await;
+            if attempt < max_retries - 1 {
+                error!("Service {} returned {}, retrying", service.name, status);
+                last_error = Some((status, format!("Upstream returned {}", status)));
+                continue;
+            }
+        } else if backoff.is_none() {
+            load_balancer.report_proxy_success(&service).await;
+        }
+
+        // 5xx responses feed the rolling error rate used by outlier detection (failed
+        // attempts were already counted)
+        if status.is_server_error() && !failed {
+            service.increment_error_count();
+            service.record_last_error(format!("Upstream returned {}", status));
+        }
+
         // Extract headers before consuming the response
-        let response_headers: Vec<(String, String)> = upstream_response
+        let mut response_headers: Vec<(String, String)> = upstream_response
             .headers()
             .iter()
             .filter_map(|(name, value)| {
@@ -462,6 +1197,16 @@ pub async fn proxy_handler(
                     .map(|v| (name.as_str().to_string(), v.to_string()))
             })
             .collect();
+        load_balancer
+            .config()
+            .header_rules
+            .apply_response(&mut response_headers);
+        if load_balancer.config().served_by_header {
+            response_headers.push((
+                SERVED_BY_HEADER.to_string(),
+                served_by_value(&service, attempt + 1),
+            ));
+        }
 
         // Check if this is a streaming response
         let content_type = upstream_response
@@ -477,22 +1222,59 @@ pub async fn proxy_handler(
 
         let is_sse = content_type.contains("text/event-stream");
         let is_chunked = transfer_encoding.to_lowercase() == "chunked";
+        // Encoded bodies are relayed byte-for-byte; hooks can't read them anyway
+        let is_encoded = upstream_response
+            .headers()
+            .get("content-encoding")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|encoding| !encoding.eq_ignore_ascii_case("identity"));
+
+        if is_sse || is_chunked || is_encoded {
+            let sse = is_sse && !is_encoded;
+            let upstream = match await_first_chunk(upstream_response, sse).await {
+                Ok(upstream) => upstream,
+                Err(message) => {
+                    error!("Error streaming from service {}: {}", service.name, message);
+                    service.record_last_error(message);
+                    load_balancer.report_proxy_failure(&service).await;
+                    let error_msg = "Bad gateway - stream failed before sending data";
+                    last_error = Some((StatusCode::BAD_GATEWAY, error_msg.to_string()));
+
+                    // Nothing reached the client yet, so another service may take it
+                    if attempt < max_retries - 1 {
+                        continue;
+                    }
+                    return (StatusCode::BAD_GATEWAY, Json(json!({"error": error_msg})))
+                        .into_response();
+                }
+            };
 
-        if is_sse || is_chunked {
             // Handle streaming response
-            return handle_streaming_response(
-                upstream_response,
+            let mut first_token_histograms = vec![service.first_token_latency.clone()];
+            if let Some(model) = &model_id {
+                first_token_histograms.push(load_balancer.first_token_latency().histogram(model));
+            }
+            let mut response = handle_streaming_response(
+                upstream,
                 status,
                 response_headers,
                 method.as_str(),
                 uri.path(),
-                &service.name,
+                load_balancer.clone(),
+                &service,
+                sse,
+                in_flight,
+                FirstTokenTimer::new(started, first_token_histograms),
             )
             .await;
+            response
+                .extensions_mut()
+                .insert(ServedBy(service.name.clone()));
+            return response;
         }
 
         // Read response body for non-streaming responses
-        let response_body = match upstream_response.bytes().await {
+        let mut response_body = match upstream_response.bytes().await {
             Ok(bytes) => bytes,
             Err(e) => {
                 error!("Failed to read response body: {}", e);
@@ -504,6 +1286,25 @@ pub async fn proxy_handler(
             }
         };
 
+        // Prompt tokens the backend reports refine the model's token estimates
+        if status.is_success() {
+            let estimated = routing_fields
+                .as_ref()
+                .filter(|r| !r.has_images)
+                .and_then(|r| r.prompt_tokens);
+            if let (Some(model), Some(estimated)) = (&model_id, estimated) {
+                if let Some(reported) = reported_prompt_tokens(&response_body) {
+                    load_balancer
+                        .token_estimator()
+                        .observe(model, estimated, reported);
+                }
+            }
+        }
+
+        load_balancer
+            .hooks()
+            .apply_response(uri.path(), &mut response_headers, &mut response_body);
+
         // Build response
         let mut response_builder = Response::builder().status(status);
 
@@ -516,7 +1317,7 @@ pub async fn proxy_handler(
             }
         }
 
-        let response = match response_builder.body(Body::from(response_body.to_vec())) {
+        let mut response = match response_builder.body(Body::from(response_body.to_vec())) {
             Ok(r) => r,
             Err(e) => {
                 error!("Failed to build response: {}", e);
@@ -535,6 +1336,9 @@ pub async fn proxy_handler(
             service.name,
             status
         );
+        response
+            .extensions_mut()
+            .insert(ServedBy(service.name.clone()));
 
         return response.into_response();
     }
@@ -550,3 +1354,137 @@ pub async fn proxy_handler(
             .into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, RouterArgs};
+    use axum::http::HeaderValue;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_pinned_target_needs_a_configured_token() {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(TARGET_HEADER, HeaderValue::from_static("missing"));
+
+        // Without a target token the header is ignored and routing proceeds normally
+        let config = Config::from_args(RouterArgs::parse_from(["infini-router"])).unwrap();
+        let load_balancer = LoadBalancer::new(&config).await.unwrap();
+        let target = pinned_target(&load_balancer, &headers, None, "/v1/chat/completions").await;
+        assert!(matches!(target, Ok(None)));
+
+        let config = Config::from_args(RouterArgs::parse_from([
+            "infini-router",
+            "--target-token",
+            "debug",
+        ]))
+        .unwrap();
+        let load_balancer = LoadBalancer::new(&config).await.unwrap();
+        let target = pinned_target(&load_balancer, &headers, None, "/v1/chat/completions").await;
+        assert_eq!(target.unwrap_err().0, StatusCode::FORBIDDEN);
+
+        headers.insert(TARGET_TOKEN_HEADER, HeaderValue::from_static("debug"));
+        let target = pinned_target(&load_balancer, &headers, None, "/v1/chat/completions").await;
+        assert_eq!(target.unwrap_err().0, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_prompt_prefix_extraction() {
+        let estimator = TokenEstimator::default();
+        let body = br#"{"model": "m", "messages": [
+            {"role": "system", "content": "abcdef"},
+            {"role": "user", "content": [{"type": "text", "text": "ghij"}]}
+        ]}"#;
+
+        let fields = extract_routing_fields(body, 8, &estimator).unwrap();
+        assert_eq!(fields.prompt_prefix, b"abcdefgh");
+
+        let fields = extract_routing_fields(body, 0, &estimator).unwrap();
+        assert!(fields.prompt_prefix.is_empty());
+
+        let fields = extract_routing_fields(br#"{"prompt": ["ab", "cd"]}"#, 3, &estimator).unwrap();
+        assert_eq!(fields.prompt_prefix, b"abc");
+    }
+
+    #[test]
+    fn test_request_requirements() {
+        let estimator = TokenEstimator::default();
+        let body = br#"{"model": "m", "messages": [
+            {"role": "user", "content": [
+                {"type": "text", "text": "what is this"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}}
+            ]}
+        ], "tools": [{"type": "function", "function": {"name": "f"}}]}"#;
+        let fields = extract_routing_fields(body, 0, &estimator).unwrap();
+        assert_eq!(fields.message_size, Some(12));
+        let requirements = request_requirements(
+            "/v1/chat/completions",
+            Some(&fields),
+            ContextOverflow::Reject,
+        );
+        assert!(requirements.vision && requirements.tool_calling && !requirements.embeddings);
+        assert_eq!(requirements.context_tokens, None);
+
+        let fields =
+            extract_routing_fields(br#"{"input": "hi", "tools": []}"#, 0, &estimator).unwrap();
+        let requirements =
+            request_requirements("/v1/embeddings", Some(&fields), ContextOverflow::Reject);
+        assert!(!requirements.vision && !requirements.tool_calling && requirements.embeddings);
+    }
+
+    #[test]
+    fn test_context_tokens() {
+        let estimator = TokenEstimator::default();
+        let body = br#"{"model": "m", "prompt": "0123456789abcdef", "max_tokens": 100}"#;
+        let fields = extract_routing_fields(body, 0, &estimator).unwrap();
+        assert_eq!(fields.context_tokens(), 104);
+        let requirements =
+            request_requirements("/v1/completions", Some(&fields), ContextOverflow::Route);
+        assert_eq!(requirements.context_tokens, Some(104));
+
+        let body = br#"{"prompt": "abcd", "max_tokens": 9.5, "max_completion_tokens": 20}"#;
+        assert_eq!(
+            extract_routing_fields(body, 0, &estimator)
+                .unwrap()
+                .max_tokens,
+            Some(20)
+        );
+    }
+
+    #[test]
+    fn test_prompt_tokens() {
+        let estimator = TokenEstimator::default();
+        let body =
+            r#"{"model": "m", "messages": [{"role": "user", "content": "请把这段话翻译成英文"}]}"#;
+        let fields = extract_routing_fields(body.as_bytes(), 0, &estimator).unwrap();
+        assert_eq!(fields.message_size, Some(30));
+        assert_eq!(fields.prompt_tokens, Some(7));
+
+        let response =
+            br#"{"choices": [], "usage": {"prompt_tokens": 212, "completion_tokens": 9}}"#;
+        assert_eq!(reported_prompt_tokens(response), Some(212));
+        assert_eq!(reported_prompt_tokens(b"data: [DONE]"), None);
+    }
+
+    #[test]
+    fn test_served_by_value() {
+        let metadata = std::collections::HashMap::from([(
+            "cache_type".to_string(),
+            serde_json::json!("paged"),
+        )]);
+        let paged = ServiceInstance::new("gpu-1".into(), "localhost".into(), 8000, 1, metadata);
+        assert_eq!(
+            served_by_value(&paged, 2),
+            "gpu-1; cache_type=paged; attempt=2"
+        );
+
+        let plain = ServiceInstance::new(
+            "gpu-2".into(),
+            "localhost".into(),
+            8001,
+            1,
+            Default::default(),
+        );
+        assert_eq!(served_by_value(&plain, 1), "gpu-2; attempt=1");
+    }
+}
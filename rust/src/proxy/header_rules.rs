@@ -0,0 +1,127 @@
+//! Operator-configured header rules
+//!
+//! Beyond hop-by-hop headers, operators can strip headers (e.g. internal auth) and set
+//! headers (e.g. an X-Internal-Token for backends) on requests sent upstream and on
+//! responses returned to clients. Set headers replace any value already present.
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::utils::errors::RouterError;
+
+/// Headers to strip and set in each direction
+#[derive(Debug, Clone, Default)]
+pub struct HeaderRules {
+    strip_request: Vec<HeaderName>,
+    set_request: Vec<(HeaderName, HeaderValue)>,
+    strip_response: Vec<HeaderName>,
+    set_response: Vec<(HeaderName, HeaderValue)>,
+}
+
+fn header_name(name: &str) -> Result<HeaderName, RouterError> {
+    HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|_| RouterError::ConfigError(format!("Invalid header name: {}", name)))
+}
+
+fn header_pairs(entries: &[String]) -> Result<Vec<(HeaderName, HeaderValue)>, RouterError> {
+    entries
+        .iter()
+        .map(|entry| {
+            let (name, value) = entry.split_once('=').ok_or_else(|| {
+                RouterError::ConfigError(format!("Invalid header (expected NAME=VALUE): {}", entry))
+            })?;
+            let value = HeaderValue::from_str(value.trim()).map_err(|_| {
+                RouterError::ConfigError(format!("Invalid header value in: {}", entry))
+            })?;
+            Ok((header_name(name)?, value))
+        })
+        .collect()
+}
+
+impl HeaderRules {
+    /// Build rules from header names to strip and NAME=VALUE headers to set
+    pub fn new(
+        strip_request: &[String],
+        set_request: &[String],
+        strip_response: &[String],
+        set_response: &[String],
+    ) -> Result<Self, RouterError> {
+        Ok(Self {
+            strip_request: strip_request
+                .iter()
+                .map(|name| header_name(name))
+                .collect::<Result<_, _>>()?,
+            set_request: header_pairs(set_request)?,
+            strip_response: strip_response
+                .iter()
+                .map(|name| header_name(name))
+                .collect::<Result<_, _>>()?,
+            set_response: header_pairs(set_response)?,
+        })
+    }
+
+    /// Rewrite the headers forwarded upstream
+    pub fn apply_request(&self, headers: &mut HeaderMap) {
+        for name in &self.strip_request {
+            headers.remove(name);
+        }
+        for (name, value) in &self.set_request {
+            headers.insert(name.clone(), value.clone());
+        }
+    }
+
+    /// Rewrite the headers returned to the client
+    pub fn apply_response(&self, headers: &mut Vec<(String, String)>) {
+        headers.retain(|(name, _)| {
+            !self
+                .strip_response
+                .iter()
+                .chain(self.set_response.iter().map(|(set, _)| set))
+                .any(|rule| rule.as_str().eq_ignore_ascii_case(name))
+        });
+        for (name, value) in &self.set_response {
+            if let Ok(value) = value.to_str() {
+                headers.push((name.to_string(), value.to_string()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_rules() {
+        let rules = HeaderRules::new(
+            &["X-Internal-Auth".to_string()],
+            &["X-Internal-Token=abc".to_string()],
+            &["server".to_string()],
+            &["X-Router=infini".to_string()],
+        )
+        .unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-internal-auth", HeaderValue::from_static("secret"));
+        headers.insert("x-internal-token", HeaderValue::from_static("forged"));
+        rules.apply_request(&mut headers);
+        assert!(!headers.contains_key("x-internal-auth"));
+        assert_eq!(headers["x-internal-token"], "abc");
+
+        let mut response = vec![
+            ("Server".to_string(), "uvicorn".to_string()),
+            ("x-router".to_string(), "other".to_string()),
+            ("content-type".to_string(), "application/json".to_string()),
+        ];
+        rules.apply_response(&mut response);
+        assert_eq!(
+            response,
+            [
+                ("content-type".to_string(), "application/json".to_string()),
+                ("x-router".to_string(), "infini".to_string()),
+            ]
+        );
+
+        assert!(HeaderRules::new(&[], &["X-Token".to_string()], &[], &[]).is_err());
+        assert!(HeaderRules::new(&["bad header".to_string()], &[], &[], &[]).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! Request/response transformation hooks
+//!
+//! Hooks run in configuration order on JSON bodies: `pre_proxy` before routing and
+//! forwarding, `post_proxy` on non-streaming responses. Non-JSON bodies pass through untouched.
+
+use axum::body::Bytes;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{json, Value};
+use tracing::warn;
+
+/// A transformation applied around proxied requests
+pub trait ProxyHook: Send + Sync {
+    /// Rewrite the request before it is routed and forwarded
+    fn pre_proxy(&self, _path: &str, _headers: &mut HeaderMap, _body: &mut Value) {}
+
+    /// Rewrite a non-streaming response before it is returned to the client
+    fn post_proxy(&self, _path: &str, _headers: &mut HeaderMap, _body: &mut Value) {}
+}
+
+/// Prepends a system message to chat requests that do not already have one
+pub struct SystemPromptHook {
+    prompt: String,
+}
+
+impl ProxyHook for SystemPromptHook {
+    fn pre_proxy(&self, _path: &str, _headers: &mut HeaderMap, body: &mut Value) {
+        if let Some(messages) = body.get_mut("messages").and_then(|m| m.as_array_mut()) {
+            let has_system = messages
+                .iter()
+                .any(|m| m.get("role").and_then(|r| r.as_str()) == Some("system"));
+            if !has_system {
+                messages.insert(0, json!({"role": "system", "content": self.prompt}));
+            }
+        }
+    }
+}
+
+/// Removes top-level fields from request bodies
+pub struct StripFieldsHook {
+    fields: Vec<String>,
+}
+
+impl ProxyHook for StripFieldsHook {
+    fn pre_proxy(&self, _path: &str, _headers: &mut HeaderMap, body: &mut Value) {
+        strip_fields(body, &self.fields);
+    }
+}
+
+/// Removes top-level fields from response bodies
+pub struct StripResponseFieldsHook {
+    fields: Vec<String>,
+}
+
+impl ProxyHook for StripResponseFieldsHook {
+    fn post_proxy(&self, _path: &str, _headers: &mut HeaderMap, body: &mut Value) {
+        strip_fields(body, &self.fields);
+    }
+}
+
+fn strip_fields(body: &mut Value, fields: &[String]) {
+    if let Some(object) = body.as_object_mut() {
+        for field in fields {
+            object.remove(field);
+        }
+    }
+}
+
+fn field_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Build a built-in hook from a `name=value` spec:
+/// `system_prompt=<text>`, `strip_fields=a,b` or `strip_response_fields=a,b`
+pub fn builtin_hook(spec: &str) -> Result<Box<dyn ProxyHook>, String> {
+    let (name, value) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid proxy hook '{}': expected name=value", spec))?;
+    match name.trim() {
+        "system_prompt" => Ok(Box::new(SystemPromptHook {
+            prompt: value.to_string(),
+        })),
+        "strip_fields" => Ok(Box::new(StripFieldsHook {
+            fields: field_list(value),
+        })),
+        "strip_response_fields" => Ok(Box::new(StripResponseFieldsHook {
+            fields: field_list(value),
+        })),
+        other => Err(format!("Unknown proxy hook '{}'", other)),
+    }
+}
+
+/// Ordered list of hooks applied by the proxy handler
+pub struct HookChain {
+    hooks: Vec<Box<dyn ProxyHook>>,
+}
+
+impl HookChain {
+    pub fn new(hooks: Vec<Box<dyn ProxyHook>>) -> Self {
+        Self { hooks }
+    }
+
+    /// Chain of built-in hooks from their specs
+    pub fn from_specs(specs: &[String]) -> Result<Self, String> {
+        let hooks = specs
+            .iter()
+            .map(|spec| builtin_hook(spec))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(hooks))
+    }
+
+    /// Run `pre_proxy` hooks over a request
+    pub fn apply_request(&self, path: &str, headers: &mut HeaderMap, body: &mut Bytes) {
+        self.apply(headers, body, |hook, headers, value| {
+            hook.pre_proxy(path, headers, value)
+        });
+    }
+
+    /// Run `post_proxy` hooks over a non-streaming response
+    pub fn apply_response(
+        &self,
+        path: &str,
+        headers: &mut Vec<(String, String)>,
+        body: &mut Bytes,
+    ) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers.iter() {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                header_map.append(name, value);
+            }
+        }
+
+        self.apply(&mut header_map, body, |hook, headers, value| {
+            hook.post_proxy(path, headers, value)
+        });
+
+        *headers = header_map
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|v| (name.as_str().to_string(), v.to_string()))
+            })
+            .collect();
+    }
+
+    fn apply<F>(&self, headers: &mut HeaderMap, body: &mut Bytes, run: F)
+    where
+        F: Fn(&dyn ProxyHook, &mut HeaderMap, &mut Value),
+    {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        let mut value: Value = match serde_json::from_slice(body) {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        for hook in &self.hooks {
+            run(hook.as_ref(), headers, &mut value);
+        }
+
+        match serde_json::to_vec(&value) {
+            Ok(bytes) => *body = Bytes::from(bytes),
+            Err(e) => warn!("Failed to serialize body after proxy hooks: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_hooks() {
+        let chain = HookChain::from_specs(&[
+            "system_prompt=Be brief.".to_string(),
+            "strip_fields=user, logit_bias".to_string(),
+            "strip_response_fields=usage".to_string(),
+        ])
+        .unwrap();
+
+        let mut headers = HeaderMap::new();
+        let mut body = Bytes::from_static(
+            br#"{"model": "m", "user": "u", "messages": [{"role": "user", "content": "hi"}]}"#,
+        );
+        chain.apply_request("/v1/chat/completions", &mut headers, &mut body);
+        let request: Value = serde_json::from_slice(&body).unwrap();
+        assert!(request.get("user").is_none());
+        assert_eq!(request["messages"][0]["role"], "system");
+        assert_eq!(request["messages"][0]["content"], "Be brief.");
+        assert_eq!(request["messages"].as_array().unwrap().len(), 2);
+
+        let mut response_headers = vec![("x-test".to_string(), "1".to_string())];
+        let mut response = Bytes::from_static(br#"{"id": "x", "usage": {}}"#);
+        chain.apply_response("/v1/chat/completions", &mut response_headers, &mut response);
+        let response: Value = serde_json::from_slice(&response).unwrap();
+        assert!(response.get("usage").is_none());
+        assert_eq!(response_headers.len(), 1);
+
+        // Non-JSON bodies are left alone
+        let mut raw = Bytes::from_static(b"not json");
+        chain.apply_request("/v1/chat/completions", &mut headers, &mut raw);
+        assert_eq!(&raw[..], b"not json");
+
+        assert!(HookChain::from_specs(&["unknown=1".to_string()]).is_err());
+    }
+}
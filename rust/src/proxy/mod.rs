@@ -1,6 +1,13 @@
 //! Proxy module
 
+pub mod audit;
+pub mod forwarded;
 pub mod handler;
+pub mod header_rules;
+pub mod hooks;
 pub mod model_extractor;
+pub mod priority;
 pub mod session_extractor;
+pub mod slo;
 pub mod streaming;
+pub mod tenant;
@@ -0,0 +1,477 @@
+//! Request priority classes and the admission queue in front of saturated backends
+//!
+//! A request's class comes from the X-Priority header or its API key (`--key-priority`),
+//! defaulting to `normal`; the header can lower a mapped key's class but not raise it.
+//! When every backend for a model is at its concurrency ceiling, requests wait in a
+//! per-model queue instead of getting 429 at once, and are admitted as slots free up:
+//! highest class first. Within a class, clients (tenant pools, else API keys) take
+//! turns by deficit round robin: each turn grants a client a quantum of tokens, and each
+//! request it gets admitted is charged its estimated size but at least a quantum (it
+//! holds a slot however small), so a client flooding the queue cannot take more than
+//! its share. New requests also queue while anyone of the same or a higher class is
+//! waiting for the model, so they cannot jump ahead.
+
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Header naming the priority class of a request
+pub const PRIORITY_HEADER: &str = "x-priority";
+
+/// How often a queued request checks whether it may go
+const ADMISSION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Priority class of a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Batch and other background traffic
+    Low,
+    Normal,
+    /// Interactive traffic
+    High,
+}
+
+impl Priority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" | "batch" => Some(Priority::Low),
+            "normal" | "default" => Some(Priority::Normal),
+            "high" | "interactive" => Some(Priority::High),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        }
+    }
+}
+
+/// Bearer API key of a request
+fn bearer_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Priority of a request: its X-Priority header, capped at (or, without the header,
+/// equal to) the priority of its bearer API key; normal when neither is set
+pub fn request_priority(
+    key_priorities: &HashMap<String, Priority>,
+    headers: &HeaderMap,
+) -> Priority {
+    let from_key = bearer_key(headers)
+        .and_then(|key| key_priorities.get(key))
+        .copied();
+    let from_header = headers
+        .get(PRIORITY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Priority::parse);
+    match (from_header, from_key) {
+        (Some(requested), Some(allowed)) => requested.min(allowed),
+        (Some(priority), None) | (None, Some(priority)) => priority,
+        (None, None) => Priority::Normal,
+    }
+}
+
+/// Client a request's share of the queue is accounted to: its tenant pool, else its API
+/// key; requests with neither share one client
+pub fn request_client(pool: Option<&str>, headers: &HeaderMap) -> String {
+    pool.map(|pool| format!("pool:{}", pool))
+        .or_else(|| bearer_key(headers).map(|key| format!("key:{}", key)))
+        .unwrap_or_default()
+}
+
+/// Tokens of admissions each client is granted per turn within a priority class
+const FAIR_SHARE_QUANTUM: u64 = 1024;
+
+/// Largest cost charged for one request, so a huge request cannot stall the scheduler
+const MAX_REQUEST_COST: u64 = 128 * FAIR_SHARE_QUANTUM;
+
+/// Requests of one client waiting in a priority class
+#[derive(Debug)]
+struct ClientQueue {
+    /// Sequence number and cost of each request, oldest first
+    waiting: VecDeque<(u64, u64)>,
+    /// Tokens the client may still be admitted for before its turn passes on
+    deficit: u64,
+}
+
+/// Requests of one priority class waiting for a model, scheduled across clients by
+/// deficit round robin
+#[derive(Debug, Default)]
+struct ClassQueue {
+    clients: HashMap<String, ClientQueue>,
+    /// Clients with waiting requests, the one whose turn it is first
+    turns: VecDeque<String>,
+}
+
+impl ClassQueue {
+    fn push(&mut self, client: &str, seq: u64, cost: u64) {
+        if !self.clients.contains_key(client) {
+            self.turns.push_back(client.to_string());
+        }
+        self.clients
+            .entry(client.to_string())
+            .or_insert_with(|| ClientQueue {
+                waiting: VecDeque::new(),
+                deficit: FAIR_SHARE_QUANTUM,
+            })
+            .waiting
+            .push_back((seq, cost));
+    }
+
+    /// The request to admit next: the oldest of the client whose turn it is, once the
+    /// client's deficit covers its cost; otherwise the client gets another quantum and
+    /// the turn passes on
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            let client = self.clients.get_mut(self.turns.front()?)?;
+            let (seq, cost) = *client.waiting.front()?;
+            if cost <= client.deficit {
+                return Some(seq);
+            }
+            client.deficit += FAIR_SHARE_QUANTUM;
+            self.turns.rotate_left(1);
+        }
+    }
+
+    /// Take a request out of the queue, charging its cost to the client if admitted
+    fn remove(&mut self, client_id: &str, seq: u64, admitted: bool) {
+        let Some(client) = self.clients.get_mut(client_id) else {
+            return;
+        };
+        if let Some(position) = client.waiting.iter().position(|(s, _)| *s == seq) {
+            let (_, cost) = client.waiting.remove(position).unwrap();
+            if admitted {
+                client.deficit = client.deficit.saturating_sub(cost);
+            }
+        }
+        // An idle client starts its next turn afresh
+        if client.waiting.is_empty() {
+            self.clients.remove(client_id);
+            self.turns.retain(|c| c != client_id);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.clients.values().map(|c| c.waiting.len()).sum()
+    }
+}
+
+/// A model's waiting requests by priority class, highest first
+type ModelQueue = BTreeMap<Reverse<Priority>, ClassQueue>;
+
+/// Per-model queues of requests waiting for a backend slot
+#[derive(Debug)]
+pub struct AdmissionQueue {
+    waiting: Mutex<HashMap<String, ModelQueue>>,
+    next_seq: AtomicU64,
+    /// Most requests waiting at once (across models)
+    max_waiting: usize,
+    /// Longest a request waits before it is answered 429 (zero disables queueing)
+    timeout: Duration,
+}
+
+/// A request's place in the queue; leaves the queue when dropped
+pub struct Ticket<'a> {
+    queue: &'a AdmissionQueue,
+    model: String,
+    priority: Priority,
+    client: String,
+    seq: u64,
+    /// The request was let through, so its cost counts against its client's share
+    admitted: bool,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut waiting = self.queue.waiting.lock().unwrap();
+        let Some(classes) = waiting.get_mut(&self.model) else {
+            return;
+        };
+        if let Some(class) = classes.get_mut(&Reverse(self.priority)) {
+            class.remove(&self.client, self.seq, self.admitted);
+            if class.clients.is_empty() {
+                classes.remove(&Reverse(self.priority));
+            }
+        }
+        if classes.is_empty() {
+            waiting.remove(&self.model);
+        }
+    }
+}
+
+impl Ticket<'_> {
+    /// Whether this request is the next of its model's queue to admit
+    fn is_next(&self) -> bool {
+        self.queue
+            .waiting
+            .lock()
+            .unwrap()
+            .get_mut(&self.model)
+            .and_then(|classes| classes.first_entry())
+            .filter(|class| *class.key() == Reverse(self.priority))
+            .and_then(|mut class| class.get_mut().next())
+            == Some(self.seq)
+    }
+}
+
+impl AdmissionQueue {
+    pub fn new(max_waiting: usize, timeout: Duration) -> Self {
+        AdmissionQueue {
+            waiting: Mutex::new(HashMap::new()),
+            next_seq: AtomicU64::new(0),
+            max_waiting,
+            timeout,
+        }
+    }
+
+    /// Whether a request of `priority` or higher is waiting for `model`
+    fn has_waiting_at(&self, model: &str, priority: Priority) -> bool {
+        self.waiting
+            .lock()
+            .unwrap()
+            .get(model)
+            .and_then(|classes| classes.keys().next())
+            .is_some_and(|Reverse(first)| *first >= priority)
+    }
+
+    /// Join the queue for `model` as `client`, with the request's `cost` in tokens; None
+    /// when the queue is full
+    fn enqueue(
+        &self,
+        model: &str,
+        priority: Priority,
+        client: &str,
+        cost: u64,
+    ) -> Option<Ticket<'_>> {
+        let mut waiting = self.waiting.lock().unwrap();
+        let queued: usize = waiting
+            .values()
+            .flat_map(|classes| classes.values())
+            .map(ClassQueue::len)
+            .sum();
+        if queued >= self.max_waiting {
+            return None;
+        }
+        // A request holds a backend slot however small it is
+        let cost = cost.clamp(FAIR_SHARE_QUANTUM, MAX_REQUEST_COST);
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        waiting
+            .entry(model.to_string())
+            .or_default()
+            .entry(Reverse(priority))
+            .or_default()
+            .push(client, seq, cost);
+        Some(Ticket {
+            queue: self,
+            model: model.to_string(),
+            priority,
+            client: client.to_string(),
+            seq,
+            admitted: false,
+        })
+    }
+
+    /// Wait until a request for `model` from `client`, estimated at `cost` tokens, may be
+    /// routed. `saturation` tells how long until a backend slot frees up when all of the
+    /// model's backends are at their ceiling.
+    /// Returns the ticket to hold until the request has taken its slot (None when it did
+    /// not have to queue), or the Retry-After to answer 429 with.
+    pub async fn admit(
+        &self,
+        model: &str,
+        priority: Priority,
+        client: &str,
+        cost: u64,
+        saturation: impl Fn() -> Option<Duration>,
+    ) -> Result<Option<Ticket<'_>>, Duration> {
+        if self.timeout.is_zero() || !self.has_waiting_at(model, priority) {
+            match saturation() {
+                None => return Ok(None),
+                Some(retry_after) if self.timeout.is_zero() => return Err(retry_after),
+                Some(_) => {}
+            }
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        let Some(mut ticket) = self.enqueue(model, priority, client, cost) else {
+            return Err(saturation().unwrap_or(ADMISSION_POLL_INTERVAL));
+        };
+        loop {
+            let retry_after = if ticket.is_next() {
+                match saturation() {
+                    None => {
+                        ticket.admitted = true;
+                        return Ok(Some(ticket));
+                    }
+                    retry_after => retry_after,
+                }
+            } else {
+                None
+            };
+            if Instant::now() >= deadline {
+                return Err(retry_after.or_else(&saturation).unwrap_or(self.timeout));
+            }
+            sleep(ADMISSION_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Requests waiting per model and class
+    pub fn waiting(&self) -> BTreeMap<String, BTreeMap<&'static str, usize>> {
+        self.waiting
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(model, classes)| {
+                let counts = classes
+                    .iter()
+                    .map(|(Reverse(priority), class)| (priority.as_str(), class.len()))
+                    .collect();
+                (model.clone(), counts)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_request_priority() {
+        let keys = HashMap::from([("sk-batch".to_string(), Priority::Low)]);
+        let mut headers = HeaderMap::new();
+        assert_eq!(request_priority(&keys, &headers), Priority::Normal);
+
+        headers.insert(PRIORITY_HEADER, HeaderValue::from_static("interactive"));
+        assert_eq!(request_priority(&keys, &headers), Priority::High);
+
+        // A key's priority caps what the header asks for
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer sk-batch"));
+        assert_eq!(request_priority(&keys, &headers), Priority::Low);
+    }
+
+    #[test]
+    fn test_request_client() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(request_client(None, &headers), "");
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer sk-a"));
+        assert_eq!(request_client(None, &headers), "key:sk-a");
+        assert_eq!(request_client(Some("team-a"), &headers), "pool:team-a");
+    }
+
+    /// Admit the next request among `tickets`, returning its client
+    fn admit_next(tickets: &mut Vec<Ticket<'_>>) -> String {
+        let position = tickets.iter().position(|t| t.is_next()).unwrap();
+        let mut ticket = tickets.remove(position);
+        ticket.admitted = true;
+        ticket.client.clone()
+    }
+
+    #[test]
+    fn test_queue_order() {
+        let queue = AdmissionQueue::new(3, Duration::from_secs(1));
+        let low = queue.enqueue("m", Priority::Low, "", 1).unwrap();
+        let normal = queue.enqueue("m", Priority::Normal, "", 1).unwrap();
+        let high = queue.enqueue("m", Priority::High, "", 1).unwrap();
+        assert!(queue.enqueue("other", Priority::High, "", 1).is_none());
+
+        assert!(high.is_next() && !normal.is_next() && !low.is_next());
+        assert!(queue.has_waiting_at("m", Priority::High));
+        assert!(!queue.has_waiting_at("other", Priority::Low));
+        drop(high);
+        assert!(normal.is_next());
+        assert_eq!(
+            queue.waiting()["m"],
+            BTreeMap::from([("low", 1), ("normal", 1)])
+        );
+        drop((normal, low));
+        assert!(queue.waiting().is_empty());
+    }
+
+    #[test]
+    fn test_fair_share() {
+        let queue = AdmissionQueue::new(16, Duration::from_secs(1));
+        // "a" floods the queue before "b" arrives; they still alternate
+        let mut tickets: Vec<_> = (0..3)
+            .map(|_| queue.enqueue("m", Priority::Normal, "a", FAIR_SHARE_QUANTUM))
+            .chain((0..2).map(|_| queue.enqueue("m", Priority::Normal, "b", FAIR_SHARE_QUANTUM)))
+            .map(Option::unwrap)
+            .collect();
+        let order: Vec<_> = (0..5).map(|_| admit_next(&mut tickets)).collect();
+        assert_eq!(order, ["a", "b", "a", "b", "a"]);
+
+        // Shares are in tokens, at least a quantum per request: a request of two quanta
+        // goes once for every two smaller ones
+        let mut tickets: Vec<_> = (0..2)
+            .map(|_| queue.enqueue("m", Priority::Normal, "large", 2 * FAIR_SHARE_QUANTUM))
+            .chain((0..4).map(|_| queue.enqueue("m", Priority::Normal, "small", 1)))
+            .map(Option::unwrap)
+            .collect();
+        let order: Vec<_> = (0..6).map(|_| admit_next(&mut tickets)).collect();
+        assert_eq!(
+            order,
+            ["small", "large", "small", "small", "large", "small"]
+        );
+    }
+
+    #[test]
+    fn test_fair_share_caps_request_cost() {
+        let queue = AdmissionQueue::new(256, Duration::from_secs(1));
+        // However large its estimate, a request waits out at most MAX_REQUEST_COST worth
+        // of turns instead of stalling behind an ever-growing deficit
+        let turns = (MAX_REQUEST_COST / FAIR_SHARE_QUANTUM) as usize;
+        let mut tickets: Vec<_> =
+            std::iter::once(queue.enqueue("m", Priority::Normal, "huge", u64::MAX))
+                .chain((0..turns).map(|_| queue.enqueue("m", Priority::Normal, "small", 1)))
+                .map(Option::unwrap)
+                .collect();
+        let order: Vec<_> = (0..turns + 1).map(|_| admit_next(&mut tickets)).collect();
+        assert_eq!(order.iter().position(|c| c == "huge"), Some(turns - 1));
+    }
+
+    #[tokio::test]
+    async fn test_admit() {
+        let queue = AdmissionQueue::new(8, Duration::from_millis(200));
+        assert!(queue
+            .admit("m", Priority::Normal, "", 1, || None)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Saturated until the deadline: 429 with the backends' Retry-After
+        let saturated = || Some(Duration::from_secs(2));
+        assert_eq!(
+            queue
+                .admit("m", Priority::Normal, "", 1, saturated)
+                .await
+                .err(),
+            Some(Duration::from_secs(2))
+        );
+
+        // Queued behind a waiting request of the same class even with capacity
+        let ahead = queue.enqueue("m", Priority::Normal, "a", 1).unwrap();
+        assert!(queue
+            .admit("m", Priority::Normal, "b", 1, || None)
+            .await
+            .is_err());
+        // A higher class goes first
+        assert!(queue
+            .admit("m", Priority::High, "b", 1, || None)
+            .await
+            .is_ok());
+        drop(ahead);
+    }
+}
@@ -27,19 +27,21 @@ pub fn extract_prompt_cache_key_from_body(body: &Bytes) -> Option<String> {
 
 /// Generate session ID from IP address and User-Agent header
 /// Returns None if IP address is unavailable
-pub fn generate_session_from_ip(
-    headers: &HeaderMap,
-    remote_addr: Option<&str>,
-) -> Option<String> {
+pub fn generate_session_from_ip(headers: &HeaderMap, remote_addr: Option<&str>) -> Option<String> {
     // Try to get IP from X-Forwarded-For header first (for proxy scenarios)
     let ip = headers
         .get("x-forwarded-for")
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.split(',').next())
         .map(|s| s.trim())
-        .or_else(|| remote_addr)
+        .or(remote_addr)
         .filter(|s| !s.is_empty())?;
 
+    Some(generate_session_from_client(ip, headers))
+}
+
+/// Generate session ID from an already resolved client address and the User-Agent header
+pub fn generate_session_from_client(ip: &str, headers: &HeaderMap) -> String {
     // Get User-Agent header
     let user_agent = headers
         .get("user-agent")
@@ -53,6 +55,17 @@ pub fn generate_session_from_ip(
     let hash = hasher.finalize();
 
     // Convert to hex string (first 16 characters for brevity)
+    format!("{:x}", hash)[..16].to_string()
+}
+
+/// Generate session ID from the leading bytes of a prompt
+/// Requests sharing a prompt prefix map to the same session (and backend KV cache)
+pub fn generate_session_from_prefix(prefix: &[u8]) -> Option<String> {
+    if prefix.is_empty() {
+        return None;
+    }
+
+    let hash = Sha256::digest(prefix);
     Some(format!("{:x}", hash)[..16].to_string())
 }
 
@@ -119,6 +132,16 @@ mod tests {
         // Should use X-Forwarded-For IP, not remote_addr
     }
 
+    #[test]
+    fn test_generate_session_from_prefix() {
+        let a = generate_session_from_prefix(b"You are a helpful assistant.");
+        let b = generate_session_from_prefix(b"You are a helpful assistant.");
+        assert!(a.is_some());
+        assert_eq!(a, b);
+        assert_ne!(a, generate_session_from_prefix(b"You are a pirate."));
+        assert_eq!(generate_session_from_prefix(b""), None);
+    }
+
     #[test]
     fn test_generate_session_from_ip_no_ip() {
         let headers = HeaderMap::new();
@@ -130,7 +153,12 @@ mod tests {
     fn test_extract_session_id_with_prompt_cache_key() {
         let body = br#"{"prompt_cache_key": "key-123"}"#;
         let headers = HeaderMap::new();
-        let session_id = extract_session_id(&headers, &Bytes::from(body.as_slice()), None, Some("model-1"));
+        let session_id = extract_session_id(
+            &headers,
+            &Bytes::from(body.as_slice()),
+            None,
+            Some("model-1"),
+        );
         assert_eq!(session_id, Some("model-1:prompt_cache:key-123".to_string()));
     }
 
@@ -139,7 +167,12 @@ mod tests {
         let body = br#"{"messages": []}"#;
         let mut headers = HeaderMap::new();
         headers.insert("user-agent", HeaderValue::from_static("test-agent"));
-        let session_id = extract_session_id(&headers, &Bytes::from(body.as_slice()), Some("192.168.1.1"), Some("model-1"));
+        let session_id = extract_session_id(
+            &headers,
+            &Bytes::from(body.as_slice()),
+            Some("192.168.1.1"),
+            Some("model-1"),
+        );
         assert!(session_id.is_some());
         assert!(session_id.unwrap().starts_with("model-1:ip:"));
     }
@@ -148,7 +181,12 @@ mod tests {
     fn test_extract_session_id_no_identifier() {
         let body = br#"{"messages": []}"#;
         let headers = HeaderMap::new();
-        let session_id = extract_session_id(&headers, &Bytes::from(body.as_slice()), None, Some("model-1"));
+        let session_id = extract_session_id(
+            &headers,
+            &Bytes::from(body.as_slice()),
+            None,
+            Some("model-1"),
+        );
         assert_eq!(session_id, None);
     }
 }
@@ -0,0 +1,345 @@
+//! SLO / error-budget tracking
+//!
+//! Every proxied request is counted per model and per service in a ring of one-minute
+//! buckets (24 hours). A request is available unless it ends in a 5xx, and meets the
+//! latency objective if it succeeds within it; streaming requests are timed to the
+//! response headers. Requests for models no known service serves are counted under
+//! `unknown`, so arbitrary model names cannot grow the tracker.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::proxy::handler::ServedBy;
+use crate::proxy::model_extractor::extract_model_from_body;
+use crate::router::load_balancer::LoadBalancer;
+use crate::utils::errors::RouterError;
+use crate::utils::time::current_timestamp_secs;
+
+/// Width of one bucket in seconds
+const BUCKET_SECS: u64 = 60;
+
+/// Buckets kept: 24 hours of history, the longest supported window
+const BUCKET_COUNT: usize = 1440;
+
+/// Model key for requests naming a model no known service serves
+pub const UNKNOWN_MODEL: &str = "unknown";
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    slot: u64,
+    total: u64,
+    failed: u64,
+    slow: u64,
+}
+
+/// Per-minute outcome counters for one model or service
+#[derive(Debug)]
+struct SloCounters {
+    buckets: Mutex<Vec<Bucket>>,
+}
+
+impl SloCounters {
+    fn new() -> Self {
+        Self {
+            buckets: Mutex::new(vec![Bucket::default(); BUCKET_COUNT]),
+        }
+    }
+
+    fn record_at(&self, now: u64, failed: bool, slow: bool) {
+        let slot = now / BUCKET_SECS;
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = &mut buckets[(slot % BUCKET_COUNT as u64) as usize];
+        if bucket.slot != slot {
+            *bucket = Bucket {
+                slot,
+                ..Default::default()
+            };
+        }
+        bucket.total += 1;
+        bucket.failed += failed as u64;
+        bucket.slow += slow as u64;
+    }
+
+    /// (total, failed, slow) over the last `window`
+    fn totals_at(&self, now: u64, window: Duration) -> (u64, u64, u64) {
+        let current = now / BUCKET_SECS;
+        let span = (window.as_secs() / BUCKET_SECS).clamp(1, BUCKET_COUNT as u64);
+        self.buckets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|b| b.slot <= current && b.slot + span > current)
+            .fold((0, 0, 0), |(total, failed, slow), b| {
+                (total + b.total, failed + b.failed, slow + b.slow)
+            })
+    }
+}
+
+/// SLO compliance over one window
+#[derive(Debug, Clone, Serialize)]
+pub struct SloWindowReport {
+    pub window: String,
+    pub requests: u64,
+    pub failed: u64,
+    pub availability: f64,
+    pub latency_compliance: f64,
+    /// Share of the window's error budget still unspent (negative once overspent)
+    pub error_budget_remaining: f64,
+    pub availability_met: bool,
+    pub latency_met: bool,
+}
+
+/// Parse a window such as "30m", "1h", "24h" or "900" (seconds)
+fn parse_window(window: &str) -> Result<Duration, RouterError> {
+    let window = window.trim();
+    let (number, unit) = match window.char_indices().last() {
+        Some((index, unit)) if unit.is_ascii_alphabetic() => (&window[..index], unit),
+        _ => (window, 's'),
+    };
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => 0,
+    };
+    let seconds = number.parse::<u64>().ok().map(|n| n * multiplier);
+    match seconds {
+        Some(seconds) if seconds > 0 && seconds <= BUCKET_SECS * BUCKET_COUNT as u64 => {
+            Ok(Duration::from_secs(seconds))
+        }
+        _ => Err(RouterError::ConfigError(format!(
+            "Invalid SLO window '{}': expected e.g. 30m, 1h or 24h (at most 24h)",
+            window
+        ))),
+    }
+}
+
+/// Tracks availability and latency objectives per model and per service
+pub struct SloTracker {
+    latency_objective: Duration,
+    availability_target: f64,
+    latency_target: f64,
+    windows: Vec<(String, Duration)>,
+    models: Mutex<HashMap<String, Arc<SloCounters>>>,
+    services: Mutex<HashMap<String, Arc<SloCounters>>>,
+}
+
+impl SloTracker {
+    pub fn new(
+        latency_objective: Duration,
+        availability_target: f64,
+        latency_target: f64,
+        windows: &[String],
+    ) -> Result<Self, RouterError> {
+        let windows = windows
+            .iter()
+            .map(|window| Ok((window.trim().to_string(), parse_window(window)?)))
+            .collect::<Result<_, RouterError>>()?;
+        Ok(Self {
+            latency_objective,
+            availability_target: availability_target.clamp(0.0, 1.0),
+            latency_target: latency_target.clamp(0.0, 1.0),
+            windows,
+            models: Mutex::new(HashMap::new()),
+            services: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Record one request's outcome
+    pub fn record(
+        &self,
+        model: Option<&str>,
+        service: Option<&str>,
+        status: u16,
+        latency: Duration,
+    ) {
+        self.record_at(current_timestamp_secs(), model, service, status, latency);
+    }
+
+    fn record_at(
+        &self,
+        now: u64,
+        model: Option<&str>,
+        service: Option<&str>,
+        status: u16,
+        latency: Duration,
+    ) {
+        let failed = status >= 500;
+        let slow = !failed && latency > self.latency_objective;
+        for (counters, key) in [(&self.models, model), (&self.services, service)] {
+            if let Some(key) = key {
+                let entry = counters
+                    .lock()
+                    .unwrap()
+                    .entry(key.to_string())
+                    .or_insert_with(|| Arc::new(SloCounters::new()))
+                    .clone();
+                entry.record_at(now, failed, slow);
+            }
+        }
+    }
+
+    fn window_report(&self, counters: &SloCounters, now: u64) -> Vec<SloWindowReport> {
+        self.windows
+            .iter()
+            .map(|(label, window)| {
+                let (total, failed, slow) = counters.totals_at(now, *window);
+                let succeeded = total - failed;
+                let availability = if total > 0 {
+                    succeeded as f64 / total as f64
+                } else {
+                    1.0
+                };
+                let latency_compliance = if succeeded > 0 {
+                    (succeeded - slow) as f64 / succeeded as f64
+                } else {
+                    1.0
+                };
+                let allowed_failures = (1.0 - self.availability_target) * total as f64;
+                let error_budget_remaining = if allowed_failures > 0.0 {
+                    1.0 - failed as f64 / allowed_failures
+                } else if failed == 0 {
+                    1.0
+                } else {
+                    // A 100% target has no budget to spend
+                    0.0
+                };
+                SloWindowReport {
+                    window: label.clone(),
+                    requests: total,
+                    failed,
+                    availability,
+                    latency_compliance,
+                    error_budget_remaining,
+                    availability_met: availability >= self.availability_target,
+                    latency_met: latency_compliance >= self.latency_target,
+                }
+            })
+            .collect()
+    }
+
+    fn group_report(
+        &self,
+        group: &Mutex<HashMap<String, Arc<SloCounters>>>,
+        now: u64,
+    ) -> HashMap<String, Vec<SloWindowReport>> {
+        let group: Vec<(String, Arc<SloCounters>)> = group
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, counters)| (key.clone(), counters.clone()))
+            .collect();
+        group
+            .into_iter()
+            .map(|(key, counters)| (key, self.window_report(&counters, now)))
+            .collect()
+    }
+
+    /// Objectives plus per-model and per-service compliance for each window
+    pub fn report(&self) -> serde_json::Value {
+        let now = current_timestamp_secs();
+        serde_json::json!({
+            "objectives": {
+                "availability_target": self.availability_target,
+                "latency_objective_ms": self.latency_objective.as_millis() as u64,
+                "latency_target": self.latency_target,
+            },
+            "models": self.group_report(&self.models, now),
+            "services": self.group_report(&self.services, now),
+        })
+    }
+}
+
+/// Middleware recording each proxied request's outcome for SLO reporting
+pub async fn slo_middleware(
+    State(load_balancer): State<Arc<LoadBalancer>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to read request body: {}", e);
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": "Failed to read request body"})),
+            )
+                .into_response();
+        }
+    };
+    let model = extract_model_from_body(&body_bytes).map(|model| {
+        if load_balancer.serves_model(&model) {
+            model
+        } else {
+            UNKNOWN_MODEL.to_string()
+        }
+    });
+
+    let started = Instant::now();
+    let response = next
+        .run(Request::from_parts(parts, Body::from(body_bytes)))
+        .await;
+
+    load_balancer.slo().record(
+        model.as_deref(),
+        response
+            .extensions()
+            .get::<ServedBy>()
+            .map(|s| s.0.as_str()),
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slo_report() {
+        let tracker = SloTracker::new(
+            Duration::from_secs(1),
+            0.9,
+            0.5,
+            &["5m".to_string(), "1h".to_string()],
+        )
+        .unwrap();
+        let now = 1_000_000;
+
+        // An hour-old failure only shows up in the 1h window
+        tracker.record_at(now - 1800, Some("m"), Some("s"), 502, Duration::ZERO);
+        for _ in 0..8 {
+            tracker.record_at(now, Some("m"), Some("s"), 200, Duration::from_millis(100));
+        }
+        tracker.record_at(now, Some("m"), None, 200, Duration::from_secs(5));
+
+        let report = tracker.window_report(&tracker.models.lock().unwrap()["m"].clone(), now);
+        let (recent, hour) = (&report[0], &report[1]);
+        assert_eq!(recent.requests, 9);
+        assert_eq!(recent.availability, 1.0);
+        assert!((recent.latency_compliance - 8.0 / 9.0).abs() < 1e-9);
+        assert!(recent.availability_met && recent.latency_met);
+
+        assert_eq!(hour.requests, 10);
+        assert_eq!(hour.failed, 1);
+        assert!((hour.availability - 0.9).abs() < 1e-9);
+        // 10 requests at a 90% target allow exactly one failure
+        assert!(hour.error_budget_remaining.abs() < 1e-9);
+
+        assert_eq!(tracker.services.lock().unwrap().len(), 1);
+        assert!(parse_window("2d").is_err());
+        assert!(parse_window("soon").is_err());
+        assert_eq!(parse_window("90").unwrap(), Duration::from_secs(90));
+    }
+}
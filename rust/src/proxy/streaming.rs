@@ -1,22 +1,226 @@
 //! Streaming support for SSE and chunked responses
+//!
+//! SSE bodies are scanned as they are relayed: an error event, or a stream that ends
+//! without the final `[DONE]` event, counts as a backend failure and feeds passive
+//! health just like a failed request. A client that disconnects mid-stream is logged
+//! but not held against the backend.
 
 use axum::{
-    body::Body,
+    body::{Body, Bytes},
     http::{HeaderName, HeaderValue, StatusCode},
     response::Response,
 };
+use futures::stream::BoxStream;
 use futures::StreamExt;
 use reqwest::Response as ReqwestResponse;
-use tracing::info;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
 
-/// Handle streaming response from upstream service
-pub async fn handle_streaming_response(
+use crate::router::latency::LatencyHistogram;
+use crate::router::load_balancer::LoadBalancer;
+use crate::router::service_instance::{InFlightGuard, ServiceInstance};
+
+/// Longest SSE line buffered while waiting for its end; longer lines are not inspected
+const MAX_SSE_LINE_BYTES: usize = 1 << 20;
+
+/// Body of a streaming upstream response
+pub type UpstreamStream = BoxStream<'static, reqwest::Result<Bytes>>;
+
+/// Wait for the first chunk of a streaming response, so a stream that fails before
+/// anything was sent to the client can still be retried. An SSE stream that closes
+/// without sending anything counts as failed; other streams may legitimately be empty.
+/// On success the returned stream still starts with the first chunk.
+pub async fn await_first_chunk(
     upstream_response: ReqwestResponse,
+    sse: bool,
+) -> Result<UpstreamStream, String> {
+    let mut stream = upstream_response.bytes_stream();
+    loop {
+        match stream.next().await {
+            Some(Ok(bytes)) if bytes.is_empty() => continue,
+            Some(Ok(bytes)) => {
+                return Ok(futures::stream::once(async move { Ok(bytes) })
+                    .chain(stream)
+                    .boxed())
+            }
+            Some(Err(e)) => return Err(format!("Stream failed before the first chunk: {}", e)),
+            None if sse => return Err("Stream closed before the first chunk".to_string()),
+            None => return Ok(futures::stream::empty().boxed()),
+        }
+    }
+}
+
+/// Records the time from sending the upstream request to the first chunk of its
+/// response (time to first token) into the service's and the model's histograms
+pub struct FirstTokenTimer {
+    started: Instant,
+    histograms: Vec<Arc<LatencyHistogram>>,
+}
+
+impl FirstTokenTimer {
+    pub fn new(started: Instant, histograms: Vec<Arc<LatencyHistogram>>) -> Self {
+        Self {
+            started,
+            histograms,
+        }
+    }
+
+    /// Record the elapsed time, once
+    fn first_chunk(&mut self) {
+        let elapsed = self.started.elapsed();
+        for histogram in self.histograms.drain(..) {
+            histogram.record(elapsed);
+        }
+    }
+}
+
+/// Incremental SSE parser looking for error events and the final `[DONE]`
+#[derive(Debug, Default)]
+struct SseScanner {
+    line: Vec<u8>,
+    skipping_line: bool,
+    event: Option<String>,
+    done: bool,
+    error: Option<String>,
+}
+
+impl SseScanner {
+    /// Scan a chunk; returns the error message if the chunk carried the first error event
+    fn feed(&mut self, bytes: &[u8]) -> Option<String> {
+        let had_error = self.error.is_some();
+        for &byte in bytes {
+            if byte != b'\n' {
+                if self.line.len() < MAX_SSE_LINE_BYTES {
+                    self.line.push(byte);
+                } else {
+                    self.skipping_line = true;
+                }
+                continue;
+            }
+            let line = std::mem::take(&mut self.line);
+            if !std::mem::take(&mut self.skipping_line) {
+                self.process_line(String::from_utf8_lossy(&line).trim_end_matches('\r'));
+            }
+        }
+        if had_error {
+            None
+        } else {
+            self.error.clone()
+        }
+    }
+
+    fn process_line(&mut self, line: &str) {
+        if line.is_empty() {
+            self.event = None;
+        } else if let Some(event) = line.strip_prefix("event:") {
+            self.event = Some(event.trim().to_string());
+        } else if let Some(data) = line.strip_prefix("data:") {
+            let data = data.trim();
+            if data == "[DONE]" {
+                self.done = true;
+            } else if self.error.is_none() {
+                self.error = if self.event.as_deref() == Some("error") {
+                    Some(data.to_string())
+                } else {
+                    error_payload(data)
+                };
+            }
+        }
+    }
+}
+
+/// Message of an OpenAI-style `{"error": ...}` event payload
+fn error_payload(data: &str) -> Option<String> {
+    if !data.starts_with('{') || !data.contains("\"error\"") {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(data).ok()?;
+    match value.get("error")? {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(message) => Some(message.clone()),
+        error => Some(
+            error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string()),
+        ),
+    }
+}
+
+/// Outcome tracking for one relayed stream. Dropped with the response body, so a drop
+/// before the upstream stream ended means the client went away.
+struct StreamMonitor {
+    load_balancer: Arc<LoadBalancer>,
+    service: ServiceInstance,
+    method: String,
+    path: String,
+    sse: Option<SseScanner>,
+    first_token: FirstTokenTimer,
+    /// Keeps the request counted as in flight until the last chunk is sent
+    _in_flight: InFlightGuard,
+    ended: bool,
+    failed: bool,
+}
+
+impl StreamMonitor {
+    async fn chunk(&mut self, bytes: &[u8]) {
+        if !bytes.is_empty() {
+            self.first_token.first_chunk();
+        }
+        let error = self.sse.as_mut().and_then(|sse| sse.feed(bytes));
+        if let Some(error) = error {
+            self.fail(format!("Stream error event: {}", error)).await;
+        }
+    }
+
+    /// Upstream stream completed; SSE streams must have sent `[DONE]`
+    async fn finish(&mut self) {
+        self.ended = true;
+        if self.sse.as_ref().is_some_and(|sse| !sse.done) {
+            self.fail("Stream ended without [DONE]".to_string()).await;
+        }
+    }
+
+    /// Count a backend failure, once per stream
+    async fn fail(&mut self, message: String) {
+        if std::mem::replace(&mut self.failed, true) {
+            return;
+        }
+        warn!(
+            "Backend {} failed mid-stream for {} {}: {}",
+            self.service.name, self.method, self.path, message
+        );
+        self.service.record_last_error(message);
+        self.load_balancer.report_proxy_failure(&self.service).await;
+    }
+}
+
+impl Drop for StreamMonitor {
+    fn drop(&mut self) {
+        if !self.ended {
+            info!(
+                "Client disconnected from stream {} {} -> {}",
+                self.method, self.path, self.service.name
+            );
+        }
+    }
+}
+
+/// Handle streaming response from upstream service; `sse` enables event inspection
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_streaming_response(
+    upstream: UpstreamStream,
     status: StatusCode,
     response_headers: Vec<(String, String)>,
     method: &str,
     path: &str,
-    service_name: &str,
+    load_balancer: Arc<LoadBalancer>,
+    service: &ServiceInstance,
+    sse: bool,
+    in_flight: InFlightGuard,
+    first_token: FirstTokenTimer,
 ) -> Response {
     // Build response with streaming body
     let mut response_builder = Response::builder().status(status);
@@ -30,18 +234,44 @@ pub async fn handle_streaming_response(
         }
     }
 
-    // Create a streaming body from the upstream response
-    let stream = upstream_response.bytes_stream();
+    // Error responses are forwarded as they are; only successful streams are inspected
+    let monitor = StreamMonitor {
+        load_balancer,
+        service: service.clone(),
+        method: method.to_string(),
+        path: path.to_string(),
+        sse: (sse && status.is_success()).then(SseScanner::default),
+        first_token,
+        _in_flight: in_flight,
+        ended: false,
+        failed: false,
+    };
 
-    // Convert reqwest::Stream to axum::Body
-    // Map reqwest::Bytes to axum::body::Bytes
-    let body_stream = stream.map(|result| match result {
-        Ok(bytes) => Ok(axum::body::Bytes::from(bytes.to_vec())),
-        Err(e) => {
-            tracing::error!("Stream error: {}", e);
-            Err(std::io::Error::other(format!("Stream error: {}", e)))
-        }
-    });
+    // Relay upstream chunks; the monitor lives as long as the body
+    // stream and sees every chunk and how the stream ended
+    let body_stream = futures::stream::unfold(
+        (upstream, monitor),
+        |(mut stream, mut monitor)| async move {
+            match stream.next().await {
+                Some(Ok(bytes)) => {
+                    monitor.chunk(&bytes).await;
+                    Some((Ok(bytes), (stream, monitor)))
+                }
+                Some(Err(e)) => {
+                    monitor.ended = true;
+                    monitor.fail(format!("Stream interrupted: {}", e)).await;
+                    Some((
+                        Err(std::io::Error::other(format!("Stream error: {}", e))),
+                        (stream, monitor),
+                    ))
+                }
+                None => {
+                    monitor.finish().await;
+                    None
+                }
+            }
+        },
+    );
 
     let body = Body::from_stream(body_stream);
 
@@ -58,7 +288,44 @@ pub async fn handle_streaming_response(
 
     info!(
         "Proxied (stream) {} {} -> {} ({})",
-        method, path, service_name, status
+        method, path, service.name, status
     );
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_scanner_done() {
+        let mut sse = SseScanner::default();
+        assert_eq!(sse.feed(b"data: {\"choices\":[]}\n\nda"), None);
+        assert_eq!(sse.feed(b"ta: [DONE]\r\n\r\n"), None);
+        assert!(sse.done);
+    }
+
+    #[test]
+    fn test_sse_scanner_errors() {
+        let mut sse = SseScanner::default();
+        assert_eq!(
+            sse.feed(b"data: {\"error\": {\"message\": \"CUDA out of memory\"}}\n\n"),
+            Some("CUDA out of memory".to_string())
+        );
+        // Only the first error is reported
+        assert_eq!(sse.feed(b"data: {\"error\": \"again\"}\n\n"), None);
+
+        let mut sse = SseScanner::default();
+        assert_eq!(
+            sse.feed(b"event: error\ndata: engine died\n\n"),
+            Some("engine died".to_string())
+        );
+
+        let mut sse = SseScanner::default();
+        assert_eq!(
+            sse.feed(b"data: {\"error\": null, \"choices\": []}\n\n"),
+            None
+        );
+        assert!(!sse.done);
+    }
+}
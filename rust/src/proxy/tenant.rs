@@ -0,0 +1,59 @@
+//! Tenant pool resolution
+//!
+//! Services reserved for one team carry a `pool` in their metadata. A request reaches a
+//! pool when its API key is mapped to it, or, behind an authenticating gateway, when the
+//! configured tenant header names it. Requests without a tenant only use unpooled services.
+
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use std::collections::HashMap;
+
+/// Tenant pool for a request: the pool its bearer API key maps to, else the tenant header
+pub fn tenant_pool(
+    tenant_keys: &HashMap<String, String>,
+    tenant_header: Option<&str>,
+    headers: &HeaderMap,
+) -> Option<String> {
+    let from_key = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .and_then(|key| tenant_keys.get(key.trim()));
+    if let Some(pool) = from_key {
+        return Some(pool.clone());
+    }
+
+    tenant_header
+        .and_then(|name| headers.get(name))
+        .and_then(|v| v.to_str().ok())
+        .map(|pool| pool.trim())
+        .filter(|pool| !pool.is_empty())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_tenant_pool() {
+        let keys = HashMap::from([("sk-a".to_string(), "team-a".to_string())]);
+        let mut headers = HeaderMap::new();
+        assert_eq!(tenant_pool(&keys, Some("x-tenant"), &headers), None);
+
+        headers.insert("x-tenant", HeaderValue::from_static("team-b"));
+        assert_eq!(
+            tenant_pool(&keys, Some("x-tenant"), &headers).as_deref(),
+            Some("team-b")
+        );
+        // The header is ignored unless configured
+        assert_eq!(tenant_pool(&keys, None, &headers), None);
+
+        // A mapped API key wins over the header
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer sk-a"));
+        assert_eq!(
+            tenant_pool(&keys, Some("x-tenant"), &headers).as_deref(),
+            Some("team-a")
+        );
+    }
+}
@@ -1,11 +1,18 @@
 //! Registry HTTP client
+//!
+//! The router syncs every few seconds, and service metadata carries large `models_list`
+//! blobs. Against a registry that versions its catalog, the client keeps a copy of it and
+//! only fetches the services changed since the last sync from `/services/delta`.
 
 use anyhow::{Context, Result};
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::Duration;
-use tracing::{info, warn};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::registry::tls::RegistryTls;
 
 /// Service information from registry
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,26 +42,167 @@ pub struct RegistryServicesResponse {
     pub services: Vec<RegistryService>,
     #[serde(default)]
     pub total: usize,
+    /// Registry run and catalog version, from registries that support delta sync
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+/// Registry /services/delta response
+#[derive(Debug, Deserialize)]
+pub struct RegistryDeltaResponse {
+    pub epoch: u64,
+    pub version: u64,
+    /// The full catalog rather than changes
+    #[serde(default)]
+    pub full: bool,
+    pub services: Vec<RegistryService>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+    /// Latest heartbeat telemetry (e.g. `load`) of every service, merged into metadata;
+    /// it does not advance the catalog version
+    #[serde(default)]
+    pub telemetry: HashMap<String, HashMap<String, serde_json::Value>>,
+}
+
+/// Local copy of a versioned registry catalog
+struct Catalog {
+    epoch: u64,
+    version: u64,
+    services: HashMap<String, RegistryService>,
+}
+
+impl Catalog {
+    fn healthy_services(&self) -> RegistryServicesResponse {
+        let services: Vec<_> = self
+            .services
+            .values()
+            .filter(|s| s.is_healthy)
+            .cloned()
+            .collect();
+        RegistryServicesResponse {
+            total: services.len(),
+            services,
+            epoch: Some(self.epoch),
+            version: Some(self.version),
+        }
+    }
 }
 
 /// Registry client
 pub struct RegistryClient {
     registry_url: String,
     client: Client,
+    catalog: Mutex<Option<Catalog>>,
 }
 
 impl RegistryClient {
     /// Create a new registry client
-    pub fn new(registry_url: String) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(10))
+    pub fn new(registry_url: String, tls: &RegistryTls) -> Result<Self> {
+        let client = tls
+            .configure(Client::builder().timeout(Duration::from_secs(10)))?
             .build()
-            .expect("Failed to create registry HTTP client");
+            .context("Failed to create registry HTTP client")?;
 
-        RegistryClient {
+        Ok(RegistryClient {
             registry_url,
             client,
+            catalog: Mutex::new(None),
+        })
+    }
+
+    /// Fetch the healthy services, by delta against the cached catalog when possible
+    pub async fn fetch_healthy_services(&self) -> Result<RegistryServicesResponse> {
+        let mut catalog = self.catalog.lock().await;
+        if let Some(cached) = catalog.as_mut() {
+            match self.fetch_delta(cached.epoch, cached.version).await {
+                Ok(Some(delta)) => {
+                    if delta.full {
+                        cached.services.clear();
+                    }
+                    if delta.version != cached.version {
+                        debug!(
+                            "Registry delta {} -> {}: {} changed, {} removed",
+                            cached.version,
+                            delta.version,
+                            delta.services.len(),
+                            delta.removed.len()
+                        );
+                    }
+                    for name in &delta.removed {
+                        cached.services.remove(name);
+                    }
+                    for service in delta.services {
+                        cached.services.insert(service.name.clone(), service);
+                    }
+                    for (name, telemetry) in delta.telemetry {
+                        if let Some(service) = cached.services.get_mut(&name) {
+                            service.metadata.extend(telemetry);
+                        }
+                    }
+                    cached.epoch = delta.epoch;
+                    cached.version = delta.version;
+                    return Ok(cached.healthy_services());
+                }
+                // The registry no longer serves deltas
+                Ok(None) => *catalog = None,
+                Err(e) => return Err(e),
+            }
         }
+
+        let response = self.fetch_services(false).await?;
+        match (response.epoch, response.version) {
+            (Some(epoch), Some(version)) => {
+                let cached = Catalog {
+                    epoch,
+                    version,
+                    services: response
+                        .services
+                        .into_iter()
+                        .map(|s| (s.name.clone(), s))
+                        .collect(),
+                };
+                let healthy = cached.healthy_services();
+                *catalog = Some(cached);
+                Ok(healthy)
+            }
+            // Unversioned registry: keep fetching the full list
+            _ => Ok(RegistryServicesResponse {
+                services: response
+                    .services
+                    .into_iter()
+                    .filter(|s| s.is_healthy)
+                    .collect(),
+                ..response
+            }),
+        }
+    }
+
+    /// Changes since `version`; `None` when the registry has no delta endpoint
+    async fn fetch_delta(&self, epoch: u64, version: u64) -> Result<Option<RegistryDeltaResponse>> {
+        let url = format!("{}/services/delta", self.registry_url);
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("since", version), ("epoch", epoch)])
+            .send()
+            .await
+            .context("Failed to send request to registry")?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            warn!("Registry has no /services/delta, falling back to full syncs");
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Registry returned error status: {}", response.status());
+        }
+
+        let delta = response
+            .json()
+            .await
+            .context("Failed to parse registry delta response")?;
+        Ok(Some(delta))
     }
 
     /// Fetch services from registry
@@ -1,3 +1,4 @@
 //! Registry client module
 
 pub mod client;
+pub mod tls;
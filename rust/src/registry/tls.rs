@@ -0,0 +1,85 @@
+//! TLS settings for talking to the registry
+//!
+//! `https://` registry URLs are verified against the bundled web PKI roots plus an
+//! optional CA bundle (for registries behind a private CA). A client certificate and
+//! key can be presented for registries that require mutual TLS.
+
+use anyhow::{bail, Context, Result};
+use reqwest::{Certificate, ClientBuilder, Identity};
+use std::path::{Path, PathBuf};
+
+/// CA bundle and client identity used for registry connections
+#[derive(Debug, Clone, Default)]
+pub struct RegistryTls {
+    /// PEM bundle of extra CA certificates trusted for the registry
+    pub ca_cert: Option<PathBuf>,
+    /// PEM client certificate (chain) presented to the registry
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key for `client_cert`
+    pub client_key: Option<PathBuf>,
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+impl RegistryTls {
+    /// Apply the CA bundle and client identity to `builder`
+    pub fn configure(&self, mut builder: ClientBuilder) -> Result<ClientBuilder> {
+        if let Some(path) = &self.ca_cert {
+            let pem = read_pem(path)?;
+            let certs = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid CA bundle {}", path.display()))?;
+            if certs.is_empty() {
+                bail!("CA bundle {} contains no certificates", path.display());
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                // rustls identities are a single PEM holding the key and the chain
+                let mut pem = read_pem(key)?;
+                pem.push(b'\n');
+                pem.extend(read_pem(cert)?);
+                let identity = Identity::from_pem(&pem).with_context(|| {
+                    format!(
+                        "Invalid client certificate {} or key {}",
+                        cert.display(),
+                        key.display()
+                    )
+                })?;
+                builder = builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => bail!("Registry client certificate and key must be given together"),
+        }
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configure_validates_files() {
+        assert!(RegistryTls::default()
+            .configure(reqwest::Client::builder())
+            .is_ok());
+
+        let cert_only = RegistryTls {
+            client_cert: Some(PathBuf::from("client.pem")),
+            ..Default::default()
+        };
+        assert!(cert_only.configure(reqwest::Client::builder()).is_err());
+
+        let missing_ca = RegistryTls {
+            ca_cert: Some(PathBuf::from("/nonexistent/ca.pem")),
+            ..Default::default()
+        };
+        assert!(missing_ca.configure(reqwest::Client::builder()).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Routing on backend-reported load
+//!
+//! Babysitters publish the backend's `load` (`queue_depth`, `in_flight`,
+//! `kv_cache_utilization`) in registry metadata with every heartbeat. A service whose
+//! queue or KV cache is past the policy's limits is saturated and only receives requests
+//! when no other service can take them; among the rest, weighted round-robin weights
+//! shrink as a backend's queue grows. Services that report no load are unaffected.
+
+use std::collections::HashMap;
+
+/// Load-aware routing settings
+#[derive(Debug, Clone)]
+pub struct LoadPolicy {
+    /// Queued requests at which a backend counts as saturated (0 disables)
+    pub max_queue_depth: u64,
+    /// KV-cache utilization (0.0-1.0) at which a backend counts as saturated (0 disables)
+    pub max_kv_cache_utilization: f64,
+}
+
+/// The `load` object a service's babysitter reported, if any
+fn reported_load(
+    metadata: &HashMap<String, serde_json::Value>,
+) -> Option<&serde_json::Map<String, serde_json::Value>> {
+    metadata.get("load").and_then(|v| v.as_object())
+}
+
+/// Requests waiting in the backend's queue, when reported
+pub fn queue_depth(metadata: &HashMap<String, serde_json::Value>) -> Option<u64> {
+    reported_load(metadata)?.get("queue_depth")?.as_u64()
+}
+
+/// KV-cache utilization of the backend (0.0-1.0), when reported
+pub fn kv_cache_utilization(metadata: &HashMap<String, serde_json::Value>) -> Option<f64> {
+    reported_load(metadata)?
+        .get("kv_cache_utilization")?
+        .as_f64()
+}
+
+impl LoadPolicy {
+    /// Whether the reported load of a service is past the saturation limits
+    pub fn is_saturated(&self, metadata: &HashMap<String, serde_json::Value>) -> bool {
+        let queue_full = self.max_queue_depth > 0
+            && queue_depth(metadata).is_some_and(|depth| depth >= self.max_queue_depth);
+        let cache_full = self.max_kv_cache_utilization > 0.0
+            && kv_cache_utilization(metadata)
+                .is_some_and(|utilization| utilization >= self.max_kv_cache_utilization);
+        queue_full || cache_full
+    }
+
+    /// Round-robin weight for a service of `weight` with the given metadata: divided by
+    /// one plus its queue depth, but never below 1 unless the weight is 0
+    pub fn routing_weight(
+        &self,
+        weight: u32,
+        metadata: &HashMap<String, serde_json::Value>,
+    ) -> u32 {
+        let Some(depth) = queue_depth(metadata).filter(|depth| *depth > 0) else {
+            return weight;
+        };
+        if weight == 0 {
+            return 0;
+        }
+        (weight as u64 / (depth + 1)).max(1) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn load(queue_depth: u64, kv_cache_utilization: f64) -> HashMap<String, serde_json::Value> {
+        HashMap::from([(
+            "load".to_string(),
+            json!({"queue_depth": queue_depth, "in_flight": 4, "kv_cache_utilization": kv_cache_utilization}),
+        )])
+    }
+
+    #[test]
+    fn test_is_saturated() {
+        let policy = LoadPolicy {
+            max_queue_depth: 8,
+            max_kv_cache_utilization: 0.95,
+        };
+        assert!(!policy.is_saturated(&load(2, 0.5)));
+        assert!(policy.is_saturated(&load(8, 0.5)));
+        assert!(policy.is_saturated(&load(0, 0.97)));
+        assert!(!policy.is_saturated(&HashMap::new()));
+
+        let disabled = LoadPolicy {
+            max_queue_depth: 0,
+            max_kv_cache_utilization: 0.0,
+        };
+        assert!(!disabled.is_saturated(&load(100, 1.0)));
+    }
+
+    #[test]
+    fn test_routing_weight() {
+        let policy = LoadPolicy {
+            max_queue_depth: 8,
+            max_kv_cache_utilization: 0.95,
+        };
+        assert_eq!(policy.routing_weight(100, &HashMap::new()), 100);
+        assert_eq!(policy.routing_weight(100, &load(0, 0.5)), 100);
+        assert_eq!(policy.routing_weight(100, &load(3, 0.5)), 25);
+        assert_eq!(policy.routing_weight(100, &load(500, 0.5)), 1);
+        assert_eq!(policy.routing_weight(0, &load(3, 0.5)), 0);
+    }
+}
@@ -0,0 +1,231 @@
+//! Per-model capabilities of services, and the capabilities a request needs
+//!
+//! Services declare what each model can do in their `model_capabilities` metadata, keyed
+//! by model id (`"*"` applies to models without their own entry):
+//!
+//! ```json
+//! {"model_capabilities": {"qwen-vl": {"context_length": 32768, "vision": true, "tool_calling": true}}}
+//! ```
+//!
+//! The context length falls back to the `max_model_len` the backend reports in
+//! `models_list` (as vLLM does). A service that declares capabilities for a model only
+//! gets requests needing capabilities it declares; a service declaring nothing for the
+//! model is assumed to handle anything, so undeclared deployments route as before.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Capabilities a request needs from the backend serving it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Requirements {
+    /// The request carries image content parts
+    pub vision: bool,
+    /// The request offers tools (or legacy functions) to the model
+    pub tool_calling: bool,
+    /// The request asks for embeddings
+    pub embeddings: bool,
+    /// Estimated tokens the request needs, when it must fit the backend's context window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_tokens: Option<u64>,
+}
+
+/// Content part types carrying an image
+pub const IMAGE_PART_TYPES: &[&str] = &["image_url", "input_image", "image"];
+
+/// Whether `path` is an embeddings endpoint
+pub fn is_embeddings_endpoint(path: &str) -> bool {
+    path.trim_end_matches('/').ends_with("/embeddings")
+}
+
+impl Requirements {
+    /// Requirements of a request to `path` with a parsed JSON `body`
+    pub fn for_request(path: &str, body: &serde_json::Value) -> Self {
+        let vision = body
+            .get("messages")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|message| message.get("content")?.as_array())
+            .flatten()
+            .any(|part| {
+                part.get("type")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|kind| IMAGE_PART_TYPES.contains(&kind))
+            });
+        let offers = |key: &str| {
+            body.get(key)
+                .and_then(|v| v.as_array())
+                .is_some_and(|tools| !tools.is_empty())
+        };
+        Requirements {
+            vision,
+            tool_calling: offers("tools") || offers("functions"),
+            embeddings: is_embeddings_endpoint(path),
+            context_tokens: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Requirements::default()
+    }
+}
+
+impl std::fmt::Display for Requirements {
+    /// Comma-separated names of the required capabilities
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<_> = [
+            (self.vision, "vision"),
+            (self.tool_calling, "tool_calling"),
+            (self.embeddings, "embeddings"),
+        ]
+        .into_iter()
+        .filter(|(required, _)| *required)
+        .map(|(_, name)| name.to_string())
+        .collect();
+        if let Some(tokens) = self.context_tokens {
+            names.push(format!("context of {} tokens", tokens));
+        }
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// What a service declares one model can do
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ModelCapabilities {
+    /// Maximum tokens (prompt plus completion) the model accepts
+    #[serde(default)]
+    pub context_length: Option<u64>,
+    #[serde(default)]
+    pub vision: bool,
+    #[serde(default)]
+    pub tool_calling: bool,
+    #[serde(default)]
+    pub embeddings: bool,
+}
+
+impl ModelCapabilities {
+    /// Whether the model has every capability the request needs
+    pub fn satisfies(&self, requirements: &Requirements) -> bool {
+        (!requirements.vision || self.vision)
+            && (!requirements.tool_calling || self.tool_calling)
+            && (!requirements.embeddings || self.embeddings)
+    }
+}
+
+/// `max_model_len` reported for `model_id` in the service's `models_list`
+fn reported_context_length(
+    metadata: &HashMap<String, serde_json::Value>,
+    model_id: &str,
+) -> Option<u64> {
+    metadata
+        .get("models_list")?
+        .as_array()?
+        .iter()
+        .find(|model| model.get("id").and_then(|v| v.as_str()) == Some(model_id))?
+        .get("max_model_len")?
+        .as_u64()
+}
+
+/// Capabilities a service declares for `model_id` (or for any model, with `"*"`); None
+/// when it declares nothing for the model
+pub fn model_capabilities(
+    metadata: &HashMap<String, serde_json::Value>,
+    model_id: Option<&str>,
+) -> Option<ModelCapabilities> {
+    let declared = metadata
+        .get("model_capabilities")
+        .and_then(|v| v.as_object())
+        .and_then(|models| {
+            model_id
+                .and_then(|id| models.get(id))
+                .or_else(|| models.get("*"))
+        })
+        .and_then(|v| serde_json::from_value::<ModelCapabilities>(v.clone()).ok());
+    let mut capabilities = declared?;
+    if capabilities.context_length.is_none() {
+        capabilities.context_length = model_id.and_then(|id| reported_context_length(metadata, id));
+    }
+    Some(capabilities)
+}
+
+/// Context length of `model_id` on a service: declared, else reported by the backend
+pub fn context_length(
+    metadata: &HashMap<String, serde_json::Value>,
+    model_id: Option<&str>,
+) -> Option<u64> {
+    model_capabilities(metadata, model_id)
+        .and_then(|capabilities| capabilities.context_length)
+        .or_else(|| model_id.and_then(|id| reported_context_length(metadata, id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_model_capabilities() {
+        let metadata = HashMap::from([
+            (
+                "model_capabilities".to_string(),
+                json!({
+                    "qwen-vl": {"vision": true, "tool_calling": true},
+                    "*": {"context_length": 8192},
+                }),
+            ),
+            (
+                "models_list".to_string(),
+                json!([{"id": "qwen-vl", "max_model_len": 32768}, {"id": "llama"}]),
+            ),
+        ]);
+
+        let vl = model_capabilities(&metadata, Some("qwen-vl")).unwrap();
+        assert_eq!(vl.context_length, Some(32768));
+        assert!(vl.satisfies(&Requirements {
+            vision: true,
+            tool_calling: true,
+            ..Default::default()
+        }));
+
+        let other = model_capabilities(&metadata, Some("llama")).unwrap();
+        assert_eq!(other.context_length, Some(8192));
+        assert!(!other.satisfies(&Requirements {
+            vision: true,
+            ..Default::default()
+        }));
+
+        let undeclared = HashMap::from([(
+            "models_list".to_string(),
+            json!([{"id": "qwen", "max_model_len": 4096}]),
+        )]);
+        assert_eq!(model_capabilities(&undeclared, Some("qwen")), None);
+        assert_eq!(context_length(&undeclared, Some("qwen")), Some(4096));
+    }
+
+    #[test]
+    fn test_requirements_for_request() {
+        let body = json!({
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "What is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}},
+            ]}],
+            "tools": [{"type": "function", "function": {"name": "lookup"}}],
+        });
+        assert_eq!(
+            Requirements::for_request("/v1/chat/completions", &body),
+            Requirements {
+                vision: true,
+                tool_calling: true,
+                ..Default::default()
+            }
+        );
+        assert!(Requirements::for_request(
+            "/v1/chat/completions",
+            &json!({"messages": [{"role": "user", "content": "hi"}], "tools": []})
+        )
+        .is_empty());
+        let embeddings = Requirements::for_request("/v1/embeddings", &json!({"input": "hi"}));
+        assert!(embeddings.embeddings);
+        assert_eq!(embeddings.to_string(), "embeddings");
+    }
+}
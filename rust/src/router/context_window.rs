@@ -0,0 +1,72 @@
+//! Fitting requests into the context windows of backends
+//!
+//! A request needs its prompt tokens plus the completion tokens it asks for
+//! (`max_completion_tokens` or `max_tokens`), with prompt tokens estimated from the
+//! prompt text (see `token_estimate`). Context lengths come from the services'
+//! `model_capabilities` metadata or the `max_model_len` their backends report. Requests
+//! that cannot fit are rejected with 400 before they take a backend slot; in `route` mode
+//! they are instead only sent to the services whose window is large enough (long-context
+//! deployments).
+
+use crate::utils::errors::RouterError;
+
+/// What to do with requests larger than a backend's context window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextOverflow {
+    /// Do not check request sizes
+    Off,
+    /// Reject requests larger than the smallest context length reported for the model
+    Reject,
+    /// Send requests only to services they fit; reject those fitting none
+    Route,
+}
+
+impl ContextOverflow {
+    pub fn parse(mode: &str) -> Result<Self, RouterError> {
+        match mode {
+            "off" => Ok(ContextOverflow::Off),
+            "reject" => Ok(ContextOverflow::Reject),
+            "route" => Ok(ContextOverflow::Route),
+            other => Err(RouterError::ConfigError(format!(
+                "Invalid context overflow mode '{}': expected off, reject or route",
+                other
+            ))),
+        }
+    }
+
+    /// The largest request (in tokens) the model accepts given the context lengths of its
+    /// services (None where a service reports none); None when it cannot be told
+    pub fn limit(&self, context_lengths: &[Option<u64>]) -> Option<u64> {
+        match self {
+            ContextOverflow::Off => None,
+            ContextOverflow::Reject => context_lengths.iter().flatten().copied().min(),
+            // A service of unknown context length might take anything
+            ContextOverflow::Route => context_lengths
+                .iter()
+                .copied()
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .max(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_limit() {
+        let lengths = [Some(8192), Some(32768)];
+        assert_eq!(ContextOverflow::Reject.limit(&lengths), Some(8192));
+        assert_eq!(ContextOverflow::Route.limit(&lengths), Some(32768));
+        assert_eq!(ContextOverflow::Off.limit(&lengths), None);
+
+        let partly_known = [Some(8192), None];
+        assert_eq!(ContextOverflow::Reject.limit(&partly_known), Some(8192));
+        assert_eq!(ContextOverflow::Route.limit(&partly_known), None);
+        assert_eq!(ContextOverflow::Reject.limit(&[]), None);
+
+        assert!(ContextOverflow::parse("sometimes").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Deprioritization of flapping backends
+//!
+//! Babysitters report `restart_count` (crash restarts since the service was last
+//! stable) and `last_restart_time` in their registry metadata. A service that restarted
+//! at least `restart_threshold` times, most recently within `window`, is flapping and
+//! only receives requests when no stable service can take them.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Flapping-backend deprioritization settings
+#[derive(Debug, Clone)]
+pub struct FlapPolicy {
+    /// Crash restarts that make a service flapping (0 disables)
+    pub restart_threshold: u64,
+    /// How recent the last restart must be for the service to count as flapping
+    pub window: Duration,
+}
+
+impl FlapPolicy {
+    /// Whether the restart metadata of a service marks it as flapping at `now` (unix time)
+    pub fn is_flapping(&self, metadata: &HashMap<String, serde_json::Value>, now: f64) -> bool {
+        if self.restart_threshold == 0 {
+            return false;
+        }
+        let restart_count = metadata
+            .get("restart_count")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let Some(last_restart) = metadata.get("last_restart_time").and_then(|v| v.as_f64()) else {
+            return false;
+        };
+        restart_count >= self.restart_threshold && now - last_restart < self.window.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_flapping() {
+        let policy = FlapPolicy {
+            restart_threshold: 3,
+            window: Duration::from_secs(600),
+        };
+        let metadata = |count: u64, last: f64| {
+            HashMap::from([
+                ("restart_count".to_string(), json!(count)),
+                ("last_restart_time".to_string(), json!(last)),
+            ])
+        };
+
+        assert!(policy.is_flapping(&metadata(3, 1000.0), 1300.0));
+        assert!(!policy.is_flapping(&metadata(2, 1000.0), 1300.0));
+        assert!(!policy.is_flapping(&metadata(5, 1000.0), 1700.0));
+        assert!(!policy.is_flapping(&HashMap::new(), 1300.0));
+
+        let disabled = FlapPolicy {
+            restart_threshold: 0,
+            ..policy
+        };
+        assert!(!disabled.is_flapping(&metadata(10, 1000.0), 1300.0));
+    }
+}
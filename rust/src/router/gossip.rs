@@ -0,0 +1,84 @@
+//! Health gossip between router replicas
+//!
+//! Replicas configured as peers (`--peer-router`) already share session pins. They also
+//! tell each other when they eject a backend, passively after consecutive proxy failures
+//! or as an outlier, so the other replicas stop sending it traffic right away instead of
+//! each burning requests to rediscover the failure. A replica never takes a peer's word
+//! that a backend recovered: it confirms that with its own recovery probes.
+
+use crate::router::session_store::peer_client;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// A backend ejection observed by one replica
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthObservation {
+    pub service: String,
+    /// Outlier ejection length; absent when the service failed passive health checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ejected_secs: Option<u64>,
+}
+
+/// Pushes health observations to peer routers
+pub struct HealthGossip {
+    peers: Vec<String>,
+    client: Client,
+}
+
+impl HealthGossip {
+    /// Gossip to other router replicas (base URLs), authenticating with `token`
+    pub fn new(peers: &[String], token: Option<&str>) -> Self {
+        Self {
+            peers: peers
+                .iter()
+                .map(|peer| peer.trim_end_matches('/').to_string())
+                .collect(),
+            client: peer_client(token),
+        }
+    }
+
+    /// Push an observation to every peer in the background
+    pub fn publish(&self, observation: HealthObservation) {
+        for peer in &self.peers {
+            let client = self.client.clone();
+            let url = format!("{}/internal/health", peer);
+            let observation = observation.clone();
+            tokio::spawn(async move {
+                match client.post(&url).json(&observation).send().await {
+                    Ok(response) if response.status().is_success() => {}
+                    Ok(response) => {
+                        debug!(
+                            "Peer {} rejected health observation: {}",
+                            url,
+                            response.status()
+                        )
+                    }
+                    Err(e) => warn!("Failed to gossip health observation to {}: {}", url, e),
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_observation_wire_format() {
+        let passive: HealthObservation =
+            serde_json::from_str(r#"{"service": "service_8100"}"#).unwrap();
+        assert_eq!(passive.service, "service_8100");
+        assert_eq!(passive.ejected_secs, None);
+
+        let outlier = HealthObservation {
+            service: "service_8101".to_string(),
+            ejected_secs: Some(30),
+        };
+        assert_eq!(
+            serde_json::to_string(&outlier).unwrap(),
+            r#"{"service":"service_8101","ejected_secs":30}"#
+        );
+    }
+}
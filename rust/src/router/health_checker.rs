@@ -1,21 +1,30 @@
 //! Health check manager
 
-use crate::router::service_instance::ServiceInstance;
+use crate::router::service_instance::{HealthCheckMode, ServiceInstance};
+use crate::utils::egress::with_upstream_proxy;
 use reqwest::Client;
-use std::time::Duration;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
 use tracing::warn;
 
 /// Health checker
 pub struct HealthChecker {
     client: Client,
-    #[allow(dead_code)]
     timeout: Duration,
     pub max_errors: u32,
+    /// Fraction of the check interval over which a round of checks is spread
+    spread: f64,
+    /// Bounds the number of checks in flight at once
+    permits: Semaphore,
 }
 
 impl HealthChecker {
-    pub fn new(timeout: Duration, max_errors: u32) -> Self {
-        let client = Client::builder()
+    pub fn new(timeout: Duration, max_errors: u32, spread: f64, concurrency: usize) -> Self {
+        let client = with_upstream_proxy(Client::builder())
             .timeout(timeout)
             .build()
             .expect("Failed to create health check HTTP client");
@@ -24,49 +33,137 @@ impl HealthChecker {
             client,
             timeout,
             max_errors,
+            spread: spread.clamp(0.0, 1.0),
+            permits: Semaphore::new(concurrency.max(1)),
         }
     }
 
-    /// Perform health check on a service instance using babysitter URL
-    pub async fn check_health(&self, service: &ServiceInstance) -> bool {
-        let check_url = format!("{}/health", service.babysitter_url);
-
-        let start_time = std::time::Instant::now();
-
-        match self.client.get(&check_url).send().await {
-            Ok(response) => {
-                let response_time = start_time.elapsed().as_secs_f64();
-                *service.response_time.write().await = response_time;
-                *service.last_check.write().await = crate::utils::time::current_timestamp();
+    /// Check every service once, staggering the checks across `interval` so hundreds of
+    /// backends are not probed in one burst
+    pub async fn check_all(&self, services: &[ServiceInstance], interval: Duration) -> Vec<bool> {
+        let window = interval.mul_f64(self.spread);
+        futures::future::join_all(services.iter().map(|service| async move {
+            tokio::time::sleep(spread_offset(&service.name, window)).await;
+            let _permit = self.permits.acquire().await;
+            self.check_health(service).await
+        }))
+        .await
+    }
 
-                if response.status().is_success() {
-                    service.set_healthy(true).await;
-                    *service.error_count.write().await = 0;
-                    true
-                } else {
-                    service.set_healthy(false).await;
-                    let mut error_count = service.error_count.write().await;
-                    *error_count += 1;
-                    false
+    /// Perform a health check on a service instance according to its `health_check` mode
+    pub async fn check_health(&self, service: &ServiceInstance) -> bool {
+        match service.health_check_mode() {
+            // Health follows the registry's heartbeat view, applied on each registry sync
+            HealthCheckMode::None => service.is_healthy(),
+            HealthCheckMode::Tcp => {
+                let start_time = Instant::now();
+                let connect = TcpStream::connect((service.host.as_str(), service.port));
+                let outcome = match tokio::time::timeout(self.timeout, connect).await {
+                    Ok(Ok(_)) => Ok(()),
+                    Ok(Err(e)) => Err(format!("TCP connect failed: {}", e)),
+                    Err(_) => Err("TCP connect timed out".to_string()),
+                };
+                if let Err(message) = &outcome {
+                    warn!(
+                        "Health check failed for service {}: {}",
+                        service.name, message
+                    );
                 }
+                self.record_outcome(service, Some(start_time.elapsed()), outcome)
+                    .await
             }
-            Err(e) => {
-                warn!(
-                    "Health check failed for service {} (babysitter: {}): {}",
-                    service.name, service.babysitter_url, e
-                );
-                service.set_healthy(false).await;
-                let mut error_count = service.error_count.write().await;
-                *error_count += 1;
-                *service.last_check.write().await = crate::utils::time::current_timestamp();
-                false
+            HealthCheckMode::Http => {
+                let check_url = service.health_check_url();
+                let start_time = Instant::now();
+                let (latency, outcome) = match self.client.get(&check_url).send().await {
+                    Ok(response) if service.is_healthy_status(response.status().as_u16()) => {
+                        (Some(start_time.elapsed()), Ok(()))
+                    }
+                    Ok(response) => (
+                        Some(start_time.elapsed()),
+                        Err(format!("Health check returned {}", response.status())),
+                    ),
+                    Err(e) => {
+                        warn!(
+                            "Health check failed for service {} ({}): {}",
+                            service.name, check_url, e
+                        );
+                        (None, Err(format!("Health check failed: {}", e)))
+                    }
+                };
+                self.record_outcome(service, latency, outcome).await
             }
         }
     }
 
+    /// Apply a probe result to the service's health, error count and history
+    async fn record_outcome(
+        &self,
+        service: &ServiceInstance,
+        latency: Option<Duration>,
+        outcome: Result<(), String>,
+    ) -> bool {
+        if let Some(latency) = latency {
+            service.health_latency.record(latency);
+        }
+        service
+            .last_check
+            .store(crate::utils::time::current_timestamp());
+
+        let healthy = outcome.is_ok();
+        service.record_health_check(healthy, latency, outcome.err());
+        service.set_healthy(healthy);
+        if healthy {
+            service.error_count.store(0, Ordering::Relaxed);
+            service.end_passive_ejection();
+        } else {
+            service.error_count.fetch_add(1, Ordering::Relaxed);
+        }
+        healthy
+    }
+
     /// Check if service should be marked unhealthy based on error count
     #[allow(dead_code)]
     pub fn should_mark_unhealthy(&self, error_count: u32) -> bool {
         error_count >= self.max_errors
     }
 }
+
+/// Stable per-service delay within `window`, so each service keeps a regular period while
+/// different services are checked at different points of the interval
+fn spread_offset(name: &str, window: Duration) -> Duration {
+    if window.is_zero() {
+        return Duration::ZERO;
+    }
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let fraction = (hasher.finish() % 10_000) as f64 / 10_000.0;
+    window.mul_f64(fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spread_offset() {
+        let window = Duration::from_secs(30);
+        assert_eq!(spread_offset("svc-1", Duration::ZERO), Duration::ZERO);
+        assert_eq!(
+            spread_offset("svc-1", window),
+            spread_offset("svc-1", window)
+        );
+
+        let offsets: Vec<_> = (0..100)
+            .map(|i| spread_offset(&format!("svc-{}", i), window))
+            .collect();
+        assert!(offsets.iter().all(|offset| *offset < window));
+        // Offsets cover the window rather than clustering at one point
+        assert!(offsets
+            .iter()
+            .any(|offset| *offset < Duration::from_secs(10)));
+        assert!(offsets
+            .iter()
+            .any(|offset| *offset > Duration::from_secs(20)));
+    }
+}
@@ -0,0 +1,131 @@
+//! Latency histograms for service instances
+
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Highest latency tracked precisely (1 hour, in milliseconds); larger values are clamped
+const MAX_TRACKED_MS: u64 = 3_600_000;
+
+/// Percentile summary of a latency histogram (milliseconds)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+/// Thread-safe HDR histogram of latencies with millisecond resolution
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    histogram: Mutex<Histogram<u64>>,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            histogram: Mutex::new(
+                Histogram::new_with_bounds(1, MAX_TRACKED_MS, 3).expect("valid histogram bounds"),
+            ),
+        }
+    }
+
+    /// Record one observation
+    pub fn record(&self, latency: Duration) {
+        let ms = (latency.as_millis() as u64).clamp(1, MAX_TRACKED_MS);
+        self.histogram.lock().unwrap().saturating_record(ms);
+    }
+
+    /// Forget all observations
+    pub fn reset(&self) {
+        self.histogram.lock().unwrap().reset();
+    }
+
+    /// Current p50/p95/p99/max
+    pub fn summary(&self) -> LatencySummary {
+        let histogram = self.histogram.lock().unwrap();
+        if histogram.is_empty() {
+            return LatencySummary::default();
+        }
+        LatencySummary {
+            count: histogram.len(),
+            p50_ms: histogram.value_at_quantile(0.50),
+            p95_ms: histogram.value_at_quantile(0.95),
+            p99_ms: histogram.value_at_quantile(0.99),
+            max_ms: histogram.max(),
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Latency histograms keyed by model
+#[derive(Debug, Default)]
+pub struct ModelLatencies {
+    models: Mutex<HashMap<String, Arc<LatencyHistogram>>>,
+}
+
+impl ModelLatencies {
+    /// Histogram for `model`, created on first use
+    pub fn histogram(&self, model: &str) -> Arc<LatencyHistogram> {
+        self.models
+            .lock()
+            .unwrap()
+            .entry(model.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Summary per model, sorted by model name
+    pub fn summaries(&self) -> BTreeMap<String, LatencySummary> {
+        self.models
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(model, histogram)| (model.clone(), histogram.summary()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let histogram = LatencyHistogram::new();
+        assert_eq!(histogram.summary().count, 0);
+
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.p50_ms, 50);
+        assert_eq!(summary.p95_ms, 95);
+        assert_eq!(summary.p99_ms, 99);
+        assert_eq!(summary.max_ms, 100);
+    }
+
+    #[test]
+    fn test_model_latencies() {
+        let latencies = ModelLatencies::default();
+        latencies.histogram("b").record(Duration::from_millis(20));
+        latencies.histogram("a").record(Duration::from_millis(10));
+        latencies.histogram("a").record(Duration::from_millis(30));
+
+        let summaries = latencies.summaries();
+        assert_eq!(summaries.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(summaries["a"].count, 2);
+        assert_eq!(summaries["a"].max_ms, 30);
+        assert_eq!(summaries["b"].count, 1);
+    }
+}
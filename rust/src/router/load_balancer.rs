@@ -1,38 +1,119 @@
 //! Load balancer implementation
 
+use crate::batch::manager::BatchManager;
 use crate::config::Config;
+use crate::models::aggregator::ModelListCache;
+use crate::proxy::audit::AuditLog;
+use crate::proxy::hooks::HookChain;
+use crate::proxy::priority::AdmissionQueue;
+use crate::proxy::slo::SloTracker;
 use crate::registry::client::RegistryClient;
+use crate::router::capabilities::{self, Requirements};
+use crate::router::gossip::{HealthGossip, HealthObservation};
 use crate::router::health_checker::HealthChecker;
+use crate::router::latency::ModelLatencies;
+use crate::router::outlier::OutlierCandidate;
 use crate::router::service_instance::ServiceInstance;
+use crate::router::session_store::SessionStore;
+use crate::router::session_table::SessionTable;
+use crate::router::slow_backends::{pool_median, FULL_WEIGHT_PERCENT};
+use crate::router::token_estimate::TokenEstimator;
 use crate::utils::errors::RouterError;
-use crate::utils::time::current_timestamp;
+use crate::utils::time::{current_timestamp, current_timestamp_secs};
+use arc_swap::ArcSwap;
+use dashmap::DashMap;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::{sleep, Duration};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
+
+/// Services added, updated or removed by one registry sync
+#[derive(Debug, Default, Serialize)]
+pub struct RegistrySyncDiff {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Requests routed to the other cache_type because none of the preferred type was available
+#[derive(Debug, Default)]
+pub struct CacheTypeFallbacks {
+    pub static_to_paged: AtomicU64,
+    pub paged_to_static: AtomicU64,
+}
+
+/// Index into non-empty `services` that weighted round-robin picks at `index`
+fn weighted_pick(
+    services: &[ServiceInstance],
+    index: usize,
+    weight: impl Fn(&ServiceInstance) -> u32,
+) -> usize {
+    // Summed in u64 so a few saturated weights cannot overflow
+    let total_weight: u64 = services.iter().map(|s| u64::from(weight(s))).sum();
+    if total_weight == 0 {
+        // Fallback to simple round-robin
+        return index % services.len();
+    }
+
+    let target_weight = index as u64 % total_weight;
+    let mut current_weight = 0;
+    for (i, service) in services.iter().enumerate() {
+        current_weight += u64::from(weight(service));
+        if current_weight > target_weight {
+            return i;
+        }
+    }
+    0
+}
+
+/// Immutable view of the service table that routing reads without taking a lock
+type ServiceSnapshot = ArcSwap<Vec<ServiceInstance>>;
+
+/// Publish the service table for routing, ordered by name
+fn publish_snapshot(services: &DashMap<String, ServiceInstance>, snapshot: &ServiceSnapshot) {
+    let mut list: Vec<_> = services.iter().map(|entry| entry.value().clone()).collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    snapshot.store(Arc::new(list));
+}
 
 /// Load balancer for managing service instances
 pub struct LoadBalancer {
-    services: Arc<RwLock<HashMap<String, ServiceInstance>>>,
+    /// Authoritative service table; registry sync updates it entry by entry, so a large
+    /// catalog update never holds the whole table
+    services: Arc<DashMap<String, ServiceInstance>>,
+    /// Copy of the table republished after every change; routing reads only this
+    snapshot: Arc<ServiceSnapshot>,
     pub registry_url: Option<String>,
-    current_index: Arc<RwLock<usize>>,
+    current_index: AtomicUsize,
     health_check_interval: u64,
     registry_sync_interval: u64,
     service_removal_grace_period: u64,
-    #[allow(dead_code)]
     config: Config,
     health_checker: Arc<HealthChecker>,
     registry_client: Option<Arc<RegistryClient>>,
+    sessions: Arc<SessionStore>,
+    gossip: Arc<HealthGossip>,
+    model_cache: Arc<ModelListCache>,
+    batches: Arc<BatchManager>,
+    hooks: Arc<HookChain>,
+    audit: Option<Arc<AuditLog>>,
+    slo: Arc<SloTracker>,
+    first_token_latency: ModelLatencies,
+    token_estimator: TokenEstimator,
+    admission: AdmissionQueue,
+    cache_type_fallbacks: CacheTypeFallbacks,
     running: Arc<RwLock<bool>>,
 }
 
 impl LoadBalancer {
     /// Create a new load balancer
-    #[allow(clippy::too_many_arguments)]
     pub async fn new(config: &Config) -> Result<Self, RouterError> {
-        let mut services = HashMap::new();
+        let services = DashMap::new();
 
         // Add static services if configured
         if let Some(ref static_services) = config.static_services {
@@ -59,23 +140,105 @@ impl LoadBalancer {
         let health_checker = Arc::new(HealthChecker::new(
             Duration::from_secs(config.health_check_timeout),
             config.max_errors,
+            config.health_check_spread,
+            config.health_check_concurrency,
         ));
 
+        let sessions = SessionStore::new(SessionTable::new(
+            config.session_max_entries,
+            Duration::from_secs(config.session_ttl),
+        ))
+        .with_peers(config.peer_routers.clone(), config.peer_token.as_deref());
+        let sessions = match &config.session_redis_url {
+            #[cfg(feature = "redis")]
+            Some(url) => {
+                info!("Sharing session affinity via Redis");
+                sessions
+                    .with_redis(
+                        url,
+                        "infini-router:session:".to_string(),
+                        Duration::from_secs(config.session_ttl),
+                    )
+                    .await?
+            }
+            #[cfg(not(feature = "redis"))]
+            Some(_) => {
+                return Err(RouterError::ConfigError(
+                    "--session-redis-url requires building with the `redis` feature".to_string(),
+                ));
+            }
+            None => sessions,
+        };
+
+        let batches = BatchManager::new(
+            config.batch_dir.as_ref().map(PathBuf::from),
+            config.batch_concurrency,
+        )?;
+
+        let hooks = HookChain::from_specs(&config.proxy_hooks).map_err(RouterError::ConfigError)?;
+
+        let audit = match &config.audit_sink {
+            Some(sink) => {
+                info!(
+                    "Audit logging to {} (prompts: {})",
+                    sink, config.audit_prompts
+                );
+                Some(Arc::new(AuditLog::new(
+                    sink,
+                    &config.audit_prompts,
+                    config.audit_sample_rate,
+                    &config.audit_redact_patterns,
+                )?))
+            }
+            None => None,
+        };
+
+        let slo = SloTracker::new(
+            Duration::from_millis(config.slo_latency_ms),
+            config.slo_availability_target,
+            config.slo_latency_target,
+            &config.slo_windows,
+        )?;
+
         let registry_client = config
             .registry_url
             .as_ref()
-            .map(|url| Arc::new(RegistryClient::new(url.clone())));
+            .map(|url| RegistryClient::new(url.clone(), &config.registry_tls).map(Arc::new))
+            .transpose()?;
+
+        let snapshot = ServiceSnapshot::default();
+        publish_snapshot(&services, &snapshot);
 
         Ok(LoadBalancer {
-            services: Arc::new(RwLock::new(services)),
+            services: Arc::new(services),
+            snapshot: Arc::new(snapshot),
             registry_url: config.registry_url.clone(),
-            current_index: Arc::new(RwLock::new(0)),
+            current_index: AtomicUsize::new(0),
             health_check_interval: config.health_check_interval,
             registry_sync_interval: config.registry_sync_interval,
             service_removal_grace_period: config.service_removal_grace_period,
             config: config.clone(),
             health_checker,
             registry_client,
+            sessions: Arc::new(sessions),
+            gossip: Arc::new(HealthGossip::new(
+                &config.peer_routers,
+                config.peer_token.as_deref(),
+            )),
+            model_cache: Arc::new(ModelListCache::new(Duration::from_secs(
+                config.models_cache_ttl,
+            ))),
+            batches: Arc::new(batches),
+            hooks: Arc::new(hooks),
+            audit,
+            slo: Arc::new(slo),
+            first_token_latency: ModelLatencies::default(),
+            token_estimator: TokenEstimator::default(),
+            admission: AdmissionQueue::new(
+                config.priority_queue_size,
+                Duration::from_secs(config.priority_queue_timeout),
+            ),
+            cache_type_fallbacks: CacheTypeFallbacks::default(),
             running: Arc::new(RwLock::new(true)),
         })
     }
@@ -83,184 +246,186 @@ impl LoadBalancer {
     /// Get next healthy service using weighted round-robin
     #[allow(dead_code)]
     pub async fn get_next_healthy_service(&self) -> Option<ServiceInstance> {
-        let services = self.services.read().await;
-        let all_services: Vec<_> = services.values().cloned().collect();
-        drop(services); // Release the lock
-
-        // Check health status for all services
-        let health_checks: Vec<bool> =
-            futures::future::join_all(all_services.iter().map(|s| s.is_healthy())).await;
-
-        let healthy_services: Vec<_> = all_services
-            .into_iter()
-            .zip(health_checks)
-            .filter(|(_, healthy)| *healthy)
-            .map(|(service, _)| service)
+        let healthy_services: Vec<_> = self
+            .snapshot
+            .load()
+            .iter()
+            .filter(|service| service.is_healthy() && self.is_selectable(service))
+            .cloned()
             .collect();
 
         if healthy_services.is_empty() {
             error!("No healthy services available");
             return None;
         }
+        let healthy_services = self.prefer_local_zone(
+            self.prefer_unsaturated(self.prefer_unthrottled(self.prefer_stable(healthy_services))),
+        );
 
         // Weighted round-robin selection
-        let total_weight: u32 = healthy_services.iter().map(|s| s.weight).sum();
-        if total_weight == 0 {
-            // Fallback to simple round-robin
-            let mut index = self.current_index.write().await;
-            let service = healthy_services[*index % healthy_services.len()].clone();
-            *index += 1;
-            service.increment_request_count().await;
-            return Some(service);
-        }
-
-        // Weighted selection
-        let mut current_index = self.current_index.write().await;
-        let target_weight = (*current_index % total_weight as usize) as u32;
-        *current_index += 1;
-        drop(current_index); // Release the lock
-
-        let mut current_weight = 0;
-        for service in &healthy_services {
-            current_weight += service.weight;
-            if current_weight > target_weight {
-                service.increment_request_count().await;
-                return Some(service.clone());
-            }
-        }
-
-        // Fallback
-        let service = healthy_services[0].clone();
-        service.increment_request_count().await;
-        Some(service)
+        Some(self.next_weighted(&healthy_services))
     }
 
-    /// Get next healthy service by model ID
-    pub async fn get_next_healthy_service_by_model(
+    /// Healthy, selectable services for `model_id` on `endpoint` within `pool` with the
+    /// capabilities in `requirements`, optionally
+    /// limited to one `cache_type`, narrowed to the local zone when it has any.
+    /// Logs why the list is empty, as the selection functions always have.
+    pub fn route_candidates(
         &self,
         model_id: Option<&str>,
-    ) -> Option<ServiceInstance> {
-        let services = self.services.read().await;
-        let all_services: Vec<_> = services.values().cloned().collect();
-        drop(services); // Release the lock
-
-        // Check health status for all services
-        let health_checks: Vec<bool> =
-            futures::future::join_all(all_services.iter().map(|s| s.is_healthy())).await;
-
-        let mut healthy_services: Vec<_> = all_services
-            .into_iter()
-            .zip(health_checks)
-            .filter(|(_, healthy)| *healthy)
-            .map(|(service, _)| service)
+        requirements: &Requirements,
+        endpoint: &str,
+        pool: Option<&str>,
+        cache_type: Option<&str>,
+    ) -> Vec<ServiceInstance> {
+        let mut healthy_services: Vec<_> = self
+            .snapshot
+            .load()
+            .iter()
+            .filter(|service| {
+                service.is_healthy()
+                    && self.is_selectable(service)
+                    && service.supports_endpoint(endpoint)
+                    && service.serves_pool(pool)
+            })
+            .cloned()
             .collect();
 
+        // Filter by cache_type metadata
+        if let Some(cache_type) = cache_type {
+            healthy_services.retain(|service| {
+                service.metadata.get("cache_type").and_then(|v| v.as_str()) == Some(cache_type)
+            });
+            if healthy_services.is_empty() {
+                warn!(
+                    "No healthy services available with cache_type '{}'",
+                    cache_type
+                );
+                return healthy_services;
+            }
+        }
+
         // Filter by model if specified
         if let Some(model_id) = model_id {
-            let mut filtered_services = Vec::new();
-            for service in &healthy_services {
-                let models = service.models.read().await;
-                if models.contains(&model_id.to_string()) {
-                    filtered_services.push(service.clone());
+            healthy_services.retain(|service| service.supports_model(model_id));
+
+            if healthy_services.is_empty() {
+                match cache_type {
+                    Some(cache_type) => warn!(
+                        "No healthy services available for model '{}' with cache_type '{}'",
+                        model_id, cache_type
+                    ),
+                    None => warn!("No healthy services available for model '{}'", model_id),
                 }
+                return healthy_services;
             }
-            healthy_services = filtered_services;
+        }
 
+        // Filter by the capabilities the request needs
+        if !requirements.is_empty() {
+            healthy_services
+                .retain(|service| service.supports_requirements(model_id, requirements));
             if healthy_services.is_empty() {
-                warn!("No healthy services available for model '{}'", model_id);
-                return None;
+                warn!(
+                    "No healthy services available for model '{}' with capabilities: {}",
+                    model_id.unwrap_or("*"),
+                    requirements
+                );
+                return healthy_services;
             }
         }
 
         if healthy_services.is_empty() {
             error!("No healthy services available");
-            return None;
+            return healthy_services;
         }
+        self.prefer_local_zone(
+            self.prefer_unsaturated(self.prefer_unthrottled(self.prefer_stable(healthy_services))),
+        )
+    }
 
-        // Weighted round-robin selection (same as get_next_healthy_service)
-        let total_weight: u32 = healthy_services.iter().map(|s| s.weight).sum();
-        if total_weight == 0 {
-            let mut index = self.current_index.write().await;
-            let service = healthy_services[*index % healthy_services.len()].clone();
-            *index += 1;
-            service.increment_request_count().await;
-            return Some(service);
+    /// The service weighted round-robin picks next from `candidates`, without advancing
+    pub fn peek_weighted(&self, candidates: &[ServiceInstance]) -> Option<ServiceInstance> {
+        if candidates.is_empty() {
+            return None;
         }
+        let index = self.current_index.load(Ordering::Relaxed);
+        Some(candidates[weighted_pick(candidates, index, |s| self.routing_weight(s))].clone())
+    }
 
-        let mut current_index = self.current_index.write().await;
-        let target_weight = (*current_index % total_weight as usize) as u32;
-        *current_index += 1;
-        drop(current_index); // Release the lock
-
-        let mut current_weight = 0;
-        for service in &healthy_services {
-            current_weight += service.weight;
-            if current_weight > target_weight {
-                service.increment_request_count().await;
-                return Some(service.clone());
-            }
-        }
+    /// Weighted round-robin over non-empty `candidates`; counts the request on the pick
+    fn next_weighted(&self, candidates: &[ServiceInstance]) -> ServiceInstance {
+        let index = self.current_index.fetch_add(1, Ordering::Relaxed);
+        let service =
+            candidates[weighted_pick(candidates, index, |s| self.routing_weight(s))].clone();
 
-        let service = healthy_services[0].clone();
-        service.increment_request_count().await;
-        Some(service)
+        service.increment_request_count();
+        service
     }
 
-    /// Get service by session key using consistent hashing
-    /// Uses hash of session_key to deterministically map to a service from the available healthy services
-    /// This ensures the same session always routes to the same service (when healthy)
-    pub async fn get_service_by_session(
+    /// Get next healthy service by model ID among services serving the endpoint and `pool`
+    pub async fn get_next_healthy_service_by_model(
         &self,
-        session_key: &str,
         model_id: Option<&str>,
+        requirements: &Requirements,
+        endpoint: &str,
+        pool: Option<&str>,
     ) -> Option<ServiceInstance> {
-        // Get all healthy services that support the model
-        let services = self.services.read().await;
-        let all_services: Vec<_> = services.values().cloned().collect();
-        drop(services);
-
-        // Check health status for all services
-        let health_checks: Vec<bool> =
-            futures::future::join_all(all_services.iter().map(|s| s.is_healthy())).await;
-
-        let mut healthy_services: Vec<_> = all_services
-            .into_iter()
-            .zip(health_checks)
-            .filter(|(_, healthy)| *healthy)
-            .map(|(service, _)| service)
-            .collect();
-
-        // Filter by model if specified
-        if let Some(model_id) = model_id {
-            let mut filtered_services = Vec::new();
-            for service in &healthy_services {
-                let models = service.models.read().await;
-                if models.contains(&model_id.to_string()) {
-                    filtered_services.push(service.clone());
-                }
-            }
-            healthy_services = filtered_services;
-
-            if healthy_services.is_empty() {
-                warn!("No healthy services available for model '{}'", model_id);
-                return None;
-            }
+        let candidates = self.route_candidates(model_id, requirements, endpoint, pool, None);
+        if candidates.is_empty() {
+            return None;
         }
+        Some(self.next_weighted(&candidates))
+    }
 
-        if healthy_services.is_empty() {
-            error!("No healthy services available");
+    /// The service a session maps to among `candidates`, and whether it is already pinned
+    /// there (otherwise it is the service the session key hashes to)
+    pub async fn session_target(
+        &self,
+        session_key: &str,
+        candidates: &[ServiceInstance],
+    ) -> Option<(ServiceInstance, bool)> {
+        if candidates.is_empty() {
             return None;
         }
 
+        // Keep the session on its previous service if it can still serve it
+        if let Some(pinned) = self.sessions.get(session_key).await {
+            if let Some(service) = candidates.iter().find(|s| s.name == pinned) {
+                return Some((service.clone(), true));
+            }
+        }
+
         // Use hash of session_key to deterministically select a service
-        // This ensures the same session always routes to the same service
+        // This ensures the same session always routes to the same service; hashing over
+        // the routing weights sends fewer new sessions to backends reporting a queue
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
         session_key.hash(&mut hasher);
         let hash_value = hasher.finish();
-        let service_index = (hash_value as usize) % healthy_services.len();
+        let service_index =
+            weighted_pick(candidates, hash_value as usize, |s| self.routing_weight(s));
+        Some((candidates[service_index].clone(), false))
+    }
 
-        let selected_service = healthy_services[service_index].clone();
-        selected_service.increment_request_count().await;
+    /// Get service by session key
+    /// A session stays pinned to the service it was first routed to while that service is healthy;
+    /// new sessions are mapped by hashing session_key over the available healthy services
+    pub async fn get_service_by_session(
+        &self,
+        session_key: &str,
+        model_id: Option<&str>,
+        requirements: &Requirements,
+        endpoint: &str,
+        pool: Option<&str>,
+    ) -> Option<ServiceInstance> {
+        let candidates = self.route_candidates(model_id, requirements, endpoint, pool, None);
+        let (selected_service, pinned) = self.session_target(session_key, &candidates).await?;
+        if !pinned {
+            self.sessions
+                .insert(session_key, &selected_service.name)
+                .await;
+        }
+        selected_service.increment_request_count();
         Some(selected_service)
     }
 
@@ -271,122 +436,43 @@ impl LoadBalancer {
         &self,
         cache_type: &str,
         model_id: Option<&str>,
+        requirements: &Requirements,
+        endpoint: &str,
+        pool: Option<&str>,
     ) -> Option<ServiceInstance> {
-        // Get all healthy services
-        let services = self.services.read().await;
-        let all_services: Vec<_> = services.values().cloned().collect();
-        drop(services);
-
-        // Check health status for all services
-        let health_checks: Vec<bool> =
-            futures::future::join_all(all_services.iter().map(|s| s.is_healthy())).await;
-
-        let mut healthy_services: Vec<_> = all_services
-            .into_iter()
-            .zip(health_checks)
-            .filter(|(_, healthy)| *healthy)
-            .map(|(service, _)| service)
-            .collect();
-
-        // Filter by cache_type metadata
-        let mut filtered_services = Vec::new();
-        for service in &healthy_services {
-            if let Some(metadata_cache_type) = service
-                .metadata
-                .get("cache_type")
-                .and_then(|v| v.as_str())
-            {
-                if metadata_cache_type == cache_type {
-                    filtered_services.push(service.clone());
-                }
-            }
-        }
-        healthy_services = filtered_services;
-
-        if healthy_services.is_empty() {
-            warn!(
-                "No healthy services available with cache_type '{}'",
-                cache_type
-            );
+        let candidates =
+            self.route_candidates(model_id, requirements, endpoint, pool, Some(cache_type));
+        if candidates.is_empty() {
             return None;
         }
-
-        // Filter by model if specified
-        if let Some(model_id) = model_id {
-            let mut filtered_services = Vec::new();
-            for service in &healthy_services {
-                let models = service.models.read().await;
-                if models.contains(&model_id.to_string()) {
-                    filtered_services.push(service.clone());
-                }
-            }
-            healthy_services = filtered_services;
-
-            if healthy_services.is_empty() {
-                warn!(
-                    "No healthy services available for model '{}' with cache_type '{}'",
-                    model_id, cache_type
-                );
-                return None;
-            }
-        }
-
-        // Weighted round-robin selection
-        let total_weight: u32 = healthy_services.iter().map(|s| s.weight).sum();
-        if total_weight == 0 {
-            let mut index = self.current_index.write().await;
-            let service = healthy_services[*index % healthy_services.len()].clone();
-            *index += 1;
-            service.increment_request_count().await;
-            return Some(service);
-        }
-
-        let mut current_index = self.current_index.write().await;
-        let target_weight = (*current_index % total_weight as usize) as u32;
-        *current_index += 1;
-        drop(current_index); // Release the lock
-
-        let mut current_weight = 0;
-        for service in &healthy_services {
-            current_weight += service.weight;
-            if current_weight > target_weight {
-                service.increment_request_count().await;
-                return Some(service.clone());
-            }
-        }
-
-        let service = healthy_services[0].clone();
-        service.increment_request_count().await;
-        Some(service)
+        Some(self.next_weighted(&candidates))
     }
 
     /// Start health check background task
     pub async fn start_health_checks(&self) {
-        let services = self.services.clone();
+        let snapshot = self.snapshot.clone();
         let health_checker = self.health_checker.clone();
         let interval = self.health_check_interval;
         let running = self.running.clone();
+        let sessions = self.sessions.clone();
 
         info!("Health check task started (interval: {}s)", interval);
 
         std::mem::drop(tokio::spawn(async move {
             while *running.read().await {
-                let services_clone = services.clone();
+                let expired = sessions.local().purge_expired();
+                if expired > 0 {
+                    info!("Expired {} idle session affinity entries", expired);
+                }
+
+                let services_list = snapshot.load_full();
                 let health_checker_clone = health_checker.clone();
 
                 std::mem::drop(tokio::spawn(async move {
-                    let services_guard = services_clone.read().await;
-                    let services_list: Vec<_> = services_guard.values().cloned().collect();
-                    drop(services_guard);
-
                     if !services_list.is_empty() {
-                        // Perform health checks in parallel
-                        let health_results: Vec<bool> =
-                            futures::future::join_all(services_list.iter().map(|service| {
-                                let health_checker = health_checker_clone.clone();
-                                let service = service.clone();
-                                async move { health_checker.check_health(&service).await }
-                            }))
+                        // Perform health checks in parallel, staggered across the interval
+                        let health_results = health_checker_clone
+                            .check_all(&services_list, Duration::from_secs(interval))
                             .await;
 
                         let healthy_count = health_results.iter().filter(|&&h| h).count();
@@ -397,9 +483,9 @@ impl LoadBalancer {
                         );
 
                         // Log unhealthy services
-                        for service in &services_list {
-                            let error_count = *service.error_count.read().await;
-                            let is_healthy = service.is_healthy().await;
+                        for service in services_list.iter() {
+                            let error_count = service.error_count();
+                            let is_healthy = service.is_healthy();
                             if !is_healthy && error_count >= health_checker_clone.max_errors {
                                 warn!(
                                     "Service {} is unhealthy (errors: {})",
@@ -426,140 +512,32 @@ impl LoadBalancer {
         };
 
         let services = self.services.clone();
+        let snapshot = self.snapshot.clone();
         let interval = self.registry_sync_interval;
         let grace_period = self.service_removal_grace_period;
         let running = self.running.clone();
+        let model_cache = self.model_cache.clone();
 
         info!("Registry sync task started (interval: {}s)", interval);
 
         std::mem::drop(tokio::spawn(async move {
             while *running.read().await {
                 let services_clone = services.clone();
+                let snapshot = snapshot.clone();
                 let registry_client_clone = registry_client.clone();
+                let model_cache = model_cache.clone();
 
                 std::mem::drop(tokio::spawn(async move {
-                    match registry_client_clone.fetch_services(true).await {
-                        Ok(registry_response) => {
-                            let mut services_guard = services_clone.write().await;
-                            let current_time = current_timestamp();
-                            let registry_service_names: std::collections::HashSet<String> =
-                                registry_response
-                                    .services
-                                    .iter()
-                                    .map(|s| s.name.clone())
-                                    .collect();
-
-                            // Update or add services from registry
-                            for registry_service in registry_response.services {
-                                // Only add services that are OpenAI API services
-                                let service_metadata = registry_service.metadata.clone();
-                                if !service_metadata
-                                    .get("type")
-                                    .and_then(|v| v.as_str())
-                                    .map(|s| s == "openai-api")
-                                    .unwrap_or(false)
-                                {
-                                    continue;
-                                }
-
-                                let service_name = registry_service.name.clone();
-
-                                if let Some(existing_service) =
-                                    services_guard.get_mut(&service_name)
-                                {
-                                    // Update existing service
-                                    existing_service.host = registry_service.host.clone();
-                                    existing_service.port = registry_service.port;
-                                    existing_service.url = registry_service.url.clone();
-                                    existing_service
-                                        .set_healthy(registry_service.is_healthy)
-                                        .await;
-                                    existing_service.metadata = service_metadata.clone();
-                                    existing_service.update_last_seen().await;
-
-                                    // Update models from metadata
-                                    let models: Vec<String> = service_metadata
-                                        .get("models")
-                                        .and_then(|v| v.as_array())
-                                        .map(|arr| {
-                                            arr.iter()
-                                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                                .collect()
-                                        })
-                                        .unwrap_or_default();
-                                    *existing_service.models.write().await = models;
-
-                                    // Update babysitter URL
-                                    let babysitter_port = existing_service.port + 1;
-                                    existing_service.babysitter_url = format!(
-                                        "http://{}:{}",
-                                        existing_service.host, babysitter_port
-                                    );
-                                } else {
-                                    // Add new service from registry
-                                    let models: Vec<String> = service_metadata
-                                        .get("models")
-                                        .and_then(|v| v.as_array())
-                                        .map(|arr| {
-                                            arr.iter()
-                                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                                .collect()
-                                        })
-                                        .unwrap_or_default();
-
-                                    let models_for_log = models.clone();
-
-                                    let new_service = ServiceInstance::new(
-                                        registry_service.name.clone(),
-                                        registry_service.host.clone(),
-                                        registry_service.port,
-                                        registry_service.weight,
-                                        service_metadata,
-                                    );
-
-                                    *new_service.models.write().await = models;
-                                    new_service.set_healthy(registry_service.is_healthy).await;
-                                    new_service.update_last_seen().await;
-
-                                    info!(
-                                        "Added OpenAI API service from registry: {} at {} (babysitter: {}, models: {:?})",
-                                        new_service.name, new_service.url, new_service.babysitter_url, models_for_log
-                                    );
-
-                                    services_guard.insert(service_name, new_service);
-                                }
-                            }
-
-                            // Remove services that are no longer in registry (but keep static services)
-                            let mut services_to_remove = Vec::new();
-                            for (name, service) in services_guard.iter() {
-                                if !registry_service_names.contains(name) {
-                                    let is_static = service
-                                        .metadata
-                                        .get("static")
-                                        .and_then(|v| v.as_bool())
-                                        .unwrap_or(false);
-                                    if !is_static {
-                                        let last_seen = *service.last_seen.read().await;
-                                        let time_since_last_seen = current_time - last_seen;
-                                        if time_since_last_seen >= grace_period as f64 {
-                                            services_to_remove.push(name.clone());
-                                        }
-                                    }
-                                }
-                            }
-
-                            for service_name in services_to_remove {
-                                services_guard.remove(&service_name);
-                                info!(
-                                    "Removed service from registry (after {}s grace period): {}",
-                                    grace_period, service_name
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            warn!("Failed to sync with registry: {}", e);
-                        }
+                    if let Err(e) = Self::sync_once(
+                        &services_clone,
+                        &snapshot,
+                        &registry_client_clone,
+                        grace_period,
+                        &model_cache,
+                    )
+                    .await
+                    {
+                        warn!("Failed to sync with registry: {}", e);
                     }
                 }));
 
@@ -568,6 +546,171 @@ impl LoadBalancer {
         }));
     }
 
+    /// Run one registry sync cycle now instead of waiting for the next interval
+    pub async fn sync_registry(&self) -> Result<RegistrySyncDiff, RouterError> {
+        let registry_client = self
+            .registry_client
+            .as_ref()
+            .ok_or_else(|| RouterError::ConfigError("No registry URL configured".to_string()))?;
+        Ok(Self::sync_once(
+            &self.services,
+            &self.snapshot,
+            registry_client,
+            self.service_removal_grace_period,
+            &self.model_cache,
+        )
+        .await?)
+    }
+
+    /// Fetch the registry's services and reconcile the local service map with them
+    async fn sync_once(
+        services: &DashMap<String, ServiceInstance>,
+        snapshot: &ServiceSnapshot,
+        registry_client: &RegistryClient,
+        grace_period: u64,
+        model_cache: &ModelListCache,
+    ) -> anyhow::Result<RegistrySyncDiff> {
+        let registry_response = registry_client.fetch_healthy_services().await?;
+        let current_time = current_timestamp();
+        let mut diff = RegistrySyncDiff::default();
+        // Whether anything affecting the aggregated model list changed
+        let mut models_changed = false;
+        let registry_service_names: std::collections::HashSet<String> = registry_response
+            .services
+            .iter()
+            .map(|s| s.name.clone())
+            .collect();
+
+        // Update or add services from registry
+        for registry_service in registry_response.services {
+            // Only add services that are OpenAI API services
+            let service_metadata = registry_service.metadata.clone();
+            if !service_metadata
+                .get("type")
+                .and_then(|v| v.as_str())
+                .map(|s| s == "openai-api")
+                .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let service_name = registry_service.name.clone();
+
+            if let Some(mut existing_service) = services.get_mut(&service_name) {
+                // Update existing service
+                let health_changed = existing_service.is_healthy() != registry_service.is_healthy;
+                if health_changed
+                    || ["models", "models_list"].iter().any(|key| {
+                        existing_service.metadata.get(*key) != service_metadata.get(*key)
+                    })
+                {
+                    models_changed = true;
+                }
+                if health_changed
+                    || existing_service.url != registry_service.url
+                    || existing_service.metadata != service_metadata
+                {
+                    diff.updated.push(service_name.clone());
+                }
+                existing_service.host = registry_service.host.clone();
+                existing_service.port = registry_service.port;
+                existing_service.url = registry_service.url.clone();
+                existing_service.set_healthy(registry_service.is_healthy);
+                existing_service.metadata = service_metadata.clone();
+                existing_service.update_last_seen();
+
+                // Update models from metadata
+                let models: Vec<String> = service_metadata
+                    .get("models")
+                    .and_then(|v| v.as_array())
+                    .map(|arr| {
+                        arr.iter()
+                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                existing_service.set_models(models);
+
+                // Update babysitter URL
+                existing_service.babysitter_url = ServiceInstance::babysitter_url_for(
+                    &existing_service.host,
+                    existing_service.port,
+                    &existing_service.metadata,
+                );
+            } else {
+                // Add new service from registry
+                let models: Vec<String> = service_metadata
+                    .get("models")
+                    .and_then(|v| v.as_array())
+                    .map(|arr| {
+                        arr.iter()
+                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                let models_for_log = models.clone();
+
+                let new_service = ServiceInstance::new(
+                    registry_service.name.clone(),
+                    registry_service.host.clone(),
+                    registry_service.port,
+                    registry_service.weight,
+                    service_metadata,
+                );
+
+                new_service.set_models(models);
+                new_service.set_healthy(registry_service.is_healthy);
+                new_service.update_last_seen();
+
+                info!(
+                    "Added OpenAI API service from registry: {} at {} (babysitter: {}, models: {:?})",
+                    new_service.name, new_service.url, new_service.babysitter_url, models_for_log
+                );
+
+                diff.added.push(service_name.clone());
+                services.insert(service_name, new_service);
+                models_changed = true;
+            }
+        }
+
+        // Remove services that are no longer in registry (but keep static services)
+        let mut services_to_remove = Vec::new();
+        for entry in services.iter() {
+            let (name, service) = entry.pair();
+            if !registry_service_names.contains(name) {
+                let is_static = service
+                    .metadata
+                    .get("static")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                if !is_static {
+                    let last_seen = service.last_seen.load();
+                    let time_since_last_seen = current_time - last_seen;
+                    if time_since_last_seen >= grace_period as f64 {
+                        services_to_remove.push(name.clone());
+                    }
+                }
+            }
+        }
+
+        for service_name in services_to_remove {
+            services.remove(&service_name);
+            models_changed = true;
+            info!(
+                "Removed service from registry (after {}s grace period): {}",
+                grace_period, service_name
+            );
+            diff.removed.push(service_name);
+        }
+
+        publish_snapshot(services, snapshot);
+        if models_changed {
+            model_cache.invalidate();
+        }
+        Ok(diff)
+    }
+
     /// Stop background tasks
     #[allow(dead_code)]
     pub async fn stop(&self) {
@@ -575,9 +718,744 @@ impl LoadBalancer {
         *running = false;
     }
 
+    /// Passive health: count a failed proxy attempt and, after
+    /// `passive_failure_threshold` consecutive failures, eject the service and start
+    /// recovery probes
+    pub async fn report_proxy_failure(&self, service: &ServiceInstance) {
+        service.increment_error_count();
+        let failures = service.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < self.config.passive_failure_threshold.max(1) {
+            return;
+        }
+        if service.eject_passively() {
+            warn!(
+                "Ejecting service {} after {} consecutive proxy failure(s)",
+                service.name, failures
+            );
+            self.gossip.publish(HealthObservation {
+                service: service.name.clone(),
+                ejected_secs: None,
+            });
+        }
+        let half_open_interval = self.config.half_open_interval;
+        if half_open_interval > 0 {
+            service.schedule_half_open_probe(Duration::from_secs(half_open_interval));
+        }
+        self.spawn_recovery_probes(service.clone());
+    }
+
+    /// Apply an ejection gossiped by a peer router (not gossiped further). Passive
+    /// failures take the service out until this replica's own recovery probes pass.
+    /// Peers are held to the local outlier limits: ejections last at most the longest
+    /// local ejection, and never take more than `max_ejection_percent` of the pool out.
+    pub async fn apply_peer_observation(&self, observation: &HealthObservation) {
+        let Some(service) = self.get_service(&observation.service).await else {
+            debug!(
+                "Ignoring peer health observation for unknown service {}",
+                observation.service
+            );
+            return;
+        };
+        let detection = &self.config.outlier_detection;
+        let services = self.snapshot.load();
+        let out = services
+            .iter()
+            .filter(|s| s.is_ejected() || s.is_passively_ejected() || !s.is_healthy())
+            .count();
+        let in_rotation =
+            !service.is_ejected() && !service.is_passively_ejected() && service.is_healthy();
+        if in_rotation && out >= detection.max_ejected(services.len()) {
+            warn!(
+                "Ignoring peer ejection of service {}: {} of {} services already out of rotation",
+                service.name,
+                out,
+                services.len()
+            );
+            return;
+        }
+        match observation.ejected_secs {
+            Some(secs) => {
+                let secs = secs.min(detection.max_ejection_time().as_secs());
+                let until = current_timestamp_secs().saturating_add(secs);
+                if service.ejected_until.fetch_max(until, Ordering::Relaxed) < until {
+                    info!(
+                        "Ejecting service {} for {}s as reported by a peer router",
+                        service.name, secs
+                    );
+                }
+            }
+            None => {
+                if service.eject_passively() {
+                    warn!(
+                        "Ejecting service {} after proxy failures reported by a peer router",
+                        service.name
+                    );
+                    self.spawn_recovery_probes(service);
+                }
+            }
+        }
+    }
+
+    /// Passive health: a successful proxied request ends the failure streak, and a
+    /// successful half-open probe puts the service back in rotation
+    pub async fn report_proxy_success(&self, service: &ServiceInstance) {
+        service.consecutive_failures.store(0, Ordering::Relaxed);
+        if service.half_open_at.load(Ordering::Relaxed) != 0 && service.end_passive_ejection() {
+            info!(
+                "Service {} served a half-open probe request, back in rotation",
+                service.name
+            );
+        }
+    }
+
+    /// A service taken out by proxy failures whose half-open probe is due, claimed to
+    /// take this request for `model_id` on `endpoint` within `pool`
+    pub async fn half_open_probe(
+        &self,
+        model_id: Option<&str>,
+        requirements: &Requirements,
+        endpoint: &str,
+        pool: Option<&str>,
+    ) -> Option<ServiceInstance> {
+        let interval = Duration::from_secs(self.config.half_open_interval);
+        if interval.is_zero() {
+            return None;
+        }
+        for service in self.snapshot.load().iter() {
+            if service.half_open_at.load(Ordering::Relaxed) == 0
+                || !service.is_passively_ejected()
+                || !service.is_healthy()
+                || service.is_ejected()
+                || service.is_draining()
+                || !service.supports_endpoint(endpoint)
+                || !service.serves_pool(pool)
+            {
+                continue;
+            }
+            if model_id.is_some_and(|model_id| !service.supports_model(model_id))
+                || !service.supports_requirements(model_id, requirements)
+            {
+                continue;
+            }
+            if service.claim_half_open_probe(interval) {
+                info!(
+                    "Sending a half-open probe request to service {}",
+                    service.name
+                );
+                service.increment_request_count();
+                return Some(service.clone());
+            }
+        }
+        None
+    }
+
+    /// Probe an ejected service every `recovery_probe_interval` seconds until it passes,
+    /// so a transient blip does not bench it until the next periodic health check
+    fn spawn_recovery_probes(&self, service: ServiceInstance) {
+        let probe_interval = Duration::from_secs(self.config.recovery_probe_interval);
+        if probe_interval.is_zero() || service.recovering.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let health_checker = self.health_checker.clone();
+        // Past one health check interval the periodic checks take over
+        let deadline = std::time::Instant::now() + Duration::from_secs(self.health_check_interval);
+
+        tokio::spawn(async move {
+            while std::time::Instant::now() < deadline {
+                sleep(probe_interval).await;
+                if !service.is_passively_ejected() {
+                    break;
+                }
+                if health_checker.check_health(&service).await {
+                    info!(
+                        "Service {} passed a recovery probe, back in rotation",
+                        service.name
+                    );
+                    service.consecutive_failures.store(0, Ordering::Relaxed);
+                    service.end_passive_ejection();
+                    break;
+                }
+            }
+            service.recovering.store(false, Ordering::Release);
+        });
+    }
+
+    /// Start the outlier detection task, if enabled
+    pub async fn start_outlier_detection(&self) {
+        let detection = self.config.outlier_detection.clone();
+        if !detection.enabled() {
+            return;
+        }
+        let snapshot = self.snapshot.clone();
+        let running = self.running.clone();
+        let gossip = self.gossip.clone();
+
+        info!(
+            "Outlier detection started (interval: {}s, error ratio: {})",
+            detection.interval, detection.error_ratio
+        );
+
+        std::mem::drop(tokio::spawn(async move {
+            while *running.read().await {
+                sleep(Duration::from_secs(detection.interval)).await;
+
+                let all_services = snapshot.load_full();
+                let now = current_timestamp_secs();
+                let mut candidates = Vec::new();
+                let mut ejected = 0;
+                for service in all_services.iter() {
+                    let ejected_until = service.ejected_until.load(Ordering::Relaxed);
+                    if service.is_ejected() {
+                        ejected += 1;
+                    } else if ejected_until + detection.window.as_secs() > now {
+                        // Back from ejection: wait for a window of fresh traffic before judging
+                        continue;
+                    } else if service.is_healthy() && !service.is_passively_ejected() {
+                        let window = service.rolling.window(detection.window);
+                        candidates.push(OutlierCandidate {
+                            name: service.name.clone(),
+                            requests: window.requests,
+                            errors: window.errors,
+                        });
+                    }
+                }
+
+                let outliers = detection.find_outliers(&candidates, all_services.len(), ejected);
+                for service in all_services.iter() {
+                    if service.is_ejected() {
+                        continue;
+                    }
+                    if outliers.contains(&service.name) {
+                        let ejections = service.ejections.fetch_add(1, Ordering::Relaxed) + 1;
+                        let ejection_time = detection.ejection_time(ejections);
+                        service
+                            .ejected_until
+                            .store(now + ejection_time.as_secs(), Ordering::Relaxed);
+                        warn!(
+                            "Ejecting outlier service {} for {}s (ejection #{})",
+                            service.name,
+                            ejection_time.as_secs(),
+                            ejections
+                        );
+                        gossip.publish(HealthObservation {
+                            service: service.name.clone(),
+                            ejected_secs: Some(ejection_time.as_secs()),
+                        });
+                    } else if candidates
+                        .iter()
+                        .any(|c| c.name == service.name && c.requests >= detection.min_requests)
+                    {
+                        // A pass with enough traffic and no outlier verdict resets the backoff
+                        service.ejections.store(0, Ordering::Relaxed);
+                    }
+                }
+            }
+        }));
+    }
+
+    /// Start the task that deprioritizes chronically slow services, if enabled
+    pub async fn start_slow_backend_checks(&self) {
+        let policy = self.config.slow_backends.clone();
+        if !policy.enabled() {
+            return;
+        }
+        let snapshot = self.snapshot.clone();
+        let running = self.running.clone();
+
+        info!(
+            "Slow-backend checks started (interval: {}s, ratio: {})",
+            policy.interval.as_secs(),
+            policy.ratio
+        );
+
+        std::mem::drop(tokio::spawn(async move {
+            while *running.read().await {
+                sleep(policy.interval).await;
+
+                // Only services with enough fresh samples are judged; the rest keep
+                // accumulating until they have
+                let all_services = snapshot.load_full();
+                let judged: Vec<_> = all_services
+                    .iter()
+                    .filter_map(|service| {
+                        let summary = service.recent_latency.summary();
+                        (summary.count >= policy.min_samples.max(1))
+                            .then_some((service, summary.p95_ms))
+                    })
+                    .collect();
+                if judged.len() < 2 {
+                    continue;
+                }
+
+                let p95s: Vec<u64> = judged.iter().map(|(_, p95)| *p95).collect();
+                let median = pool_median(&p95s);
+                for (service, p95) in judged {
+                    let percent = policy.weight_percent(p95, median);
+                    let previous = service.weight_percent.swap(percent, Ordering::Relaxed);
+                    if percent < FULL_WEIGHT_PERCENT && previous == FULL_WEIGHT_PERCENT {
+                        warn!(
+                            "Service {} is slow (p95 {}ms, pool median {}ms): weight reduced to {}%",
+                            service.name, p95, median, percent
+                        );
+                    } else if percent == FULL_WEIGHT_PERCENT && previous < FULL_WEIGHT_PERCENT {
+                        info!(
+                            "Service {} recovered (p95 {}ms, pool median {}ms): full weight restored",
+                            service.name, p95, median
+                        );
+                    }
+                    service.recent_latency.reset();
+                }
+            }
+        }));
+    }
+
     /// Get all services
     pub async fn get_all_services(&self) -> Vec<ServiceInstance> {
-        let services = self.services.read().await;
-        services.values().cloned().collect()
+        self.services
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Look up a service by name
+    pub async fn get_service(&self, name: &str) -> Option<ServiceInstance> {
+        self.services.get(name).map(|entry| entry.value().clone())
+    }
+
+    /// Concurrency ceiling for a service: `max_concurrency` metadata, else the global setting
+    fn concurrency_limit(&self, service: &ServiceInstance) -> u32 {
+        service
+            .metadata
+            .get("max_concurrency")
+            .and_then(|v| v.as_u64())
+            .map(|limit| limit.min(u32::MAX as u64) as u32)
+            .unwrap_or(self.config.max_concurrency_per_service)
+    }
+
+    /// Whether the service can take another request (0 means unlimited)
+    fn has_capacity(&self, service: &ServiceInstance) -> bool {
+        let limit = self.concurrency_limit(service);
+        limit == 0 || service.in_flight_count() < limit
+    }
+
+    /// Leave out flapping services while any stable service is available
+    fn prefer_stable(&self, services: Vec<ServiceInstance>) -> Vec<ServiceInstance> {
+        let now = current_timestamp();
+        let stable: Vec<_> = services
+            .iter()
+            .filter(|service| !self.config.flapping.is_flapping(&service.metadata, now))
+            .cloned()
+            .collect();
+        if stable.is_empty() {
+            debug!("Only flapping services available");
+            services
+        } else {
+            stable
+        }
+    }
+
+    /// Leave out services backing off after a 429/503 while any other service is available
+    fn prefer_unthrottled(&self, services: Vec<ServiceInstance>) -> Vec<ServiceInstance> {
+        let unthrottled: Vec<_> = services
+            .iter()
+            .filter(|service| !service.is_throttled())
+            .cloned()
+            .collect();
+        if unthrottled.is_empty() {
+            debug!("Only throttled services available");
+            services
+        } else {
+            unthrottled
+        }
+    }
+
+    /// Leave out services whose backend reports a full queue or KV cache while any
+    /// other service is available
+    fn prefer_unsaturated(&self, services: Vec<ServiceInstance>) -> Vec<ServiceInstance> {
+        let unsaturated: Vec<_> = services
+            .iter()
+            .filter(|service| !self.config.backend_load.is_saturated(&service.metadata))
+            .cloned()
+            .collect();
+        if unsaturated.is_empty() {
+            debug!("Only saturated services available");
+            services
+        } else {
+            unsaturated
+        }
+    }
+
+    /// Weighted round-robin weight of a service, reduced by its backend's reported queue
+    fn routing_weight(&self, service: &ServiceInstance) -> u32 {
+        self.config
+            .backend_load
+            .routing_weight(service.effective_weight(), &service.metadata)
+    }
+
+    /// Narrow candidates to the router's zone, spilling to other zones only when no
+    /// same-zone service is available
+    fn prefer_local_zone(&self, services: Vec<ServiceInstance>) -> Vec<ServiceInstance> {
+        let Some(zone) = self.config.zone.as_deref() else {
+            return services;
+        };
+        let local: Vec<_> = services
+            .iter()
+            .filter(|service| service.zone() == Some(zone))
+            .cloned()
+            .collect();
+        if local.is_empty() {
+            debug!(
+                "No available services in zone '{}', spilling to other zones",
+                zone
+            );
+            services
+        } else {
+            local
+        }
+    }
+
+    /// A healthy service can take a request unless it is ejected (as an outlier or after
+    /// proxy failures), draining or at its ceiling
+    fn is_selectable(&self, service: &ServiceInstance) -> bool {
+        !service.is_ejected()
+            && !service.is_passively_ejected()
+            && !service.is_draining()
+            && self.has_capacity(service)
+    }
+
+    /// If healthy services exist for the model but all are at their concurrency ceiling,
+    /// return how long clients should wait before retrying
+    pub fn saturation_retry_after(
+        &self,
+        model_id: Option<&str>,
+        requirements: &Requirements,
+        endpoint: &str,
+        pool: Option<&str>,
+    ) -> Option<Duration> {
+        let services = self.snapshot.load();
+        let candidates: Vec<_> = services
+            .iter()
+            .filter(|service| {
+                service.is_healthy()
+                    && !service.is_ejected()
+                    && !service.is_passively_ejected()
+                    && !service.is_draining()
+                    && service.supports_endpoint(endpoint)
+                    && service.serves_pool(pool)
+                    && model_id.is_none_or(|model_id| service.supports_model(model_id))
+                    && service.supports_requirements(model_id, requirements)
+            })
+            .collect();
+
+        if candidates.is_empty() || candidates.iter().any(|s| self.has_capacity(s)) {
+            return None;
+        }
+
+        // A slot frees up roughly one request duration from now on the fastest backend
+        let soonest = candidates
+            .iter()
+            .map(|s| s.avg_request_duration())
+            .min()
+            .unwrap_or_default();
+        Some(Duration::from_secs(
+            soonest.as_secs_f64().ceil().clamp(1.0, 60.0) as u64,
+        ))
+    }
+
+    /// Whether any known service serves `model_id`
+    pub fn serves_model(&self, model_id: &str) -> bool {
+        self.snapshot
+            .load()
+            .iter()
+            .any(|service| service.supports_model(model_id))
+    }
+
+    /// Largest request (in estimated tokens) `model_id` accepts on `endpoint` within `pool`
+    /// under the context overflow policy; None when requests are not checked or the context
+    /// lengths of its services are unknown
+    pub fn context_limit(&self, model_id: &str, endpoint: &str, pool: Option<&str>) -> Option<u64> {
+        let context_lengths: Vec<_> = self
+            .snapshot
+            .load()
+            .iter()
+            .filter(|service| {
+                service.supports_model(model_id)
+                    && service.supports_endpoint(endpoint)
+                    && service.serves_pool(pool)
+            })
+            .map(|service| capabilities::context_length(&service.metadata, Some(model_id)))
+            .collect();
+        self.config.context_overflow.limit(&context_lengths)
+    }
+
+    /// Count a request routed away from its preferred cache_type
+    pub fn record_cache_type_fallback(&self, preferred: &str) {
+        let counter = if preferred == "static" {
+            &self.cache_type_fallbacks.static_to_paged
+        } else {
+            &self.cache_type_fallbacks.paged_to_static
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Cache-type fallback counters
+    pub fn cache_type_fallbacks(&self) -> &CacheTypeFallbacks {
+        &self.cache_type_fallbacks
+    }
+
+    /// Router configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Cache of the aggregated /models list
+    pub fn model_cache(&self) -> &ModelListCache {
+        &self.model_cache
+    }
+
+    /// Batch API state
+    pub fn batches(&self) -> &BatchManager {
+        &self.batches
+    }
+
+    /// Request/response transformation hooks
+    pub fn hooks(&self) -> &HookChain {
+        &self.hooks
+    }
+
+    /// Audit log, if an audit sink is configured
+    pub fn audit(&self) -> Option<&AuditLog> {
+        self.audit.as_deref()
+    }
+
+    /// Availability and latency-objective tracking for /stats/slo
+    pub fn slo(&self) -> &SloTracker {
+        &self.slo
+    }
+
+    /// Time to the first chunk of streaming responses, per model
+    pub fn first_token_latency(&self) -> &ModelLatencies {
+        &self.first_token_latency
+    }
+
+    /// Prompt token estimates, calibrated per model from backend usage reports
+    pub fn token_estimator(&self) -> &TokenEstimator {
+        &self.token_estimator
+    }
+
+    /// Priority queue of requests waiting for a slot on saturated backends
+    pub fn admission(&self) -> &AdmissionQueue {
+        &self.admission
+    }
+
+    /// Session affinity store
+    pub fn sessions(&self) -> &SessionStore {
+        &self.sessions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RouterArgs, StaticService};
+    use crate::registry::tls::RegistryTls;
+    use clap::Parser;
+    use serde_json::json;
+
+    /// A load balancer over static services `names`, configured by router `args`
+    async fn balancer(args: &[&str], names: &[&str]) -> LoadBalancer {
+        let args = RouterArgs::parse_from(std::iter::once(&"infini-router").chain(args));
+        let mut config = Config::from_args(args).unwrap();
+        config.static_services = Some(
+            names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| StaticService {
+                    name: name.to_string(),
+                    host: "localhost".into(),
+                    port: 8100 + i as u16,
+                    weight: 1,
+                    metadata: json!({}),
+                })
+                .collect(),
+        );
+        LoadBalancer::new(&config).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_passive_failure_threshold() {
+        let lb = balancer(
+            &[
+                "--passive-failure-threshold",
+                "3",
+                "--recovery-probe-interval",
+                "0",
+            ],
+            &["svc"],
+        )
+        .await;
+        let service = lb.get_service("svc").await.unwrap();
+
+        lb.report_proxy_failure(&service).await;
+        lb.report_proxy_failure(&service).await;
+        // A success ends the streak
+        lb.report_proxy_success(&service).await;
+        lb.report_proxy_failure(&service).await;
+        lb.report_proxy_failure(&service).await;
+        assert!(!service.is_passively_ejected());
+        assert!(lb.is_selectable(&service));
+
+        lb.report_proxy_failure(&service).await;
+        assert!(service.is_passively_ejected());
+        assert!(!lb.is_selectable(&service));
+        assert_eq!(service.error_count(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_recovery() {
+        let lb = balancer(&["--recovery-probe-interval", "0"], &["svc"]).await;
+        let service = lb.get_service("svc").await.unwrap();
+        let requirements = Requirements::default();
+
+        lb.report_proxy_failure(&service).await;
+        assert!(service.is_passively_ejected());
+        // The first probe is not due until a half-open interval has passed
+        assert!(lb
+            .half_open_probe(None, &requirements, "/v1/chat/completions", None)
+            .await
+            .is_none());
+
+        service
+            .half_open_at
+            .store(current_timestamp_secs(), Ordering::Relaxed);
+        let probe = lb
+            .half_open_probe(None, &requirements, "/v1/chat/completions", None)
+            .await
+            .unwrap();
+        assert_eq!(probe.name, "svc");
+        // One probe at a time
+        assert!(lb
+            .half_open_probe(None, &requirements, "/v1/chat/completions", None)
+            .await
+            .is_none());
+
+        lb.report_proxy_success(&probe).await;
+        assert!(!service.is_passively_ejected());
+        assert!(lb.is_selectable(&service));
+    }
+
+    #[tokio::test]
+    async fn test_peer_observation_respects_max_ejected() {
+        let lb = balancer(
+            &[
+                "--recovery-probe-interval",
+                "0",
+                "--outlier-max-ejection-percent",
+                "50",
+            ],
+            &["a", "b", "c", "d"],
+        )
+        .await;
+        let observe = |service: &str, ejected_secs| HealthObservation {
+            service: service.to_string(),
+            ejected_secs,
+        };
+
+        lb.apply_peer_observation(&observe("a", None)).await;
+        lb.apply_peer_observation(&observe("b", Some(u64::MAX)))
+            .await;
+        // Half the pool is already out: further peer ejections are ignored
+        lb.apply_peer_observation(&observe("c", None)).await;
+        lb.apply_peer_observation(&observe("d", Some(30))).await;
+
+        let a = lb.get_service("a").await.unwrap();
+        let b = lb.get_service("b").await.unwrap();
+        let c = lb.get_service("c").await.unwrap();
+        let d = lb.get_service("d").await.unwrap();
+        assert!(a.is_passively_ejected());
+        assert!(b.is_ejected());
+        assert!(!c.is_passively_ejected() && !c.is_ejected());
+        assert!(!d.is_passively_ejected() && !d.is_ejected());
+
+        // A peer cannot eject for longer than the longest local ejection
+        let longest = lb.config.outlier_detection.max_ejection_time().as_secs();
+        assert!(b.ejected_until.load(Ordering::Relaxed) <= current_timestamp_secs() + longest);
+
+        // Services already out of rotation still take peer updates
+        lb.apply_peer_observation(&observe("a", Some(30))).await;
+        assert!(a.is_ejected());
+    }
+
+    #[test]
+    fn test_weighted_pick_with_huge_weights() {
+        let services: Vec<_> = [u32::MAX, u32::MAX, 1]
+            .into_iter()
+            .enumerate()
+            .map(|(i, weight)| {
+                ServiceInstance::new(
+                    format!("s{}", i),
+                    "localhost".into(),
+                    8000,
+                    weight,
+                    HashMap::new(),
+                )
+            })
+            .collect();
+        let weight = |s: &ServiceInstance| s.effective_weight();
+        assert_eq!(weighted_pick(&services, 0, weight), 0);
+        assert_eq!(weighted_pick(&services, u32::MAX as usize, weight), 1);
+    }
+
+    #[tokio::test]
+    async fn test_registry_sync_keeps_passive_ejection() {
+        let mut server = mockito::Server::new_async().await;
+        let _services = server
+            .mock("GET", "/services")
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "services": [{
+                        "name": "svc",
+                        "host": "localhost",
+                        "port": 8100,
+                        "url": "http://localhost:8100",
+                        "hostname": "node-1",
+                        "status": "running",
+                        "timestamp": "2026-01-01T00:00:00Z",
+                        "metadata": {"type": "openai-api"},
+                        "is_healthy": true
+                    }],
+                    "total": 1
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let registry_client = RegistryClient::new(server.url(), &RegistryTls::default()).unwrap();
+
+        let service = ServiceInstance::new(
+            "svc".into(),
+            "localhost".into(),
+            8100,
+            1,
+            HashMap::from([("type".to_string(), json!("openai-api"))]),
+        );
+        service.eject_passively();
+        service.schedule_half_open_probe(Duration::from_secs(10));
+        let half_open_at = service.half_open_at.load(Ordering::Relaxed);
+        let services = DashMap::from_iter([(service.name.clone(), service.clone())]);
+        let snapshot = ServiceSnapshot::default();
+        let model_cache = ModelListCache::new(Duration::from_secs(1));
+
+        LoadBalancer::sync_once(&services, &snapshot, &registry_client, 60, &model_cache)
+            .await
+            .unwrap();
+
+        // The registry still reports the service healthy, but only a probe ends the
+        // ejection
+        assert!(service.is_healthy());
+        assert!(service.is_passively_ejected());
+        assert_eq!(service.half_open_at.load(Ordering::Relaxed), half_open_at);
+        assert_eq!(snapshot.load().len(), 1);
     }
 }
@@ -1,5 +1,18 @@
 //! Router and load balancing modules
 
+pub mod backend_load;
+pub mod capabilities;
+pub mod context_window;
+pub mod flapping;
+pub mod gossip;
 pub mod health_checker;
+pub mod latency;
 pub mod load_balancer;
+pub mod outlier;
+pub mod rolling_stats;
 pub mod service_instance;
+pub mod session_store;
+pub mod session_table;
+pub mod slow_backends;
+pub mod throttle;
+pub mod token_estimate;
@@ -0,0 +1,150 @@
+//! Outlier detection by error rate
+//!
+//! Periodically compares each service's error rate over a rolling window with the pool
+//! average and ejects services that stand out, like Envoy's outlier detection. Ejection
+//! time grows with repeated ejections, and at most `max_ejection_percent` of the pool is
+//! ejected at once so a pool-wide problem cannot empty it.
+
+use std::time::Duration;
+
+/// Services with a lower error rate are never treated as outliers
+const MIN_OUTLIER_ERROR_RATE: f64 = 0.05;
+
+/// Cap on the ejection-time multiplier for repeatedly ejected services
+const MAX_EJECTION_MULTIPLIER: u32 = 10;
+
+/// Outlier detection settings
+#[derive(Debug, Clone)]
+pub struct OutlierDetection {
+    /// Eject services whose error rate exceeds this multiple of the pool average (0 disables)
+    pub error_ratio: f64,
+    /// Seconds between detection passes
+    pub interval: u64,
+    /// Window the error rate is computed over
+    pub window: Duration,
+    /// Requests a service needs in the window before it is judged
+    pub min_requests: u64,
+    /// Largest share of the pool (percent) ejected at once
+    pub max_ejection_percent: f64,
+    /// Ejection time for a first ejection; repeated ejections last proportionally longer
+    pub base_ejection: Duration,
+}
+
+/// Traffic seen by one service over the detection window
+#[derive(Debug, Clone)]
+pub struct OutlierCandidate {
+    pub name: String,
+    pub requests: u64,
+    pub errors: u64,
+}
+
+impl OutlierCandidate {
+    fn error_rate(&self) -> f64 {
+        (self.errors as f64 / self.requests as f64).min(1.0)
+    }
+}
+
+impl OutlierDetection {
+    pub fn enabled(&self) -> bool {
+        self.error_ratio > 0.0 && self.interval > 0
+    }
+
+    /// How long to eject a service for its `ejections`-th consecutive ejection
+    pub fn ejection_time(&self, ejections: u32) -> Duration {
+        self.base_ejection * ejections.clamp(1, MAX_EJECTION_MULTIPLIER)
+    }
+
+    /// Longest ejection this router applies, for any number of repeats
+    pub fn max_ejection_time(&self) -> Duration {
+        self.ejection_time(MAX_EJECTION_MULTIPLIER)
+    }
+
+    /// Most services of a pool of `pool_size` that may be ejected at once
+    pub fn max_ejected(&self, pool_size: usize) -> usize {
+        ((pool_size as f64 * self.max_ejection_percent / 100.0).floor() as usize).max(1)
+    }
+
+    /// Services to eject, worst first. `candidates` are the services currently in
+    /// rotation, `pool_size` counts every service and `ejected` those already ejected.
+    pub fn find_outliers(
+        &self,
+        candidates: &[OutlierCandidate],
+        pool_size: usize,
+        ejected: usize,
+    ) -> Vec<String> {
+        let judged: Vec<&OutlierCandidate> = candidates
+            .iter()
+            .filter(|c| c.requests > 0 && c.requests >= self.min_requests)
+            .collect();
+        // An outlier needs a pool to stand out from
+        if judged.len() < 2 {
+            return Vec::new();
+        }
+
+        let requests: u64 = judged.iter().map(|c| c.requests).sum();
+        let errors: u64 = judged.iter().map(|c| c.errors).sum();
+        let pool_rate = errors as f64 / requests as f64;
+        let threshold = (pool_rate * self.error_ratio).max(MIN_OUTLIER_ERROR_RATE);
+
+        let mut outliers: Vec<&OutlierCandidate> = judged
+            .into_iter()
+            .filter(|c| c.error_rate() > threshold)
+            .collect();
+        outliers.sort_by(|a, b| b.error_rate().total_cmp(&a.error_rate()));
+
+        outliers
+            .into_iter()
+            .take(self.max_ejected(pool_size).saturating_sub(ejected))
+            .map(|c| c.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(name: &str, requests: u64, errors: u64) -> OutlierCandidate {
+        OutlierCandidate {
+            name: name.to_string(),
+            requests,
+            errors,
+        }
+    }
+
+    #[test]
+    fn test_find_outliers() {
+        let detection = OutlierDetection {
+            error_ratio: 2.0,
+            interval: 10,
+            window: Duration::from_secs(60),
+            min_requests: 20,
+            max_ejection_percent: 50.0,
+            base_ejection: Duration::from_secs(30),
+        };
+        let pool = [
+            candidate("a", 100, 1),
+            candidate("b", 100, 1),
+            candidate("c", 100, 40),
+            candidate("d", 100, 30),
+            candidate("f", 100, 1),
+            candidate("g", 100, 1),
+            // Too little traffic to judge
+            candidate("e", 5, 5),
+        ];
+
+        assert_eq!(detection.find_outliers(&pool, 7, 0), ["c", "d"]);
+        // Only 3 of 7 may be ejected at once
+        assert_eq!(detection.find_outliers(&pool, 7, 2), ["c"]);
+        assert!(detection.find_outliers(&pool, 7, 3).is_empty());
+        // A uniformly low error rate ejects nobody
+        let healthy = [candidate("a", 100, 1), candidate("b", 100, 3)];
+        assert!(detection.find_outliers(&healthy, 2, 0).is_empty());
+
+        assert_eq!(detection.ejection_time(3), Duration::from_secs(90));
+        assert_eq!(detection.ejection_time(50), Duration::from_secs(300));
+        assert_eq!(detection.max_ejection_time(), Duration::from_secs(300));
+        assert_eq!(detection.max_ejected(7), 3);
+        assert_eq!(detection.max_ejected(1), 1);
+    }
+}
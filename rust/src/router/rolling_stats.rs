@@ -0,0 +1,180 @@
+//! Rolling-window request statistics
+//!
+//! Counters live in a ring of 10-second buckets covering the last hour, so the
+//! 1m/5m/1h views are computed on demand without keeping per-request samples.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::utils::time::current_timestamp_secs;
+
+/// Width of one bucket in seconds
+const BUCKET_SECS: u64 = 10;
+
+/// Buckets kept: one hour of history
+const BUCKET_COUNT: usize = 360;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    /// Bucket index since the Unix epoch (timestamp / BUCKET_SECS)
+    slot: u64,
+    requests: u64,
+    errors: u64,
+    latency_ms_sum: u64,
+    latency_ms_max: u64,
+}
+
+/// Aggregates over one window
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowStats {
+    pub requests: u64,
+    pub errors: u64,
+    pub requests_per_second: f64,
+    pub error_rate: f64,
+    pub avg_latency_ms: f64,
+    pub max_latency_ms: u64,
+}
+
+/// The windows exposed in /stats
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RollingWindows {
+    #[serde(rename = "1m")]
+    pub one_minute: WindowStats,
+    #[serde(rename = "5m")]
+    pub five_minutes: WindowStats,
+    #[serde(rename = "1h")]
+    pub one_hour: WindowStats,
+}
+
+/// Ring buffer of request/error/latency counters
+#[derive(Debug)]
+pub struct RollingStats {
+    buckets: Mutex<Vec<Bucket>>,
+}
+
+impl RollingStats {
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(vec![Bucket::default(); BUCKET_COUNT]),
+        }
+    }
+
+    /// Record a request (successful or not) and its latency
+    pub fn record_request(&self, latency: Duration) {
+        self.record_request_at(current_timestamp_secs(), latency);
+    }
+
+    /// Record that a request failed
+    pub fn record_error(&self) {
+        self.record_error_at(current_timestamp_secs());
+    }
+
+    fn record_request_at(&self, now: u64, latency: Duration) {
+        let latency_ms = latency.as_millis() as u64;
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = Self::bucket_at(&mut buckets, now);
+        bucket.requests += 1;
+        bucket.latency_ms_sum += latency_ms;
+        bucket.latency_ms_max = bucket.latency_ms_max.max(latency_ms);
+    }
+
+    fn record_error_at(&self, now: u64) {
+        let mut buckets = self.buckets.lock().unwrap();
+        Self::bucket_at(&mut buckets, now).errors += 1;
+    }
+
+    /// Bucket for `now`, reset if it still holds data from an older slot
+    fn bucket_at(buckets: &mut [Bucket], now: u64) -> &mut Bucket {
+        let slot = now / BUCKET_SECS;
+        let bucket = &mut buckets[(slot % BUCKET_COUNT as u64) as usize];
+        if bucket.slot != slot {
+            *bucket = Bucket {
+                slot,
+                ..Default::default()
+            };
+        }
+        bucket
+    }
+
+    /// Aggregates over the last `window` (rounded to whole buckets)
+    pub fn window(&self, window: Duration) -> WindowStats {
+        self.window_at(current_timestamp_secs(), window)
+    }
+
+    fn window_at(&self, now: u64, window: Duration) -> WindowStats {
+        let current = now / BUCKET_SECS;
+        let span = (window.as_secs() / BUCKET_SECS).clamp(1, BUCKET_COUNT as u64);
+
+        let mut stats = WindowStats::default();
+        let mut latency_ms_sum = 0;
+        for bucket in self.buckets.lock().unwrap().iter() {
+            if bucket.slot > current || bucket.slot + span <= current {
+                continue;
+            }
+            stats.requests += bucket.requests;
+            stats.errors += bucket.errors;
+            latency_ms_sum += bucket.latency_ms_sum;
+            stats.max_latency_ms = stats.max_latency_ms.max(bucket.latency_ms_max);
+        }
+
+        stats.requests_per_second = stats.requests as f64 / (span * BUCKET_SECS) as f64;
+        if stats.requests > 0 {
+            stats.avg_latency_ms = latency_ms_sum as f64 / stats.requests as f64;
+            // Failed requests are counted in `requests` too
+            stats.error_rate = (stats.errors as f64 / stats.requests as f64).min(1.0);
+        }
+        stats
+    }
+
+    /// 1m, 5m and 1h windows
+    pub fn windows(&self) -> RollingWindows {
+        RollingWindows {
+            one_minute: self.window(Duration::from_secs(60)),
+            five_minutes: self.window(Duration::from_secs(300)),
+            one_hour: self.window(Duration::from_secs(3600)),
+        }
+    }
+}
+
+impl Default for RollingStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_windows() {
+        let stats = RollingStats::new();
+        let start = 1_000_000;
+
+        // 10 minutes ago: one slow request; just now: two requests and an error
+        stats.record_request_at(start, Duration::from_millis(900));
+        let now = start + 600;
+        stats.record_request_at(now, Duration::from_millis(100));
+        stats.record_request_at(now, Duration::from_millis(300));
+        stats.record_error_at(now);
+
+        let minute = stats.window_at(now, Duration::from_secs(60));
+        assert_eq!(minute.requests, 2);
+        assert_eq!(minute.errors, 1);
+        assert_eq!(minute.avg_latency_ms, 200.0);
+        assert_eq!(minute.max_latency_ms, 300);
+        assert_eq!(minute.error_rate, 0.5);
+
+        let hour = stats.window_at(now, Duration::from_secs(3600));
+        assert_eq!(hour.requests, 3);
+        assert_eq!(hour.max_latency_ms, 900);
+
+        // Buckets older than an hour are overwritten, not counted
+        let later = now + 3600;
+        stats.record_request_at(later, Duration::from_millis(50));
+        let hour = stats.window_at(later, Duration::from_secs(3600));
+        assert_eq!(hour.requests, 1);
+        assert_eq!(hour.errors, 0);
+    }
+}
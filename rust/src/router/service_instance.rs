@@ -1,9 +1,63 @@
 //! Service instance representation
 
+use crate::router::capabilities::{self, Requirements};
+use crate::router::latency::{LatencyHistogram, LatencySummary};
+use crate::router::rolling_stats::{RollingStats, RollingWindows};
+use crate::router::slow_backends::FULL_WEIGHT_PERCENT;
+use arc_swap::ArcSwap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Health checks remembered per service
+const HEALTH_HISTORY_LEN: usize = 20;
+
+/// Outcome of one health check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheckRecord {
+    pub timestamp: f64,
+    pub healthy: bool,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// How the router checks a service, from its `health_check` metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthCheckMode {
+    /// GET the health URL and expect a success status (default)
+    Http,
+    /// Only verify the service port accepts connections
+    Tcp,
+    /// Never probe; trust the registry's heartbeat-based health
+    None,
+}
+
+/// Most recent error recorded against a service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceError {
+    pub timestamp: f64,
+    pub message: String,
+}
+
+/// An f64 stored as its bit pattern, for lock-free timestamps
+#[derive(Debug, Default)]
+pub struct AtomicF64(AtomicU64);
+
+impl AtomicF64 {
+    pub fn new(value: f64) -> Self {
+        Self(AtomicU64::new(value.to_bits()))
+    }
+
+    pub fn load(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
 
 /// Service instance metadata
 #[derive(Clone, Debug)]
@@ -13,15 +67,53 @@ pub struct ServiceInstance {
     pub port: u16,
     pub url: String,
     pub babysitter_url: String,
-    pub healthy: Arc<RwLock<bool>>,
-    pub models: Arc<RwLock<Vec<String>>>,
+    pub healthy: Arc<AtomicBool>,
+    /// Models served; replaced as a whole so routing reads never wait
+    pub models: Arc<ArcSwap<Vec<String>>>,
     pub metadata: HashMap<String, serde_json::Value>,
-    pub request_count: Arc<RwLock<u64>>,
-    pub error_count: Arc<RwLock<u32>>,
+    pub request_count: Arc<AtomicU64>,
+    pub error_count: Arc<AtomicU32>,
     pub weight: u32,
-    pub last_seen: Arc<RwLock<f64>>,
-    pub last_check: Arc<RwLock<f64>>,
-    pub response_time: Arc<RwLock<f64>>,
+    pub last_seen: Arc<AtomicF64>,
+    pub last_check: Arc<AtomicF64>,
+    /// Babysitter health check latency
+    pub health_latency: Arc<LatencyHistogram>,
+    /// Proxied request latency (until the last byte is sent)
+    pub request_latency: Arc<LatencyHistogram>,
+    /// Proxied request latency since the slow-backend check last judged this service
+    pub recent_latency: Arc<LatencyHistogram>,
+    /// Time to the first chunk of streaming responses
+    pub first_token_latency: Arc<LatencyHistogram>,
+    /// Requests, errors and latency over the last 1m/5m/1h
+    pub rolling: Arc<RollingStats>,
+    /// Requests currently being proxied to this service
+    pub in_flight: Arc<AtomicU32>,
+    /// Moving average of proxied request duration (milliseconds)
+    pub avg_request_ms: Arc<AtomicU64>,
+    /// Most recent health checks, oldest first
+    pub health_history: Arc<Mutex<VecDeque<HealthCheckRecord>>>,
+    pub last_error: Arc<Mutex<Option<ServiceError>>>,
+    /// Failed proxy attempts since the last successful one (passive health)
+    pub consecutive_failures: Arc<AtomicU32>,
+    /// Set while recovery probes run after a passive ejection
+    pub recovering: Arc<AtomicBool>,
+    /// Set while proxy failures keep the service out of rotation, until a probe passes;
+    /// kept apart from `healthy`, which registry syncs and health checks overwrite
+    pub passively_ejected: Arc<AtomicBool>,
+    /// Unix time (seconds) until which outlier detection keeps the service out of rotation
+    pub ejected_until: Arc<AtomicU64>,
+    /// Consecutive outlier ejections, scaling the ejection time
+    pub ejections: Arc<AtomicU32>,
+    /// Share of `weight` used for selection; lowered while the service is chronically slow
+    pub weight_percent: Arc<AtomicU32>,
+    /// Set while its babysitter drains it before a planned restart; no new requests
+    /// are routed to it
+    pub draining: Arc<AtomicBool>,
+    /// Unix time (seconds) until which the service is backed off after a 429/503
+    pub throttled_until: Arc<AtomicU64>,
+    /// Unix time (seconds) from which a service taken out by proxy failures may get its
+    /// next live request as a probe (0 while it was not taken out that way)
+    pub half_open_at: Arc<AtomicU64>,
 }
 
 impl ServiceInstance {
@@ -35,8 +127,7 @@ impl ServiceInstance {
         metadata: HashMap<String, serde_json::Value>,
     ) -> Self {
         let url = format!("http://{}:{}", host, port);
-        let babysitter_port = port + 1;
-        let babysitter_url = format!("http://{}:{}", host, babysitter_port);
+        let babysitter_url = Self::babysitter_url_for(&host, port, &metadata);
 
         // Extract models from metadata if available
         let models = metadata
@@ -57,56 +148,340 @@ impl ServiceInstance {
             port,
             url,
             babysitter_url,
-            healthy: Arc::new(RwLock::new(true)),
-            models: Arc::new(RwLock::new(models)),
+            healthy: Arc::new(AtomicBool::new(true)),
+            models: Arc::new(ArcSwap::from_pointee(models)),
             metadata,
-            request_count: Arc::new(RwLock::new(0)),
-            error_count: Arc::new(RwLock::new(0)),
+            request_count: Arc::new(AtomicU64::new(0)),
+            error_count: Arc::new(AtomicU32::new(0)),
             weight,
-            last_seen: Arc::new(RwLock::new(last_seen)),
-            last_check: Arc::new(RwLock::new(0.0)),
-            response_time: Arc::new(RwLock::new(0.0)),
+            last_seen: Arc::new(AtomicF64::new(last_seen)),
+            last_check: Arc::new(AtomicF64::new(0.0)),
+            health_latency: Arc::new(LatencyHistogram::new()),
+            request_latency: Arc::new(LatencyHistogram::new()),
+            recent_latency: Arc::new(LatencyHistogram::new()),
+            first_token_latency: Arc::new(LatencyHistogram::new()),
+            rolling: Arc::new(RollingStats::new()),
+            in_flight: Arc::new(AtomicU32::new(0)),
+            avg_request_ms: Arc::new(AtomicU64::new(0)),
+            health_history: Arc::new(Mutex::new(VecDeque::with_capacity(HEALTH_HISTORY_LEN))),
+            last_error: Arc::new(Mutex::new(None)),
+            consecutive_failures: Arc::new(AtomicU32::new(0)),
+            recovering: Arc::new(AtomicBool::new(false)),
+            passively_ejected: Arc::new(AtomicBool::new(false)),
+            ejected_until: Arc::new(AtomicU64::new(0)),
+            weight_percent: Arc::new(AtomicU32::new(FULL_WEIGHT_PERCENT)),
+            ejections: Arc::new(AtomicU32::new(0)),
+            draining: Arc::new(AtomicBool::new(false)),
+            throttled_until: Arc::new(AtomicU64::new(0)),
+            half_open_at: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Babysitter URL advertised in the registration metadata (`babysitter_url`, then
+    /// `babysitter_port`), falling back to the port+1 convention
+    pub fn babysitter_url_for(
+        host: &str,
+        port: u16,
+        metadata: &HashMap<String, serde_json::Value>,
+    ) -> String {
+        if let Some(url) = metadata.get("babysitter_url").and_then(|v| v.as_str()) {
+            return url.trim_end_matches('/').to_string();
+        }
+        let babysitter_port = metadata
+            .get("babysitter_port")
+            .and_then(|v| v.as_u64())
+            .and_then(|p| u16::try_from(p).ok())
+            .unwrap_or_else(|| port.saturating_add(1));
+        format!("http://{}:{}", host, babysitter_port)
+    }
+
+    /// URL probed by health checks: the babysitter's `health_path` (default /health), or
+    /// the service's own when metadata sets `health_target` to `service` (no babysitter)
+    pub fn health_check_url(&self) -> String {
+        let base = match self.metadata.get("health_target").and_then(|v| v.as_str()) {
+            Some("service") => &self.url,
+            _ => &self.babysitter_url,
+        };
+        let path = self
+            .metadata
+            .get("health_path")
+            .and_then(|v| v.as_str())
+            .unwrap_or("/health");
+        format!("{}/{}", base, path.trim_start_matches('/'))
+    }
+
+    /// Whether a health check response status counts as healthy: exactly
+    /// `health_expect_status` when advertised, any 2xx otherwise
+    pub fn is_healthy_status(&self, status: u16) -> bool {
+        match self
+            .metadata
+            .get("health_expect_status")
+            .and_then(|v| v.as_u64())
+        {
+            Some(expected) => u64::from(status) == expected,
+            None => (200..300).contains(&status),
+        }
+    }
+
+    /// Health check mode advertised in metadata (`http`, `tcp` or `none`)
+    pub fn health_check_mode(&self) -> HealthCheckMode {
+        match self.metadata.get("health_check").and_then(|v| v.as_str()) {
+            Some("tcp") => HealthCheckMode::Tcp,
+            Some("none") => HealthCheckMode::None,
+            _ => HealthCheckMode::Http,
         }
     }
 
+    /// Zone (datacenter, rack, ...) advertised in metadata
+    pub fn zone(&self) -> Option<&str> {
+        self.metadata.get("zone").and_then(|v| v.as_str())
+    }
+
+    /// Tenant pool the service is reserved for, from metadata; unpooled services are shared
+    pub fn pool(&self) -> Option<&str> {
+        self.metadata.get("pool").and_then(|v| v.as_str())
+    }
+
+    /// Whether a request from tenant `pool` (None for untenanted requests) may use this service
+    pub fn serves_pool(&self, pool: Option<&str>) -> bool {
+        self.pool().is_none_or(|own| Some(own) == pool)
+    }
+
+    /// Whether outlier detection currently keeps the service out of rotation
+    pub fn is_ejected(&self) -> bool {
+        self.ejected_until.load(Ordering::Relaxed) > crate::utils::time::current_timestamp_secs()
+    }
+
+    /// Whether proxy failures currently keep the service out of rotation
+    pub fn is_passively_ejected(&self) -> bool {
+        self.passively_ejected.load(Ordering::Relaxed)
+    }
+
+    /// Take the service out of rotation after proxy failures; true if it was in rotation
+    pub fn eject_passively(&self) -> bool {
+        !self.passively_ejected.swap(true, Ordering::AcqRel)
+    }
+
+    /// Put a passively ejected service back in rotation and stop half-open probing;
+    /// true if it was out
+    pub fn end_passive_ejection(&self) -> bool {
+        self.half_open_at.store(0, Ordering::Relaxed);
+        self.passively_ejected.swap(false, Ordering::AcqRel)
+    }
+
+    /// Whether the service asked the router to back off and is still within that time
+    pub fn is_throttled(&self) -> bool {
+        self.throttled_until.load(Ordering::Relaxed) > crate::utils::time::current_timestamp_secs()
+    }
+
+    /// Back off from the service for `backoff` (at least a second)
+    pub fn throttle(&self, backoff: Duration) {
+        let until = crate::utils::time::current_timestamp_secs() + backoff.as_secs().max(1);
+        self.throttled_until.fetch_max(until, Ordering::Relaxed);
+    }
+
+    /// Whether the service is being drained and takes no new requests
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Weight used for weighted round-robin: `weight` scaled by `weight_percent`,
+    /// saturating for huge registry-supplied weights
+    pub fn effective_weight(&self) -> u32 {
+        self.weight
+            .saturating_mul(self.weight_percent.load(Ordering::Relaxed))
+    }
+
     /// Check if service is healthy
-    pub async fn is_healthy(&self) -> bool {
-        *self.healthy.read().await
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
     }
 
     /// Increment request count
-    pub async fn increment_request_count(&self) {
-        let mut count = self.request_count.write().await;
-        *count += 1;
+    pub fn increment_request_count(&self) {
+        self.request_count.fetch_add(1, Ordering::Relaxed);
     }
 
     /// Increment error count
-    pub async fn increment_error_count(&self) {
-        let mut count = self.error_count.write().await;
-        *count += 1;
+    pub fn increment_error_count(&self) {
+        self.rolling.record_error();
+        self.error_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Proxied requests routed to this service
+    pub fn request_count(&self) -> u64 {
+        self.request_count.load(Ordering::Relaxed)
     }
 
-    /// Update health status
-    pub async fn set_healthy(&self, healthy: bool) {
-        let mut status = self.healthy.write().await;
-        *status = healthy;
+    /// Errors counted against this service (reset by a passing health check)
+    pub fn error_count(&self) -> u32 {
+        self.error_count.load(Ordering::Relaxed)
+    }
+
+    /// Update health status from the registry or a health check; passive ejection and
+    /// half-open probing are left alone
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::Relaxed);
+    }
+
+    /// Allow a live probe request `interval` from now
+    pub fn schedule_half_open_probe(&self, interval: Duration) {
+        let at = crate::utils::time::current_timestamp_secs() + interval.as_secs().max(1);
+        self.half_open_at.store(at, Ordering::Relaxed);
+    }
+
+    /// Claim the probe slot if it is due, pushing the next one `interval` out
+    pub fn claim_half_open_probe(&self, interval: Duration) -> bool {
+        let at = self.half_open_at.load(Ordering::Relaxed);
+        let now = crate::utils::time::current_timestamp_secs();
+        at != 0
+            && now >= at
+            && self
+                .half_open_at
+                .compare_exchange(
+                    at,
+                    now + interval.as_secs().max(1),
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
     }
 
     /// Update last seen timestamp
-    pub async fn update_last_seen(&self) {
-        let mut last_seen = self.last_seen.write().await;
-        *last_seen = crate::utils::time::current_timestamp();
+    pub fn update_last_seen(&self) {
+        self.last_seen
+            .store(crate::utils::time::current_timestamp());
+    }
+
+    /// Remember a health check outcome; failures also become the last error
+    pub fn record_health_check(
+        &self,
+        healthy: bool,
+        latency: Option<Duration>,
+        error: Option<String>,
+    ) {
+        if let Some(message) = &error {
+            self.record_last_error(message.clone());
+        }
+        let mut history = self.health_history.lock().unwrap();
+        if history.len() == HEALTH_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(HealthCheckRecord {
+            timestamp: crate::utils::time::current_timestamp(),
+            healthy,
+            latency_ms: latency.map(|latency| latency.as_millis() as u64),
+            error,
+        });
+    }
+
+    /// Remember the latest error seen for this service
+    pub fn record_last_error(&self, message: String) {
+        *self.last_error.lock().unwrap() = Some(ServiceError {
+            timestamp: crate::utils::time::current_timestamp(),
+            message,
+        });
+    }
+
+    /// Count a request as in flight until the returned guard is dropped
+    pub fn track_in_flight(&self) -> InFlightGuard {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard {
+            in_flight: self.in_flight.clone(),
+            avg_request_ms: self.avg_request_ms.clone(),
+            request_latency: self.request_latency.clone(),
+            recent_latency: self.recent_latency.clone(),
+            rolling: self.rolling.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Number of requests currently in flight
+    pub fn in_flight_count(&self) -> u32 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Average proxied request duration
+    pub fn avg_request_duration(&self) -> Duration {
+        Duration::from_millis(self.avg_request_ms.load(Ordering::Relaxed))
+    }
+
+    /// Check if service serves the request path; services without an `endpoints`
+    /// metadata list accept every path, otherwise the path must equal or sit under an entry
+    pub fn supports_endpoint(&self, path: &str) -> bool {
+        let endpoints = match self.metadata.get("endpoints").and_then(|v| v.as_array()) {
+            Some(endpoints) => endpoints,
+            None => return true,
+        };
+        endpoints.iter().filter_map(|v| v.as_str()).any(|endpoint| {
+            let endpoint = endpoint.trim_end_matches('/');
+            path.strip_prefix(endpoint)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
     }
 
     /// Check if service supports a specific model
-    #[allow(dead_code)]
-    pub async fn supports_model(&self, model_id: &str) -> bool {
-        let models = self.models.read().await;
-        models.contains(&model_id.to_string())
+    pub fn supports_model(&self, model_id: &str) -> bool {
+        self.models.load().iter().any(|m| m == model_id)
+    }
+
+    /// Check if the service can handle a request for `model_id` needing `requirements`;
+    /// services declaring no capabilities or context length for the model are assumed to
+    pub fn supports_requirements(
+        &self,
+        model_id: Option<&str>,
+        requirements: &Requirements,
+    ) -> bool {
+        if requirements.is_empty() {
+            return true;
+        }
+        let capable = capabilities::model_capabilities(&self.metadata, model_id)
+            .is_none_or(|declared| declared.satisfies(requirements));
+        let fits = requirements.context_tokens.is_none_or(|tokens| {
+            capabilities::context_length(&self.metadata, model_id)
+                .is_none_or(|length| tokens <= length)
+        });
+        capable && fits
+    }
+
+    /// Replace the served model list
+    pub fn set_models(&self, models: Vec<String>) {
+        self.models.store(Arc::new(models));
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Keeps a request counted as in flight; records its duration when dropped
+pub struct InFlightGuard {
+    in_flight: Arc<AtomicU32>,
+    avg_request_ms: Arc<AtomicU64>,
+    request_latency: Arc<LatencyHistogram>,
+    recent_latency: Arc<LatencyHistogram>,
+    rolling: Arc<RollingStats>,
+    started: Instant,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        let elapsed = self.started.elapsed();
+        self.request_latency.record(elapsed);
+        self.recent_latency.record(elapsed);
+        self.rolling.record_request(elapsed);
+
+        // Exponential moving average (alpha = 0.2); concurrent updates may race, which is fine
+        let elapsed_ms = elapsed.as_millis() as u64;
+        let previous = self.avg_request_ms.load(Ordering::Relaxed);
+        let updated = if previous == 0 {
+            elapsed_ms
+        } else {
+            (previous * 4 + elapsed_ms) / 5
+        };
+        self.avg_request_ms.store(updated, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceInfo {
     pub name: String,
     pub host: String,
@@ -116,28 +491,136 @@ pub struct ServiceInfo {
     pub healthy: bool,
     pub request_count: u64,
     pub error_count: u32,
-    pub response_time: f64,
+    pub health_check_latency: LatencySummary,
+    pub request_latency: LatencySummary,
+    pub first_token_latency: LatencySummary,
+    pub windows: RollingWindows,
+    pub in_flight: u32,
+    /// Out of rotation after outlier detection or proxy failures
+    pub ejected: bool,
+    /// Out of rotation while drained for a planned restart
+    pub draining: bool,
+    /// Backed off after a 429/503; only used when no other service can take a request
+    pub throttled: bool,
     pub weight: u32,
+    /// Share of the weight in use (below 100 while deprioritized as slow)
+    pub weight_percent: u32,
     pub models: Vec<String>,
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
 impl ServiceInstance {
     /// Convert to serializable info
-    pub async fn to_info(&self) -> ServiceInfo {
+    pub fn to_info(&self) -> ServiceInfo {
         ServiceInfo {
             name: self.name.clone(),
             host: self.host.clone(),
             port: self.port,
             url: self.url.clone(),
             babysitter_url: self.babysitter_url.clone(),
-            healthy: *self.healthy.read().await,
-            request_count: *self.request_count.read().await,
-            error_count: *self.error_count.read().await,
-            response_time: *self.response_time.read().await,
+            healthy: self.is_healthy(),
+            request_count: self.request_count(),
+            error_count: self.error_count(),
+            health_check_latency: self.health_latency.summary(),
+            request_latency: self.request_latency.summary(),
+            first_token_latency: self.first_token_latency.summary(),
+            windows: self.rolling.windows(),
+            in_flight: self.in_flight_count(),
+            ejected: self.is_ejected() || self.is_passively_ejected(),
+            draining: self.is_draining(),
+            throttled: self.is_throttled(),
             weight: self.weight,
-            models: self.models.read().await.clone(),
+            weight_percent: self.weight_percent.load(Ordering::Relaxed),
+            models: self.models.load().to_vec(),
             metadata: self.metadata.clone(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_supports_endpoint() {
+        let any = ServiceInstance::new("a".into(), "localhost".into(), 8000, 1, HashMap::new());
+        assert!(any.supports_endpoint("/v1/chat/completions"));
+
+        let mut metadata = HashMap::new();
+        metadata.insert("endpoints".to_string(), json!(["/v1/embeddings/"]));
+        let embeddings = ServiceInstance::new("b".into(), "localhost".into(), 8001, 1, metadata);
+        assert!(embeddings.supports_endpoint("/v1/embeddings"));
+        assert!(!embeddings.supports_endpoint("/v1/embeddings_v2"));
+        assert!(!embeddings.supports_endpoint("/v1/chat/completions"));
+    }
+
+    #[test]
+    fn test_serves_pool() {
+        let shared = ServiceInstance::new("s".into(), "localhost".into(), 8000, 1, HashMap::new());
+        let pooled = ServiceInstance::new(
+            "p".into(),
+            "localhost".into(),
+            8001,
+            1,
+            HashMap::from([("pool".to_string(), json!("team-a"))]),
+        );
+
+        assert!(shared.serves_pool(None) && shared.serves_pool(Some("team-b")));
+        assert!(pooled.serves_pool(Some("team-a")));
+        assert!(!pooled.serves_pool(Some("team-b")));
+        assert!(!pooled.serves_pool(None));
+    }
+
+    #[test]
+    fn test_effective_weight_saturates() {
+        let service = ServiceInstance::new("a".into(), "localhost".into(), 8000, 3, HashMap::new());
+        service.weight_percent.store(50, Ordering::Relaxed);
+        assert_eq!(service.effective_weight(), 150);
+
+        let heavy = ServiceInstance::new(
+            "b".into(),
+            "localhost".into(),
+            8000,
+            u32::MAX,
+            HashMap::new(),
+        );
+        assert_eq!(heavy.effective_weight(), u32::MAX);
+    }
+
+    #[test]
+    fn test_claim_half_open_probe() {
+        let service = ServiceInstance::new("a".into(), "localhost".into(), 8000, 1, HashMap::new());
+        let interval = Duration::from_secs(10);
+        assert!(!service.claim_half_open_probe(interval));
+
+        service.schedule_half_open_probe(interval);
+        assert!(!service.claim_half_open_probe(interval));
+
+        // Due: one request gets the slot, the next waits another interval
+        service.half_open_at.store(1, Ordering::Relaxed);
+        assert!(service.claim_half_open_probe(interval));
+        assert!(!service.claim_half_open_probe(interval));
+    }
+
+    #[test]
+    fn test_health_check_url() {
+        let managed = ServiceInstance::new("a".into(), "localhost".into(), 8000, 1, HashMap::new());
+        assert_eq!(managed.health_check_url(), "http://localhost:8001/health");
+
+        let mut metadata = HashMap::new();
+        metadata.insert("health_target".to_string(), json!("service"));
+        let direct = ServiceInstance::new("b".into(), "localhost".into(), 8000, 1, metadata);
+        assert_eq!(direct.health_check_url(), "http://localhost:8000/health");
+        assert!(direct.is_healthy_status(204));
+        assert!(!direct.is_healthy_status(301));
+
+        let mut metadata = HashMap::new();
+        metadata.insert("health_path".to_string(), json!("ready"));
+        metadata.insert("health_expect_status".to_string(), json!(204));
+        let custom = ServiceInstance::new("c".into(), "localhost".into(), 8000, 1, metadata);
+        assert_eq!(custom.health_check_url(), "http://localhost:8001/ready");
+        assert!(custom.is_healthy_status(204));
+        assert!(!custom.is_healthy_status(200));
+    }
+}
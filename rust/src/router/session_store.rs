@@ -0,0 +1,316 @@
+//! Session affinity storage
+//!
+//! Sessions are always tracked in the in-process LRU table. With the `redis` feature and
+//! a configured Redis URL, pins are also written through to Redis so they survive router
+//! restarts and are shared by every router replica using the same Redis.
+//!
+//! Without Redis, replicas can instead be pointed at each other as peers: every new pin is
+//! pushed to the peers' `/internal/sessions` endpoint so all routers behind a VIP map a
+//! session to the same backend.
+
+use crate::router::session_table::SessionTable;
+#[cfg(feature = "redis")]
+use crate::utils::errors::RouterError;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// A session pin exchanged between router replicas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPin {
+    pub session_key: String,
+    pub service: String,
+}
+
+/// HTTP client for requests to peer routers, presenting the peer token if one is set
+pub fn peer_client(token: Option<&str>) -> Client {
+    let mut headers = HeaderMap::new();
+    if let Some(value) = token.and_then(|t| HeaderValue::from_str(&format!("Bearer {}", t)).ok()) {
+        headers.insert(AUTHORIZATION, value);
+    }
+    Client::builder()
+        .timeout(Duration::from_secs(2))
+        .default_headers(headers)
+        .build()
+        .unwrap_or_else(|_| Client::new())
+}
+
+/// Session store: local LRU table with an optional shared Redis tier and peer replication
+pub struct SessionStore {
+    local: SessionTable,
+    #[cfg(feature = "redis")]
+    redis: Option<RedisSessions>,
+    peers: Vec<String>,
+    peer_client: Client,
+}
+
+impl SessionStore {
+    pub fn new(local: SessionTable) -> Self {
+        Self {
+            local,
+            #[cfg(feature = "redis")]
+            redis: None,
+            peers: Vec::new(),
+            peer_client: Client::new(),
+        }
+    }
+
+    /// Replicate new session pins to other router replicas (base URLs), authenticating
+    /// with `token`
+    pub fn with_peers(mut self, peers: Vec<String>, token: Option<&str>) -> Self {
+        self.peers = peers
+            .into_iter()
+            .map(|peer| peer.trim_end_matches('/').to_string())
+            .collect();
+        self.peer_client = peer_client(token);
+        self
+    }
+
+    /// Write session pins through to Redis
+    #[cfg(feature = "redis")]
+    pub async fn with_redis(
+        mut self,
+        url: &str,
+        key_prefix: String,
+        ttl: Duration,
+    ) -> Result<Self, RouterError> {
+        self.redis = Some(RedisSessions::connect(url, key_prefix, ttl).await?);
+        Ok(self)
+    }
+
+    /// Service the session is pinned to, consulting Redis when the local table has no entry
+    pub async fn get(&self, session_key: &str) -> Option<String> {
+        if let Some(service) = self.local.get(session_key) {
+            return Some(service);
+        }
+
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            let service = redis.get(session_key).await?;
+            self.local.insert(session_key, &service);
+            return Some(service);
+        }
+
+        None
+    }
+
+    /// Pin a session to a service
+    pub async fn insert(&self, session_key: &str, service: &str) {
+        self.local.insert(session_key, service);
+
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            redis.set(session_key, service).await;
+        }
+
+        self.replicate(SessionPin {
+            session_key: session_key.to_string(),
+            service: service.to_string(),
+        });
+    }
+
+    /// Record a pin received from a peer (not replicated further)
+    pub fn insert_from_peer(&self, pin: &SessionPin) {
+        self.local.insert(&pin.session_key, &pin.service);
+    }
+
+    /// Drop sessions pinned to any service in `scope` (all sessions when `None`), locally
+    /// and in Redis; returns how many were removed
+    pub async fn flush(&self, scope: Option<&HashSet<String>>) -> usize {
+        let flushed = self.local.flush(scope).len();
+
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            // Redis may hold pins this replica never saw; those are counted too
+            return flushed.max(redis.flush(scope).await);
+        }
+
+        flushed
+    }
+
+    /// Ask every peer to apply the same flush to its local table
+    pub async fn flush_peers(&self, model: Option<&str>, service: Option<&str>) {
+        let mut query = vec![("local", "true")];
+        query.extend(model.map(|model| ("model", model)));
+        query.extend(service.map(|service| ("service", service)));
+        let requests = self.peers.iter().map(|peer| {
+            let url = format!("{}/admin/sessions/flush", peer);
+            let request = self.peer_client.post(&url).query(&query).send();
+            async move {
+                match request.await {
+                    Ok(response) if response.status().is_success() => {}
+                    Ok(response) => {
+                        warn!("Peer {} rejected session flush: {}", url, response.status())
+                    }
+                    Err(e) => warn!("Failed to forward session flush to {}: {}", url, e),
+                }
+            }
+        });
+        futures::future::join_all(requests).await;
+    }
+
+    /// Push a pin to every peer in the background
+    fn replicate(&self, pin: SessionPin) {
+        for peer in &self.peers {
+            let client = self.peer_client.clone();
+            let url = format!("{}/internal/sessions", peer);
+            let pin = pin.clone();
+            tokio::spawn(async move {
+                match client.post(&url).json(&pin).send().await {
+                    Ok(response) if response.status().is_success() => {}
+                    Ok(response) => {
+                        debug!("Peer {} rejected session pin: {}", url, response.status())
+                    }
+                    Err(e) => warn!("Failed to replicate session pin to {}: {}", url, e),
+                }
+            });
+        }
+    }
+
+    pub fn peers(&self) -> &[String] {
+        &self.peers
+    }
+
+    /// The in-process table (Redis entries expire on their own)
+    pub fn local(&self) -> &SessionTable {
+        &self.local
+    }
+
+    /// Name of the shared backend, if any
+    pub fn backend(&self) -> &'static str {
+        #[cfg(feature = "redis")]
+        if self.redis.is_some() {
+            return "redis";
+        }
+        "memory"
+    }
+}
+
+/// Upper bound on a Redis round trip before routing proceeds without it
+#[cfg(feature = "redis")]
+const REDIS_TIMEOUT: Duration = Duration::from_millis(200);
+
+#[cfg(feature = "redis")]
+struct RedisSessions {
+    connection: redis::aio::ConnectionManager,
+    key_prefix: String,
+    ttl_secs: u64,
+}
+
+#[cfg(feature = "redis")]
+impl RedisSessions {
+    async fn connect(url: &str, key_prefix: String, ttl: Duration) -> Result<Self, RouterError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid Redis URL {}: {}", url, e)))?;
+        let connection = client
+            .get_connection_manager()
+            .await
+            .map_err(|e| RouterError::ConfigError(format!("Cannot connect to Redis: {}", e)))?;
+        Ok(Self {
+            connection,
+            key_prefix,
+            ttl_secs: ttl.as_secs().max(1),
+        })
+    }
+
+    async fn get(&self, session_key: &str) -> Option<String> {
+        let mut connection = self.connection.clone();
+        // GETEX refreshes the idle TTL, matching the local table's semantics
+        let mut command = redis::cmd("GETEX");
+        command
+            .arg(format!("{}{}", self.key_prefix, session_key))
+            .arg("EX")
+            .arg(self.ttl_secs);
+        let query = command.query_async::<_, Option<String>>(&mut connection);
+        match tokio::time::timeout(REDIS_TIMEOUT, query).await {
+            Ok(Ok(service)) => service,
+            Ok(Err(e)) => {
+                warn!("Redis session lookup failed: {}", e);
+                None
+            }
+            Err(_) => {
+                warn!("Redis session lookup timed out");
+                None
+            }
+        }
+    }
+
+    /// Delete pins to services in `scope` (all pins when `None`); returns how many were deleted
+    async fn flush(&self, scope: Option<&HashSet<String>>) -> usize {
+        let mut connection = self.connection.clone();
+        let pattern = format!("{}*", self.key_prefix);
+        let mut cursor: u64 = 0;
+        let mut flushed = 0;
+        loop {
+            let (next, keys): (u64, Vec<String>) = match redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(500)
+                .query_async(&mut connection)
+                .await
+            {
+                Ok(page) => page,
+                Err(e) => {
+                    warn!("Redis session flush failed: {}", e);
+                    return flushed;
+                }
+            };
+
+            let keys = match scope {
+                Some(services) if !keys.is_empty() => {
+                    let values: Vec<Option<String>> = redis::cmd("MGET")
+                        .arg(&keys)
+                        .query_async(&mut connection)
+                        .await
+                        .unwrap_or_default();
+                    keys.into_iter()
+                        .zip(values)
+                        .filter(|(_, service)| {
+                            service
+                                .as_ref()
+                                .is_some_and(|service| services.contains(service))
+                        })
+                        .map(|(key, _)| key)
+                        .collect()
+                }
+                _ => keys,
+            };
+            if !keys.is_empty() {
+                match redis::cmd("DEL")
+                    .arg(&keys)
+                    .query_async::<_, usize>(&mut connection)
+                    .await
+                {
+                    Ok(deleted) => flushed += deleted,
+                    Err(e) => warn!("Redis session flush failed: {}", e),
+                }
+            }
+
+            if next == 0 {
+                return flushed;
+            }
+            cursor = next;
+        }
+    }
+
+    async fn set(&self, session_key: &str, service: &str) {
+        let mut connection = self.connection.clone();
+        let mut command = redis::cmd("SET");
+        command
+            .arg(format!("{}{}", self.key_prefix, session_key))
+            .arg(service)
+            .arg("EX")
+            .arg(self.ttl_secs);
+        let query = command.query_async::<_, ()>(&mut connection);
+        match tokio::time::timeout(REDIS_TIMEOUT, query).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Redis session update failed: {}", e),
+            Err(_) => warn!("Redis session update timed out"),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Bounded session → service affinity table
+//!
+//! Remembers which service a session was routed to so follow-up requests hit the
+//! same backend (and its prompt cache). Entries are evicted least-recently-used once
+//! the table is full, and expire after a period of inactivity.
+
+use lru::LruCache;
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct SessionEntry {
+    service: String,
+    last_used: Instant,
+}
+
+/// LRU session table with idle TTL
+pub struct SessionTable {
+    entries: Mutex<LruCache<String, SessionEntry>>,
+    ttl: Duration,
+}
+
+impl SessionTable {
+    pub fn new(max_entries: usize, ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    /// Service the session is pinned to, refreshing its recency and idle timer
+    pub fn get(&self, session_key: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        match entries.get_mut(session_key) {
+            Some(entry) if now.duration_since(entry.last_used) <= self.ttl => {
+                entry.last_used = now;
+                Some(entry.service.clone())
+            }
+            Some(_) => {
+                entries.pop(session_key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Pin a session to a service, evicting the least recently used entry if full
+    pub fn insert(&self, session_key: &str, service: &str) {
+        self.entries.lock().unwrap().put(
+            session_key.to_string(),
+            SessionEntry {
+                service: service.to_string(),
+                last_used: Instant::now(),
+            },
+        );
+    }
+
+    /// Drop entries that have been idle longer than the TTL; returns how many were removed
+    pub fn purge_expired(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let expired: Vec<String> = entries
+            .iter()
+            .filter(|(_, entry)| now.duration_since(entry.last_used) > self.ttl)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            entries.pop(key);
+        }
+        expired.len()
+    }
+
+    /// Drop every session pinned to a service matching `scope` (all sessions when `None`);
+    /// returns the removed session keys
+    pub fn flush(&self, scope: Option<&HashSet<String>>) -> Vec<String> {
+        let mut entries = self.entries.lock().unwrap();
+        let flushed: Vec<String> = entries
+            .iter()
+            .filter(|(_, entry)| scope.is_none_or(|services| services.contains(&entry.service)))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &flushed {
+            entries.pop(key);
+        }
+        flushed
+    }
+
+    /// Number of live sessions pinned to a service
+    pub fn count_for(&self, service: &str) -> usize {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry)| {
+                entry.service == service && now.duration_since(entry.last_used) <= self.ttl
+            })
+            .count()
+    }
+
+    pub fn size(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.lock().unwrap().cap().get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction() {
+        let table = SessionTable::new(2, Duration::from_secs(60));
+        table.insert("a", "svc-1");
+        table.insert("b", "svc-2");
+        // Touch "a" so "b" becomes least recently used
+        assert_eq!(table.get("a").as_deref(), Some("svc-1"));
+        table.insert("c", "svc-3");
+
+        assert_eq!(table.size(), 2);
+        assert_eq!(table.count_for("svc-1"), 1);
+        assert!(table.get("b").is_none());
+        assert_eq!(table.get("c").as_deref(), Some("svc-3"));
+    }
+
+    #[test]
+    fn test_flush_scoped() {
+        let table = SessionTable::new(10, Duration::from_secs(60));
+        table.insert("a", "svc-1");
+        table.insert("b", "svc-2");
+        table.insert("c", "svc-1");
+
+        let scope = HashSet::from(["svc-1".to_string()]);
+        assert_eq!(table.flush(Some(&scope)).len(), 2);
+        assert_eq!(table.get("b").as_deref(), Some("svc-2"));
+        assert_eq!(table.flush(None), ["b"]);
+        assert_eq!(table.size(), 0);
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let table = SessionTable::new(10, Duration::ZERO);
+        table.insert("a", "svc-1");
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(table.purge_expired(), 1);
+        assert_eq!(table.size(), 0);
+
+        table.insert("b", "svc-2");
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(table.get("b").is_none());
+    }
+}
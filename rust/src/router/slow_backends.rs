@@ -0,0 +1,75 @@
+//! Latency-based weighting of chronically slow backends
+//!
+//! Each pass compares the p95 latency every service saw since it was last judged with
+//! the median p95 of the pool. A service whose p95 exceeds `ratio` times the median
+//! keeps only a share of its weight, proportional to how much slower it is; once its
+//! p95 is back under the threshold the full weight is restored.
+
+use std::time::Duration;
+
+/// Full weight, in percent
+pub const FULL_WEIGHT_PERCENT: u32 = 100;
+
+/// Slow services keep at least this share of their weight, so their recovery can be seen
+const MIN_WEIGHT_PERCENT: u32 = 5;
+
+/// Slow-backend deprioritization settings
+#[derive(Debug, Clone)]
+pub struct SlowBackendPolicy {
+    /// Deprioritize services whose p95 exceeds this multiple of the pool median (0 disables)
+    pub ratio: f64,
+    /// Time between passes
+    pub interval: Duration,
+    /// Requests a service needs since its last judgement before it is judged again
+    pub min_samples: u64,
+}
+
+impl SlowBackendPolicy {
+    pub fn enabled(&self) -> bool {
+        self.ratio > 0.0 && !self.interval.is_zero()
+    }
+
+    /// Weight percentage for a service with the given p95, against the pool's median p95
+    pub fn weight_percent(&self, p95_ms: u64, median_ms: u64) -> u32 {
+        if median_ms == 0 || (p95_ms as f64) <= median_ms as f64 * self.ratio {
+            return FULL_WEIGHT_PERCENT;
+        }
+        let percent = (FULL_WEIGHT_PERCENT as f64 * median_ms as f64 / p95_ms as f64) as u32;
+        percent.clamp(MIN_WEIGHT_PERCENT, FULL_WEIGHT_PERCENT)
+    }
+}
+
+/// Median of the services' p95 latencies
+pub fn pool_median(p95s: &[u64]) -> u64 {
+    let mut sorted = p95s.to_vec();
+    sorted.sort_unstable();
+    match sorted.len() {
+        0 => 0,
+        n if n % 2 == 1 => sorted[n / 2],
+        n => (sorted[n / 2 - 1] + sorted[n / 2]) / 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weight_percent() {
+        let policy = SlowBackendPolicy {
+            ratio: 3.0,
+            interval: Duration::from_secs(30),
+            min_samples: 20,
+        };
+        let median = pool_median(&[100, 120, 900, 110]);
+        assert_eq!(median, 115);
+
+        // Within 3x of the median keeps full weight
+        assert_eq!(policy.weight_percent(300, median), FULL_WEIGHT_PERCENT);
+        // 900ms is ~7.8x the median: weight drops to ~12%
+        assert_eq!(policy.weight_percent(900, median), 12);
+        // Extremely slow services keep a minimum share
+        assert_eq!(policy.weight_percent(100_000, median), MIN_WEIGHT_PERCENT);
+        assert_eq!(policy.weight_percent(500, 0), FULL_WEIGHT_PERCENT);
+    }
+}
@@ -0,0 +1,76 @@
+//! Backoff from backends that push back with 429 / 503
+//!
+//! A 429, or a 503 carrying Retry-After, throttles the service until the Retry-After
+//! delay (or a default backoff) has passed. Throttled services only receive requests no
+//! unthrottled service can take, and the request may be retried on another service.
+
+use chrono::DateTime;
+use std::time::Duration;
+
+/// Throttled-backend settings
+#[derive(Debug, Clone)]
+pub struct ThrottlePolicy {
+    /// Retry throttled requests on another service
+    pub retry: bool,
+    /// Backoff when a 429 carries no usable Retry-After
+    pub default_backoff: Duration,
+    /// Longest backoff honoured, however long Retry-After asks for
+    pub max_backoff: Duration,
+}
+
+impl ThrottlePolicy {
+    /// How long a response with `status` and `retry_after` throttles its service at
+    /// `now` (unix time), if it does
+    pub fn backoff(&self, status: u16, retry_after: Option<&str>, now: u64) -> Option<Duration> {
+        let requested = retry_after.and_then(|value| parse_retry_after(value, now));
+        let backoff = match status {
+            429 => requested.unwrap_or(self.default_backoff),
+            503 => requested?,
+            _ => return None,
+        };
+        Some(backoff.min(self.max_backoff))
+    }
+}
+
+/// Retry-After as delay-seconds or an HTTP date
+fn parse_retry_after(value: &str, now: u64) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?.timestamp();
+    Some(Duration::from_secs(
+        (date.max(0) as u64).saturating_sub(now),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = ThrottlePolicy {
+            retry: true,
+            default_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(60),
+        };
+        let now = 1_700_000_000;
+
+        assert_eq!(policy.backoff(429, None, now), Some(Duration::from_secs(5)));
+        assert_eq!(
+            policy.backoff(429, Some("12"), now),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(
+            policy.backoff(503, Some("3600"), now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            policy.backoff(503, Some("Tue, 14 Nov 2023 22:13:40 GMT"), now),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(policy.backoff(503, None, now), None);
+        assert_eq!(policy.backoff(500, Some("10"), now), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Prompt token estimates without running the model's tokenizer
+//!
+//! Byte length badly misestimates tokens: a CJK character takes three UTF-8 bytes but
+//! about one token, while code spends a token on most punctuation. Text is counted per
+//! character class with typical chars-per-token ratios, and each model's estimates are
+//! scaled by a factor learned from the `usage.prompt_tokens` its backends report.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Chars per token for letters, digits and whitespace
+const WORD_CHARS_PER_TOKEN: f64 = 4.0;
+
+/// Chars per token for ASCII punctuation and symbols (code-heavy prompts)
+const SYMBOL_CHARS_PER_TOKEN: f64 = 1.5;
+
+/// Chars per token for CJK ideographs, kana and hangul
+const CJK_CHARS_PER_TOKEN: f64 = 1.5;
+
+/// Smallest estimate a reported count is learned from; template tokens dominate below
+const MIN_CALIBRATION_TOKENS: u64 = 64;
+
+/// Weight of each reported count in the learned factor
+const CALIBRATION_ALPHA: f64 = 0.1;
+
+/// Bounds of the learned factor
+const MIN_FACTOR: f64 = 0.25;
+const MAX_FACTOR: f64 = 4.0;
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // hiragana, katakana
+        | '\u{3400}'..='\u{4dbf}' // CJK extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK unified ideographs
+        | '\u{ac00}'..='\u{d7af}' // hangul syllables
+        | '\u{f900}'..='\u{faff}' // CJK compatibility ideographs
+        | '\u{ff00}'..='\u{ffef}' // full-width forms
+        | '\u{20000}'..='\u{2ffff}' // CJK extensions B onwards
+    )
+}
+
+/// Uncalibrated token estimate of `text`
+pub fn text_tokens(text: &str) -> f64 {
+    let (mut words, mut symbols, mut cjk) = (0usize, 0usize, 0usize);
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+        } else if c.is_ascii_punctuation() {
+            symbols += 1;
+        } else {
+            words += 1;
+        }
+    }
+    words as f64 / WORD_CHARS_PER_TOKEN
+        + symbols as f64 / SYMBOL_CHARS_PER_TOKEN
+        + cjk as f64 / CJK_CHARS_PER_TOKEN
+}
+
+/// Per-model correction factors for token estimates, learned from backend usage reports
+#[derive(Debug, Default)]
+pub struct TokenEstimator {
+    factors: Mutex<HashMap<String, f64>>,
+}
+
+impl TokenEstimator {
+    /// Learned factor for `model_id` (1.0 until its backends report usage)
+    pub fn factor(&self, model_id: Option<&str>) -> f64 {
+        model_id
+            .and_then(|model| self.factors.lock().unwrap().get(model).copied())
+            .unwrap_or(1.0)
+    }
+
+    /// Estimated tokens of text whose uncalibrated estimate is `text_tokens`
+    pub fn estimate(&self, model_id: Option<&str>, text_tokens: f64) -> u64 {
+        (text_tokens * self.factor(model_id)).ceil() as u64
+    }
+
+    /// Learn from a backend reporting `reported` prompt tokens for a prompt estimated at
+    /// `estimated`
+    pub fn observe(&self, model_id: &str, estimated: u64, reported: u64) {
+        if estimated < MIN_CALIBRATION_TOKENS || reported == 0 {
+            return;
+        }
+        let mut factors = self.factors.lock().unwrap();
+        let factor = factors.entry(model_id.to_string()).or_insert(1.0);
+        let observed = *factor * reported as f64 / estimated as f64;
+        *factor = (*factor * (1.0 - CALIBRATION_ALPHA) + observed * CALIBRATION_ALPHA)
+            .clamp(MIN_FACTOR, MAX_FACTOR);
+    }
+
+    /// Learned factor per model, sorted by model name
+    pub fn factors(&self) -> BTreeMap<String, f64> {
+        self.factors
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(model, factor)| (model.clone(), *factor))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_tokens() {
+        assert_eq!(text_tokens(""), 0.0);
+        assert_eq!(text_tokens("abcd efg"), 2.0);
+        // Three bytes per character, but about one token each
+        assert_eq!(text_tokens("你好世界"), 4.0 / CJK_CHARS_PER_TOKEN);
+        assert_eq!(text_tokens("f(x);"), 2.5);
+    }
+
+    #[test]
+    fn test_observe_learns_factor() {
+        let estimator = TokenEstimator::default();
+        assert_eq!(estimator.estimate(Some("m"), 99.5), 100);
+
+        // Too small to learn from
+        estimator.observe("m", 10, 20);
+        assert_eq!(estimator.factor(Some("m")), 1.0);
+
+        for _ in 0..100 {
+            let estimated = estimator.estimate(Some("m"), 1000.0);
+            estimator.observe("m", estimated, 1500);
+        }
+        assert!((estimator.factor(Some("m")) - 1.5).abs() < 0.01);
+        assert_eq!(estimator.factor(Some("other")), 1.0);
+        assert_eq!(estimator.factor(None), 1.0);
+    }
+}
@@ -0,0 +1,30 @@
+//! Outbound (egress) proxy for upstream HTTP clients
+//!
+//! reqwest already honours HTTP_PROXY / HTTPS_PROXY / ALL_PROXY and NO_PROXY. When backends
+//! need a different proxy than the rest of the process, UPSTREAM_PROXY overrides those for
+//! the proxy and health-check clients, with UPSTREAM_NO_PROXY (else NO_PROXY) as exceptions.
+
+use reqwest::{ClientBuilder, NoProxy, Proxy};
+use tracing::error;
+
+/// Route a client through UPSTREAM_PROXY when it is set
+pub fn with_upstream_proxy(builder: ClientBuilder) -> ClientBuilder {
+    let Some(url) = std::env::var("UPSTREAM_PROXY")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+    else {
+        return builder;
+    };
+
+    let no_proxy = std::env::var("UPSTREAM_NO_PROXY")
+        .or_else(|_| std::env::var("NO_PROXY"))
+        .ok()
+        .and_then(|list| NoProxy::from_string(&list));
+    match Proxy::all(url.trim()) {
+        Ok(proxy) => builder.proxy(proxy.no_proxy(no_proxy)),
+        Err(e) => {
+            error!("Ignoring invalid UPSTREAM_PROXY '{}': {}", url, e);
+            builder
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Listener binding shared by the router, registry and babysitter
+//!
+//! Each binary listens on one port and any number of `--bind` addresses (0.0.0.0 when
+//! none are given). A lone IPv6 wildcard (`::`) is bound dual-stack so it also accepts
+//! IPv4; when IPv4 addresses are bound too, IPv6 sockets are IPv6-only so the two
+//! wildcards do not collide.
+
+use axum::Router;
+use futures::future::FutureExt;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::future::{Future, IntoFuture};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use tokio::net::TcpListener;
+
+/// Pending connections queued per listener
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Parse a `--bind` address, accepting bracketed IPv6 (`[::]`)
+pub fn parse_bind_addr(value: &str) -> Result<IpAddr, String> {
+    let value = value.trim();
+    let unbracketed = value
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .unwrap_or(value);
+    unbracketed
+        .parse()
+        .map_err(|_| format!("Invalid bind address '{}'", value))
+}
+
+/// Socket addresses for `binds` on `port`, defaulting to all IPv4 interfaces
+pub fn bind_addrs(binds: &[IpAddr], port: u16) -> Vec<SocketAddr> {
+    if binds.is_empty() {
+        return vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)];
+    }
+    binds.iter().map(|ip| SocketAddr::new(*ip, port)).collect()
+}
+
+/// Bind a listener on every address in `addrs`
+pub fn bind_all(addrs: &[SocketAddr]) -> io::Result<Vec<TcpListener>> {
+    let v6_only = addrs.iter().any(|addr| addr.is_ipv4());
+    addrs
+        .iter()
+        .map(|addr| {
+            bind(*addr, v6_only)
+                .map_err(|e| io::Error::new(e.kind(), format!("Failed to bind {}: {}", addr, e)))
+        })
+        .collect()
+}
+
+fn bind(addr: SocketAddr, v6_only: bool) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(v6_only)?;
+    }
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Human-readable list of listener URLs for logs
+pub fn describe(addrs: &[SocketAddr]) -> String {
+    addrs
+        .iter()
+        .map(|addr| format!("http://{}", addr))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Serve `app` on every listener, recording peer addresses, until `shutdown` completes
+pub async fn serve_all(
+    listeners: Vec<TcpListener>,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<()> {
+    let shutdown = shutdown.shared();
+    let servers = listeners.into_iter().map(|listener| {
+        axum::serve(
+            listener,
+            app.clone()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown.clone())
+        .into_future()
+    });
+    for result in futures::future::join_all(servers).await {
+        result?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_addrs() {
+        assert_eq!(parse_bind_addr("[::]"), Ok("::".parse().unwrap()));
+        assert_eq!(parse_bind_addr("10.0.0.1"), Ok("10.0.0.1".parse().unwrap()));
+        assert!(parse_bind_addr("localhost").is_err());
+
+        assert_eq!(
+            bind_addrs(&[], 8080),
+            vec!["0.0.0.0:8080".parse::<SocketAddr>().unwrap()]
+        );
+        assert_eq!(
+            bind_addrs(&["::".parse().unwrap()], 8080),
+            vec!["[::]:8080".parse::<SocketAddr>().unwrap()]
+        );
+    }
+}
@@ -1,4 +1,6 @@
 //! Utility modules
 
+pub mod egress;
 pub mod errors;
+pub mod listen;
 pub mod time;
@@ -11,7 +11,7 @@ ROUTER_BIN="$PROJECT_ROOT/rust/target/release/infini-router"
 BABYSITTER_BIN="$PROJECT_ROOT/rust/target/release/infini-babysitter"
 REGISTRY_BIN="$PROJECT_ROOT/rust/target/release/infini-registry"
 REGISTRY_SCRIPT="$PROJECT_ROOT/python/service_registry.py"
-MOCK_SERVICE_SCRIPT="$SCRIPT_DIR/mock_service.py"
+MOCK_BIN="$PROJECT_ROOT/rust/target/release/infini-mock"
 
 # Test ports - allocate ports with gaps to avoid conflicts
 # Each service needs its own port, and each babysitter needs its own port (service_port + 1)
@@ -52,8 +52,9 @@ if [ ! -f "$REGISTRY_BIN" ]; then
     exit 1
 fi
 
-if [ ! -f "$MOCK_SERVICE_SCRIPT" ]; then
-    echo "Error: Mock service script not found: $MOCK_SERVICE_SCRIPT"
+if [ ! -f "$MOCK_BIN" ]; then
+    echo "Error: Mock service binary not found. Please build it first:"
+    echo "  cd rust && cargo build --release --bin infini-mock"
     exit 1
 fi
 
@@ -72,7 +73,7 @@ cleanup() {
     sleep 2
     
     # Force kill any remaining processes (pkill handles both parent and child processes)
-    pkill -9 -f "mock_service.py" 2>/dev/null || true
+    pkill -9 -f "infini-mock" 2>/dev/null || true
     pkill -9 -f "service_registry.py" 2>/dev/null || true
     pkill -9 -f "infini-router" 2>/dev/null || true
     pkill -9 -f "infini-babysitter" 2>/dev/null || true
@@ -90,43 +91,6 @@ echo "Starting registry on port $REGISTRY_PORT..."
 mkdir -p "$PROJECT_ROOT/logs"
 cd "$PROJECT_ROOT"
 
-# Setup Python environment - use dedicated integration test environment
-PYTHON_CMD="python3"
-CONDA_ENV="infinilm-integration-test"
-
-if command -v conda &> /dev/null; then
-    # Check if integration test environment exists
-    if conda env list | grep -q "^${CONDA_ENV} "; then
-        PYTHON_CMD="conda run -n ${CONDA_ENV} python"
-        echo "Using conda environment: ${CONDA_ENV}"
-        
-        # Verify aiohttp is available
-        if ! $PYTHON_CMD -c "import aiohttp" 2>/dev/null; then
-            echo "Installing dependencies in ${CONDA_ENV}..."
-            conda run -n ${CONDA_ENV} pip install aiohttp requests > /dev/null 2>&1
-        fi
-    elif [ -f "$PROJECT_ROOT/python/activate_env.sh" ]; then
-        source "$PROJECT_ROOT/python/activate_env.sh"
-        echo "Using activated environment from activate_env.sh"
-    else
-        echo "⚠️  Conda environment '${CONDA_ENV}' not found. Creating it..."
-        conda create -n ${CONDA_ENV} python=3.10 -y > /dev/null 2>&1
-        conda run -n ${CONDA_ENV} pip install aiohttp requests > /dev/null 2>&1
-        PYTHON_CMD="conda run -n ${CONDA_ENV} python"
-        echo "✅ Created and configured ${CONDA_ENV} environment"
-    fi
-elif [ -f "$PROJECT_ROOT/python/activate_env.sh" ]; then
-    source "$PROJECT_ROOT/python/activate_env.sh"
-    echo "Using activated environment from activate_env.sh"
-fi
-
-# Final verification
-if ! $PYTHON_CMD -c "import aiohttp" 2>/dev/null; then
-    echo "Error: aiohttp not available. Please install it:"
-    echo "  conda run -n ${CONDA_ENV} pip install aiohttp"
-    exit 1
-fi
-
 # Start Rust registry
 echo "Starting registry..."
 $REGISTRY_BIN --port $REGISTRY_PORT 2>&1 | tee /tmp/registry_babysitter_test.log &
@@ -159,8 +123,8 @@ heartbeat_interval = 10
 
 [backend]
 type = "command"
-command = "$PYTHON_CMD"
-args = ["$MOCK_SERVICE_SCRIPT", "--name", "service-model-a", "--port", "$SERVICE1_PORT", "--models", "model-a,model-shared", "--registry-url", "http://127.0.0.1:$REGISTRY_PORT"]
+command = "$MOCK_BIN"
+args = ["--name", "service-model-a", "--port", "$SERVICE1_PORT", "--models", "model-a,model-shared", "--registry-url", "http://127.0.0.1:$REGISTRY_PORT"]
 EOF
 
 # Babysitter 2: Manages service with model-b and model-shared
@@ -176,8 +140,8 @@ heartbeat_interval = 10
 
 [backend]
 type = "command"
-command = "$PYTHON_CMD"
-args = ["$MOCK_SERVICE_SCRIPT", "--name", "service-model-b", "--port", "$SERVICE2_PORT", "--models", "model-b,model-shared", "--registry-url", "http://127.0.0.1:$REGISTRY_PORT"]
+command = "$MOCK_BIN"
+args = ["--name", "service-model-b", "--port", "$SERVICE2_PORT", "--models", "model-b,model-shared", "--registry-url", "http://127.0.0.1:$REGISTRY_PORT"]
 EOF
 
 # Babysitter 3: Manages service with model-c only
@@ -193,8 +157,8 @@ heartbeat_interval = 10
 
 [backend]
 type = "command"
-command = "$PYTHON_CMD"
-args = ["$MOCK_SERVICE_SCRIPT", "--name", "service-model-c", "--port", "$SERVICE3_PORT", "--models", "model-c", "--registry-url", "http://127.0.0.1:$REGISTRY_PORT"]
+command = "$MOCK_BIN"
+args = ["--name", "service-model-c", "--port", "$SERVICE3_PORT", "--models", "model-c", "--registry-url", "http://127.0.0.1:$REGISTRY_PORT"]
 EOF
 
 # Start babysitters
@@ -485,7 +449,7 @@ if [ $TESTS_FAILED -gt 0 ]; then
     tail -50 /tmp/router_babysitter_test.log 2>/dev/null || echo "No router log file"
     echo ""
     echo "--- Checking if mock services are running ---"
-    ps aux | grep -E "(mock_service|python.*mock)" | grep -v grep || echo "No mock service processes found"
+    ps aux | grep -E "infini-mock" | grep -v grep || echo "No mock service processes found"
     echo ""
     echo "--- Checking ports ---"
     netstat -tuln 2>/dev/null | grep -E ":(6001|6003|6005|6002|6004|6006|8900|8901)" || lsof -i :6001,6003,6005,6002,6004,6006,8900,8901 2>/dev/null || echo "Could not check ports"